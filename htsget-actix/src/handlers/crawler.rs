@@ -0,0 +1,15 @@
+use actix_web::HttpResponse;
+
+use htsget_http::ROBOTS_TXT;
+
+/// Responds to `/robots.txt` with a static response which disallows crawling.
+pub async fn robots_txt() -> HttpResponse {
+  HttpResponse::Ok()
+    .content_type("text/plain")
+    .body(ROBOTS_TXT)
+}
+
+/// Responds to `/favicon.ico` with no content.
+pub async fn favicon() -> HttpResponse {
+  HttpResponse::NoContent().finish()
+}
@@ -1,13 +1,16 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use actix_web::{
-  web::{Data, Path, Query},
+  web::{Bytes, Data, Path, Query},
   HttpRequest, Responder,
 };
 use tracing::info;
 use tracing::instrument;
 
-use htsget_http::{get, Endpoint};
+use htsget_http::{
+  audit_denied, check_authorization, check_duplicate_query_keys, check_get_body, get, Endpoint,
+};
 use htsget_search::HtsGet;
 
 use crate::handlers::extract_request;
@@ -16,38 +19,103 @@ use crate::AppState;
 use super::handle_response;
 
 /// GET request reads endpoint
-#[instrument(skip(app_state))]
+#[instrument(skip(app_state, body))]
 pub async fn reads<H: HtsGet + Clone + Send + Sync + 'static>(
   request: Query<HashMap<String, String>>,
   path: Path<String>,
   http_request: HttpRequest,
   app_state: Data<AppState<H>>,
+  body: Bytes,
 ) -> impl Responder {
+  let raw_query = http_request.query_string().to_string();
   let request = extract_request(request, path, http_request);
 
   info!(request = ?request, "reads endpoint GET request");
 
-  handle_response(get(app_state.get_ref().htsget.clone(), request, Endpoint::Reads).await)
+  let service_info_link = app_state.get_ref().service_info_link(&Endpoint::Reads);
+  let elapsed_time_header = app_state.get_ref().elapsed_time_header;
+  let response_style = app_state.get_ref().response_style;
+  let age_header = app_state.get_ref().age_header;
+  let relative_urls = app_state.get_ref().relative_urls;
+  let content_length = app_state.get_ref().content_length;
+
+  let start = Instant::now();
+  let response = match check_get_body(app_state.get_ref().reject_get_body, &body)
+    .and_then(|_| {
+      check_duplicate_query_keys(app_state.get_ref().reject_duplicate_query_keys, &raw_query)
+    })
+    .and_then(|_| check_authorization(app_state.get_ref().require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      get(
+        app_state.get_ref().htsget.clone(),
+        request,
+        Endpoint::Reads,
+        &app_state.get_ref().format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.get_ref().audit_log, &request, err)),
+  };
+
+  handle_response(
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
+  )
 }
 
 /// GET request variants endpoint
-#[instrument(skip(app_state))]
+#[instrument(skip(app_state, body))]
 pub async fn variants<H: HtsGet + Clone + Send + Sync + 'static>(
   request: Query<HashMap<String, String>>,
   path: Path<String>,
   http_request: HttpRequest,
   app_state: Data<AppState<H>>,
+  body: Bytes,
 ) -> impl Responder {
+  let raw_query = http_request.query_string().to_string();
   let request = extract_request(request, path, http_request);
 
   info!(request = ?request, "variants endpoint GET request");
 
+  let service_info_link = app_state.get_ref().service_info_link(&Endpoint::Variants);
+  let elapsed_time_header = app_state.get_ref().elapsed_time_header;
+  let response_style = app_state.get_ref().response_style;
+  let age_header = app_state.get_ref().age_header;
+  let relative_urls = app_state.get_ref().relative_urls;
+  let content_length = app_state.get_ref().content_length;
+
+  let start = Instant::now();
+  let response = match check_get_body(app_state.get_ref().reject_get_body, &body)
+    .and_then(|_| {
+      check_duplicate_query_keys(app_state.get_ref().reject_duplicate_query_keys, &raw_query)
+    })
+    .and_then(|_| check_authorization(app_state.get_ref().require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      get(
+        app_state.get_ref().htsget.clone(),
+        request,
+        Endpoint::Variants,
+        &app_state.get_ref().format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.get_ref().audit_log, &request, err)),
+  };
+
   handle_response(
-    get(
-      app_state.get_ref().htsget.clone(),
-      request,
-      Endpoint::Variants,
-    )
-    .await,
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
   )
 }
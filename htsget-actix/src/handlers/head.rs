@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use actix_web::{
+  web::{Data, Path, Query},
+  HttpRequest, HttpResponse,
+};
+use tracing::info;
+use tracing::instrument;
+
+use htsget_http::{
+  audit_denied, check_authorization, check_duplicate_query_keys, head, Endpoint, FORMAT_HEADER,
+};
+use htsget_search::HtsGet;
+
+use crate::handlers::{extract_request, HttpVersionCompat};
+use crate::AppState;
+
+/// HEAD request reads endpoint
+#[instrument(skip(app_state))]
+pub async fn reads<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: Query<HashMap<String, String>>,
+  path: Path<String>,
+  http_request: HttpRequest,
+  app_state: Data<AppState<H>>,
+) -> HttpResponse {
+  info!("reads endpoint HEAD request");
+
+  head_response(
+    request,
+    path,
+    http_request,
+    app_state.get_ref(),
+    Endpoint::Reads,
+  )
+  .await
+}
+
+/// HEAD request variants endpoint
+#[instrument(skip(app_state))]
+pub async fn variants<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: Query<HashMap<String, String>>,
+  path: Path<String>,
+  http_request: HttpRequest,
+  app_state: Data<AppState<H>>,
+) -> HttpResponse {
+  info!("variants endpoint HEAD request");
+
+  head_response(
+    request,
+    path,
+    http_request,
+    app_state.get_ref(),
+    Endpoint::Variants,
+  )
+  .await
+}
+
+/// Checks that the file of the request exists, without searching it. Responds with the
+/// `htsget-format` header set to the format of the file, or the status code of the error.
+async fn head_response<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: Query<HashMap<String, String>>,
+  path: Path<String>,
+  http_request: HttpRequest,
+  app_state: &AppState<H>,
+  endpoint: Endpoint,
+) -> HttpResponse {
+  let raw_query = http_request.query_string().to_string();
+  let request = extract_request(request, path, http_request);
+
+  let response = match check_duplicate_query_keys(app_state.reject_duplicate_query_keys, &raw_query)
+    .and_then(|_| check_authorization(app_state.require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      head(
+        app_state.htsget.clone(),
+        request,
+        endpoint,
+        &app_state.format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
+
+  match response {
+    Ok(format) => HttpResponse::Ok()
+      .insert_header((CONTENT_TYPE, "application/json"))
+      .insert_header((FORMAT_HEADER, format.to_string()))
+      .finish(),
+    Err(error) => {
+      let (_, status_code) = error.to_json_representation();
+      let mut response = HttpResponse::build(HttpVersionCompat::status_code_1_to_0_2(status_code));
+      if status_code == http_1::StatusCode::UNAUTHORIZED {
+        response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+      }
+
+      response.finish()
+    }
+  }
+}
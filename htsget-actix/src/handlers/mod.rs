@@ -1,19 +1,30 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::http::header::{HeaderValue, AGE, CONTENT_LENGTH, ETAG, LINK, WWW_AUTHENTICATE};
 use actix_web::web::{Path, Query};
-use actix_web::{http::StatusCode, Either, HttpRequest, Responder};
+use actix_web::{
+  http::StatusCode, CustomizeResponder, Either, HttpRequest, HttpResponse, Responder,
+};
 use http::{HeaderMap as HttpHeaderMap, HeaderName, Method};
 
-use htsget_config::types::{JsonResponse, Request};
-use htsget_http::Result;
-use pretty_json::PrettyJson;
+use htsget_config::types::{JsonResponse, Request, ResponseStyle, ServerTiming};
+use htsget_http::{Result, ELAPSED_MS_HEADER, SERVER_TIMING_HEADER};
+use pretty_json::{PrettyJson, StyledJson};
 
+pub use crate::handlers::crawler::{favicon, robots_txt};
+pub use crate::handlers::ready::ready;
 pub use crate::handlers::service_info::{
-  get_service_info_json, reads_service_info, variants_service_info,
+  get_service_info_json, reads_service_info, reads_service_info_head, root, variants_service_info,
+  variants_service_info_head,
 };
 
+pub mod crawler;
 pub mod get;
+pub mod head;
 pub mod post;
+pub mod ready;
 pub mod service_info;
 
 mod pretty_json;
@@ -32,18 +43,146 @@ impl From<&HttpRequest> for HeaderMap {
   }
 }
 
-/// Handles a response, converting errors to json and using the proper HTTP status code
-fn handle_response(response: Result<JsonResponse>) -> Either<impl Responder, impl Responder> {
-  match response {
+/// Handles a response, converting errors to json and using the proper HTTP status code. The
+/// service-info link is set as the `Link` header, and the elapsed search time is set as the
+/// `X-Htsget-Elapsed-Ms` header, if they are present. Unauthorized responses include a
+/// `WWW-Authenticate: Bearer` header. Successful responses are serialized using the response style,
+/// and include the `Age` header if enabled and the response was served from a response cache, and
+/// the `Server-Timing` header if server timings were recorded. The `Content-Length` header is set
+/// explicitly if enabled.
+fn handle_response(
+  response: Result<(JsonResponse, Option<Duration>)>,
+  service_info_link: Option<String>,
+  elapsed: Option<Duration>,
+  response_style: ResponseStyle,
+  age_header: bool,
+  relative_urls: bool,
+  content_length: bool,
+) -> impl Responder {
+  let response = match response {
     Err(error) => {
       let (json, status_code) = error.to_json_representation();
-      Either::Left(
-        PrettyJson(json)
-          .customize()
-          .with_status(HttpVersionCompat::status_code_1_to_0_2(status_code)),
-      )
+      let responder = with_elapsed(
+        with_link(
+          PrettyJson(json)
+            .customize()
+            .with_status(HttpVersionCompat::status_code_1_to_0_2(status_code)),
+          service_info_link,
+        ),
+        elapsed,
+      );
+
+      Either::Left(if status_code == http_1::StatusCode::UNAUTHORIZED {
+        responder.insert_header((WWW_AUTHENTICATE, "Bearer"))
+      } else {
+        responder
+      })
+    }
+    Ok((mut json, age)) => {
+      if relative_urls {
+        json.htsget = json.htsget.with_relative_urls();
+      }
+      let age = age.filter(|_| age_header);
+      let server_timing = json.htsget.server_timing;
+      let etag = json.htsget.etag_header_value();
+
+      Either::Right(with_etag(
+        with_server_timing(
+          with_age(
+            with_elapsed(
+              with_link(
+                StyledJson(json, response_style)
+                  .customize()
+                  .with_status(StatusCode::OK),
+                service_info_link,
+              ),
+              elapsed,
+            ),
+            age,
+          ),
+          server_timing,
+        ),
+        etag,
+      ))
+    }
+  };
+
+  ContentLength(response, content_length)
+}
+
+/// A responder which sets the `Content-Length` header from the size of the body, if enabled and
+/// the size is known.
+struct ContentLength<R>(R, bool);
+
+impl<R: Responder> Responder for ContentLength<R> {
+  type Body = BoxBody;
+
+  fn respond_to(self, request: &HttpRequest) -> HttpResponse {
+    let mut response = self.0.respond_to(request).map_into_boxed_body();
+    if let (true, BodySize::Sized(size)) = (self.1, response.body().size()) {
+      response
+        .headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from(size));
+    }
+
+    response
+  }
+}
+
+/// Set the `X-Htsget-Elapsed-Ms` header on the responder if the elapsed time is present.
+fn with_elapsed<R: Responder>(
+  responder: CustomizeResponder<R>,
+  elapsed: Option<Duration>,
+) -> CustomizeResponder<R> {
+  match elapsed {
+    Some(elapsed) => responder.insert_header((ELAPSED_MS_HEADER, elapsed.as_millis().to_string())),
+    None => responder,
+  }
+}
+
+/// Set the `Age` header on the responder if the age is present.
+fn with_age<R: Responder>(
+  responder: CustomizeResponder<R>,
+  age: Option<Duration>,
+) -> CustomizeResponder<R> {
+  match age {
+    Some(age) => responder.insert_header((AGE, age.as_secs().to_string())),
+    None => responder,
+  }
+}
+
+/// Set the `Server-Timing` header on the responder if the server timing is present.
+fn with_server_timing<R: Responder>(
+  responder: CustomizeResponder<R>,
+  server_timing: Option<ServerTiming>,
+) -> CustomizeResponder<R> {
+  match server_timing {
+    Some(server_timing) => {
+      responder.insert_header((SERVER_TIMING_HEADER, server_timing.header_value()))
     }
-    Ok(json) => Either::Right(PrettyJson(json).customize().with_status(StatusCode::OK)),
+    None => responder,
+  }
+}
+
+/// Set the `ETag` header on the responder if the ETag is present.
+fn with_etag<R: Responder>(
+  responder: CustomizeResponder<R>,
+  etag: Option<String>,
+) -> CustomizeResponder<R> {
+  match etag {
+    Some(etag) => responder.insert_header((ETAG, etag)),
+    None => responder,
+  }
+}
+
+/// Set the `Link` header on the responder if the link is present.
+fn with_link<R: Responder>(
+  responder: CustomizeResponder<R>,
+  link: Option<String>,
+) -> CustomizeResponder<R> {
+  match link {
+    Some(link) => responder.insert_header((LINK, link)),
+    None => responder,
   }
 }
 
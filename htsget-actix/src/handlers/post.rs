@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use actix_web::web::Query;
 use actix_web::{
@@ -8,7 +9,7 @@ use actix_web::{
 use tracing::info;
 use tracing::instrument;
 
-use htsget_http::{post, Endpoint, PostRequest};
+use htsget_http::{audit_denied, check_authorization, post, Endpoint, PostRequest};
 use htsget_search::HtsGet;
 
 use crate::handlers::extract_request;
@@ -29,14 +30,39 @@ pub async fn reads<H: HtsGet + Clone + Send + Sync + 'static>(
 
   info!(body = ?body, "reads endpoint POST request");
 
+  let service_info_link = app_state.get_ref().service_info_link(&Endpoint::Reads);
+  let elapsed_time_header = app_state.get_ref().elapsed_time_header;
+  let response_style = app_state.get_ref().response_style;
+  let age_header = app_state.get_ref().age_header;
+  let relative_urls = app_state.get_ref().relative_urls;
+  let content_length = app_state.get_ref().content_length;
+
+  let start = Instant::now();
+  let response =
+    match check_authorization(app_state.get_ref().require_authorization, request.headers()) {
+      Ok(_) => {
+        post(
+          app_state.get_ref().htsget.clone(),
+          body.into_inner(),
+          request,
+          Endpoint::Reads,
+          &app_state.get_ref().format_aliases,
+          app_state.get_ref().max_distinct_references,
+          app_state.get_ref().query_concurrency,
+        )
+        .await
+      }
+      Err(err) => Err(audit_denied(&app_state.get_ref().audit_log, &request, err)),
+    };
+
   handle_response(
-    post(
-      app_state.get_ref().htsget.clone(),
-      body.into_inner(),
-      request,
-      Endpoint::Reads,
-    )
-    .await,
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
   )
 }
 
@@ -53,13 +79,38 @@ pub async fn variants<H: HtsGet + Clone + Send + Sync + 'static>(
 
   info!(body = ?body, "variants endpoint POST request");
 
+  let service_info_link = app_state.get_ref().service_info_link(&Endpoint::Variants);
+  let elapsed_time_header = app_state.get_ref().elapsed_time_header;
+  let response_style = app_state.get_ref().response_style;
+  let age_header = app_state.get_ref().age_header;
+  let relative_urls = app_state.get_ref().relative_urls;
+  let content_length = app_state.get_ref().content_length;
+
+  let start = Instant::now();
+  let response =
+    match check_authorization(app_state.get_ref().require_authorization, request.headers()) {
+      Ok(_) => {
+        post(
+          app_state.get_ref().htsget.clone(),
+          body.into_inner(),
+          request,
+          Endpoint::Variants,
+          &app_state.get_ref().format_aliases,
+          app_state.get_ref().max_distinct_references,
+          app_state.get_ref().query_concurrency,
+        )
+        .await
+      }
+      Err(err) => Err(audit_denied(&app_state.get_ref().audit_log, &request, err)),
+    };
+
   handle_response(
-    post(
-      app_state.get_ref().htsget.clone(),
-      body.into_inner(),
-      request,
-      Endpoint::Variants,
-    )
-    .await,
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
   )
 }
@@ -1,5 +1,6 @@
 use actix_web::body::BoxBody;
 use actix_web::{http::StatusCode, HttpRequest, HttpResponse, Responder};
+use htsget_config::types::{JsonResponse, ResponseStyle, StrictJsonResponse};
 use serde::Serialize;
 
 pub struct PrettyJson<T>(pub T);
@@ -8,14 +9,33 @@ impl<T: Serialize> Responder for PrettyJson<T> {
   type Body = BoxBody;
 
   fn respond_to(self, _: &HttpRequest) -> HttpResponse {
-    let mut body = match serde_json::to_string_pretty(&self.0) {
-      Ok(body) => body,
-      Err(e) => return HttpResponse::from_error(e),
-    };
-    body.push('\n');
-
-    HttpResponse::build(StatusCode::OK)
-      .content_type("application/json")
-      .body(body)
+    json_response(serde_json::to_string_pretty(&self.0))
   }
 }
+
+/// A ticket response which is serialized using the response style.
+pub struct StyledJson(pub JsonResponse, pub ResponseStyle);
+
+impl Responder for StyledJson {
+  type Body = BoxBody;
+
+  fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+    json_response(match self.1 {
+      ResponseStyle::Pretty => serde_json::to_string_pretty(&self.0),
+      ResponseStyle::Compact => serde_json::to_string(&self.0),
+      ResponseStyle::Strict => serde_json::to_string_pretty(&StrictJsonResponse(&self.0)),
+    })
+  }
+}
+
+fn json_response(body: serde_json::Result<String>) -> HttpResponse {
+  let mut body = match body {
+    Ok(body) => body,
+    Err(e) => return HttpResponse::from_error(e),
+  };
+  body.push('\n');
+
+  HttpResponse::build(StatusCode::OK)
+    .content_type("application/json")
+    .body(body)
+}
@@ -0,0 +1,27 @@
+use actix_web::web::Data;
+use actix_web::{http::StatusCode, Responder};
+use tracing::info;
+use tracing::instrument;
+
+use htsget_http::get_readiness;
+use htsget_search::HtsGet;
+
+use crate::handlers::pretty_json::PrettyJson;
+use crate::AppState;
+
+/// Probes each storage backend, returning a 503 with the per-backend status if any are unreachable.
+#[instrument(skip(app_state))]
+pub async fn ready<H: HtsGet + Clone + Send + Sync + 'static>(
+  app_state: Data<AppState<H>>,
+) -> impl Responder {
+  info!("readiness request");
+
+  let readiness = get_readiness(&app_state.get_ref().htsget).await;
+  let status_code = if readiness.ready {
+    StatusCode::OK
+  } else {
+    StatusCode::SERVICE_UNAVAILABLE
+  };
+
+  PrettyJson(readiness).customize().with_status(status_code)
+}
@@ -1,8 +1,9 @@
 use actix_web::web::Data;
-use actix_web::Responder;
+use actix_web::{HttpRequest, HttpResponse, Responder};
 use tracing::info;
 use tracing::instrument;
 
+use htsget_http::get_root_json;
 use htsget_http::get_service_info_json as get_base_service_info_json;
 use htsget_http::Endpoint;
 use htsget_search::HtsGet;
@@ -38,3 +39,38 @@ pub async fn variants_service_info<H: HtsGet + Clone + Send + Sync + 'static>(
 ) -> impl Responder {
   get_service_info_json(app_state.get_ref(), Endpoint::Variants)
 }
+
+/// Gets the JSON to return for the root path
+pub async fn root<H: HtsGet + Clone + Send + Sync + 'static>(
+  app_state: Data<AppState<H>>,
+) -> impl Responder {
+  PrettyJson(get_root_json(&app_state.get_ref().config_service_info))
+}
+
+/// Gets the headers of a service-info endpoint, without the JSON body.
+fn head_service_info<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: &HttpRequest,
+  app_state: &AppState<H>,
+  endpoint: Endpoint,
+) -> HttpResponse {
+  get_service_info_json(app_state, endpoint)
+    .respond_to(request)
+    .drop_body()
+    .map_into_boxed_body()
+}
+
+/// Gets the headers of the reads service-info endpoint, without the JSON body
+pub async fn reads_service_info_head<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: HttpRequest,
+  app_state: Data<AppState<H>>,
+) -> HttpResponse {
+  head_service_info(&request, app_state.get_ref(), Endpoint::Reads)
+}
+
+/// Gets the headers of the variants service-info endpoint, without the JSON body
+pub async fn variants_service_info_head<H: HtsGet + Clone + Send + Sync + 'static>(
+  request: HttpRequest,
+  app_state: Data<AppState<H>>,
+) -> HttpResponse {
+  head_service_info(&request, app_state.get_ref(), Endpoint::Variants)
+}
@@ -1,6 +1,12 @@
 use actix_cors::Cors;
-use actix_web::dev::Server;
-use actix_web::{web, App, HttpServer};
+use actix_web::body::MessageBody;
+use actix_web::dev::{Server, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{
+  HeaderName, HeaderValue, ACCESS_CONTROL_REQUEST_METHOD, RETRY_AFTER, X_CONTENT_TYPE_OPTIONS,
+};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::{from_fn, Condition, DefaultHeaders, Next};
+use actix_web::{web, App, Error, HttpResponse, HttpServer};
 use tracing::info;
 use tracing::instrument;
 use tracing_actix_web::TracingLogger;
@@ -8,9 +14,16 @@ use tracing_actix_web::TracingLogger;
 use htsget_config::config::cors::CorsConfig;
 pub use htsget_config::config::{Config, DataServerConfig, ServiceInfo, TicketServerConfig, USAGE};
 pub use htsget_config::storage::Storage;
+pub use htsget_config::types::{
+  AuditLog, DeprecatedRoutes, FormatAliases, Maintenance, ResponseStyle,
+};
+use htsget_http::{check_maintenance, exposed_ticket_headers, Endpoint, DEPRECATION_HEADER};
 use htsget_search::HtsGet;
 
-use crate::handlers::{get, post, reads_service_info, variants_service_info, HttpVersionCompat};
+use crate::handlers::{
+  favicon, get, head, post, reads_service_info, reads_service_info_head, ready, robots_txt, root,
+  variants_service_info, variants_service_info_head, HttpVersionCompat,
+};
 
 pub mod handlers;
 
@@ -18,33 +31,119 @@ pub mod handlers;
 pub struct AppState<H: HtsGet> {
   pub htsget: H,
   pub config_service_info: ServiceInfo,
+  pub service_info_link: bool,
+  pub reject_get_body: bool,
+  pub format_aliases: FormatAliases,
+  pub max_distinct_references: Option<usize>,
+  pub query_concurrency: Option<usize>,
+  pub elapsed_time_header: bool,
+  pub require_authorization: bool,
+  pub response_style: ResponseStyle,
+  pub age_header: bool,
+  pub relative_urls: bool,
+  pub content_length: bool,
+  pub reject_duplicate_query_keys: bool,
+  pub audit_log: AuditLog,
 }
 
-/// Configure the query server.
+impl<H: HtsGet> AppState<H> {
+  /// Get the service-info link for the endpoint if it should be included in ticket responses.
+  pub(crate) fn service_info_link(&self, endpoint: &Endpoint) -> Option<String> {
+    self.service_info_link.then(|| endpoint.service_info_link())
+  }
+}
+
+/// The maximum request path length, longer paths are rejected with 414 URI Too Long.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxPathLength(pub Option<usize>);
+
+/// Configure the query server, using the options in the ticket server config.
 pub fn configure_server<H: HtsGet + Clone + Send + Sync + 'static>(
   service_config: &mut web::ServiceConfig,
   htsget: H,
   config_service_info: ServiceInfo,
+  config: &TicketServerConfig,
 ) {
   service_config
+    .app_data(MaxPathLength(config.max_path_length()))
+    .app_data(config.deprecated_routes().clone())
+    .app_data(config.maintenance().clone())
     .app_data(web::Data::new(AppState {
       htsget,
       config_service_info,
+      service_info_link: config.service_info_link(),
+      reject_get_body: config.reject_get_body(),
+      format_aliases: config.format_aliases().clone(),
+      max_distinct_references: config.max_distinct_references(),
+      query_concurrency: config.query_concurrency(),
+      elapsed_time_header: config.elapsed_time_header(),
+      require_authorization: config.require_authorization(),
+      response_style: config.response_style(),
+      age_header: config.age_header(),
+      relative_urls: config.relative_urls(),
+      content_length: config.content_length(),
+      reject_duplicate_query_keys: config.reject_duplicate_query_keys(),
+      audit_log: config.audit_log().clone(),
     }))
+    .route("/ready", web::get().to(ready::<H>))
     .service(
       web::scope("/reads")
         .route("/service-info", web::get().to(reads_service_info::<H>))
         .route("/service-info", web::post().to(reads_service_info::<H>))
+        .route(
+          "/service-info",
+          web::head().to(reads_service_info_head::<H>),
+        )
         .route("/{id:.+}", web::get().to(get::reads::<H>))
+        .route("/{id:.+}", web::head().to(head::reads::<H>))
         .route("/{id:.+}", web::post().to(post::reads::<H>)),
     )
     .service(
       web::scope("/variants")
         .route("/service-info", web::get().to(variants_service_info::<H>))
         .route("/service-info", web::post().to(variants_service_info::<H>))
+        .route(
+          "/service-info",
+          web::head().to(variants_service_info_head::<H>),
+        )
         .route("/{id:.+}", web::get().to(get::variants::<H>))
+        .route("/{id:.+}", web::head().to(head::variants::<H>))
         .route("/{id:.+}", web::post().to(post::variants::<H>)),
     );
+
+  if config.root_response() {
+    service_config.route("/", web::get().to(root::<H>));
+  }
+
+  if config.crawler_responses() {
+    service_config
+      .route("/robots.txt", web::get().to(robots_txt))
+      .route("/favicon.ico", web::get().to(favicon));
+  }
+}
+
+/// Configure security headers, such as `X-Content-Type-Options: nosniff`, which are added to
+/// responses if enabled.
+pub fn configure_security_headers(security_headers: bool) -> Condition<DefaultHeaders> {
+  Condition::new(
+    security_headers,
+    DefaultHeaders::new().add((X_CONTENT_TYPE_OPTIONS, "nosniff")),
+  )
+}
+
+/// Get the cors config of the ticket server, exposing any custom headers that the ticket server
+/// responds with.
+pub fn ticket_server_cors(config: &TicketServerConfig) -> CorsConfig {
+  config
+    .cors()
+    .clone()
+    .with_additional_expose_headers(exposed_ticket_headers(
+      config.service_info_link(),
+      config.elapsed_time_header(),
+      config.require_authorization(),
+      config.age_header(),
+      !config.deprecated_routes().is_empty(),
+    ))
 }
 
 /// Configure cors, settings allowed methods, max age, allowed origins, and if credentials
@@ -105,6 +204,143 @@ pub fn configure_cors(cors: CorsConfig) -> Cors {
   cors_layer.max_age(cors.max_age())
 }
 
+/// Remove any cors headers from client and server error responses.
+async fn remove_cors_headers_on_error(
+  request: ServiceRequest,
+  next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+  let mut response = next.call(request).await?;
+
+  if response.status().is_client_error() || response.status().is_server_error() {
+    let headers = response.headers_mut();
+    let cors_headers: Vec<_> = headers
+      .keys()
+      .filter(|name| name.as_str().starts_with("access-control-"))
+      .cloned()
+      .collect();
+
+    for name in cors_headers {
+      headers.remove(&name);
+    }
+  }
+
+  Ok(response)
+}
+
+/// Respond to cors preflight requests with 204 No Content rather than 200 OK. Preflight requests
+/// are answered by the cors middleware, so they never reach the handlers.
+async fn preflight_no_content(
+  request: ServiceRequest,
+  next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+  let preflight = request.method() == Method::OPTIONS
+    && request
+      .headers()
+      .contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+
+  let mut response = next.call(request).await?;
+  if preflight && response.status() == StatusCode::OK {
+    *response.response_mut().status_mut() = StatusCode::NO_CONTENT;
+  }
+
+  Ok(response)
+}
+
+/// Reject requests with a path longer than the configured maximum path length.
+async fn reject_long_paths(
+  request: ServiceRequest,
+  next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, Error> {
+  let max_path_length = request
+    .app_data::<MaxPathLength>()
+    .and_then(|max_path_length| max_path_length.0);
+
+  if max_path_length.is_some_and(|max_path_length| request.path().len() > max_path_length) {
+    return Ok(
+      request
+        .into_response(HttpResponse::new(StatusCode::URI_TOO_LONG))
+        .map_into_boxed_body(),
+    );
+  }
+
+  Ok(next.call(request).await?.map_into_boxed_body())
+}
+
+/// Add a `Deprecation` header to responses for requests with a path matching a deprecated route.
+async fn add_deprecation_header(
+  request: ServiceRequest,
+  next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+  let deprecated = request
+    .app_data::<DeprecatedRoutes>()
+    .is_some_and(|deprecated_routes| deprecated_routes.is_deprecated(request.path()));
+
+  let mut response = next.call(request).await?;
+  if deprecated {
+    response.headers_mut().insert(
+      HeaderName::from_static(DEPRECATION_HEADER),
+      HeaderValue::from_static("true"),
+    );
+  }
+
+  Ok(response)
+}
+
+/// Reject queries with a 503 Service Unavailable and a `Retry-After` header while maintenance
+/// mode is active.
+async fn reject_during_maintenance(
+  request: ServiceRequest,
+  next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse, Error> {
+  if let Some(maintenance) = request.app_data::<Maintenance>() {
+    if let Err(err) = check_maintenance(maintenance, request.path()).await {
+      let (json, status_code) = err.to_json_representation();
+      let response = HttpResponse::build(HttpVersionCompat::status_code_1_to_0_2(status_code))
+        .insert_header((RETRY_AFTER, maintenance.retry_after_header_value()))
+        .json(json);
+
+      return Ok(request.into_response(response).map_into_boxed_body());
+    }
+  }
+
+  Ok(next.call(request).await?.map_into_boxed_body())
+}
+
+/// Create the ticket server app, with the routes from [configure_server] wrapped in the
+/// middleware that the ticket server config enables.
+pub fn configure_app<H: HtsGet + Clone + Send + Sync + 'static>(
+  htsget: H,
+  service_info: ServiceInfo,
+  config: &TicketServerConfig,
+) -> App<
+  impl ServiceFactory<
+    ServiceRequest,
+    Config = (),
+    Response = ServiceResponse<impl MessageBody>,
+    Error = Error,
+    InitError = (),
+  >,
+> {
+  App::new()
+    .configure(|service_config: &mut web::ServiceConfig| {
+      configure_server(service_config, htsget, service_info, config);
+    })
+    .wrap(from_fn(reject_during_maintenance))
+    .wrap(from_fn(add_deprecation_header))
+    .wrap(from_fn(reject_long_paths))
+    .wrap(configure_cors(ticket_server_cors(config)))
+    .wrap(Condition::new(
+      config.cors().preflight_no_content(),
+      from_fn(preflight_no_content),
+    ))
+    .wrap(configure_security_headers(config.security_headers()))
+    .wrap(Condition::new(
+      !config.cors().on_errors(),
+      from_fn(remove_cors_headers_on_error),
+    ))
+    .wrap(TracingLogger::default())
+}
+
 /// Run the server using a http-actix `HttpServer`.
 #[instrument(skip_all)]
 pub fn run_server<H: HtsGet + Clone + Send + Sync + 'static>(
@@ -116,12 +352,7 @@ pub fn run_server<H: HtsGet + Clone + Send + Sync + 'static>(
 
   let config_copy = config.clone();
   let server = HttpServer::new(Box::new(move || {
-    App::new()
-      .configure(|service_config: &mut web::ServiceConfig| {
-        configure_server(service_config, htsget.clone(), service_info.clone());
-      })
-      .wrap(configure_cors(config_copy.cors().clone()))
-      .wrap(TracingLogger::default())
+    configure_app(htsget.clone(), service_info.clone(), &config_copy)
   }));
 
   let server = match config.into_tls() {
@@ -142,18 +373,27 @@ pub fn run_server<H: HtsGet + Clone + Send + Sync + 'static>(
 #[cfg(test)]
 mod tests {
   use std::path::Path;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
 
-  use actix_web::body::{BoxBody, EitherBody};
   use actix_web::dev::ServiceResponse;
-  use actix_web::{test, web, App};
+  use actix_web::http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN};
+  use actix_web::test;
   use async_trait::async_trait;
   use rustls::crypto::aws_lc_rs;
   use tempfile::TempDir;
 
   use htsget_axum::server::BindServer;
-  use htsget_config::types::JsonResponse;
+  use htsget_config::types::{
+    DeprecatedRoutes, HtsGetError, JsonResponse, Maintenance, Query, Response as HtsGetResponse,
+    RetryAfterFormat,
+  };
   use htsget_test::http::server::expected_url_path;
-  use htsget_test::http::{config_with_tls, default_test_config};
+  use htsget_test::http::{
+    config_with_age_header, config_with_server_timing, config_with_service_info,
+    config_with_ticket_server, config_with_tls, config_with_unreachable_backend,
+    default_test_config,
+  };
   use htsget_test::http::{cors, server};
   use htsget_test::http::{
     Header as TestHeader, Response as TestResponse, TestRequest, TestServer,
@@ -269,24 +509,15 @@ mod tests {
       }
     }
 
-    async fn get_response(
-      &self,
-      request: test::TestRequest,
-    ) -> ServiceResponse<EitherBody<BoxBody>> {
-      let app = test::init_service(
-        App::new()
-          .configure(|service_config: &mut web::ServiceConfig| {
-            configure_server(
-              service_config,
-              self.config.clone().owned_resolvers(),
-              self.config.service_info().clone(),
-            );
-          })
-          .wrap(configure_cors(self.config.ticket_server().cors().clone())),
-      )
+    async fn get_response(&self, request: test::TestRequest) -> ServiceResponse {
+      let app = test::init_service(configure_app(
+        self.config.clone().owned_resolvers(),
+        self.config.service_info().clone(),
+        self.config.ticket_server(),
+      ))
       .await;
 
-      request.send_request(&app).await
+      request.send_request(&app).await.map_into_boxed_body()
     }
   }
 
@@ -321,6 +552,211 @@ mod tests {
     server::test_service_info(&ActixTestServer::default()).await;
   }
 
+  #[actix_web::test]
+  async fn service_info_head() {
+    server::test_service_info_head(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn head_request() {
+    server::test_head(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn service_info_ga4gh_fields() {
+    server::test_service_info_ga4gh_fields(&ActixTestServer {
+      config: config_with_service_info(),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn service_info_link() {
+    server::test_service_info_link(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_service_info_link(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn max_path_length() {
+    server::test_max_path_length(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_max_path_length(Some(64))
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn elapsed_time_header() {
+    server::test_elapsed_time_header(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_elapsed_time_header(true)
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn no_elapsed_time_header() {
+    server::test_elapsed_time_header(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn age_header() {
+    server::test_age_header(&ActixTestServer {
+      config: config_with_age_header(),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn deprecation_header() {
+    server::test_deprecation_header(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_deprecated_routes(DeprecatedRoutes::new(&["^/reads/"]).unwrap())
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn relative_urls() {
+    server::test_relative_urls(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_relative_urls(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn canonical_format() {
+    server::test_canonical_format(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn reject_duplicate_query_keys() {
+    server::test_reject_duplicate_query_keys(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_reject_duplicate_query_keys(true)
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn content_length() {
+    server::test_content_length(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_content_length(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn server_timing() {
+    server::test_server_timing(&ActixTestServer {
+      config: config_with_server_timing(),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn maintenance() {
+    let tmp = TempDir::new().unwrap();
+    server::test_maintenance(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server
+          .with_maintenance(Maintenance::new(tmp.path().join("maintenance")).with_retry_after(120))
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn maintenance_http_date() {
+    let tmp = TempDir::new().unwrap();
+    server::test_maintenance(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_maintenance(
+          Maintenance::new(tmp.path().join("maintenance"))
+            .with_retry_after(120)
+            .with_retry_after_format(RetryAfterFormat::HttpDate),
+        )
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn root_response() {
+    server::test_root_response(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_root_response(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn crawler_responses() {
+    server::test_crawler_responses(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_crawler_responses(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn require_authorization() {
+    server::test_require_authorization(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_require_authorization(true)
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn max_distinct_references() {
+    server::test_max_distinct_references(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_max_distinct_references(Some(2))
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn security_headers() {
+    server::test_security_headers(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_security_headers(true)),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn ready() {
+    server::test_ready(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn ready_backend_down() {
+    server::test_ready_backend_down(&ActixTestServer {
+      config: config_with_unreachable_backend(),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn invalid_reference_name() {
+    server::test_invalid_reference_name(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn reject_get_body() {
+    server::test_reject_get_body(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_reject_get_body(true)),
+    })
+    .await;
+  }
+
   #[actix_web::test]
   async fn get_https_tickets() {
     let base_path = TempDir::new().unwrap();
@@ -369,4 +805,79 @@ mod tests {
   async fn cors_preflight_request() {
     cors::test_cors_preflight_request(&ActixTestServer::default()).await;
   }
+
+  /// A searcher which counts the number of searches.
+  #[derive(Debug, Clone, Default)]
+  struct CountingHtsGet {
+    searches: Arc<AtomicUsize>,
+  }
+
+  #[async_trait]
+  impl HtsGet for CountingHtsGet {
+    async fn search(self, _query: Query) -> htsget_config::types::Result<HtsGetResponse> {
+      self.searches.fetch_add(1, Ordering::SeqCst);
+      Err(HtsGetError::not_found("not found"))
+    }
+  }
+
+  #[actix_web::test]
+  async fn cors_preflight_no_content() {
+    let config = default_test_config();
+    let htsget = CountingHtsGet::default();
+    let ticket_server = config.ticket_server();
+    let app = test::init_service(configure_app(
+      htsget.clone(),
+      config.service_info().clone(),
+      &TicketServerConfig::new(
+        ticket_server.addr(),
+        None,
+        ticket_server.cors().clone().with_preflight_no_content(true),
+      ),
+    ))
+    .await;
+
+    let response = test::TestRequest::default()
+      .method(Method::OPTIONS)
+      .uri("/reads/id")
+      .insert_header((ORIGIN, "http://example.com"))
+      .insert_header((ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+      .send_request(&app)
+      .await;
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+      response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+      "http://example.com"
+    );
+    assert_eq!(htsget.searches.load(Ordering::SeqCst), 0);
+  }
+
+  #[actix_web::test]
+  async fn cors_expose_custom_headers() {
+    cors::test_cors_expose_custom_headers(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_elapsed_time_header(true)
+      }),
+    })
+    .await;
+  }
+
+  #[actix_web::test]
+  async fn cors_error_response() {
+    cors::test_cors_error_response(&ActixTestServer::default()).await;
+  }
+
+  #[actix_web::test]
+  async fn cors_error_response_without_headers() {
+    cors::test_cors_error_response_without_headers(&ActixTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        TicketServerConfig::new(
+          ticket_server.addr(),
+          ticket_server.tls().cloned(),
+          ticket_server.cors().clone().with_on_errors(false),
+        )
+      }),
+    })
+    .await;
+  }
 }
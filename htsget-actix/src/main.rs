@@ -21,25 +21,21 @@ async fn main() -> io::Result<()> {
 
     debug!(config = ?config, "config parsed");
 
-    if config.data_server().enabled() {
-      let local_server = data::join_handle(config.data_server().clone()).await?;
-
-      let ticket_server_config = config.ticket_server().clone();
-      let service_info = config.service_info().clone();
-
-      select! {
-        local_server = local_server => Ok(local_server??),
-        actix_server = run_server(
-          config.owned_resolvers(),
-          ticket_server_config,
-          service_info
-        )? => actix_server
+    let ticket_server_config = config.ticket_server().clone();
+    let service_info = config.service_info().clone();
+
+    match data::join_handle_if_enabled(config.data_server().clone()).await? {
+      Some(local_server) => {
+        select! {
+          local_server = local_server => Ok(local_server??),
+          actix_server = run_server(
+            config.owned_resolvers(),
+            ticket_server_config,
+            service_info
+          )? => actix_server
+        }
       }
-    } else {
-      let ticket_server_config = config.ticket_server().clone();
-      let service_info = config.service_info().clone();
-
-      run_server(config.owned_resolvers(), ticket_server_config, service_info)?.await
+      None => run_server(config.owned_resolvers(), ticket_server_config, service_info)?.await,
     }
   } else {
     Ok(())
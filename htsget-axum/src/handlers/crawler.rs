@@ -0,0 +1,15 @@
+use axum::response::IntoResponse;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+
+use htsget_http::ROBOTS_TXT;
+
+/// Responds to `/robots.txt` with a static response which disallows crawling.
+pub async fn robots_txt() -> impl IntoResponse {
+  ([(CONTENT_TYPE, "text/plain")], ROBOTS_TXT)
+}
+
+/// Responds to `/favicon.ico` with no content.
+pub async fn favicon() -> impl IntoResponse {
+  StatusCode::NO_CONTENT
+}
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
-use axum::extract::{Path, Query, State};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, RawQuery, State};
 use axum::response::IntoResponse;
 use http::HeaderMap;
 
-use htsget_http::{get, Endpoint};
+use htsget_http::{
+  audit_denied, check_authorization, check_duplicate_query_keys, check_get_body, get, Endpoint,
+};
 use htsget_search::HtsGet;
 
 use crate::handlers::extract_request;
@@ -15,23 +19,99 @@ use super::handle_response;
 /// GET request reads endpoint.
 pub async fn reads<H: HtsGet + Send + Sync + 'static>(
   request: Query<HashMap<String, String>>,
+  RawQuery(raw_query): RawQuery,
   path: Path<String>,
   headers: HeaderMap,
   State(app_state): State<AppState<H>>,
+  body: Bytes,
 ) -> impl IntoResponse {
   let request = extract_request(request, path, headers);
+  let service_info_link = app_state.service_info_link(&Endpoint::Reads);
+  let elapsed_time_header = app_state.elapsed_time_header;
+  let response_style = app_state.response_style;
+  let age_header = app_state.age_header;
+  let relative_urls = app_state.relative_urls;
+  let content_length = app_state.content_length;
 
-  handle_response(get(app_state.htsget, request, Endpoint::Reads).await)
+  let start = Instant::now();
+  let response = match check_get_body(app_state.reject_get_body, &body)
+    .and_then(|_| {
+      check_duplicate_query_keys(
+        app_state.reject_duplicate_query_keys,
+        raw_query.as_deref().unwrap_or_default(),
+      )
+    })
+    .and_then(|_| check_authorization(app_state.require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      get(
+        app_state.htsget,
+        request,
+        Endpoint::Reads,
+        &app_state.format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
+
+  handle_response(
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
+  )
 }
 
 /// GET request variants endpoint.
 pub async fn variants<H: HtsGet + Send + Sync + 'static>(
   request: Query<HashMap<String, String>>,
+  RawQuery(raw_query): RawQuery,
   path: Path<String>,
   headers: HeaderMap,
   State(app_state): State<AppState<H>>,
+  body: Bytes,
 ) -> impl IntoResponse {
   let request = extract_request(request, path, headers);
+  let service_info_link = app_state.service_info_link(&Endpoint::Variants);
+  let elapsed_time_header = app_state.elapsed_time_header;
+  let response_style = app_state.response_style;
+  let age_header = app_state.age_header;
+  let relative_urls = app_state.relative_urls;
+  let content_length = app_state.content_length;
+
+  let start = Instant::now();
+  let response = match check_get_body(app_state.reject_get_body, &body)
+    .and_then(|_| {
+      check_duplicate_query_keys(
+        app_state.reject_duplicate_query_keys,
+        raw_query.as_deref().unwrap_or_default(),
+      )
+    })
+    .and_then(|_| check_authorization(app_state.require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      get(
+        app_state.htsget,
+        request,
+        Endpoint::Variants,
+        &app_state.format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
 
-  handle_response(get(app_state.htsget, request, Endpoint::Variants).await)
+  handle_response(
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
+  )
 }
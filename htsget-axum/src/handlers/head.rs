@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use axum::extract::{Path, Query, RawQuery, State};
+use axum::response::{IntoResponse, Response};
+use http::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use http::{HeaderMap, HeaderValue, StatusCode};
+
+use htsget_config::types::Request;
+use htsget_http::{
+  audit_denied, check_authorization, check_duplicate_query_keys, head, Endpoint, FORMAT_HEADER,
+};
+use htsget_search::HtsGet;
+
+use crate::handlers::extract_request;
+use crate::server::AppState;
+
+/// HEAD request reads endpoint.
+pub async fn reads<H: HtsGet + Send + Sync + 'static>(
+  request: Query<HashMap<String, String>>,
+  RawQuery(raw_query): RawQuery,
+  path: Path<String>,
+  headers: HeaderMap,
+  State(app_state): State<AppState<H>>,
+) -> impl IntoResponse {
+  head_response(
+    extract_request(request, path, headers),
+    raw_query,
+    app_state,
+    Endpoint::Reads,
+  )
+  .await
+}
+
+/// HEAD request variants endpoint.
+pub async fn variants<H: HtsGet + Send + Sync + 'static>(
+  request: Query<HashMap<String, String>>,
+  RawQuery(raw_query): RawQuery,
+  path: Path<String>,
+  headers: HeaderMap,
+  State(app_state): State<AppState<H>>,
+) -> impl IntoResponse {
+  head_response(
+    extract_request(request, path, headers),
+    raw_query,
+    app_state,
+    Endpoint::Variants,
+  )
+  .await
+}
+
+/// Checks that the file of the request exists, without searching it. Responds with the
+/// `htsget-format` header set to the format of the file, or the status code of the error.
+async fn head_response<H: HtsGet + Send + Sync + 'static>(
+  request: Request,
+  raw_query: Option<String>,
+  app_state: AppState<H>,
+  endpoint: Endpoint,
+) -> Response {
+  let response = match check_duplicate_query_keys(
+    app_state.reject_duplicate_query_keys,
+    raw_query.as_deref().unwrap_or_default(),
+  )
+  .and_then(|_| check_authorization(app_state.require_authorization, request.headers()))
+  {
+    Ok(_) => {
+      head(
+        app_state.htsget,
+        request,
+        endpoint,
+        &app_state.format_aliases,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
+
+  let mut headers = HeaderMap::new();
+  match response {
+    Ok(format) => {
+      headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+      if let Ok(format) = HeaderValue::try_from(format.to_string()) {
+        headers.insert(FORMAT_HEADER, format);
+      }
+
+      (StatusCode::OK, headers).into_response()
+    }
+    Err(error) => {
+      let (_, status_code) = error.to_json_representation();
+      if status_code == StatusCode::UNAUTHORIZED {
+        headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+      }
+
+      (status_code, headers).into_response()
+    }
+  }
+}
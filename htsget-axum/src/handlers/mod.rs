@@ -1,29 +1,113 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
+use axum::body::HttpBody;
 use axum::extract::{Path, Query};
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 use axum_extra::response::ErasedJson;
-use http::{HeaderMap, StatusCode};
+use http::header::{AGE, CONTENT_LENGTH, ETAG, LINK, WWW_AUTHENTICATE};
+use http::{HeaderMap, HeaderValue, StatusCode};
 
-use htsget_config::types::{JsonResponse, Request};
+use htsget_config::types::{JsonResponse, Request, ResponseStyle, StrictJsonResponse};
+use htsget_http::{ELAPSED_MS_HEADER, SERVER_TIMING_HEADER};
 
+pub use crate::handlers::crawler::{favicon, robots_txt};
+pub use crate::handlers::ready::ready;
 pub use crate::handlers::service_info::{
-  get_service_info_json, reads_service_info, variants_service_info,
+  get_service_info_json, reads_service_info, root, variants_service_info,
 };
 
+pub mod crawler;
 pub mod get;
+pub mod head;
 pub mod post;
+pub mod ready;
 pub mod service_info;
 
-/// Handles a response, converting errors to json and using the proper HTTP status code
-fn handle_response(response: htsget_http::Result<JsonResponse>) -> (StatusCode, impl IntoResponse) {
-  match response {
+/// Handles a response, converting errors to json and using the proper HTTP status code. The
+/// service-info link is set as the `Link` header, and the elapsed search time is set as the
+/// `X-Htsget-Elapsed-Ms` header, if they are present. Unauthorized responses include a
+/// `WWW-Authenticate: Bearer` header. Successful responses are serialized using the response style,
+/// and include the `Age` header if enabled and the response was served from a response cache, and
+/// the `Server-Timing` header if server timings were recorded. The `Content-Length` header is set
+/// explicitly if enabled.
+fn handle_response(
+  response: htsget_http::Result<(JsonResponse, Option<Duration>)>,
+  service_info_link: Option<String>,
+  elapsed: Option<Duration>,
+  response_style: ResponseStyle,
+  age_header: bool,
+  relative_urls: bool,
+  content_length: bool,
+) -> Response {
+  let mut headers = HeaderMap::new();
+  if let Some(link) = service_info_link.and_then(|link| HeaderValue::try_from(link).ok()) {
+    headers.insert(LINK, link);
+  }
+  if let Some(elapsed) =
+    elapsed.and_then(|elapsed| HeaderValue::try_from(elapsed.as_millis().to_string()).ok())
+  {
+    headers.insert(ELAPSED_MS_HEADER, elapsed);
+  }
+
+  let response = match response {
     Err(error) => {
       let (json, status_code) = error.to_json_representation();
-      (status_code, ErasedJson::pretty(json))
+      if status_code == StatusCode::UNAUTHORIZED {
+        headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+      }
+
+      (status_code, headers, ErasedJson::pretty(json))
+    }
+    Ok((mut json, age)) => {
+      if relative_urls {
+        json.htsget = json.htsget.with_relative_urls();
+      }
+      if let Some(age) = age.filter(|_| age_header) {
+        headers.insert(AGE, HeaderValue::from(age.as_secs()));
+      }
+      if let Some(server_timing) = json
+        .htsget
+        .server_timing
+        .and_then(|server_timing| HeaderValue::try_from(server_timing.header_value()).ok())
+      {
+        headers.insert(SERVER_TIMING_HEADER, server_timing);
+      }
+      if let Some(etag) = json
+        .htsget
+        .etag_header_value()
+        .and_then(|etag| HeaderValue::try_from(etag).ok())
+      {
+        headers.insert(ETAG, etag);
+      }
+
+      let json = match response_style {
+        ResponseStyle::Pretty => ErasedJson::pretty(json),
+        ResponseStyle::Compact => ErasedJson::new(json),
+        ResponseStyle::Strict => ErasedJson::pretty(StrictJsonResponse(&json)),
+      };
+
+      (StatusCode::OK, headers, json)
     }
-    Ok(json) => (StatusCode::OK, ErasedJson::pretty(json)),
   }
+  .into_response();
+
+  if content_length {
+    with_content_length(response)
+  } else {
+    response
+  }
+}
+
+/// Set the `Content-Length` header of the response from the exact size of the body, if it is known.
+fn with_content_length(mut response: Response) -> Response {
+  if let Some(size) = response.body().size_hint().exact() {
+    response
+      .headers_mut()
+      .insert(CONTENT_LENGTH, HeaderValue::from(size));
+  }
+
+  response
 }
 
 fn extract_request(
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
 use axum::Json;
 use http::HeaderMap;
 
-use htsget_http::{post, Endpoint, PostRequest};
+use htsget_http::{audit_denied, check_authorization, post, Endpoint, PostRequest};
 use htsget_search::HtsGet;
 
 use crate::handlers::extract_request;
@@ -22,8 +23,39 @@ pub async fn reads<H: HtsGet + Clone + Send + Sync + 'static>(
   Json(body): Json<PostRequest>,
 ) -> impl IntoResponse {
   let request = extract_request(request, path, headers);
+  let service_info_link = app_state.service_info_link(&Endpoint::Reads);
+  let elapsed_time_header = app_state.elapsed_time_header;
+  let response_style = app_state.response_style;
+  let age_header = app_state.age_header;
+  let relative_urls = app_state.relative_urls;
+  let content_length = app_state.content_length;
 
-  handle_response(post(app_state.htsget, body, request, Endpoint::Reads).await)
+  let start = Instant::now();
+  let response = match check_authorization(app_state.require_authorization, request.headers()) {
+    Ok(_) => {
+      post(
+        app_state.htsget,
+        body,
+        request,
+        Endpoint::Reads,
+        &app_state.format_aliases,
+        app_state.max_distinct_references,
+        app_state.query_concurrency,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
+
+  handle_response(
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
+  )
 }
 
 /// POST request variants endpoint.
@@ -35,6 +67,37 @@ pub async fn variants<H: HtsGet + Clone + Send + Sync + 'static>(
   Json(body): Json<PostRequest>,
 ) -> impl IntoResponse {
   let request = extract_request(request, path, headers);
+  let service_info_link = app_state.service_info_link(&Endpoint::Variants);
+  let elapsed_time_header = app_state.elapsed_time_header;
+  let response_style = app_state.response_style;
+  let age_header = app_state.age_header;
+  let relative_urls = app_state.relative_urls;
+  let content_length = app_state.content_length;
+
+  let start = Instant::now();
+  let response = match check_authorization(app_state.require_authorization, request.headers()) {
+    Ok(_) => {
+      post(
+        app_state.htsget,
+        body,
+        request,
+        Endpoint::Variants,
+        &app_state.format_aliases,
+        app_state.max_distinct_references,
+        app_state.query_concurrency,
+      )
+      .await
+    }
+    Err(err) => Err(audit_denied(&app_state.audit_log, &request, err)),
+  };
 
-  handle_response(post(app_state.htsget, body, request, Endpoint::Variants).await)
+  handle_response(
+    response,
+    service_info_link,
+    elapsed_time_header.then(|| start.elapsed()),
+    response_style,
+    age_header,
+    relative_urls,
+    content_length,
+  )
 }
@@ -0,0 +1,23 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum_extra::response::ErasedJson;
+use http::StatusCode;
+
+use htsget_http::get_readiness;
+use htsget_search::HtsGet;
+
+use crate::server::AppState;
+
+/// Probes each storage backend, returning a 503 with the per-backend status if any are unreachable.
+pub async fn ready<H: HtsGet + Send + Sync + 'static>(
+  State(app_state): State<AppState<H>>,
+) -> impl IntoResponse {
+  let readiness = get_readiness(&app_state.htsget).await;
+  let status_code = if readiness.ready {
+    StatusCode::OK
+  } else {
+    StatusCode::SERVICE_UNAVAILABLE
+  };
+
+  (status_code, ErasedJson::pretty(readiness))
+}
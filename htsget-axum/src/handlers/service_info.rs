@@ -2,6 +2,7 @@ use axum::extract::State;
 use axum::response::IntoResponse;
 use axum_extra::response::ErasedJson;
 
+use htsget_http::get_root_json;
 use htsget_http::get_service_info_json as get_base_service_info_json;
 use htsget_http::Endpoint;
 use htsget_search::HtsGet;
@@ -33,3 +34,10 @@ pub async fn variants_service_info<H: HtsGet + Send + Sync + 'static>(
 ) -> impl IntoResponse {
   get_service_info_json(app_state, Endpoint::Variants)
 }
+
+/// Gets the JSON to return for the root path
+pub async fn root<H: HtsGet + Send + Sync + 'static>(
+  State(app_state): State<AppState<H>>,
+) -> impl IntoResponse {
+  ErasedJson::pretty(get_root_json(&app_state.service_info))
+}
@@ -22,16 +22,16 @@ async fn main() -> io::Result<()> {
 
     debug!(config = ?config, "config parsed");
 
-    if config.data_server().enabled() {
-      let local_server = data::join_handle(config.data_server().clone()).await?;
-      let ticket_server = ticket::join_handle(config).await?;
+    match data::join_handle_if_enabled(config.data_server().clone()).await? {
+      Some(local_server) => {
+        let ticket_server = ticket::join_handle(config).await?;
 
-      select! {
-        local_server = local_server => Ok(local_server??),
-        axum_server = ticket_server => Ok(axum_server??)
+        select! {
+          local_server = local_server => Ok(local_server??),
+          axum_server = ticket_server => Ok(axum_server??)
+        }
       }
-    } else {
-      Ok(ticket::join_handle(config).await?.await??)
+      None => Ok(ticket::join_handle(config).await?.await??),
     }
   } else {
     Ok(())
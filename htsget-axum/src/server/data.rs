@@ -2,13 +2,28 @@
 //!
 
 use crate::error::Result;
-use crate::server::{configure_cors, BindServer, Server};
+use crate::server::{
+  add_security_headers, configure_cors, preflight_no_content, remove_cors_headers_on_error,
+  shutdown_signal, BindServer, Server,
+};
+use axum::extract::{Request, State};
+use axum::middleware::{from_fn, from_fn_with_state, map_response, Next};
+use axum::response::{IntoResponse, Response};
 use axum::Router;
 use htsget_config::config::cors::CorsConfig;
 use htsget_config::config::DataServerConfig;
+use htsget_config::storage::local::UrlSigningKey;
+use htsget_config::storage::uri_decode;
+use htsget_config::types::{AuditLog, Request as HtsGetRequest};
+use htsget_http::{audit_denied, HtsGetError};
+use http::StatusCode;
+use std::collections::HashMap;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::Path;
+use std::time::Duration;
 use tokio::task::JoinHandle;
+use tower::util::option_layer;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 use tracing::info;
@@ -19,6 +34,10 @@ pub struct DataServer {
   server: Server,
   serve_at: String,
   cors: CorsConfig,
+  signing_key: Option<UrlSigningKey>,
+  security_headers: bool,
+  drain_timeout: Option<Duration>,
+  audit_log: AuditLog,
 }
 
 impl DataServer {
@@ -28,22 +47,107 @@ impl DataServer {
       server,
       serve_at,
       cors,
+      signing_key: None,
+      security_headers: false,
+      drain_timeout: None,
+      audit_log: Default::default(),
     }
   }
 
+  /// Set the key used to verify signed urls. If set, requests without a valid and unexpired
+  /// signature are rejected.
+  pub fn with_signing_key(mut self, signing_key: Option<UrlSigningKey>) -> Self {
+    self.signing_key = signing_key;
+    self
+  }
+
+  /// Set whether security headers, such as `X-Content-Type-Options: nosniff`, are added to responses.
+  pub fn with_security_headers(mut self, security_headers: bool) -> Self {
+    self.security_headers = security_headers;
+    self
+  }
+
+  /// Set the time to wait for in-flight requests to complete after a shutdown signal.
+  pub fn with_drain_timeout(mut self, drain_timeout: Option<Duration>) -> Self {
+    self.drain_timeout = drain_timeout;
+    self
+  }
+
+  /// Set the audit logging of requests denied because of an invalid url signature.
+  pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+    self.audit_log = audit_log;
+    self
+  }
+
   /// Run the data server, using the provided path, key and certificate.
   pub async fn serve<P: AsRef<Path>>(self, path: P) -> Result<()> {
     self
       .server
-      .serve(Self::router(self.cors, &self.serve_at, path))
+      .serve(Self::router(
+        self.cors,
+        &self.serve_at,
+        path,
+        self.signing_key,
+        self.security_headers,
+        self.audit_log,
+      ))
+      .await
+  }
+
+  /// Run the data server until the shutdown signal completes, waiting for in-flight requests
+  /// to complete within the drain timeout.
+  pub async fn serve_with_shutdown<P, F>(self, path: P, signal: F) -> Result<()>
+  where
+    P: AsRef<Path>,
+    F: Future<Output = ()> + Send + 'static,
+  {
+    self
+      .server
+      .serve_with_shutdown(
+        Self::router(
+          self.cors,
+          &self.serve_at,
+          path,
+          self.signing_key,
+          self.security_headers,
+          self.audit_log,
+        ),
+        signal,
+        self.drain_timeout.unwrap_or_default(),
+      )
       .await
   }
 
   /// Create the router for the data server.
-  pub fn router<P: AsRef<Path>>(cors: CorsConfig, serve_at: &str, path: P) -> Router {
-    Router::new()
-      .nest_service(serve_at, ServeDir::new(path))
+  pub fn router<P: AsRef<Path>>(
+    cors: CorsConfig,
+    serve_at: &str,
+    path: P,
+    signing_key: Option<UrlSigningKey>,
+    security_headers: bool,
+    audit_log: AuditLog,
+  ) -> Router {
+    let router = Router::new().nest_service(serve_at, ServeDir::new(path));
+    let router = match signing_key {
+      Some(signing_key) => router.layer(from_fn_with_state(
+        (signing_key, audit_log),
+        verify_signature,
+      )),
+      None => router,
+    };
+
+    let remove_cors_on_errors =
+      (!cors.on_errors()).then(|| map_response(remove_cors_headers_on_error));
+    let preflight_no_content = cors
+      .preflight_no_content()
+      .then(|| from_fn(preflight_no_content));
+    let security_headers = security_headers.then(|| map_response(add_security_headers));
+
+    router
       .layer(configure_cors(cors))
+      .layer(option_layer(preflight_no_content))
+      .layer(option_layer(remove_cors_on_errors))
+      .layer(option_layer(security_headers))
       .layer(TraceLayer::new_for_http())
   }
 
@@ -67,24 +171,76 @@ impl From<DataServerConfig> for BindServer {
   }
 }
 
+/// Reject requests which do not have a valid and unexpired url signature. Urls are signed over the
+/// decoded path, so the request path is percent-decoded before it is verified. Rejected requests
+/// are audit logged if enabled.
+async fn verify_signature(
+  State((signing_key, audit_log)): State<(UrlSigningKey, AuditLog)>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let verified = uri_decode(request.uri().path())
+    .is_some_and(|path| signing_key.verify(&path, request.uri().query()));
+  if verified {
+    next.run(request).await
+  } else {
+    let denied = HtsGetRequest::new(
+      request.uri().path().to_string(),
+      HashMap::new(),
+      request.headers().clone(),
+    );
+    audit_denied(
+      &audit_log,
+      &denied,
+      HtsGetError::PermissionDenied("missing, invalid or expired url signature".to_string()),
+    );
+
+    StatusCode::FORBIDDEN.into_response()
+  }
+}
+
 /// Spawn a task to run the data server.
 pub async fn join_handle(config: DataServerConfig) -> Result<JoinHandle<Result<()>>> {
   let serve_at = config.serve_at().to_string();
   let local_path = config.local_path().to_path_buf();
   let data_server = BindServer::from(config.clone())
     .bind_data_server(serve_at)
-    .await?;
+    .await?
+    .with_signing_key(config.signing_key().cloned())
+    .with_security_headers(config.security_headers())
+    .with_drain_timeout(config.drain_timeout().map(Duration::from_secs))
+    .with_audit_log(config.audit_log().clone());
 
   info!(address = ?data_server.local_addr()?, "data server address bound to");
 
-  Ok(tokio::spawn(
-    async move { data_server.serve(&local_path).await },
-  ))
+  Ok(tokio::spawn(async move {
+    if config.drain_timeout().is_some() {
+      data_server
+        .serve_with_shutdown(&local_path, shutdown_signal())
+        .await
+    } else {
+      data_server.serve(&local_path).await
+    }
+  }))
+}
+
+/// Spawn a task to run the data server if it is enabled. Returns `None` without binding an address
+/// if the data server is disabled.
+pub async fn join_handle_if_enabled(
+  config: DataServerConfig,
+) -> Result<Option<JoinHandle<Result<()>>>> {
+  if !config.enabled() {
+    info!("data server is disabled");
+    return Ok(None);
+  }
+
+  join_handle(config).await.map(Some)
 }
 
 #[cfg(test)]
 mod tests {
   use std::str::FromStr;
+  use std::time::Duration;
 
   use async_trait::async_trait;
   use http::header::HeaderName;
@@ -94,6 +250,8 @@ mod tests {
   use tempfile::{tempdir, TempDir};
   use tokio::fs::{create_dir, File};
   use tokio::io::AsyncWriteExt;
+  use tokio::sync::oneshot;
+  use tokio::time::timeout;
 
   use htsget_config::config::Config;
   use htsget_config::tls::TlsServerConfig;
@@ -201,6 +359,25 @@ mod tests {
     }
   }
 
+  #[tokio::test]
+  async fn join_handle_disabled() {
+    let config = default_test_config().data_server().clone();
+    let config = DataServerConfig::new(
+      false,
+      config.addr(),
+      config.local_path().to_path_buf(),
+      config.serve_at().to_string(),
+      None,
+      config.cors().clone(),
+    );
+
+    assert!(join_handle_if_enabled(config.clone())
+      .await
+      .unwrap()
+      .is_none());
+    assert!(tokio::net::TcpListener::bind(config.addr()).await.is_ok());
+  }
+
   #[tokio::test]
   async fn test_http_server() {
     let (_, base_path) = create_local_test_files().await;
@@ -277,7 +454,155 @@ mod tests {
     )
   }
 
+  #[tokio::test]
+  async fn signed_url_valid() {
+    let (_, base_path) = create_local_test_files().await;
+    let signing_key = UrlSigningKey::new("secret");
+
+    let port = start_signed_data_server(
+      None,
+      Some(signing_key.clone()),
+      base_path.path().to_path_buf(),
+    )
+    .await;
+
+    let query = signing_key.signed_query("/data/key1", Duration::from_secs(60));
+    let response = get_data(&format!("http://localhost:{port}/data/key1?{query}")).await;
+
+    assert!(response.is_success());
+    assert_eq!(response.body, b"value1");
+  }
+
+  #[tokio::test]
+  async fn signed_url_valid_with_encoded_key() {
+    let (_, base_path) = create_local_test_files().await;
+    File::create(base_path.path().join("key é 1"))
+      .await
+      .unwrap()
+      .write_all(b"value")
+      .await
+      .unwrap();
+    let signing_key = UrlSigningKey::new("secret");
+
+    let port = start_signed_data_server(
+      None,
+      Some(signing_key.clone()),
+      base_path.path().to_path_buf(),
+    )
+    .await;
+
+    let query = signing_key.signed_query("/data/key é 1", Duration::from_secs(60));
+    let response = get_data(&format!(
+      "http://localhost:{port}/data/key%20%C3%A9%201?{query}"
+    ))
+    .await;
+
+    assert!(response.is_success());
+    assert_eq!(response.body, b"value");
+  }
+
+  #[tokio::test]
+  async fn signed_url_expired() {
+    let (_, base_path) = create_local_test_files().await;
+    let signing_key = UrlSigningKey::new("secret");
+
+    let port = start_signed_data_server(
+      None,
+      Some(signing_key.clone()),
+      base_path.path().to_path_buf(),
+    )
+    .await;
+
+    let signature = signing_key.sign("/data/key1", 1);
+    let response = get_data(&format!(
+      "http://localhost:{port}/data/key1?expires=1&signature={signature}"
+    ))
+    .await;
+
+    assert_eq!(response.status, 403);
+  }
+
+  #[tokio::test]
+  async fn signed_url_missing() {
+    let (_, base_path) = create_local_test_files().await;
+
+    let port = start_signed_data_server(
+      None,
+      Some(UrlSigningKey::new("secret")),
+      base_path.path().to_path_buf(),
+    )
+    .await;
+
+    let response = get_data(&format!("http://localhost:{port}/data/key1")).await;
+
+    assert_eq!(response.status, 403);
+  }
+
+  #[tokio::test]
+  async fn in_flight_download_completes_after_shutdown() {
+    let base_path = TempDir::new().unwrap();
+    let value = vec![b'a'; 32 * 1024 * 1024];
+    File::create(base_path.path().join("key1"))
+      .await
+      .unwrap()
+      .write_all(&value)
+      .await
+      .unwrap();
+
+    let addr = SocketAddr::from_str("127.0.0.1:0").unwrap();
+    let server = Server::bind_addr(addr, None).await.unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let data_server = DataServer::new(server, "/data".to_string(), default_cors_config())
+      .with_drain_timeout(Some(Duration::from_secs(10)));
+    let path = base_path.path().to_path_buf();
+    let handle = tokio::spawn(async move {
+      data_server
+        .serve_with_shutdown(path, async move {
+          let _ = shutdown_rx.await;
+        })
+        .await
+    });
+
+    let response = Client::new()
+      .get(format!("http://localhost:{port}/data/key1"))
+      .send()
+      .await
+      .unwrap();
+    assert!(response.status().is_success());
+
+    shutdown_tx.send(()).unwrap();
+
+    let body = response.bytes().await.unwrap();
+    assert_eq!(body.len(), value.len());
+
+    timeout(Duration::from_secs(10), handle)
+      .await
+      .unwrap()
+      .unwrap()
+      .unwrap();
+  }
+
+  async fn get_data(uri: &str) -> TestResponse {
+    let test_server = DataTestServer::default();
+    let request = test_server.request().method(Method::GET).uri(uri);
+
+    test_server.test_server(request, "".to_string()).await
+  }
+
   async fn start_data_server<P>(cert_key_pair: Option<TlsServerConfig>, path: P) -> u16
+  where
+    P: AsRef<Path> + Send + 'static,
+  {
+    start_signed_data_server(cert_key_pair, None, path).await
+  }
+
+  async fn start_signed_data_server<P>(
+    cert_key_pair: Option<TlsServerConfig>,
+    signing_key: Option<UrlSigningKey>,
+    path: P,
+  ) -> u16
   where
     P: AsRef<Path> + Send + 'static,
   {
@@ -285,7 +610,8 @@ mod tests {
     let server = Server::bind_addr(addr, cert_key_pair).await.unwrap();
     let port = server.local_addr().unwrap().port();
 
-    let data_server = DataServer::new(server, "/data".to_string(), default_cors_config());
+    let data_server = DataServer::new(server, "/data".to_string(), default_cors_config())
+      .with_signing_key(signing_key);
     tokio::spawn(async move { data_server.serve(path).await.unwrap() });
 
     port
@@ -4,28 +4,41 @@
 pub mod data;
 pub mod ticket;
 
+use std::future::{self, Future, IntoFuture};
 use std::net::SocketAddr;
+use std::pin::pin;
 use std::sync::Arc;
 use std::time::Duration;
 
-use axum::extract::Request;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use axum::Router;
-use http::HeaderValue;
+use axum_extra::response::ErasedJson;
+use http::header::{ACCESS_CONTROL_REQUEST_METHOD, RETRY_AFTER, X_CONTENT_TYPE_OPTIONS};
+use http::{HeaderName, HeaderValue, Method, StatusCode};
 use hyper::body::Incoming;
 use hyper::service::service_fn;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder;
 use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout};
+use tokio::{select, signal};
 use tokio_rustls::TlsAcceptor;
 use tower::Service;
 use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, ExposeHeaders};
 use tracing::trace;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 use htsget_config::config::cors::CorsConfig;
-use htsget_config::config::ServiceInfo;
+use htsget_config::config::{ServiceInfo, TicketServerConfig};
 use htsget_config::tls::TlsServerConfig;
-use htsget_config::types::Scheme;
+use htsget_config::types::{
+  AuditLog, DeprecatedRoutes, FormatAliases, Maintenance, ResponseStyle, Scheme,
+};
+use htsget_http::{check_maintenance, Endpoint, DEPRECATION_HEADER};
 use htsget_search::HtsGet;
 
 use crate::error::Error::ServerError;
@@ -38,6 +51,19 @@ use crate::server::ticket::TicketServer;
 pub struct AppState<H: HtsGet> {
   pub(crate) htsget: H,
   pub(crate) service_info: ServiceInfo,
+  pub(crate) service_info_link: bool,
+  pub(crate) reject_get_body: bool,
+  pub(crate) format_aliases: FormatAliases,
+  pub(crate) max_distinct_references: Option<usize>,
+  pub(crate) query_concurrency: Option<usize>,
+  pub(crate) elapsed_time_header: bool,
+  pub(crate) require_authorization: bool,
+  pub(crate) response_style: ResponseStyle,
+  pub(crate) age_header: bool,
+  pub(crate) relative_urls: bool,
+  pub(crate) content_length: bool,
+  pub(crate) reject_duplicate_query_keys: bool,
+  pub(crate) audit_log: AuditLog,
 }
 
 impl<H: HtsGet> AppState<H> {
@@ -46,8 +72,230 @@ impl<H: HtsGet> AppState<H> {
     Self {
       htsget,
       service_info,
+      service_info_link: false,
+      reject_get_body: false,
+      format_aliases: Default::default(),
+      max_distinct_references: None,
+      query_concurrency: None,
+      elapsed_time_header: false,
+      require_authorization: false,
+      response_style: Default::default(),
+      age_header: false,
+      relative_urls: false,
+      content_length: false,
+      reject_duplicate_query_keys: false,
+      audit_log: Default::default(),
     }
   }
+
+  /// Set whether ticket responses include a `Link` header pointing to the service-info endpoint.
+  pub fn with_service_info_link(mut self, service_info_link: bool) -> Self {
+    self.service_info_link = service_info_link;
+    self
+  }
+
+  /// Set whether GET requests which have a body are rejected.
+  pub fn with_reject_get_body(mut self, reject_get_body: bool) -> Self {
+    self.reject_get_body = reject_get_body;
+    self
+  }
+
+  /// Set the format aliases which are mapped to their canonical format.
+  pub fn with_format_aliases(mut self, format_aliases: FormatAliases) -> Self {
+    self.format_aliases = format_aliases;
+    self
+  }
+
+  /// Set the maximum number of distinct reference names across the regions of a POST request.
+  pub fn with_max_distinct_references(mut self, max_distinct_references: Option<usize>) -> Self {
+    self.max_distinct_references = max_distinct_references;
+    self
+  }
+
+  /// Set the maximum number of regions of a POST request which are searched concurrently.
+  pub fn with_query_concurrency(mut self, query_concurrency: Option<usize>) -> Self {
+    self.query_concurrency = query_concurrency;
+    self
+  }
+
+  /// Set whether ticket responses include an `X-Htsget-Elapsed-Ms` header with the search duration.
+  pub fn with_elapsed_time_header(mut self, elapsed_time_header: bool) -> Self {
+    self.elapsed_time_header = elapsed_time_header;
+    self
+  }
+
+  /// Set whether ticket requests without an `Authorization` header are rejected.
+  pub fn with_require_authorization(mut self, require_authorization: bool) -> Self {
+    self.require_authorization = require_authorization;
+    self
+  }
+
+  /// Set the style used to serialize ticket responses.
+  pub fn with_response_style(mut self, response_style: ResponseStyle) -> Self {
+    self.response_style = response_style;
+    self
+  }
+
+  /// Set whether to set the `Age` header on ticket responses served from a response cache.
+  pub fn with_age_header(mut self, age_header: bool) -> Self {
+    self.age_header = age_header;
+    self
+  }
+
+  /// Set whether ticket urls are relative to the origin of the request.
+  pub fn with_relative_urls(mut self, relative_urls: bool) -> Self {
+    self.relative_urls = relative_urls;
+    self
+  }
+
+  /// Set whether ticket responses set an explicit `Content-Length` header.
+  pub fn with_content_length(mut self, content_length: bool) -> Self {
+    self.content_length = content_length;
+    self
+  }
+
+  /// Set whether GET requests which repeat a scalar query parameter are rejected.
+  pub fn with_reject_duplicate_query_keys(mut self, reject_duplicate_query_keys: bool) -> Self {
+    self.reject_duplicate_query_keys = reject_duplicate_query_keys;
+    self
+  }
+
+  /// Set the audit logging of denied requests.
+  pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+    self.audit_log = audit_log;
+    self
+  }
+
+  /// Get the service-info link for the endpoint if it should be included in ticket responses.
+  pub(crate) fn service_info_link(&self, endpoint: &Endpoint) -> Option<String> {
+    self.service_info_link.then(|| endpoint.service_info_link())
+  }
+}
+
+/// Remove any cors headers from client and server error responses.
+pub(crate) async fn remove_cors_headers_on_error(mut response: Response) -> Response {
+  if response.status().is_client_error() || response.status().is_server_error() {
+    let headers = response.headers_mut();
+    let cors_headers: Vec<_> = headers
+      .keys()
+      .filter(|name| name.as_str().starts_with("access-control-"))
+      .cloned()
+      .collect();
+
+    for name in cors_headers {
+      headers.remove(name);
+    }
+  }
+
+  response
+}
+
+/// Respond to cors preflight requests with 204 No Content rather than 200 OK. Preflight requests
+/// are answered by the cors layer, so they never reach the handlers.
+pub(crate) async fn preflight_no_content(request: Request, next: Next) -> Response {
+  let preflight = request.method() == Method::OPTIONS
+    && request
+      .headers()
+      .contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+
+  let mut response = next.run(request).await;
+  if preflight && response.status() == StatusCode::OK {
+    *response.status_mut() = StatusCode::NO_CONTENT;
+  }
+
+  response
+}
+
+/// Add security headers, such as `X-Content-Type-Options: nosniff`, to the response.
+pub(crate) async fn add_security_headers(mut response: Response) -> Response {
+  response
+    .headers_mut()
+    .insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+
+  response
+}
+
+/// Reject requests with a path longer than the maximum path length.
+pub(crate) async fn reject_long_paths(
+  State(max_path_length): State<usize>,
+  request: Request,
+) -> std::result::Result<Request, StatusCode> {
+  if request.uri().path().len() > max_path_length {
+    return Err(StatusCode::URI_TOO_LONG);
+  }
+
+  Ok(request)
+}
+
+/// Add a `Deprecation` header to responses for requests with a path matching a deprecated route.
+pub(crate) async fn add_deprecation_header(
+  State(deprecated_routes): State<DeprecatedRoutes>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let deprecated = deprecated_routes.is_deprecated(request.uri().path());
+  let mut response = next.run(request).await;
+
+  if deprecated {
+    response.headers_mut().insert(
+      HeaderName::from_static(DEPRECATION_HEADER),
+      HeaderValue::from_static("true"),
+    );
+  }
+
+  response
+}
+
+/// Reject queries with a 503 Service Unavailable and a `Retry-After` header while maintenance
+/// mode is active.
+pub(crate) async fn reject_during_maintenance(
+  State(maintenance): State<Maintenance>,
+  request: Request,
+  next: Next,
+) -> Response {
+  if let Err(err) = check_maintenance(&maintenance, request.uri().path()).await {
+    let (json, status_code) = err.to_json_representation();
+    return (
+      status_code,
+      [(RETRY_AFTER, maintenance.retry_after_header_value())],
+      ErasedJson::pretty(json),
+    )
+      .into_response();
+  }
+
+  next.run(request).await
+}
+
+/// Wait for a SIGINT or SIGTERM signal to shut down the server.
+pub async fn shutdown_signal() {
+  let ctrl_c = async {
+    if let Err(err) = signal::ctrl_c().await {
+      error!("failed to listen for SIGINT: {}", err);
+      future::pending::<()>().await;
+    }
+  };
+
+  #[cfg(unix)]
+  let terminate = async {
+    match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+      Ok(mut terminate) => {
+        terminate.recv().await;
+      }
+      Err(err) => {
+        error!("failed to listen for SIGTERM: {}", err);
+        future::pending::<()>().await;
+      }
+    }
+  };
+  #[cfg(not(unix))]
+  let terminate = future::pending::<()>();
+
+  select! {
+    _ = ctrl_c => {},
+    _ = terminate => {},
+  }
+
+  info!("shutdown signal received");
 }
 
 /// Configure cors, settings allowed methods, max age, allowed origins, and if credentials
@@ -161,18 +409,14 @@ impl BindServer {
     &mut self,
     htsget: H,
     service_info: ServiceInfo,
+    config: TicketServerConfig,
   ) -> Result<TicketServer<H>>
   where
     H: HtsGet + Clone + Send + Sync + 'static,
   {
     let server = self.bind_server().await?;
 
-    Ok(TicketServer::new(
-      server,
-      htsget,
-      service_info,
-      self.cors.clone(),
-    ))
+    Ok(TicketServer::new(server, htsget, service_info, config))
   }
 
   /// Get the [SocketAddr] of this formatter.
@@ -204,21 +448,58 @@ impl Server {
 
   /// Run the actual server, using the router, key and certificate.
   pub async fn serve(self, app: Router) -> Result<()> {
+    self
+      .serve_with_shutdown(app, future::pending(), Duration::ZERO)
+      .await
+  }
+
+  /// Run the actual server until the shutdown signal completes. In-flight requests are given
+  /// up to the drain timeout to complete before the server exits.
+  pub async fn serve_with_shutdown<F>(
+    self,
+    app: Router,
+    signal: F,
+    drain_timeout: Duration,
+  ) -> Result<()>
+  where
+    F: Future<Output = ()> + Send + 'static,
+  {
     match self.cert_key_pair {
-      None => axum::serve(self.listener, app)
-        .await
-        .map_err(|err| ServerError(err.to_string())),
+      None => {
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+        let server = axum::serve(self.listener, app).with_graceful_shutdown(async move {
+          signal.await;
+          let _ = shutdown_tx.send(true);
+        });
+
+        select! {
+          result = server.into_future() => result.map_err(|err| ServerError(err.to_string())),
+          _ = async {
+            let _ = shutdown_rx.wait_for(|shutdown| *shutdown).await;
+            sleep(drain_timeout).await;
+          } => {
+            warn!("drain timeout elapsed before all connections completed");
+            Ok(())
+          }
+        }
+      }
       Some(tls) => {
         let tls_acceptor = TlsAcceptor::from(Arc::new(tls.into_inner()));
+        let mut connections = JoinSet::new();
+        let mut signal = pin!(signal);
 
         loop {
           let tower_service = app.clone();
           let tls_acceptor = tls_acceptor.clone();
 
           trace!("accepting connection");
-          let (cnx, addr) = self.listener.accept().await.unwrap();
+          let (cnx, addr) = select! {
+            accept = self.listener.accept() => accept?,
+            Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+            _ = &mut signal => break,
+          };
 
-          tokio::spawn(async move {
+          connections.spawn(async move {
             let Ok(stream) = tls_acceptor.accept(cnx).await else {
               error!("error during tls handshake connection from {}", addr);
               return;
@@ -237,6 +518,13 @@ impl Server {
             }
           });
         }
+
+        let drain = async { while connections.join_next().await.is_some() {} };
+        if timeout(drain_timeout, drain).await.is_err() {
+          warn!("drain timeout elapsed before all connections completed");
+        }
+
+        Ok(())
       }
     }
   }
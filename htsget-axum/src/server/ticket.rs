@@ -2,12 +2,19 @@
 //!
 
 use crate::error::Result;
-use crate::handlers::{get, post, reads_service_info, variants_service_info};
-use crate::server::{configure_cors, AppState, BindServer, Server};
+use crate::handlers::{
+  favicon, get, head, post, reads_service_info, ready, robots_txt, root, variants_service_info,
+};
+use crate::server::{
+  add_deprecation_header, add_security_headers, configure_cors, preflight_no_content,
+  reject_during_maintenance, reject_long_paths, remove_cors_headers_on_error, AppState, BindServer,
+  Server,
+};
+use axum::middleware::{from_fn, from_fn_with_state, map_request_with_state, map_response};
 use axum::routing::get;
 use axum::Router;
-use htsget_config::config::cors::CorsConfig;
 use htsget_config::config::{Config, ServiceInfo, TicketServerConfig};
+use htsget_http::exposed_ticket_headers;
 use htsget_search::HtsGet;
 use std::net::SocketAddr;
 use tokio::task::JoinHandle;
@@ -35,7 +42,7 @@ pub struct TicketServer<H> {
   server: Server,
   htsget: H,
   service_info: ServiceInfo,
-  cors: CorsConfig,
+  config: TicketServerConfig,
 }
 
 impl<H> TicketServer<H>
@@ -43,12 +50,17 @@ where
   H: HtsGet + Clone + Send + Sync + 'static,
 {
   /// Create a new ticket server.
-  pub fn new(server: Server, htsget: H, service_info: ServiceInfo, cors: CorsConfig) -> Self {
+  pub fn new(
+    server: Server,
+    htsget: H,
+    service_info: ServiceInfo,
+    config: TicketServerConfig,
+  ) -> Self {
     Self {
       server,
       htsget,
       service_info,
-      cors,
+      config,
     }
   }
 
@@ -56,29 +68,100 @@ where
   pub async fn serve(self) -> Result<()> {
     self
       .server
-      .serve(Self::router(self.htsget, self.service_info, self.cors))
+      .serve(Self::router(self.htsget, self.service_info, &self.config))
       .await
   }
 
-  /// Create the router for the ticket server.
-  pub fn router(htsget: H, service_info: ServiceInfo, cors: CorsConfig) -> Router {
-    Router::default()
+  /// Create the router for the ticket server, using the options in the ticket server config.
+  pub fn router(htsget: H, service_info: ServiceInfo, config: &TicketServerConfig) -> Router {
+    let deprecated_routes = config.deprecated_routes().clone();
+    let cors = config
+      .cors()
+      .clone()
+      .with_additional_expose_headers(exposed_ticket_headers(
+        config.service_info_link(),
+        config.elapsed_time_header(),
+        config.require_authorization(),
+        config.age_header(),
+        !deprecated_routes.is_empty(),
+      ));
+    let remove_cors_on_errors =
+      (!cors.on_errors()).then(|| map_response(remove_cors_headers_on_error));
+    let preflight_no_content = cors
+      .preflight_no_content()
+      .then(|| from_fn(preflight_no_content));
+    let security_headers = config
+      .security_headers()
+      .then(|| map_response(add_security_headers));
+    let max_path_length = config
+      .max_path_length()
+      .map(|max_path_length| map_request_with_state(max_path_length, reject_long_paths));
+    let deprecated_routes = (!deprecated_routes.is_empty())
+      .then(|| from_fn_with_state(deprecated_routes, add_deprecation_header));
+    let maintenance = config
+      .maintenance()
+      .is_enabled()
+      .then(|| from_fn_with_state(config.maintenance().clone(), reject_during_maintenance));
+
+    let router = Router::default();
+    let router = if config.root_response() {
+      router.route("/", get(root::<H>))
+    } else {
+      router
+    };
+    let router = if config.crawler_responses() {
+      router
+        .route("/robots.txt", get(robots_txt))
+        .route("/favicon.ico", get(favicon))
+    } else {
+      router
+    };
+
+    router
+      .route("/ready", get(ready::<H>))
       .route(
         "/reads/service-info",
         get(reads_service_info::<H>).post(reads_service_info::<H>),
       )
-      .route("/reads/*id", get(get::reads).post(post::reads))
+      .route(
+        "/reads/*id",
+        get(get::reads).head(head::reads).post(post::reads),
+      )
       .route(
         "/variants/service-info",
         get(variants_service_info::<H>).post(variants_service_info::<H>),
       )
-      .route("/variants/*id", get(get::variants).post(post::variants))
+      .route(
+        "/variants/*id",
+        get(get::variants).head(head::variants).post(post::variants),
+      )
       .layer(
         ServiceBuilder::new()
           .layer(TraceLayer::new_for_http())
-          .layer(configure_cors(cors)),
+          .option_layer(security_headers)
+          .option_layer(remove_cors_on_errors)
+          .option_layer(preflight_no_content)
+          .layer(configure_cors(cors))
+          .option_layer(max_path_length)
+          .option_layer(deprecated_routes)
+          .option_layer(maintenance),
+      )
+      .with_state(
+        AppState::new(htsget, service_info)
+          .with_service_info_link(config.service_info_link())
+          .with_reject_get_body(config.reject_get_body())
+          .with_format_aliases(config.format_aliases().clone())
+          .with_max_distinct_references(config.max_distinct_references())
+          .with_query_concurrency(config.query_concurrency())
+          .with_elapsed_time_header(config.elapsed_time_header())
+          .with_require_authorization(config.require_authorization())
+          .with_response_style(config.response_style())
+          .with_age_header(config.age_header())
+          .with_relative_urls(config.relative_urls())
+          .with_content_length(config.content_length())
+          .with_reject_duplicate_query_keys(config.reject_duplicate_query_keys())
+          .with_audit_log(config.audit_log().clone()),
       )
-      .with_state(AppState::new(htsget, service_info))
   }
 
   /// Get the local address the server has bound to.
@@ -90,8 +173,9 @@ where
 /// Spawn a task to run the ticket server.
 pub async fn join_handle(config: Config) -> Result<JoinHandle<Result<()>>> {
   let service_info = config.service_info().clone();
-  let ticket_server = BindServer::from(config.ticket_server().clone())
-    .bind_ticket_server(config.owned_resolvers(), service_info)
+  let ticket_server_config = config.ticket_server().clone();
+  let ticket_server = BindServer::from(ticket_server_config.clone())
+    .bind_ticket_server(config.owned_resolvers(), service_info, ticket_server_config)
     .await?;
 
   info!(address = ?ticket_server.local_addr()?, "ticket server address bound to");
@@ -110,15 +194,23 @@ mod tests {
   use axum::body::{to_bytes, Body};
   use axum::response::Response;
   use htsget_config::config::Config;
-  use htsget_config::types::JsonResponse;
+  use htsget_config::types::{
+    DeprecatedRoutes, HtsGetError, JsonResponse, Maintenance, Query, Response as HtsGetResponse,
+    RetryAfterFormat,
+  };
   use htsget_test::http::server::expected_url_path;
   use htsget_test::http::{
-    config_with_tls, cors, default_test_config, server, Header, Response as TestResponse,
-    TestRequest, TestServer,
+    config_with_age_header, config_with_server_timing, config_with_service_info,
+    config_with_ticket_server, config_with_tls, config_with_unreachable_backend, cors,
+    default_test_config, server, Header, Response as TestResponse, TestRequest, TestServer,
   };
-  use http::header::HeaderName;
-  use http::{Method, Request};
+  use http::header::{
+    HeaderName, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+  };
+  use http::{Method, Request, StatusCode};
   use rustls::crypto::aws_lc_rs;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
   use tempfile::TempDir;
   use tower::ServiceExt;
 
@@ -220,7 +312,7 @@ mod tests {
       let app = TicketServer::router(
         self.config.clone().owned_resolvers(),
         self.config.service_info().clone(),
-        self.config.ticket_server().cors().clone(),
+        self.config.ticket_server(),
       );
 
       app.oneshot(request).await
@@ -258,6 +350,211 @@ mod tests {
     server::test_service_info(&AxumTestServer::default()).await;
   }
 
+  #[tokio::test]
+  async fn service_info_head() {
+    server::test_service_info_head(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn head_request() {
+    server::test_head(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn service_info_ga4gh_fields() {
+    server::test_service_info_ga4gh_fields(&AxumTestServer {
+      config: config_with_service_info(),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn service_info_link() {
+    server::test_service_info_link(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_service_info_link(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn max_path_length() {
+    server::test_max_path_length(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_max_path_length(Some(64))
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn elapsed_time_header() {
+    server::test_elapsed_time_header(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_elapsed_time_header(true)
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn no_elapsed_time_header() {
+    server::test_elapsed_time_header(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn age_header() {
+    server::test_age_header(&AxumTestServer {
+      config: config_with_age_header(),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn deprecation_header() {
+    server::test_deprecation_header(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_deprecated_routes(DeprecatedRoutes::new(&["^/reads/"]).unwrap())
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn relative_urls() {
+    server::test_relative_urls(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_relative_urls(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn canonical_format() {
+    server::test_canonical_format(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn reject_duplicate_query_keys() {
+    server::test_reject_duplicate_query_keys(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_reject_duplicate_query_keys(true)
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn content_length() {
+    server::test_content_length(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_content_length(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn server_timing() {
+    server::test_server_timing(&AxumTestServer {
+      config: config_with_server_timing(),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn maintenance() {
+    let tmp = TempDir::new().unwrap();
+    server::test_maintenance(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server
+          .with_maintenance(Maintenance::new(tmp.path().join("maintenance")).with_retry_after(120))
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn maintenance_http_date() {
+    let tmp = TempDir::new().unwrap();
+    server::test_maintenance(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_maintenance(
+          Maintenance::new(tmp.path().join("maintenance"))
+            .with_retry_after(120)
+            .with_retry_after_format(RetryAfterFormat::HttpDate),
+        )
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn root_response() {
+    server::test_root_response(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_root_response(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn crawler_responses() {
+    server::test_crawler_responses(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_crawler_responses(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn require_authorization() {
+    server::test_require_authorization(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_require_authorization(true)
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn max_distinct_references() {
+    server::test_max_distinct_references(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_max_distinct_references(Some(2))
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn security_headers() {
+    server::test_security_headers(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_security_headers(true)),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn ready() {
+    server::test_ready(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn ready_backend_down() {
+    server::test_ready_backend_down(&AxumTestServer {
+      config: config_with_unreachable_backend(),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn invalid_reference_name() {
+    server::test_invalid_reference_name(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn reject_get_body() {
+    server::test_reject_get_body(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| ticket_server.with_reject_get_body(true)),
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn get_https_tickets() {
     let base_path = TempDir::new().unwrap();
@@ -307,6 +604,81 @@ mod tests {
     cors::test_cors_preflight_request(&AxumTestServer::default()).await;
   }
 
+  /// A searcher which counts the number of searches.
+  #[derive(Debug, Clone, Default)]
+  struct CountingHtsGet {
+    searches: Arc<AtomicUsize>,
+  }
+
+  #[async_trait]
+  impl HtsGet for CountingHtsGet {
+    async fn search(self, _query: Query) -> htsget_config::types::Result<HtsGetResponse> {
+      self.searches.fetch_add(1, Ordering::SeqCst);
+      Err(HtsGetError::not_found("not found"))
+    }
+  }
+
+  #[tokio::test]
+  async fn cors_preflight_no_content() {
+    let config = default_test_config();
+    let htsget = CountingHtsGet::default();
+    let ticket_server = config.ticket_server();
+    let app = TicketServer::router(
+      htsget.clone(),
+      config.service_info().clone(),
+      &TicketServerConfig::new(
+        ticket_server.addr(),
+        None,
+        ticket_server.cors().clone().with_preflight_no_content(true),
+      ),
+    );
+
+    let request = Request::builder()
+      .method(Method::OPTIONS)
+      .uri("/reads/id")
+      .header(ORIGIN, "http://example.com")
+      .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+      .body(Body::empty())
+      .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+      response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+      "http://example.com"
+    );
+    assert_eq!(htsget.searches.load(Ordering::SeqCst), 0);
+  }
+
+  #[tokio::test]
+  async fn cors_expose_custom_headers() {
+    cors::test_cors_expose_custom_headers(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        ticket_server.with_elapsed_time_header(true)
+      }),
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn cors_error_response() {
+    cors::test_cors_error_response(&AxumTestServer::default()).await;
+  }
+
+  #[tokio::test]
+  async fn cors_error_response_without_headers() {
+    cors::test_cors_error_response_without_headers(&AxumTestServer {
+      config: config_with_ticket_server(|ticket_server| {
+        TicketServerConfig::new(
+          ticket_server.addr(),
+          ticket_server.tls().cloned(),
+          ticket_server.cors().clone().with_on_errors(false),
+        )
+      }),
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn test_errors() {
     server::test_errors(&AxumTestServer::default()).await;
@@ -163,6 +163,8 @@ pub struct CorsConfig {
   allow_methods: AllowType<Method>,
   max_age: usize,
   expose_headers: AllowType<HeaderName>,
+  on_errors: bool,
+  preflight_no_content: bool,
 }
 
 impl CorsConfig {
@@ -182,9 +184,23 @@ impl CorsConfig {
       allow_methods,
       max_age,
       expose_headers,
+      on_errors: true,
+      preflight_no_content: false,
     }
   }
 
+  /// Set whether cors headers are included on error responses.
+  pub fn with_on_errors(mut self, on_errors: bool) -> Self {
+    self.on_errors = on_errors;
+    self
+  }
+
+  /// Set whether cors preflight requests are answered with 204 No Content rather than 200 OK.
+  pub fn with_preflight_no_content(mut self, preflight_no_content: bool) -> Self {
+    self.preflight_no_content = preflight_no_content;
+    self
+  }
+
   /// Get allow credentials.
   pub fn allow_credentials(&self) -> bool {
     self.allow_credentials
@@ -214,6 +230,32 @@ impl CorsConfig {
   pub fn expose_headers(&self) -> &AllowType<HeaderName> {
     &self.expose_headers
   }
+
+  /// Add headers to the exposed headers. This has no effect if all headers are already exposed.
+  pub fn with_additional_expose_headers(
+    mut self,
+    headers: impl IntoIterator<Item = HeaderName>,
+  ) -> Self {
+    if let AllowType::List(expose_headers) = &mut self.expose_headers {
+      for header in headers {
+        if !expose_headers.contains(&header) {
+          expose_headers.push(header);
+        }
+      }
+    }
+
+    self
+  }
+
+  /// Get whether cors headers are included on error responses.
+  pub fn on_errors(&self) -> bool {
+    self.on_errors
+  }
+
+  /// Get whether cors preflight requests are answered with 204 No Content rather than 200 OK.
+  pub fn preflight_no_content(&self) -> bool {
+    self.preflight_no_content
+  }
 }
 
 impl Default for CorsConfig {
@@ -227,6 +269,8 @@ impl Default for CorsConfig {
       allow_methods: AllowType::Tagged(TaggedTypeAll::All),
       max_age: CORS_MAX_AGE,
       expose_headers: AllowType::List(vec![]),
+      on_errors: true,
+      preflight_no_content: false,
     }
   }
 }
@@ -253,6 +297,26 @@ mod tests {
     assert_eq!(expected, get_result(&deserialized));
   }
 
+  #[test]
+  fn additional_expose_headers() {
+    let config = CorsConfig::default().with_additional_expose_headers([
+      HeaderName::from_static("link"),
+      HeaderName::from_static("link"),
+    ]);
+    assert_eq!(
+      config.expose_headers(),
+      &AllowType::List(vec![HeaderName::from_static("link")])
+    );
+
+    let config = CorsConfig::default()
+      .with_additional_expose_headers([])
+      .with_additional_expose_headers([HeaderName::from_static("x-htsget-elapsed-ms")]);
+    assert_eq!(
+      config.expose_headers(),
+      &AllowType::List(vec![HeaderName::from_static("x-htsget-elapsed-ms")])
+    );
+  }
+
   #[test]
   fn unit_variant_any_allow_type() {
     test_cors_config(
@@ -289,6 +353,18 @@ mod tests {
     );
   }
 
+  #[test]
+  fn on_errors() {
+    test_cors_config("on_errors = false", &false, |config| &config.on_errors);
+  }
+
+  #[test]
+  fn preflight_no_content() {
+    test_cors_config("preflight_no_content = true", &true, |config| {
+      &config.preflight_no_content
+    });
+  }
+
   #[test]
   fn tagged_any_allow_type_err_on_mirror() {
     let allow_type_method = "expose_headers = \"Mirror\"";
@@ -8,19 +8,22 @@ use http::header::HeaderName;
 use http::Method;
 use serde::{Deserialize, Serialize};
 use serde_with::with_prefix;
-use tracing::instrument;
 use tracing::subscriber::set_global_default;
-use tracing_subscriber::fmt::{format, layer};
+use tracing::{instrument, warn};
+use tracing_subscriber::fmt::{format, layer, MakeWriter};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Registry};
 
 use crate::config::cors::{AllowType, CorsConfig, HeaderValue, TaggedAllowTypes};
 use crate::config::parser::from_path;
 use crate::config::FormattingStyle::{Compact, Full, Json, Pretty};
-use crate::error::Error::{ArgParseError, TracingError};
+use crate::error::Error::{ArgParseError, ParseError, TracingError};
 use crate::error::Result;
 use crate::resolver::Resolver;
+use crate::storage::local::UrlSigningKey;
+use crate::storage::Storage;
 use crate::tls::TlsServerConfig;
+use crate::types::{AuditLog, DeprecatedRoutes, FormatAliases, Maintenance, ResponseStyle, Scheme};
 
 pub mod cors;
 pub mod parser;
@@ -70,6 +73,19 @@ pub enum FormattingStyle {
   Json,
 }
 
+/// Determines what happens at startup when the ticket server and the data server use different
+/// schemes, which may cause mixed-content issues for clients.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum SchemeMismatch {
+  /// Allow the mismatch.
+  Ignore,
+  /// Log a warning.
+  #[default]
+  Warn,
+  /// Fail to start with an error.
+  Error,
+}
+
 with_prefix!(ticket_server_prefix "ticket_server_");
 with_prefix!(data_server_prefix "data_server_");
 with_prefix!(cors_prefix "cors_");
@@ -97,12 +113,356 @@ pub struct TicketServerConfig {
   tls: Option<TlsServerConfig>,
   #[serde(flatten, with = "cors_prefix")]
   cors: CorsConfig,
+  require_https: bool,
+  service_info_link: bool,
+  reject_get_body: bool,
+  format_aliases: FormatAliases,
+  max_total_response_bytes: Option<u64>,
+  security_headers: bool,
+  max_path_length: Option<usize>,
+  search_timeout: Option<u64>,
+  max_distinct_references: Option<usize>,
+  query_concurrency: Option<usize>,
+  elapsed_time_header: bool,
+  require_authorization: bool,
+  response_style: ResponseStyle,
+  age_header: bool,
+  deprecated_routes: DeprecatedRoutes,
+  relative_urls: bool,
+  root_response: bool,
+  crawler_responses: bool,
+  content_length: bool,
+  maintenance: Maintenance,
+  server_timing: bool,
+  scheme_mismatch: SchemeMismatch,
+  reject_duplicate_query_keys: bool,
+  warn_overlapping_resolvers: bool,
+  http2: bool,
+  audit_log: AuditLog,
 }
 
 impl TicketServerConfig {
   /// Create a new ticket server config.
   pub fn new(addr: SocketAddr, tls: Option<TlsServerConfig>, cors: CorsConfig) -> Self {
-    Self { addr, tls, cors }
+    Self {
+      addr,
+      tls,
+      cors,
+      require_https: false,
+      service_info_link: false,
+      reject_get_body: false,
+      format_aliases: Default::default(),
+      max_total_response_bytes: None,
+      security_headers: false,
+      max_path_length: None,
+      search_timeout: None,
+      max_distinct_references: None,
+      query_concurrency: None,
+      elapsed_time_header: false,
+      require_authorization: false,
+      response_style: Default::default(),
+      age_header: false,
+      deprecated_routes: Default::default(),
+      relative_urls: false,
+      root_response: false,
+      crawler_responses: false,
+      content_length: false,
+      maintenance: Default::default(),
+      server_timing: false,
+      scheme_mismatch: Default::default(),
+      reject_duplicate_query_keys: false,
+      warn_overlapping_resolvers: false,
+      http2: true,
+      audit_log: Default::default(),
+    }
+  }
+
+  /// Set whether tickets must use https urls.
+  pub fn with_require_https(mut self, require_https: bool) -> Self {
+    self.require_https = require_https;
+    self
+  }
+
+  /// Get whether tickets must use https urls.
+  pub fn require_https(&self) -> bool {
+    self.require_https
+  }
+
+  /// Set whether ticket responses include a `Link` header pointing to the service-info endpoint.
+  pub fn with_service_info_link(mut self, service_info_link: bool) -> Self {
+    self.service_info_link = service_info_link;
+    self
+  }
+
+  /// Get whether ticket responses include a `Link` header pointing to the service-info endpoint.
+  pub fn service_info_link(&self) -> bool {
+    self.service_info_link
+  }
+
+  /// Set whether GET requests which have a body are rejected.
+  pub fn with_reject_get_body(mut self, reject_get_body: bool) -> Self {
+    self.reject_get_body = reject_get_body;
+    self
+  }
+
+  /// Get whether GET requests which have a body are rejected.
+  pub fn reject_get_body(&self) -> bool {
+    self.reject_get_body
+  }
+
+  /// Set the format aliases which are mapped to canonical formats.
+  pub fn with_format_aliases(mut self, format_aliases: FormatAliases) -> Self {
+    self.format_aliases = format_aliases;
+    self
+  }
+
+  /// Get the format aliases which are mapped to canonical formats.
+  pub fn format_aliases(&self) -> &FormatAliases {
+    &self.format_aliases
+  }
+
+  /// Set the maximum total size of the byte ranges that a ticket response may contain.
+  pub fn with_max_total_response_bytes(mut self, max_total_response_bytes: Option<u64>) -> Self {
+    self.max_total_response_bytes = max_total_response_bytes;
+    self
+  }
+
+  /// Get the maximum total size of the byte ranges that a ticket response may contain.
+  pub fn max_total_response_bytes(&self) -> Option<u64> {
+    self.max_total_response_bytes
+  }
+
+  /// Set whether security headers, such as `X-Content-Type-Options: nosniff`, are added to responses.
+  pub fn with_security_headers(mut self, security_headers: bool) -> Self {
+    self.security_headers = security_headers;
+    self
+  }
+
+  /// Get whether security headers are added to responses.
+  pub fn security_headers(&self) -> bool {
+    self.security_headers
+  }
+
+  /// Set the maximum request path length, longer paths are rejected with 414 URI Too Long.
+  pub fn with_max_path_length(mut self, max_path_length: Option<usize>) -> Self {
+    self.max_path_length = max_path_length;
+    self
+  }
+
+  /// Get the maximum request path length.
+  pub fn max_path_length(&self) -> Option<usize> {
+    self.max_path_length
+  }
+
+  /// Set the number of seconds a search may take before it is aborted.
+  pub fn with_search_timeout(mut self, search_timeout: Option<u64>) -> Self {
+    self.search_timeout = search_timeout;
+    self
+  }
+
+  /// Get the number of seconds a search may take before it is aborted.
+  pub fn search_timeout(&self) -> Option<u64> {
+    self.search_timeout
+  }
+
+  /// Set the maximum number of distinct reference names across the regions of a POST request.
+  pub fn with_max_distinct_references(mut self, max_distinct_references: Option<usize>) -> Self {
+    self.max_distinct_references = max_distinct_references;
+    self
+  }
+
+  /// Get the maximum number of distinct reference names across the regions of a POST request.
+  pub fn max_distinct_references(&self) -> Option<usize> {
+    self.max_distinct_references
+  }
+
+  /// Set the maximum number of regions of a POST request which are searched concurrently.
+  pub fn with_query_concurrency(mut self, query_concurrency: Option<usize>) -> Self {
+    self.query_concurrency = query_concurrency;
+    self
+  }
+
+  /// Get the maximum number of regions of a POST request which are searched concurrently.
+  pub fn query_concurrency(&self) -> Option<usize> {
+    self.query_concurrency
+  }
+
+  /// Set whether ticket responses include an `X-Htsget-Elapsed-Ms` header with the search duration.
+  pub fn with_elapsed_time_header(mut self, elapsed_time_header: bool) -> Self {
+    self.elapsed_time_header = elapsed_time_header;
+    self
+  }
+
+  /// Get whether ticket responses include an `X-Htsget-Elapsed-Ms` header with the search duration.
+  pub fn elapsed_time_header(&self) -> bool {
+    self.elapsed_time_header
+  }
+
+  /// Set whether ticket requests without an `Authorization` header are rejected.
+  pub fn with_require_authorization(mut self, require_authorization: bool) -> Self {
+    self.require_authorization = require_authorization;
+    self
+  }
+
+  /// Get whether ticket requests without an `Authorization` header are rejected.
+  pub fn require_authorization(&self) -> bool {
+    self.require_authorization
+  }
+
+  /// Set the style used to serialize ticket responses.
+  pub fn with_response_style(mut self, response_style: ResponseStyle) -> Self {
+    self.response_style = response_style;
+    self
+  }
+
+  /// Get the style used to serialize ticket responses.
+  pub fn response_style(&self) -> ResponseStyle {
+    self.response_style
+  }
+
+  /// Set whether to set the `Age` header on ticket responses served from a response cache.
+  pub fn with_age_header(mut self, age_header: bool) -> Self {
+    self.age_header = age_header;
+    self
+  }
+
+  /// Get whether to set the `Age` header on ticket responses served from a response cache.
+  pub fn age_header(&self) -> bool {
+    self.age_header
+  }
+
+  /// Set the route patterns which respond with a `Deprecation` header.
+  pub fn with_deprecated_routes(mut self, deprecated_routes: DeprecatedRoutes) -> Self {
+    self.deprecated_routes = deprecated_routes;
+    self
+  }
+
+  /// Get the route patterns which respond with a `Deprecation` header.
+  pub fn deprecated_routes(&self) -> &DeprecatedRoutes {
+    &self.deprecated_routes
+  }
+
+  /// Set whether ticket urls are relative to the origin of the request.
+  pub fn with_relative_urls(mut self, relative_urls: bool) -> Self {
+    self.relative_urls = relative_urls;
+    self
+  }
+
+  /// Get whether ticket urls are relative to the origin of the request.
+  pub fn relative_urls(&self) -> bool {
+    self.relative_urls
+  }
+
+  /// Set whether GET requests to the root path respond with a landing response.
+  pub fn with_root_response(mut self, root_response: bool) -> Self {
+    self.root_response = root_response;
+    self
+  }
+
+  /// Get whether GET requests to the root path respond with a landing response.
+  pub fn root_response(&self) -> bool {
+    self.root_response
+  }
+
+  /// Set whether `/robots.txt` and `/favicon.ico` respond with static responses.
+  pub fn with_crawler_responses(mut self, crawler_responses: bool) -> Self {
+    self.crawler_responses = crawler_responses;
+    self
+  }
+
+  /// Get whether `/robots.txt` and `/favicon.ico` respond with static responses.
+  pub fn crawler_responses(&self) -> bool {
+    self.crawler_responses
+  }
+
+  /// Set whether ticket responses set an explicit `Content-Length` header.
+  pub fn with_content_length(mut self, content_length: bool) -> Self {
+    self.content_length = content_length;
+    self
+  }
+
+  /// Get whether ticket responses set an explicit `Content-Length` header.
+  pub fn content_length(&self) -> bool {
+    self.content_length
+  }
+
+  /// Set the maintenance mode, which rejects queries while the maintenance file exists.
+  pub fn with_maintenance(mut self, maintenance: Maintenance) -> Self {
+    self.maintenance = maintenance;
+    self
+  }
+
+  /// Get the maintenance mode, which rejects queries while the maintenance file exists.
+  pub fn maintenance(&self) -> &Maintenance {
+    &self.maintenance
+  }
+
+  /// Set whether ticket responses include a `Server-Timing` header with the durations of the
+  /// stages of resolving the query.
+  pub fn with_server_timing(mut self, server_timing: bool) -> Self {
+    self.server_timing = server_timing;
+    self
+  }
+
+  /// Get whether ticket responses include a `Server-Timing` header with the durations of the
+  /// stages of resolving the query.
+  pub fn server_timing(&self) -> bool {
+    self.server_timing
+  }
+
+  /// Set what happens when the ticket server and data server schemes are inconsistent.
+  pub fn with_scheme_mismatch(mut self, scheme_mismatch: SchemeMismatch) -> Self {
+    self.scheme_mismatch = scheme_mismatch;
+    self
+  }
+
+  /// Get what happens when the ticket server and data server schemes are inconsistent.
+  pub fn scheme_mismatch(&self) -> SchemeMismatch {
+    self.scheme_mismatch
+  }
+
+  /// Set whether GET requests which repeat a scalar query parameter are rejected.
+  pub fn with_reject_duplicate_query_keys(mut self, reject_duplicate_query_keys: bool) -> Self {
+    self.reject_duplicate_query_keys = reject_duplicate_query_keys;
+    self
+  }
+
+  /// Get whether GET requests which repeat a scalar query parameter are rejected.
+  pub fn reject_duplicate_query_keys(&self) -> bool {
+    self.reject_duplicate_query_keys
+  }
+
+  /// Set whether a warning is logged at startup for resolvers with regexes that match the same ids.
+  pub fn with_warn_overlapping_resolvers(mut self, warn_overlapping_resolvers: bool) -> Self {
+    self.warn_overlapping_resolvers = warn_overlapping_resolvers;
+    self
+  }
+
+  /// Get whether a warning is logged at startup for resolvers with regexes that match the same ids.
+  pub fn warn_overlapping_resolvers(&self) -> bool {
+    self.warn_overlapping_resolvers
+  }
+
+  /// Set whether HTTP/2 is advertised to clients when using TLS.
+  pub fn with_http2(mut self, http2: bool) -> Self {
+    self.http2 = http2;
+    self
+  }
+
+  /// Get whether HTTP/2 is advertised to clients when using TLS.
+  pub fn http2(&self) -> bool {
+    self.http2
+  }
+
+  /// Set the audit logging of denied requests.
+  pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+    self.audit_log = audit_log;
+    self
+  }
+
+  /// Get the audit logging of denied requests.
+  pub fn audit_log(&self) -> &AuditLog {
+    &self.audit_log
   }
 
   /// Get the addr.
@@ -115,9 +475,9 @@ impl TicketServerConfig {
     self.tls.as_ref()
   }
 
-  /// Get the TLS config.
+  /// Get the TLS config, which advertises HTTP/2 if it is enabled.
   pub fn into_tls(self) -> Option<TlsServerConfig> {
-    self.tls
+    self.tls.map(|tls| tls.with_http2(self.http2))
   }
 
   /// Get cors config.
@@ -168,6 +528,12 @@ pub struct DataServerConfig {
   tls: Option<TlsServerConfig>,
   #[serde(flatten, with = "cors_prefix")]
   cors: CorsConfig,
+  #[serde(skip_serializing)]
+  signing_key: Option<UrlSigningKey>,
+  security_headers: bool,
+  drain_timeout: Option<u64>,
+  http2: bool,
+  audit_log: AuditLog,
 }
 
 impl DataServerConfig {
@@ -187,9 +553,69 @@ impl DataServerConfig {
       serve_at,
       tls,
       cors,
+      signing_key: None,
+      security_headers: false,
+      drain_timeout: None,
+      http2: true,
+      audit_log: Default::default(),
     }
   }
 
+  /// Set the key used to verify signed urls.
+  pub fn with_signing_key(mut self, signing_key: Option<UrlSigningKey>) -> Self {
+    self.signing_key = signing_key;
+    self
+  }
+
+  /// Get the key used to verify signed urls.
+  pub fn signing_key(&self) -> Option<&UrlSigningKey> {
+    self.signing_key.as_ref()
+  }
+
+  /// Set whether security headers, such as `X-Content-Type-Options: nosniff`, are added to responses.
+  pub fn with_security_headers(mut self, security_headers: bool) -> Self {
+    self.security_headers = security_headers;
+    self
+  }
+
+  /// Get whether security headers are added to responses.
+  pub fn security_headers(&self) -> bool {
+    self.security_headers
+  }
+
+  /// Set the number of seconds to wait for in-flight requests to complete after a shutdown signal.
+  pub fn with_drain_timeout(mut self, drain_timeout: Option<u64>) -> Self {
+    self.drain_timeout = drain_timeout;
+    self
+  }
+
+  /// Get the number of seconds to wait for in-flight requests to complete after a shutdown signal.
+  pub fn drain_timeout(&self) -> Option<u64> {
+    self.drain_timeout
+  }
+
+  /// Set whether HTTP/2 is advertised to clients when using TLS.
+  pub fn with_http2(mut self, http2: bool) -> Self {
+    self.http2 = http2;
+    self
+  }
+
+  /// Get whether HTTP/2 is advertised to clients when using TLS.
+  pub fn http2(&self) -> bool {
+    self.http2
+  }
+
+  /// Set the audit logging of requests denied because of an invalid url signature.
+  pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+    self.audit_log = audit_log;
+    self
+  }
+
+  /// Get the audit logging of requests denied because of an invalid url signature.
+  pub fn audit_log(&self) -> &AuditLog {
+    &self.audit_log
+  }
+
   /// Get the address.
   pub fn addr(&self) -> SocketAddr {
     self.addr
@@ -210,9 +636,9 @@ impl DataServerConfig {
     self.tls.as_ref()
   }
 
-  /// Get the TLS config.
+  /// Get the TLS config, which advertises HTTP/2 if it is enabled.
   pub fn into_tls(self) -> Option<TlsServerConfig> {
-    self.tls
+    self.tls.map(|tls| tls.with_http2(self.http2))
   }
 
   /// Get cors config.
@@ -267,6 +693,11 @@ impl Default for DataServerConfig {
       serve_at: Default::default(),
       tls: None,
       cors: CorsConfig::default(),
+      signing_key: None,
+      security_headers: false,
+      drain_timeout: None,
+      http2: true,
+      audit_log: Default::default(),
     }
   }
 }
@@ -280,6 +711,9 @@ pub struct ServiceInfo {
   version: Option<String>,
   organization_name: Option<String>,
   organization_url: Option<String>,
+  type_group: Option<String>,
+  type_artifact: Option<String>,
+  type_version: Option<String>,
   contact_url: Option<String>,
   documentation_url: Option<String>,
   created_at: Option<String>,
@@ -313,6 +747,21 @@ impl ServiceInfo {
     self.organization_url.as_deref()
   }
 
+  /// Get the service type group.
+  pub fn type_group(&self) -> Option<&str> {
+    self.type_group.as_deref()
+  }
+
+  /// Get the service type artifact.
+  pub fn type_artifact(&self) -> Option<&str> {
+    self.type_artifact.as_deref()
+  }
+
+  /// Get the service type version.
+  pub fn type_version(&self) -> Option<&str> {
+    self.type_version.as_deref()
+  }
+
   /// Get the contact url.
   pub fn contact_url(&self) -> Option<&str> {
     self.contact_url.as_deref()
@@ -345,6 +794,32 @@ impl Default for TicketServerConfig {
       addr: default_addr().parse().expect("expected valid address"),
       tls: None,
       cors: CorsConfig::default(),
+      require_https: false,
+      service_info_link: false,
+      reject_get_body: false,
+      format_aliases: Default::default(),
+      max_total_response_bytes: None,
+      security_headers: false,
+      max_path_length: None,
+      search_timeout: None,
+      max_distinct_references: None,
+      query_concurrency: None,
+      elapsed_time_header: false,
+      require_authorization: false,
+      response_style: Default::default(),
+      age_header: false,
+      deprecated_routes: Default::default(),
+      relative_urls: false,
+      root_response: false,
+      crawler_responses: false,
+      content_length: false,
+      maintenance: Default::default(),
+      server_timing: false,
+      scheme_mismatch: Default::default(),
+      reject_duplicate_query_keys: false,
+      warn_overlapping_resolvers: false,
+      http2: true,
+      audit_log: Default::default(),
     }
   }
 }
@@ -410,23 +885,146 @@ impl Config {
   pub fn from_path(path: &Path) -> io::Result<Self> {
     let config: Self = from_path(path)?;
 
-    Ok(config.resolvers_from_data_server_config())
+    Ok(
+      config
+        .resolvers_from_data_server_config()
+        .resolvers_from_ticket_server_config()
+        .validate()?,
+    )
+  }
+
+  /// Validate the config, returning an error if any options are inconsistent.
+  pub fn validate(self) -> Result<Self> {
+    if self.ticket_server.require_https() {
+      if let Some(resolver) = self
+        .resolvers
+        .iter()
+        .find(|resolver| resolver.storage().ticket_scheme() != Scheme::Https)
+      {
+        return Err(ParseError(format!(
+          "resolver with regex `{}` produces non-https tickets, but https tickets are required",
+          resolver.regex()
+        )));
+      }
+    }
+
+    if let Some(message) = self.data_server_scheme_mismatch() {
+      if self.ticket_server.scheme_mismatch() == SchemeMismatch::Error {
+        return Err(ParseError(message));
+      }
+    }
+
+    if !self.data_server.enabled() {
+      if let Some(resolver) = self.resolvers.iter().find(|resolver| {
+        matches!(resolver.storage(), Storage::Local(local) if local.use_data_server_config())
+      }) {
+        return Err(ParseError(format!(
+          "resolver with regex `{}` uses the data server config, but the data server is disabled",
+          resolver.regex()
+        )));
+      }
+    }
+
+    Ok(self)
+  }
+
+  /// Get the warnings about the config which should be logged, such as a scheme mismatch when it
+  /// is set to warn, or overlapping resolvers when they are warned about.
+  pub fn warnings(&self) -> Vec<String> {
+    let mut warnings: Vec<String> = self
+      .data_server_scheme_mismatch()
+      .filter(|_| self.ticket_server.scheme_mismatch() == SchemeMismatch::Warn)
+      .into_iter()
+      .collect();
+
+    if self.ticket_server.warn_overlapping_resolvers() {
+      warnings.extend(self.overlapping_resolvers());
+    }
+
+    warnings
   }
 
-  /// Setup tracing, using a global subscriber.
+  /// Get a message describing the inconsistency if the ticket server and the data server use
+  /// different schemes, and a resolver produces tickets which point to the data server.
+  pub fn data_server_scheme_mismatch(&self) -> Option<String> {
+    let scheme = |tls: Option<&TlsServerConfig>| match tls {
+      Some(_) => Scheme::Https,
+      None => Scheme::Http,
+    };
+    let ticket_scheme = scheme(self.ticket_server.tls());
+    let data_scheme = scheme(self.data_server.tls());
+
+    if !self.data_server.enabled() || ticket_scheme == data_scheme {
+      return None;
+    }
+
+    self
+      .resolvers
+      .iter()
+      .find(|resolver| {
+        matches!(resolver.storage(), Storage::Local(local) if local.use_data_server_config())
+      })
+      .map(|resolver| {
+        format!(
+          "the ticket server uses {ticket_scheme}, but resolver with regex `{}` produces tickets \
+          for the data server, which uses {data_scheme}",
+          resolver.regex()
+        )
+      })
+  }
+
+  /// Get messages describing each pair of resolvers with regexes that match the same ids. Ids are
+  /// resolved by the first matching resolver, so the second resolver of a pair may never be used
+  /// for these ids.
+  pub fn overlapping_resolvers(&self) -> Vec<String> {
+    self
+      .resolvers
+      .iter()
+      .enumerate()
+      .flat_map(|(i, first)| {
+        self.resolvers[i + 1..].iter().filter_map(move |second| {
+          first.overlapping_id(second).map(|id| {
+            format!(
+              "resolvers with regex `{}` and `{}` overlap, ids such as `{id}` are resolved by \
+              the first resolver",
+              first.regex(),
+              second.regex()
+            )
+          })
+        })
+      })
+      .collect()
+  }
+
+  /// Setup tracing, using a global subscriber, and log the config warnings. The warnings are
+  /// logged here rather than when the config is read, because there is no subscriber before this.
   pub fn setup_tracing(&self) -> Result<()> {
+    self.setup_tracing_with_writer(io::stdout)
+  }
+
+  /// Setup tracing with a global subscriber which writes to the writer, and log the config
+  /// warnings.
+  fn setup_tracing_with_writer<W>(&self, make_writer: W) -> Result<()>
+  where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+  {
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
     let subscriber = Registry::default().with(env_filter);
+    let layer = layer().with_writer(make_writer);
 
     match self.formatting_style() {
-      Full => set_global_default(subscriber.with(layer())),
-      Compact => set_global_default(subscriber.with(layer().event_format(format().compact()))),
-      Pretty => set_global_default(subscriber.with(layer().event_format(format().pretty()))),
-      Json => set_global_default(subscriber.with(layer().event_format(format().json()))),
+      Full => set_global_default(subscriber.with(layer)),
+      Compact => set_global_default(subscriber.with(layer.event_format(format().compact()))),
+      Pretty => set_global_default(subscriber.with(layer.event_format(format().pretty()))),
+      Json => set_global_default(subscriber.with(layer.event_format(format().json()))),
     }
     .map_err(|err| TracingError(err.to_string()))?;
 
+    for warning in self.warnings() {
+      warn!("{warning}");
+    }
+
     Ok(())
   }
 
@@ -487,6 +1085,17 @@ impl Config {
       resolvers,
     )
   }
+
+  /// Set the resolver limits which default to the ticket server config.
+  pub fn resolvers_from_ticket_server_config(mut self) -> Self {
+    let ticket_server = &self.ticket_server;
+    self
+      .resolvers
+      .iter_mut()
+      .for_each(|resolver| resolver.resolvers_from_ticket_server_config(ticket_server));
+
+    self
+  }
 }
 
 #[cfg(test)]
@@ -496,8 +1105,11 @@ pub(crate) mod tests {
   use crate::config::parser::from_str;
   use crate::storage::Storage;
   use crate::tls::tests::with_test_certificates;
+  use crate::types::Format;
+  use crate::types::RetryAfterFormat;
   use crate::types::Scheme::Http;
   use figment::Jail;
+  use htsget_test::util::TestWriter;
   use http::uri::Authority;
 
   use super::*;
@@ -525,12 +1137,14 @@ pub(crate) mod tests {
       test_fn(
         from_path::<Config>(path)
           .map_err(|err| err.to_string())?
-          .resolvers_from_data_server_config(),
+          .resolvers_from_data_server_config()
+          .resolvers_from_ticket_server_config(),
       );
       test_fn(
         from_str::<Config>(contents.unwrap_or(""))
           .map_err(|err| err.to_string())?
-          .resolvers_from_data_server_config(),
+          .resolvers_from_data_server_config()
+          .resolvers_from_ticket_server_config(),
       );
 
       Ok(())
@@ -765,6 +1379,608 @@ pub(crate) mod tests {
     });
   }
 
+  #[test]
+  fn config_ticket_server_require_https_http_data_server() {
+    Jail::expect_with(|jail| {
+      jail.create_file(
+        "test.toml",
+        r#"
+        ticket_server_require_https = true
+
+        [[resolvers]]
+        [resolvers.storage]
+        backend = "Local"
+        use_data_server_config = true
+        "#,
+      )?;
+
+      assert!(Config::from_path(Path::new("test.toml")).is_err());
+
+      Ok(())
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_scheme_mismatch_error() {
+    with_test_certificates(|path, _, _| {
+      Jail::expect_with(|jail| {
+        jail.create_file(
+          "test.toml",
+          &format!(
+            r#"
+            ticket_server_tls.key = "{}"
+            ticket_server_tls.cert = "{}"
+            ticket_server_scheme_mismatch = "Error"
+
+            [[resolvers]]
+            [resolvers.storage]
+            backend = "Local"
+            use_data_server_config = true
+            "#,
+            path.join("key.pem").to_string_lossy().escape_default(),
+            path.join("cert.pem").to_string_lossy().escape_default()
+          ),
+        )?;
+
+        assert!(Config::from_path(Path::new("test.toml")).is_err());
+
+        Ok(())
+      });
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_scheme_mismatch_warn() {
+    with_test_certificates(|path, _, _| {
+      test_config_from_file(
+        &format!(
+          r#"
+          ticket_server_tls.key = "{}"
+          ticket_server_tls.cert = "{}"
+
+          [[resolvers]]
+          [resolvers.storage]
+          backend = "Local"
+          use_data_server_config = true
+          "#,
+          path.join("key.pem").to_string_lossy().escape_default(),
+          path.join("cert.pem").to_string_lossy().escape_default()
+        ),
+        |config| {
+          assert_eq!(
+            config.ticket_server().scheme_mismatch(),
+            SchemeMismatch::Warn
+          );
+          assert!(config.data_server_scheme_mismatch().is_some());
+          assert_eq!(config.warnings().len(), 1);
+        },
+      );
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_warn_overlapping_resolvers() {
+    test_config_from_file(
+      r#"
+      ticket_server_warn_overlapping_resolvers = true
+
+      [[resolvers]]
+      regex = "^dataset/(?P<id>.*)$"
+
+      [[resolvers]]
+      regex = "^dataset/sample_(?P<id>.*)$"
+      "#,
+      |config| {
+        assert!(config.ticket_server().warn_overlapping_resolvers());
+        assert_eq!(config.overlapping_resolvers().len(), 1);
+        assert_eq!(config.warnings(), config.overlapping_resolvers());
+      },
+    );
+  }
+
+  #[test]
+  fn config_setup_tracing_logs_warnings() {
+    let writer = TestWriter::default();
+
+    with_test_certificates(|path, _, _| {
+      Jail::expect_with(|jail| {
+        jail.create_file(
+          "test.toml",
+          &format!(
+            r#"
+            ticket_server_tls.key = "{}"
+            ticket_server_tls.cert = "{}"
+            ticket_server_warn_overlapping_resolvers = true
+
+            [[resolvers]]
+            regex = "^dataset/(?P<id>.*)$"
+            [resolvers.storage]
+            backend = "Local"
+            use_data_server_config = true
+
+            [[resolvers]]
+            regex = "^dataset/sample_(?P<id>.*)$"
+            "#,
+            path.join("key.pem").to_string_lossy().escape_default(),
+            path.join("cert.pem").to_string_lossy().escape_default()
+          ),
+        )?;
+
+        // The same order as the server binaries: read the config, then setup tracing.
+        let config = Config::from_path(Path::new("test.toml")).map_err(|err| err.to_string())?;
+        config
+          .setup_tracing_with_writer(writer.make_writer())
+          .map_err(|err| err.to_string())?;
+
+        Ok(())
+      });
+    });
+
+    let logs = writer.logs();
+    assert!(logs.contains("WARN"));
+    assert!(logs.contains("the ticket server uses https"));
+    assert!(logs.contains(
+      "resolvers with regex `^dataset/(?P<id>.*)$` and `^dataset/sample_(?P<id>.*)$` overlap"
+    ));
+  }
+
+  #[test]
+  fn config_servers_http2() {
+    with_test_certificates(|path, _, _| {
+      let key = path
+        .join("key.pem")
+        .to_string_lossy()
+        .escape_default()
+        .to_string();
+      let cert = path
+        .join("cert.pem")
+        .to_string_lossy()
+        .escape_default()
+        .to_string();
+      test_config_from_file(
+        &format!(
+          r#"
+          ticket_server_tls.key = "{key}"
+          ticket_server_tls.cert = "{cert}"
+          ticket_server_http2 = false
+          data_server_tls.key = "{key}"
+          data_server_tls.cert = "{cert}"
+          "#
+        ),
+        |config| {
+          assert!(!config.ticket_server().http2());
+          assert!(config.data_server().http2());
+
+          let ticket_tls = config.ticket_server().clone().into_tls().unwrap();
+          assert_eq!(ticket_tls.alpn_protocols(), vec![b"http/1.1".to_vec()]);
+
+          let data_tls = config.data_server().clone().into_tls().unwrap();
+          assert_eq!(
+            data_tls.alpn_protocols(),
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+          );
+        },
+      );
+    });
+  }
+
+  #[test]
+  fn config_data_server_disabled_use_data_server_config() {
+    Jail::expect_with(|jail| {
+      jail.create_file(
+        "test.toml",
+        r#"
+        data_server_enabled = false
+
+        [[resolvers]]
+        [resolvers.storage]
+        backend = "Local"
+        use_data_server_config = true
+        "#,
+      )?;
+
+      assert!(Config::from_path(Path::new("test.toml")).is_err());
+
+      Ok(())
+    });
+  }
+
+  #[cfg(feature = "s3-storage")]
+  #[test]
+  fn config_data_server_disabled_s3() {
+    test_config_from_file(
+      r#"
+      data_server_enabled = false
+
+      [[resolvers]]
+      regex = "^(example_bucket)/(?P<key>.*)$"
+      substitution_string = "$key"
+      [resolvers.storage]
+      backend = "S3"
+      "#,
+      |config| {
+        assert!(!config.data_server().enabled());
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::S3(_)
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_require_https_local_https() {
+    test_config_from_file(
+      r#"
+      ticket_server_require_https = true
+
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      scheme = "HTTPS"
+      "#,
+      |config| {
+        assert!(config.ticket_server().require_https());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_service_info_link_file() {
+    test_config_from_file(r#"ticket_server_service_info_link = true"#, |config| {
+      assert!(config.ticket_server().service_info_link());
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_service_info_link_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_SERVICE_INFO_LINK", "true")],
+      |config| {
+        assert!(config.ticket_server().service_info_link());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_audit_log_file() {
+    test_config_from_file(
+      r#"
+      ticket_server_audit_log.enabled = true
+      ticket_server_audit_log.subject_header = "x-forwarded-user"
+      "#,
+      |config| {
+        assert_eq!(
+          config.ticket_server().audit_log(),
+          &AuditLog::new(Some("x-forwarded-user".to_string()))
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_data_server_audit_log_file() {
+    test_config_from_file(
+      r#"
+      data_server_audit_log.enabled = true
+      data_server_audit_log.subject_header = "x-forwarded-user"
+      "#,
+      |config| {
+        assert_eq!(
+          config.data_server().audit_log(),
+          &AuditLog::new(Some("x-forwarded-user".to_string()))
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_reject_duplicate_query_keys_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_REJECT_DUPLICATE_QUERY_KEYS", "true")],
+      |config| {
+        assert!(config.ticket_server().reject_duplicate_query_keys());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_reject_get_body_file() {
+    test_config_from_file(r#"ticket_server_reject_get_body = true"#, |config| {
+      assert!(config.ticket_server().reject_get_body());
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_reject_get_body_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_REJECT_GET_BODY", "true")],
+      |config| {
+        assert!(config.ticket_server().reject_get_body());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_max_total_response_bytes_file() {
+    test_config_from_file(
+      r#"
+      ticket_server_max_total_response_bytes = 1000
+
+      [[resolvers]]
+      regex = "regex"
+
+      [[resolvers]]
+      regex = "regex"
+      max_total_response_bytes = 10
+      "#,
+      |config| {
+        assert_eq!(
+          config.ticket_server().max_total_response_bytes(),
+          Some(1000)
+        );
+        assert_eq!(config.resolvers()[0].max_total_response_bytes(), Some(1000));
+        assert_eq!(config.resolvers()[1].max_total_response_bytes(), Some(10));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_search_timeout_file() {
+    test_config_from_file(
+      r#"
+      ticket_server_search_timeout = 5
+
+      [[resolvers]]
+      regex = "fast"
+
+      [[resolvers]]
+      regex = "slow"
+      search_timeout = 60
+      "#,
+      |config| {
+        assert_eq!(config.ticket_server().search_timeout(), Some(5));
+        assert_eq!(config.resolvers()[0].search_timeout(), Some(5));
+        assert_eq!(config.resolvers()[1].search_timeout(), Some(60));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_max_distinct_references_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_MAX_DISTINCT_REFERENCES", "2")],
+      |config| {
+        assert_eq!(config.ticket_server().max_distinct_references(), Some(2));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_query_concurrency_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_QUERY_CONCURRENCY", "4")],
+      |config| {
+        assert_eq!(config.ticket_server().query_concurrency(), Some(4));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_require_authorization_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_REQUIRE_AUTHORIZATION", "true")],
+      |config| {
+        assert!(config.ticket_server().require_authorization());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_relative_urls_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_RELATIVE_URLS", "true")],
+      |config| {
+        assert!(config.ticket_server().relative_urls());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_server_timing_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_SERVER_TIMING", "true")],
+      |config| {
+        assert!(config.ticket_server().server_timing());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_server_timing_file() {
+    test_config_from_file(
+      r#"
+      ticket_server_server_timing = true
+
+      [[resolvers]]
+      regex = "regex"
+      "#,
+      |config| {
+        assert!(config.ticket_server().server_timing());
+        assert!(config.resolvers()[0].server_timing());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_content_length_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_CONTENT_LENGTH", "true")],
+      |config| {
+        assert!(config.ticket_server().content_length());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_root_response_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_ROOT_RESPONSE", "true")],
+      |config| {
+        assert!(config.ticket_server().root_response());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_crawler_responses_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_CRAWLER_RESPONSES", "true")],
+      |config| {
+        assert!(config.ticket_server().crawler_responses());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_age_header_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_AGE_HEADER", "true")],
+      |config| {
+        assert!(config.ticket_server().age_header());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_response_style_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_RESPONSE_STYLE", "Strict")],
+      |config| {
+        assert_eq!(
+          config.ticket_server().response_style(),
+          ResponseStyle::Strict
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_response_style_file() {
+    test_config_from_file(r#"ticket_server_response_style = "Compact""#, |config| {
+      assert_eq!(
+        config.ticket_server().response_style(),
+        ResponseStyle::Compact
+      );
+    });
+  }
+
+  #[test]
+  fn config_ticket_server_elapsed_time_header_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_ELAPSED_TIME_HEADER", "true")],
+      |config| {
+        assert!(config.ticket_server().elapsed_time_header());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_search_timeout_env() {
+    test_config_from_env(
+      vec![("HTSGET_TICKET_SERVER_SEARCH_TIMEOUT", "10")],
+      |config| {
+        assert_eq!(config.ticket_server().search_timeout(), Some(10));
+      },
+    );
+  }
+
+  #[test]
+  fn config_security_headers_env() {
+    test_config_from_env(
+      vec![
+        ("HTSGET_TICKET_SERVER_SECURITY_HEADERS", "true"),
+        ("HTSGET_DATA_SERVER_SECURITY_HEADERS", "true"),
+      ],
+      |config| {
+        assert!(config.ticket_server().security_headers());
+        assert!(config.data_server().security_headers());
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_deprecated_routes_file() {
+    test_config_from_file(
+      r#"ticket_server_deprecated_routes = ["^/reads/legacy/"]"#,
+      |config| {
+        let deprecated_routes = config.ticket_server().deprecated_routes();
+
+        assert!(deprecated_routes.is_deprecated("/reads/legacy/id"));
+        assert!(!deprecated_routes.is_deprecated("/reads/id"));
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_maintenance_file() {
+    test_config_from_file(
+      r#"ticket_server_maintenance = { file = "/tmp/htsget-maintenance", retry_after = 300 }"#,
+      |config| {
+        let maintenance = config.ticket_server().maintenance();
+
+        assert_eq!(
+          maintenance.file(),
+          Some(Path::new("/tmp/htsget-maintenance"))
+        );
+        assert_eq!(maintenance.retry_after(), 300);
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_maintenance_env() {
+    test_config_from_env(
+      vec![(
+        "HTSGET_TICKET_SERVER_MAINTENANCE",
+        "{file=\"/tmp/htsget-maintenance\"}",
+      )],
+      |config| {
+        let maintenance = config.ticket_server().maintenance();
+
+        assert!(maintenance.is_enabled());
+        assert_eq!(maintenance.retry_after(), 60);
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_maintenance_retry_after_format_file() {
+    test_config_from_file(
+      r#"ticket_server_maintenance = { file = "/tmp/htsget-maintenance", retry_after_format = "HttpDate" }"#,
+      |config| {
+        assert_eq!(
+          config.ticket_server().maintenance().retry_after_format(),
+          RetryAfterFormat::HttpDate
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_ticket_server_format_aliases_file() {
+    test_config_from_file(
+      r#"ticket_server_format_aliases = { gvcf = "vcf", SAM = "BAM" }"#,
+      |config| {
+        let format_aliases = config.ticket_server().format_aliases();
+
+        assert_eq!(format_aliases.get("gvcf"), Some(Format::Vcf));
+        assert_eq!(format_aliases.get("sam"), Some(Format::Bam));
+        assert_eq!(format_aliases.get("bed"), None);
+      },
+    );
+  }
+
   #[test]
   fn resolvers_from_data_server_config() {
     test_config_from_file(
@@ -786,4 +2002,23 @@ pub(crate) mod tests {
       },
     );
   }
+  #[test]
+  fn resolvers_from_data_server_config_signing_key() {
+    test_config_from_file(
+      r#"
+    data_server_signing_key = "secret"
+
+    [[resolvers]]
+    [resolvers.storage]
+    backend = "Local"
+    use_data_server_config = true
+    url_expiry_secs = 60
+    "#,
+      |config| {
+        assert!(config.data_server().signing_key().is_some());
+        assert!(matches!(config.resolvers.first().unwrap().storage(),
+      Storage::Local(local_storage) if local_storage.signing_key().is_some() && local_storage.url_expiry_secs() == 60));
+      },
+    );
+  }
 }
@@ -0,0 +1,265 @@
+//! Module providing an in-memory cache of parsed indexes, so that repeated searches of the same
+//! file do not read and parse the index again.
+//!
+
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use crate::types::Format;
+
+/// A parsed index, along with the size of the data file that it indexes.
+struct CachedIndex {
+  size: u64,
+  index: Arc<dyn Any + Send + Sync>,
+}
+
+/// A value of a least recently used cache, along with when it was last used.
+#[derive(Debug)]
+struct LruEntry<V> {
+  last_used: u64,
+  value: V,
+}
+
+/// The values of a least recently used cache, along with the order in which they were last used.
+#[derive(Debug)]
+pub(crate) struct LruEntries<K, V> {
+  counter: u64,
+  values: HashMap<K, LruEntry<V>>,
+  recency: BTreeMap<u64, K>,
+}
+
+impl<K, V> Default for LruEntries<K, V> {
+  fn default() -> Self {
+    Self {
+      counter: 0,
+      values: HashMap::new(),
+      recency: BTreeMap::new(),
+    }
+  }
+}
+
+impl<K: Clone + Eq + Hash, V> LruEntries<K, V> {
+  /// Get the next value of the recency counter.
+  fn next_use(&mut self) -> u64 {
+    self.counter += 1;
+    self.counter
+  }
+
+  /// Get the number of values.
+  pub(crate) fn len(&self) -> usize {
+    self.values.len()
+  }
+
+  /// Get the value for the key, marking it as the most recently used value.
+  pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+    let last_used = self.next_use();
+    let entry = self.values.get_mut(key)?;
+
+    let previous_use = mem::replace(&mut entry.last_used, last_used);
+    self.recency.remove(&previous_use);
+    self.recency.insert(last_used, key.clone());
+
+    Some(&entry.value)
+  }
+
+  /// Remove the value for the key, returning it if it exists.
+  pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+    let entry = self.values.remove(key)?;
+    self.recency.remove(&entry.last_used);
+    Some(entry.value)
+  }
+
+  /// Insert the value for the key, evicting the least recently used values so that at most
+  /// `capacity` values are held.
+  pub(crate) fn insert(&mut self, key: K, value: V, capacity: usize) {
+    if capacity == 0 {
+      return;
+    }
+
+    self.remove(&key);
+    while self.values.len() >= capacity {
+      let Some((_, oldest)) = self.recency.pop_first() else {
+        break;
+      };
+      self.values.remove(&oldest);
+    }
+
+    let last_used = self.next_use();
+    self.recency.insert(last_used, key.clone());
+    self.values.insert(key, LruEntry { last_used, value });
+  }
+}
+
+/// A least recently used cache of parsed indexes, keyed by the storage key and format of the
+/// index. Entries are invalidated when the size of the data file that the index belongs to
+/// changes. Clones of this value share the same underlying cache.
+#[derive(Clone)]
+pub struct IndexCache {
+  capacity: usize,
+  prefix: String,
+  entries: Arc<Mutex<LruEntries<(String, Format), CachedIndex>>>,
+}
+
+impl IndexCache {
+  /// Create a new index cache which holds at most `capacity` indexes.
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      prefix: Default::default(),
+      entries: Default::default(),
+    }
+  }
+
+  /// Set a prefix which is added to all keys, so that storage backends which share this cache,
+  /// such as different S3 buckets, do not share indexes.
+  pub fn with_prefix(mut self, prefix: String) -> Self {
+    self.prefix = prefix;
+    self
+  }
+
+  /// Get the maximum number of indexes held by the cache.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Get the number of indexes held by the cache.
+  pub fn len(&self) -> usize {
+    self
+      .entries
+      .lock()
+      .map(|entries| entries.len())
+      .unwrap_or(0)
+  }
+
+  /// Get whether the cache holds no indexes.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Get the cached index for the key and format, if it was cached for a data file of the same
+  /// size. An index which was cached for a data file of a different size is removed from the
+  /// cache.
+  pub fn get<T: Send + Sync + 'static>(
+    &self,
+    key: &str,
+    format: Format,
+    size: u64,
+  ) -> Option<Arc<T>> {
+    let key = (format!("{}{key}", self.prefix), format);
+    let mut entries = self.entries.lock().ok()?;
+
+    let entry = entries.get(&key)?;
+    if entry.size != size {
+      entries.remove(&key);
+      return None;
+    }
+
+    entry.index.clone().downcast::<T>().ok()
+  }
+
+  /// Cache the index for the key and format, along with the size of its data file, evicting the
+  /// least recently used index if the cache is full.
+  pub fn insert<T: Send + Sync + 'static>(
+    &self,
+    key: &str,
+    format: Format,
+    size: u64,
+    index: Arc<T>,
+  ) {
+    let key = (format!("{}{key}", self.prefix), format);
+    if let Ok(mut entries) = self.entries.lock() {
+      entries.insert(key, CachedIndex { size, index }, self.capacity);
+    }
+  }
+}
+
+impl Debug for IndexCache {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("IndexCache")
+      .field("capacity", &self.capacity)
+      .field("prefix", &self.prefix)
+      .field("len", &self.len())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn get_cached_index() {
+    let cache = IndexCache::new(2);
+    cache.insert("key.bai", Format::Bam, 10, Arc::new(vec![1u8]));
+
+    assert_eq!(
+      cache.get::<Vec<u8>>("key.bai", Format::Bam, 10),
+      Some(Arc::new(vec![1]))
+    );
+    assert_eq!(cache.get::<Vec<u8>>("key.bai", Format::Cram, 10), None);
+    assert_eq!(cache.get::<u8>("key.bai", Format::Bam, 10), None);
+    assert_eq!(
+      cache
+        .clone()
+        .with_prefix("bucket/".to_string())
+        .get::<Vec<u8>>("key.bai", Format::Bam, 10),
+      None
+    );
+  }
+
+  #[test]
+  fn get_shares_cached_index() {
+    let cache = IndexCache::new(1);
+    let index = Arc::new(vec![1u8]);
+    cache.insert("key.bai", Format::Bam, 10, index.clone());
+
+    let cached = cache.get::<Vec<u8>>("key.bai", Format::Bam, 10).unwrap();
+    assert!(Arc::ptr_eq(&cached, &index));
+  }
+
+  #[test]
+  fn evicts_least_recently_used() {
+    let cache = IndexCache::new(2);
+    cache.insert("key1.bai", Format::Bam, 10, Arc::new(1u8));
+    cache.insert("key2.bai", Format::Bam, 10, Arc::new(2u8));
+
+    assert_eq!(
+      cache.get::<u8>("key1.bai", Format::Bam, 10),
+      Some(Arc::new(1))
+    );
+    cache.insert("key3.bai", Format::Bam, 10, Arc::new(3u8));
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(
+      cache.get::<u8>("key1.bai", Format::Bam, 10),
+      Some(Arc::new(1))
+    );
+    assert_eq!(cache.get::<u8>("key2.bai", Format::Bam, 10), None);
+    assert_eq!(
+      cache.get::<u8>("key3.bai", Format::Bam, 10),
+      Some(Arc::new(3))
+    );
+  }
+
+  #[test]
+  fn invalidates_changed_size() {
+    let cache = IndexCache::new(2);
+    let cloned = cache.clone();
+    cache.insert("key.bai", Format::Bam, 10, Arc::new(1u8));
+
+    assert_eq!(cloned.get::<u8>("key.bai", Format::Bam, 11), None);
+    assert!(cache.is_empty());
+  }
+
+  #[test]
+  fn zero_capacity() {
+    let cache = IndexCache::new(0);
+    cache.insert("key.bai", Format::Bam, 10, Arc::new(1u8));
+
+    assert!(cache.is_empty());
+  }
+}
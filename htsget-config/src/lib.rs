@@ -2,6 +2,7 @@ pub use clap::command;
 
 pub mod config;
 pub mod error;
+pub mod index_cache;
 pub mod resolver;
 pub mod storage;
 pub mod tls;
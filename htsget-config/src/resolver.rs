@@ -1,21 +1,31 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::result;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use regex::{Error, Regex};
+use regex::{Error, Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use serde_with::with_prefix;
-use tracing::instrument;
-
-use crate::config::DataServerConfig;
+use tracing::{instrument, warn};
+
+use crate::config::{DataServerConfig, TicketServerConfig};
+use crate::index_cache::IndexCache;
+#[cfg(feature = "azure-storage")]
+use crate::storage::azure::Azure;
+#[cfg(feature = "gcs-storage")]
+use crate::storage::gcs::Gcs;
 use crate::storage::local::Local;
 #[cfg(feature = "s3-storage")]
 use crate::storage::s3::S3;
 #[cfg(feature = "url-storage")]
 use crate::storage::url::UrlStorageClient;
-use crate::storage::{ResolvedId, Storage};
+use crate::storage::{ResolvedId, Storage, StorageCell};
 use crate::types::Format::{Bam, Bcf, Cram, Vcf};
-use crate::types::{Class, Fields, Format, Interval, Query, Response, Result, TaggedTypeAll, Tags};
+use crate::types::{
+  Class, Fields, Format, FormatConflict, HtsGetError, Interval, OversizedRange, Query, Response,
+  Result, SearchOptions, ServerTiming, TaggedTypeAll, Tags, UnmappedVariants,
+};
 
 /// A trait which matches the query id, replacing the match in the substitution text.
 pub trait IdResolver {
@@ -26,26 +36,50 @@ pub trait IdResolver {
 /// A trait for determining the response from `Storage`.
 #[async_trait]
 pub trait ResolveResponse {
+  /// Whether responses can be stored in, and served from, the response cache.
+  const CACHEABLE: bool = true;
+
   /// Convert from `LocalStorage`.
-  async fn from_local(local_storage: &Local, query: &Query) -> Result<Response>;
+  async fn from_local(
+    local_storage: &Local,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response>;
 
   /// Convert from `S3Storage`.
   #[cfg(feature = "s3-storage")]
-  async fn from_s3(s3_storage: &S3, query: &Query) -> Result<Response>;
+  async fn from_s3(s3_storage: &S3, query: &Query, options: &SearchOptions) -> Result<Response>;
 
   /// Convert from `UrlStorage`.
   #[cfg(feature = "url-storage")]
-  async fn from_url(url_storage: &UrlStorageClient, query: &Query) -> Result<Response>;
+  async fn from_url(
+    url_storage: &UrlStorageClient,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response>;
+
+  /// Convert from `GcsStorage`.
+  #[cfg(feature = "gcs-storage")]
+  async fn from_gcs(gcs_storage: &Gcs, query: &Query, options: &SearchOptions) -> Result<Response>;
+
+  /// Convert from `AzureBlobStorage`.
+  #[cfg(feature = "azure-storage")]
+  async fn from_azure(
+    azure_storage: &Azure,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response>;
 }
 
 /// A trait which uses storage to resolve requests into responses.
 #[async_trait]
 pub trait StorageResolver {
-  /// Resolve a request into a response.
+  /// Resolve a request into a response, along with the time since the response was computed if
+  /// it was served from the response cache.
   async fn resolve_request<T: ResolveResponse>(
     &self,
     query: &mut Query,
-  ) -> Option<Result<Response>>;
+  ) -> Option<Result<(Response, Option<Duration>)>>;
 }
 
 /// Determines whether the query matches for use with the storage.
@@ -54,9 +88,225 @@ pub trait QueryAllowed {
   fn query_allowed(&self, query: &Query) -> bool;
 }
 
+/// A transform from the resolved id of a file to the id used to locate its index files, for storage
+/// layouts which keep indexes separate from the data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexLocation {
+  #[serde(with = "serde_regex")]
+  regex: Regex,
+  substitution_string: String,
+}
+
+impl IndexLocation {
+  /// Create a new index location.
+  pub fn new(regex: &str, substitution_string: &str) -> result::Result<Self, regex::Error> {
+    Ok(Self {
+      regex: Regex::new(regex)?,
+      substitution_string: substitution_string.to_string(),
+    })
+  }
+
+  /// Get the regex.
+  pub fn regex(&self) -> &Regex {
+    &self.regex
+  }
+
+  /// Get the substitution string.
+  pub fn substitution_string(&self) -> &str {
+    &self.substitution_string
+  }
+
+  /// Get the index id from the resolved id.
+  pub fn index_id(&self, id: &str) -> String {
+    self
+      .regex
+      .replace(id, &self.substitution_string)
+      .to_string()
+  }
+}
+
+/// The default maximum number of responses held by a response cache.
+pub const DEFAULT_RESPONSE_CACHE_CAPACITY: usize = 1000;
+
+/// The normalized form of a query used to look up cached responses. This contains every part of
+/// the query that the search depends on, so that queries which differ in any of them, such as
+/// invalid fields or tags, do not share a response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ResponseCacheKey {
+  id: String,
+  index_id: String,
+  format: Format,
+  format_omitted: bool,
+  class: Class,
+  reference_name: Option<String>,
+  interval: Interval,
+  fields: Option<BTreeSet<String>>,
+  tags: Option<BTreeSet<String>>,
+  no_tags: Option<BTreeSet<String>>,
+}
+
+impl From<&Query> for ResponseCacheKey {
+  fn from(query: &Query) -> Self {
+    let sorted = |set: &HashSet<String>| set.iter().cloned().collect::<BTreeSet<_>>();
+
+    Self {
+      id: query.id().to_string(),
+      index_id: query.index_id().to_string(),
+      format: query.format(),
+      format_omitted: query.format_omitted(),
+      class: query.class(),
+      reference_name: query.reference_name().map(str::to_string),
+      interval: query.interval(),
+      fields: match query.fields() {
+        Fields::Tagged(TaggedTypeAll::All) => None,
+        Fields::List(fields) => Some(sorted(fields)),
+      },
+      tags: match query.tags() {
+        Tags::Tagged(TaggedTypeAll::All) => None,
+        Tags::List(tags) => Some(sorted(tags)),
+      },
+      no_tags: query.no_tags().0.as_ref().map(sorted),
+    }
+  }
+}
+
+/// A cached response, along with when it was computed and when it was last used.
+#[derive(Debug)]
+struct CachedResponse {
+  computed: Instant,
+  last_used: u64,
+  response: Response,
+}
+
+/// The cached responses, along with the order in which they were last used.
+#[derive(Debug, Default)]
+struct CachedResponses {
+  counter: u64,
+  responses: HashMap<ResponseCacheKey, CachedResponse>,
+  recency: BTreeMap<u64, ResponseCacheKey>,
+}
+
+impl CachedResponses {
+  /// Get the next value of the recency counter.
+  fn next_use(&mut self) -> u64 {
+    self.counter += 1;
+    self.counter
+  }
+
+  /// Remove the response for the key, returning it if it exists.
+  fn remove(&mut self, key: &ResponseCacheKey) -> Option<CachedResponse> {
+    let cached = self.responses.remove(key)?;
+    self.recency.remove(&cached.last_used);
+    Some(cached)
+  }
+}
+
+/// A least recently used cache of computed responses, keyed by the normalized query. Responses
+/// expire after the ttl. Clones of the cache share the same responses.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+  ttl: Duration,
+  capacity: usize,
+  responses: Arc<Mutex<CachedResponses>>,
+}
+
+impl ResponseCache {
+  /// Create a new response cache which holds at most `capacity` responses, where responses
+  /// expire after the ttl.
+  pub fn new(ttl: Duration, capacity: usize) -> Self {
+    Self {
+      ttl,
+      capacity,
+      responses: Default::default(),
+    }
+  }
+
+  /// Get the maximum number of responses held by the cache.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Get the number of responses held by the cache.
+  pub fn len(&self) -> usize {
+    self
+      .responses
+      .lock()
+      .map(|responses| responses.responses.len())
+      .unwrap_or(0)
+  }
+
+  /// Get whether the cache holds no responses.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Get the cached response for the query, if it exists and has not expired, along with the
+  /// time since it was cached.
+  pub fn get(&self, query: &Query) -> Option<(Response, Duration)> {
+    let mut responses = self.responses.lock().ok()?;
+    let key = ResponseCacheKey::from(query);
+
+    let last_used = responses.next_use();
+    let cached = responses.responses.get_mut(&key)?;
+    if cached.computed.elapsed() >= self.ttl {
+      responses.remove(&key);
+      return None;
+    }
+
+    let previous_use = cached.last_used;
+    cached.last_used = last_used;
+    let response = (cached.response.clone(), cached.computed.elapsed());
+
+    responses.recency.remove(&previous_use);
+    responses.recency.insert(last_used, key);
+
+    Some(response)
+  }
+
+  /// Cache the response for the query, removing any expired responses and evicting the least
+  /// recently used response if the cache is full.
+  pub fn insert(&self, query: &Query, response: Response) {
+    if self.capacity == 0 {
+      return;
+    }
+
+    if let Ok(mut responses) = self.responses.lock() {
+      let expired = responses
+        .responses
+        .iter()
+        .filter(|(_, cached)| cached.computed.elapsed() >= self.ttl)
+        .map(|(key, _)| key.clone())
+        .collect::<Vec<_>>();
+      for key in expired {
+        responses.remove(&key);
+      }
+
+      let key = ResponseCacheKey::from(query);
+      responses.remove(&key);
+      while responses.responses.len() >= self.capacity {
+        let Some((_, oldest)) = responses.recency.pop_first() else {
+          break;
+        };
+        responses.responses.remove(&oldest);
+      }
+
+      let last_used = responses.next_use();
+      responses.recency.insert(last_used, key.clone());
+      responses.responses.insert(
+        key,
+        CachedResponse {
+          computed: Instant::now(),
+          last_used,
+          response,
+        },
+      );
+    }
+  }
+}
+
 /// A regex storage is a storage that matches ids using Regex.
 #[derive(Serialize, Debug, Clone, Deserialize)]
-#[serde(default)]
+#[serde(try_from = "ResolverConfig")]
 pub struct Resolver {
   #[serde(with = "serde_regex")]
   regex: Regex,
@@ -64,6 +314,174 @@ pub struct Resolver {
   substitution_string: String,
   storage: Storage,
   allow_guard: AllowGuard,
+  case_insensitive: bool,
+  validate_reference_names: bool,
+  validate_bgzf: bool,
+  whole_file_fallback_max_size: Option<u64>,
+  index_location: Option<IndexLocation>,
+  include_total_size: bool,
+  max_total_response_bytes: Option<u64>,
+  index_extensions: Vec<String>,
+  include_sender_public_key: bool,
+  search_timeout: Option<u64>,
+  include_content_range: bool,
+  oversized_range: OversizedRange,
+  max_bytes_per_url: Option<u64>,
+  min_block_size: Option<u64>,
+  max_urls_per_ticket: Option<usize>,
+  max_query_span: HashMap<Format, u64>,
+  log_index_scan: bool,
+  merge_gap: Option<u64>,
+  label: Option<String>,
+  format: Option<Format>,
+  format_conflict: FormatConflict,
+  unmapped_variants: UnmappedVariants,
+  max_id_length: Option<usize>,
+  response_cache_ttl: Option<u64>,
+  response_cache_capacity: usize,
+  index_cache_capacity: Option<usize>,
+  preserve_classes: bool,
+  default_format: Option<Format>,
+  provenance: BTreeMap<String, String>,
+  header_without_index: bool,
+  normalize_chr_prefix: bool,
+  #[serde(skip)]
+  server_timing: bool,
+  #[serde(skip)]
+  response_cache: Option<ResponseCache>,
+  #[serde(skip)]
+  index_cache: Option<IndexCache>,
+  #[serde(skip)]
+  probe_storage: StorageCell,
+}
+
+/// The deserialized form of a resolver, before the regex is compiled.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+struct ResolverConfig {
+  regex: String,
+  substitution_string: String,
+  storage: Storage,
+  allow_guard: AllowGuard,
+  case_insensitive: bool,
+  validate_reference_names: bool,
+  validate_bgzf: bool,
+  whole_file_fallback_max_size: Option<u64>,
+  index_location: Option<IndexLocation>,
+  include_total_size: bool,
+  max_total_response_bytes: Option<u64>,
+  index_extensions: Vec<String>,
+  include_sender_public_key: bool,
+  search_timeout: Option<u64>,
+  include_content_range: bool,
+  oversized_range: OversizedRange,
+  max_bytes_per_url: Option<u64>,
+  min_block_size: Option<u64>,
+  max_urls_per_ticket: Option<usize>,
+  max_query_span: HashMap<Format, u64>,
+  log_index_scan: bool,
+  merge_gap: Option<u64>,
+  label: Option<String>,
+  format: Option<Format>,
+  format_conflict: FormatConflict,
+  unmapped_variants: UnmappedVariants,
+  max_id_length: Option<usize>,
+  response_cache_ttl: Option<u64>,
+  response_cache_capacity: usize,
+  index_cache_capacity: Option<usize>,
+  preserve_classes: bool,
+  default_format: Option<Format>,
+  provenance: BTreeMap<String, String>,
+  header_without_index: bool,
+  normalize_chr_prefix: bool,
+}
+
+impl Default for ResolverConfig {
+  fn default() -> Self {
+    Self {
+      regex: ".*".to_string(),
+      substitution_string: "$0".to_string(),
+      storage: Default::default(),
+      allow_guard: Default::default(),
+      case_insensitive: false,
+      validate_reference_names: false,
+      validate_bgzf: false,
+      whole_file_fallback_max_size: None,
+      index_location: None,
+      include_total_size: false,
+      max_total_response_bytes: None,
+      index_extensions: vec![],
+      include_sender_public_key: false,
+      search_timeout: None,
+      include_content_range: false,
+      oversized_range: OversizedRange::default(),
+      max_bytes_per_url: None,
+      min_block_size: None,
+      max_urls_per_ticket: None,
+      max_query_span: HashMap::new(),
+      log_index_scan: false,
+      merge_gap: None,
+      label: None,
+      format: None,
+      format_conflict: FormatConflict::default(),
+      unmapped_variants: UnmappedVariants::default(),
+      max_id_length: None,
+      response_cache_ttl: None,
+      response_cache_capacity: DEFAULT_RESPONSE_CACHE_CAPACITY,
+      index_cache_capacity: None,
+      preserve_classes: false,
+      default_format: None,
+      provenance: BTreeMap::new(),
+      header_without_index: false,
+      normalize_chr_prefix: false,
+    }
+  }
+}
+
+impl TryFrom<ResolverConfig> for Resolver {
+  type Error = Error;
+
+  fn try_from(config: ResolverConfig) -> result::Result<Self, Self::Error> {
+    Ok(
+      Self::new(
+        config.storage,
+        &config.regex,
+        &config.substitution_string,
+        config.allow_guard,
+      )?
+      .with_case_insensitive(config.case_insensitive)?
+      .with_validate_reference_names(config.validate_reference_names)
+      .with_validate_bgzf(config.validate_bgzf)
+      .with_whole_file_fallback_max_size(config.whole_file_fallback_max_size)
+      .with_index_location(config.index_location)
+      .with_include_total_size(config.include_total_size)
+      .with_max_total_response_bytes(config.max_total_response_bytes)
+      .with_index_extensions(config.index_extensions)
+      .with_include_sender_public_key(config.include_sender_public_key)
+      .with_search_timeout(config.search_timeout)
+      .with_include_content_range(config.include_content_range)
+      .with_oversized_range(config.oversized_range)
+      .with_max_bytes_per_url(config.max_bytes_per_url)
+      .with_min_block_size(config.min_block_size)
+      .with_max_urls_per_ticket(config.max_urls_per_ticket)
+      .with_max_query_span(config.max_query_span)
+      .with_log_index_scan(config.log_index_scan)
+      .with_merge_gap(config.merge_gap)
+      .with_label(config.label)
+      .with_format(config.format)
+      .with_format_conflict(config.format_conflict)
+      .with_unmapped_variants(config.unmapped_variants)
+      .with_max_id_length(config.max_id_length)
+      .with_response_cache_capacity(config.response_cache_capacity)
+      .with_response_cache_ttl(config.response_cache_ttl)
+      .with_index_cache_capacity(config.index_cache_capacity)
+      .with_preserve_classes(config.preserve_classes)
+      .with_default_format(config.default_format)
+      .with_provenance(config.provenance)
+      .with_header_without_index(config.header_without_index)
+      .with_normalize_chr_prefix(config.normalize_chr_prefix),
+    )
+  }
 }
 
 /// A type which holds a resolved storage and an resolved id.
@@ -288,22 +706,102 @@ impl Resolver {
       substitution_string: replacement_string.to_string(),
       storage,
       allow_guard,
+      case_insensitive: false,
+      validate_reference_names: false,
+      validate_bgzf: false,
+      whole_file_fallback_max_size: None,
+      index_location: None,
+      include_total_size: false,
+      max_total_response_bytes: None,
+      index_extensions: vec![],
+      include_sender_public_key: false,
+      search_timeout: None,
+      include_content_range: false,
+      oversized_range: OversizedRange::default(),
+      max_bytes_per_url: None,
+      min_block_size: None,
+      max_urls_per_ticket: None,
+      max_query_span: HashMap::new(),
+      log_index_scan: false,
+      merge_gap: None,
+      label: None,
+      format: None,
+      format_conflict: FormatConflict::default(),
+      unmapped_variants: UnmappedVariants::default(),
+      max_id_length: None,
+      response_cache_ttl: None,
+      response_cache_capacity: DEFAULT_RESPONSE_CACHE_CAPACITY,
+      index_cache_capacity: None,
+      preserve_classes: false,
+      default_format: None,
+      provenance: BTreeMap::new(),
+      header_without_index: false,
+      normalize_chr_prefix: false,
+      server_timing: false,
+      response_cache: None,
+      index_cache: None,
+      probe_storage: Default::default(),
     })
   }
 
+  /// Set whether the regex should match ids case-insensitively. This recompiles the regex.
+  pub fn with_case_insensitive(mut self, case_insensitive: bool) -> result::Result<Self, Error> {
+    self.regex = RegexBuilder::new(self.regex.as_str())
+      .case_insensitive(case_insensitive)
+      .build()?;
+    self.case_insensitive = case_insensitive;
+
+    Ok(self)
+  }
+
   /// Set the local resolvers from the data server config.
   pub fn resolvers_from_data_server_config(&mut self, config: &DataServerConfig) {
     match self.storage() {
       Storage::Local(local) => {
         if local.use_data_server_config() {
-          self.storage = Storage::Local(config.into());
+          let max_concurrent_requests = local.max_concurrent_requests().clone();
+          let cache_eof = local.cache_eof().clone();
+          let read_buffer_size = local.read_buffer_size();
+          let read_ahead = local.read_ahead();
+          let reject_path_traversal = local.reject_path_traversal();
+          let etag = local.etag();
+          let index_local_path = local.index_local_path().map(str::to_string);
+          let url_expiry_secs = local.url_expiry_secs();
+          self.storage = Storage::Local(
+            Local::from(config)
+              .set_max_concurrent_requests(max_concurrent_requests)
+              .set_cache_eof(cache_eof)
+              .set_read_buffer_size(read_buffer_size)
+              .set_read_ahead(read_ahead)
+              .set_reject_path_traversal(reject_path_traversal)
+              .set_etag(etag)
+              .set_index_local_path(index_local_path)
+              .set_url_expiry_secs(url_expiry_secs),
+          );
+          self.probe_storage = Default::default();
         }
       }
       #[cfg(feature = "s3-storage")]
       Storage::S3(_) => {}
       #[cfg(feature = "url-storage")]
       Storage::Url(_) => {}
+      #[cfg(feature = "gcs-storage")]
+      Storage::Gcs(_) => {}
+      #[cfg(feature = "azure-storage")]
+      Storage::Azure(_) => {}
+    }
+  }
+
+  /// Set the maximum total response size and search timeout from the ticket server config, if
+  /// they are not already set, and whether server timings are recorded.
+  pub fn resolvers_from_ticket_server_config(&mut self, config: &TicketServerConfig) {
+    if self.max_total_response_bytes.is_none() {
+      self.max_total_response_bytes = config.max_total_response_bytes();
     }
+    if self.search_timeout.is_none() {
+      self.search_timeout = config.search_timeout();
+    }
+    self.server_timing = config.server_timing();
   }
 
   /// Get the match associated with the capture group at index `i` using the `regex_match`.
@@ -316,183 +814,802 @@ impl Resolver {
     &self.regex
   }
 
-  /// Get the substitution string.
-  pub fn substitution_string(&self) -> &str {
-    &self.substitution_string
+  /// Get an example id which is matched by both the regex of this resolver and the regex of the
+  /// `other` resolver, if one can be found. Candidate ids are built from the literal prefix of each
+  /// regex, so not every overlap is found. Allow guards are not considered.
+  pub fn overlapping_id(&self, other: &Resolver) -> Option<String> {
+    [self, other]
+      .into_iter()
+      .flat_map(|resolver| resolver.candidate_ids())
+      .find(|id| self.regex.is_match(id) && other.regex.is_match(id))
   }
 
-  /// Get the query guard.
-  pub fn allow_guard(&self) -> &AllowGuard {
-    &self.allow_guard
+  /// Get candidate ids which may match the regex, built from its literal prefix.
+  fn candidate_ids(&self) -> [String; 3] {
+    let pattern = self.regex.as_str();
+    let prefix: String = pattern
+      .strip_prefix('^')
+      .unwrap_or(pattern)
+      .chars()
+      .take_while(|c| !r"\.+*?()|[]{}^$".contains(*c))
+      .collect();
+
+    [
+      prefix.clone(),
+      format!("{prefix}id"),
+      format!("{prefix}id/id"),
+    ]
   }
 
-  /// Get the storage backend.
-  pub fn storage(&self) -> &Storage {
-    &self.storage
+  /// Set whether the reference name of a query should be validated against the file header
+  /// before searching.
+  pub fn with_validate_reference_names(mut self, validate_reference_names: bool) -> Self {
+    self.validate_reference_names = validate_reference_names;
+    self
   }
 
-  /// Get allow formats.
-  pub fn allow_formats(&self) -> &[Format] {
-    self.allow_guard.allow_formats()
+  /// Get whether the reference name of a query is validated against the file header.
+  pub fn validate_reference_names(&self) -> bool {
+    self.validate_reference_names
   }
 
-  /// Get allow classes.
-  pub fn allow_classes(&self) -> &[Class] {
-    self.allow_guard.allow_classes()
+  /// Set whether VCF files should be checked to be BGZF compressed before searching.
+  pub fn with_validate_bgzf(mut self, validate_bgzf: bool) -> Self {
+    self.validate_bgzf = validate_bgzf;
+    self
   }
 
-  /// Get allow interval.
-  pub fn allow_interval(&self) -> Interval {
-    self.allow_guard.allow_interval
+  /// Get whether VCF files are checked to be BGZF compressed before searching.
+  pub fn validate_bgzf(&self) -> bool {
+    self.validate_bgzf
   }
 
-  /// Get allow reference names.
-  pub fn allow_reference_names(&self) -> &ReferenceNames {
-    &self.allow_guard.allow_reference_names
+  /// Set the maximum file size for which the whole file is returned if the index is missing.
+  pub fn with_whole_file_fallback_max_size(
+    mut self,
+    whole_file_fallback_max_size: Option<u64>,
+  ) -> Self {
+    self.whole_file_fallback_max_size = whole_file_fallback_max_size;
+    self
   }
 
-  /// Get allow fields.
-  pub fn allow_fields(&self) -> &Fields {
-    &self.allow_guard.allow_fields
+  /// Get the maximum file size for which the whole file is returned if the index is missing.
+  pub fn whole_file_fallback_max_size(&self) -> Option<u64> {
+    self.whole_file_fallback_max_size
   }
 
-  /// Get allow tags.
-  pub fn allow_tags(&self) -> &Tags {
-    &self.allow_guard.allow_tags
+  /// Set the transform used to locate index files from the resolved id.
+  pub fn with_index_location(mut self, index_location: Option<IndexLocation>) -> Self {
+    self.index_location = index_location;
+    self
   }
-}
 
-impl IdResolver for Resolver {
-  #[instrument(level = "trace", skip(self), ret)]
-  fn resolve_id(&self, query: &Query) -> Option<ResolvedId> {
-    if self.regex.is_match(query.id()) && self.allow_guard.query_allowed(query) {
-      Some(ResolvedId::new(
-        self
-          .regex
-          .replace(query.id(), &self.substitution_string)
-          .to_string(),
-      ))
-    } else {
-      None
-    }
+  /// Get the transform used to locate index files from the resolved id.
+  pub fn index_location(&self) -> Option<&IndexLocation> {
+    self.index_location.as_ref()
   }
-}
 
-#[async_trait]
-impl StorageResolver for Resolver {
-  #[instrument(level = "trace", skip(self), ret)]
-  async fn resolve_request<T: ResolveResponse>(
-    &self,
-    query: &mut Query,
-  ) -> Option<Result<Response>> {
-    let resolved_id = self.resolve_id(query)?;
-    let _matched_id = query.id().to_string();
+  /// Set whether ticket responses include the total size of the byte ranges.
+  pub fn with_include_total_size(mut self, include_total_size: bool) -> Self {
+    self.include_total_size = include_total_size;
+    self
+  }
 
-    query.set_id(resolved_id.into_inner());
+  /// Get whether ticket responses include the total size of the byte ranges.
+  pub fn include_total_size(&self) -> bool {
+    self.include_total_size
+  }
 
-    match self.storage() {
-      Storage::Local(local_storage) => Some(T::from_local(local_storage, query).await),
-      #[cfg(feature = "s3-storage")]
-      Storage::S3(s3_storage) => {
-        let first_match = self.get_match(1, &_matched_id);
-        let mut s3_storage = s3_storage.clone();
-        if s3_storage.bucket.is_empty() {
-          s3_storage.bucket = first_match?.to_string();
-        }
+  /// Set the maximum total size of the byte ranges that a ticket response may contain.
+  pub fn with_max_total_response_bytes(mut self, max_total_response_bytes: Option<u64>) -> Self {
+    self.max_total_response_bytes = max_total_response_bytes;
+    self
+  }
 
-        Some(T::from_s3(&s3_storage, query).await)
-      }
-      #[cfg(feature = "url-storage")]
-      Storage::Url(url_storage) => Some(T::from_url(url_storage, query).await),
-    }
+  /// Get the maximum total size of the byte ranges that a ticket response may contain.
+  pub fn max_total_response_bytes(&self) -> Option<u64> {
+    self.max_total_response_bytes
   }
-}
 
-impl IdResolver for &[Resolver] {
-  #[instrument(level = "trace", skip(self), ret)]
-  fn resolve_id(&self, query: &Query) -> Option<ResolvedId> {
-    self.iter().find_map(|resolver| resolver.resolve_id(query))
+  /// Set the additional index file extensions which are tried, in order, after the default
+  /// index file ending, e.g. `.crai` for a CRAM index which does not end in `.cram.crai`.
+  pub fn with_index_extensions(mut self, index_extensions: Vec<String>) -> Self {
+    self.index_extensions = index_extensions;
+    self
   }
-}
 
-#[async_trait]
-impl StorageResolver for &[Resolver] {
-  #[instrument(level = "trace", skip(self), ret)]
-  async fn resolve_request<T: ResolveResponse>(
-    &self,
-    query: &mut Query,
-  ) -> Option<Result<Response>> {
-    for resolver in self.iter() {
-      if let Some(resolved_storage) = resolver.resolve_request::<T>(query).await {
-        return Some(resolved_storage);
-      }
-    }
+  /// Get the additional index file extensions.
+  pub fn index_extensions(&self) -> &[String] {
+    &self.index_extensions
+  }
 
-    None
+  /// Set whether ticket responses include the public key of the sender of encrypted data.
+  pub fn with_include_sender_public_key(mut self, include_sender_public_key: bool) -> Self {
+    self.include_sender_public_key = include_sender_public_key;
+    self
   }
-}
 
-#[cfg(test)]
-mod tests {
-  use http::uri::Authority;
+  /// Get whether ticket responses include the public key of the sender of encrypted data.
+  pub fn include_sender_public_key(&self) -> bool {
+    self.include_sender_public_key
+  }
 
-  #[cfg(feature = "url-storage")]
-  use {
-    crate::storage::url, crate::storage::url::ValidatedUrl, http::Uri as InnerUrl,
-    reqwest::ClientBuilder, std::str::FromStr,
-  };
+  /// Set the number of seconds a search may take before it is aborted.
+  pub fn with_search_timeout(mut self, search_timeout: Option<u64>) -> Self {
+    self.search_timeout = search_timeout;
+    self
+  }
 
-  use crate::config::tests::{test_config_from_env, test_config_from_file};
-  #[cfg(feature = "s3-storage")]
-  use crate::storage::s3::S3;
-  use crate::types::Scheme::Http;
-  use crate::types::Url;
+  /// Get the number of seconds a search may take before it is aborted.
+  pub fn search_timeout(&self) -> Option<u64> {
+    self.search_timeout
+  }
 
-  use super::*;
+  /// Set whether each url in ticket responses includes its byte range within the concatenated file.
+  pub fn with_include_content_range(mut self, include_content_range: bool) -> Self {
+    self.include_content_range = include_content_range;
+    self
+  }
 
-  struct TestResolveResponse;
+  /// Get whether each url in ticket responses includes its byte range within the concatenated file.
+  pub fn include_content_range(&self) -> bool {
+    self.include_content_range
+  }
 
-  #[async_trait]
-  impl ResolveResponse for TestResolveResponse {
-    async fn from_local(local_storage: &Local, _: &Query) -> Result<Response> {
-      Ok(Response::new(
-        Bam,
-        vec![Url::new(local_storage.authority().to_string())],
-      ))
-    }
+  /// Set how byte ranges which extend past the end of the file are handled.
+  pub fn with_oversized_range(mut self, oversized_range: OversizedRange) -> Self {
+    self.oversized_range = oversized_range;
+    self
+  }
 
-    #[cfg(feature = "s3-storage")]
-    async fn from_s3(s3_storage: &S3, _: &Query) -> Result<Response> {
-      Ok(Response::new(Bam, vec![Url::new(s3_storage.bucket())]))
-    }
+  /// Get how byte ranges which extend past the end of the file are handled.
+  pub fn oversized_range(&self) -> OversizedRange {
+    self.oversized_range
+  }
 
-    #[cfg(feature = "url-storage")]
-    async fn from_url(url_storage: &UrlStorageClient, _: &Query) -> Result<Response> {
-      Ok(Response::new(
-        Bam,
-        vec![Url::new(url_storage.url().to_string())],
-      ))
-    }
+  /// Set the maximum number of bytes a single url in ticket responses may cover.
+  pub fn with_max_bytes_per_url(mut self, max_bytes_per_url: Option<u64>) -> Self {
+    self.max_bytes_per_url = max_bytes_per_url;
+    self
   }
 
-  #[tokio::test]
-  async fn resolver_resolve_local_request() {
-    let local_storage = Local::new(
-      Http,
-      Authority::from_static("127.0.0.1:8080"),
-      "data".to_string(),
-      "/data".to_string(),
-      false,
-    );
-    let resolver = Resolver::new(
-      Storage::Local(local_storage),
-      "id",
-      "$0-test",
-      AllowGuard::default(),
-    )
-    .unwrap();
+  /// Get the maximum number of bytes a single url in ticket responses may cover.
+  pub fn max_bytes_per_url(&self) -> Option<u64> {
+    self.max_bytes_per_url
+  }
 
-    expected_resolved_request(resolver, "127.0.0.1:8080").await;
+  /// Set the minimum size of a fragment produced when splitting ranges.
+  pub fn with_min_block_size(mut self, min_block_size: Option<u64>) -> Self {
+    self.min_block_size = min_block_size;
+    self
+  }
+
+  /// Get the minimum size of a fragment produced when splitting ranges.
+  pub fn min_block_size(&self) -> Option<u64> {
+    self.min_block_size
+  }
+
+  /// Set the maximum number of urls in ticket responses. Ranges are coalesced across gaps until
+  /// the response fits.
+  pub fn with_max_urls_per_ticket(mut self, max_urls_per_ticket: Option<usize>) -> Self {
+    self.max_urls_per_ticket = max_urls_per_ticket;
+    self
+  }
+
+  /// Get the maximum number of urls in ticket responses.
+  pub fn max_urls_per_ticket(&self) -> Option<usize> {
+    self.max_urls_per_ticket
+  }
+
+  /// Set the maximum number of bases that the range of a query may span, for each format. Queries
+  /// with a wider range are rejected.
+  pub fn with_max_query_span(mut self, max_query_span: HashMap<Format, u64>) -> Self {
+    self.max_query_span = max_query_span;
+    self
+  }
+
+  /// Get the maximum number of bases that the range of a query may span, for each format.
+  pub fn max_query_span(&self) -> &HashMap<Format, u64> {
+    &self.max_query_span
+  }
+
+  /// Set whether the number of index bins and chunks scanned by a search is logged.
+  pub fn with_log_index_scan(mut self, log_index_scan: bool) -> Self {
+    self.log_index_scan = log_index_scan;
+    self
+  }
+
+  /// Get whether the number of index bins and chunks scanned by a search is logged.
+  pub fn log_index_scan(&self) -> bool {
+    self.log_index_scan
+  }
+
+  /// Set the maximum number of bytes between two byte ranges which are merged into one range.
+  pub fn with_merge_gap(mut self, merge_gap: Option<u64>) -> Self {
+    self.merge_gap = merge_gap;
+    self
+  }
+
+  /// Get the maximum number of bytes between two byte ranges which are merged into one range.
+  pub fn merge_gap(&self) -> Option<u64> {
+    self.merge_gap
+  }
+
+  /// Set the human-readable label of the dataset served by this resolver.
+  pub fn with_label(mut self, label: Option<String>) -> Self {
+    self.label = label;
+    self
+  }
+
+  /// Get the human-readable label of the dataset served by this resolver.
+  pub fn label(&self) -> Option<&str> {
+    self.label.as_deref()
+  }
+
+  /// Set the format of the objects that this resolver serves.
+  pub fn with_format(mut self, format: Option<Format>) -> Self {
+    self.format = format;
+    self
+  }
+
+  /// Get the format of the objects that this resolver serves.
+  pub fn format(&self) -> Option<Format> {
+    self.format
+  }
+
+  /// Set how a requested format which conflicts with the format of this resolver is handled.
+  pub fn with_format_conflict(mut self, format_conflict: FormatConflict) -> Self {
+    self.format_conflict = format_conflict;
+    self
+  }
+
+  /// Get how a requested format which conflicts with the format of this resolver is handled.
+  pub fn format_conflict(&self) -> FormatConflict {
+    self.format_conflict
+  }
+
+  /// Set the format used when a request omits the format. The default format is only applied to
+  /// requests for the endpoint that it belongs to, otherwise the endpoint default is used.
+  pub fn with_default_format(mut self, default_format: Option<Format>) -> Self {
+    self.default_format = default_format;
+    self
+  }
+
+  /// Get the format used when a request omits the format.
+  pub fn default_format(&self) -> Option<Format> {
+    self.default_format
+  }
+
+  /// Apply the default format if the request omitted the format and the default format belongs
+  /// to the same endpoint as the format of the query.
+  fn resolve_default_format(&self, query: &mut Query) {
+    let same_endpoint =
+      |format: Format| matches!(format, Bam | Cram) == matches!(query.format(), Bam | Cram);
+
+    if let Some(default_format) = self.default_format {
+      if query.format_omitted() && same_endpoint(default_format) {
+        query.set_format(default_format);
+      }
+    }
+  }
+
+  /// Apply the format conflict policy if the requested format differs from the format of this
+  /// resolver.
+  fn resolve_format(&self, query: &mut Query) -> Result<()> {
+    match self.format {
+      Some(format) if format != query.format() => match self.format_conflict {
+        FormatConflict::Request => Ok(()),
+        FormatConflict::Resolver => {
+          query.set_format(format);
+          Ok(())
+        }
+        FormatConflict::Error => Err(HtsGetError::unsupported_format(format!(
+          "requested format `{}` conflicts with the format `{format}` of `{}`",
+          query.format(),
+          query.id()
+        ))),
+      },
+      _ => Ok(()),
+    }
+  }
+
+  /// Reject queries with a range that spans more bases than the maximum query span of the format.
+  /// Queries without an end position are not limited.
+  fn check_query_span(&self, query: &Query) -> Result<()> {
+    let (Some(max_query_span), Some(end)) = (
+      self.max_query_span.get(&query.format()),
+      query.interval().end(),
+    ) else {
+      return Ok(());
+    };
+
+    let span = u64::from(end.saturating_sub(query.interval().start().unwrap_or_default()));
+    if span > *max_query_span {
+      return Err(HtsGetError::invalid_range(format!(
+        "the range of the query spans {span} bases, which exceeds the maximum of {max_query_span} \
+        bases for `{}`",
+        query.format()
+      )));
+    }
+
+    Ok(())
+  }
+
+  /// Set how the `*` reference name is handled for variants.
+  pub fn with_unmapped_variants(mut self, unmapped_variants: UnmappedVariants) -> Self {
+    self.unmapped_variants = unmapped_variants;
+    self
+  }
+
+  /// Get how the `*` reference name is handled for variants.
+  pub fn unmapped_variants(&self) -> UnmappedVariants {
+    self.unmapped_variants
+  }
+
+  /// Set the maximum length of ids, in bytes. Longer ids which match the regex are logged and
+  /// rejected, so that ids matched by other resolvers are not affected. The regex engine matches
+  /// in time linear in the length of the id, so matching before checking the length is bounded.
+  pub fn with_max_id_length(mut self, max_id_length: Option<usize>) -> Self {
+    self.max_id_length = max_id_length;
+    self
+  }
+
+  /// Get the maximum length of ids, in bytes.
+  pub fn max_id_length(&self) -> Option<usize> {
+    self.max_id_length
+  }
+
+  /// Resolve the id of the query, returning an error if the id matches but is longer than the
+  /// maximum id length.
+  fn resolve_id_within_max_length(&self, query: &Query) -> Option<Result<ResolvedId>> {
+    let resolved_id = self.resolve_id(query)?;
+
+    match self.max_id_length {
+      Some(max_id_length) if query.id().len() > max_id_length => {
+        warn!(
+          id_length = query.id().len(),
+          regex = self.regex.as_str(),
+          "rejecting id which exceeded the maximum id length"
+        );
+
+        Some(Err(HtsGetError::invalid_input(format!(
+          "the id is longer than the maximum of {max_id_length} bytes"
+        ))))
+      }
+      _ => Some(Ok(resolved_id)),
+    }
+  }
+
+  /// Set the number of seconds that responses are cached for. Responses are not cached if this is
+  /// not set.
+  pub fn with_response_cache_ttl(mut self, response_cache_ttl: Option<u64>) -> Self {
+    self.response_cache_ttl = response_cache_ttl;
+    self.response_cache = response_cache_ttl
+      .map(|ttl| ResponseCache::new(Duration::from_secs(ttl), self.response_cache_capacity));
+    self
+  }
+
+  /// Get the number of seconds that responses are cached for.
+  pub fn response_cache_ttl(&self) -> Option<u64> {
+    self.response_cache_ttl
+  }
+
+  /// Set the maximum number of responses that are cached, evicting the least recently used
+  /// response when the cache is full.
+  pub fn with_response_cache_capacity(mut self, response_cache_capacity: usize) -> Self {
+    let response_cache_ttl = self.response_cache_ttl;
+    self.response_cache_capacity = response_cache_capacity;
+    self.with_response_cache_ttl(response_cache_ttl)
+  }
+
+  /// Get the maximum number of responses that are cached.
+  pub fn response_cache_capacity(&self) -> usize {
+    self.response_cache_capacity
+  }
+
+  /// Set the maximum number of parsed indexes that are cached. Indexes are not cached if this is
+  /// not set.
+  pub fn with_index_cache_capacity(mut self, index_cache_capacity: Option<usize>) -> Self {
+    self.index_cache_capacity = index_cache_capacity;
+    self.index_cache = index_cache_capacity.map(IndexCache::new);
+    self
+  }
+
+  /// Get the maximum number of parsed indexes that are cached.
+  pub fn index_cache_capacity(&self) -> Option<usize> {
+    self.index_cache_capacity
+  }
+
+  /// Set whether adjacent header and body ranges are kept as separate ranges when merging, rather
+  /// than being merged into a range without a class.
+  pub fn with_preserve_classes(mut self, preserve_classes: bool) -> Self {
+    self.preserve_classes = preserve_classes;
+    self
+  }
+
+  /// Get whether adjacent header and body ranges are kept as separate ranges when merging.
+  pub fn preserve_classes(&self) -> bool {
+    self.preserve_classes
+  }
+
+  /// Set the provenance metadata of the dataset, such as the reference build or pipeline version,
+  /// which is included in responses. Responses do not contain provenance if this is empty.
+  pub fn with_provenance(mut self, provenance: BTreeMap<String, String>) -> Self {
+    self.provenance = provenance;
+    self
+  }
+
+  /// Get the provenance metadata of the dataset.
+  pub fn provenance(&self) -> &BTreeMap<String, String> {
+    &self.provenance
+  }
+
+  /// Set whether header queries for files without an index are served by reading the header from
+  /// the start of the file. Body queries for these files still respond with not found.
+  pub fn with_header_without_index(mut self, header_without_index: bool) -> Self {
+    self.header_without_index = header_without_index;
+    self
+  }
+
+  /// Get whether header queries for files without an index are served by reading the header.
+  pub fn header_without_index(&self) -> bool {
+    self.header_without_index
+  }
+
+  /// Set whether a `chr` prefix is added to or stripped from requested reference names which are
+  /// not in the header, so that `chr1` and `1` are interchangeable regardless of the convention of
+  /// the file.
+  pub fn with_normalize_chr_prefix(mut self, normalize_chr_prefix: bool) -> Self {
+    self.normalize_chr_prefix = normalize_chr_prefix;
+    self
+  }
+
+  /// Get whether a `chr` prefix is added to or stripped from requested reference names.
+  pub fn normalize_chr_prefix(&self) -> bool {
+    self.normalize_chr_prefix
+  }
+
+  /// Set whether the durations of the stages of resolving a query are recorded in the response.
+  pub fn with_server_timing(mut self, server_timing: bool) -> Self {
+    self.server_timing = server_timing;
+    self
+  }
+
+  /// Get whether the durations of the stages of resolving a query are recorded in the response.
+  pub fn server_timing(&self) -> bool {
+    self.server_timing
+  }
+
+  /// Get the options which control how queries matched by this resolver are searched.
+  pub fn search_options(&self) -> SearchOptions {
+    SearchOptions::default()
+      .with_validate_reference_names(self.validate_reference_names)
+      .with_validate_bgzf(self.validate_bgzf)
+      .with_whole_file_fallback_max_size(self.whole_file_fallback_max_size)
+      .with_include_total_size(self.include_total_size)
+      .with_max_total_response_bytes(self.max_total_response_bytes)
+      .with_index_extensions(self.index_extensions.clone())
+      .with_include_sender_public_key(self.include_sender_public_key)
+      .with_search_timeout(self.search_timeout.map(Duration::from_secs))
+      .with_include_content_range(self.include_content_range)
+      .with_oversized_range(self.oversized_range)
+      .with_max_bytes_per_url(self.max_bytes_per_url)
+      .with_min_block_size(self.min_block_size)
+      .with_max_urls_per_ticket(self.max_urls_per_ticket)
+      .with_log_index_scan(self.log_index_scan)
+      .with_merge_gap(self.merge_gap)
+      .with_unmapped_variants(self.unmapped_variants)
+      .with_server_timing(self.server_timing)
+      .with_preserve_classes(self.preserve_classes)
+      .with_header_without_index(self.header_without_index)
+      .with_normalize_chr_prefix(self.normalize_chr_prefix)
+      .with_index_cache(self.index_cache.clone())
+  }
+
+  /// Name the dataset label in not found errors, if the label is set.
+  fn label_not_found(&self, error: HtsGetError) -> HtsGetError {
+    match (error, &self.label) {
+      (HtsGetError::NotFound(message), Some(label)) => {
+        HtsGetError::NotFound(format!("{message}, in dataset `{label}`"))
+      }
+      (error, _) => error,
+    }
+  }
+
+  /// Get whether the regex matches ids case-insensitively.
+  pub fn case_insensitive(&self) -> bool {
+    self.case_insensitive
+  }
+
+  /// Get the substitution string.
+  pub fn substitution_string(&self) -> &str {
+    &self.substitution_string
+  }
+
+  /// Get the query guard.
+  pub fn allow_guard(&self) -> &AllowGuard {
+    &self.allow_guard
+  }
+
+  /// Get the storage backend.
+  pub fn storage(&self) -> &Storage {
+    &self.storage
+  }
+
+  /// Get the storage backend which is built once to probe the readiness of the storage.
+  pub fn probe_storage(&self) -> &StorageCell {
+    &self.probe_storage
+  }
+
+  /// Get allow formats.
+  pub fn allow_formats(&self) -> &[Format] {
+    self.allow_guard.allow_formats()
+  }
+
+  /// Get allow classes.
+  pub fn allow_classes(&self) -> &[Class] {
+    self.allow_guard.allow_classes()
+  }
+
+  /// Get allow interval.
+  pub fn allow_interval(&self) -> Interval {
+    self.allow_guard.allow_interval
+  }
+
+  /// Get allow reference names.
+  pub fn allow_reference_names(&self) -> &ReferenceNames {
+    &self.allow_guard.allow_reference_names
+  }
+
+  /// Get allow fields.
+  pub fn allow_fields(&self) -> &Fields {
+    &self.allow_guard.allow_fields
+  }
+
+  /// Get allow tags.
+  pub fn allow_tags(&self) -> &Tags {
+    &self.allow_guard.allow_tags
+  }
+}
+
+impl IdResolver for Resolver {
+  #[instrument(level = "trace", skip(self), ret)]
+  fn resolve_id(&self, query: &Query) -> Option<ResolvedId> {
+    if self.regex.is_match(query.id()) && self.allow_guard.query_allowed(query) {
+      Some(ResolvedId::new(
+        self
+          .regex
+          .replace(query.id(), &self.substitution_string)
+          .to_string(),
+      ))
+    } else {
+      None
+    }
+  }
+}
+
+#[async_trait]
+impl StorageResolver for Resolver {
+  #[instrument(level = "trace", skip(self), ret)]
+  async fn resolve_request<T: ResolveResponse>(
+    &self,
+    query: &mut Query,
+  ) -> Option<Result<(Response, Option<Duration>)>> {
+    let start = Instant::now();
+    let resolved_id = match self.resolve_id_within_max_length(query)? {
+      Ok(resolved_id) => resolved_id,
+      Err(err) => return Some(Err(err)),
+    };
+    self.resolve_default_format(query);
+    if let Err(err) = self
+      .resolve_format(query)
+      .and_then(|_| self.check_query_span(query))
+    {
+      return Some(Err(err));
+    }
+    let _matched_id = query.id().to_string();
+
+    query.set_id(resolved_id.into_inner());
+    query.set_index_id(
+      self
+        .index_location
+        .as_ref()
+        .map(|index_location| index_location.index_id(query.id())),
+    );
+    let options = self.search_options();
+    let resolve = start.elapsed();
+
+    let mut age = None;
+    let response = match self.storage() {
+      Storage::Local(local_storage) => {
+        // Signed or encrypted urls are generated per request, so they cannot be cached.
+        #[cfg(feature = "experimental")]
+        let cacheable = local_storage.signing_key().is_none() && local_storage.keys().is_none();
+        #[cfg(not(feature = "experimental"))]
+        let cacheable = local_storage.signing_key().is_none();
+
+        let response_cache = self
+          .response_cache
+          .as_ref()
+          .filter(|_| cacheable && T::CACHEABLE);
+        match response_cache.and_then(|cache| cache.get(query)) {
+          Some((response, cached_age)) => {
+            age = Some(cached_age);
+            // The cached stage timings belong to the original request, so only the time taken to
+            // resolve this request is reported.
+            let server_timing = response.server_timing.map(|_| ServerTiming::default());
+            Ok(response.with_server_timing(server_timing))
+          }
+          None => {
+            let response = T::from_local(local_storage, query, &options).await;
+            if let (Some(cache), Ok(response)) = (response_cache, &response) {
+              cache.insert(query, response.clone());
+            }
+
+            response
+          }
+        }
+      }
+      #[cfg(feature = "s3-storage")]
+      Storage::S3(s3_storage) => {
+        let first_match = self.get_match(1, &_matched_id);
+        let mut s3_storage = s3_storage.clone();
+        if s3_storage.bucket.is_empty() {
+          s3_storage.bucket = first_match?.to_string();
+        }
+
+        T::from_s3(&s3_storage, query, &options).await
+      }
+      #[cfg(feature = "url-storage")]
+      Storage::Url(url_storage) => {
+        let url_storage = url_storage
+          .clone()
+          .set_request_ids(_matched_id, query.id().to_string())
+          .apply_forward_headers_override(query.request().headers());
+
+        T::from_url(&url_storage, query, &options).await
+      }
+      #[cfg(feature = "gcs-storage")]
+      Storage::Gcs(gcs_storage) => {
+        let first_match = self.get_match(1, &_matched_id);
+        let mut gcs_storage = gcs_storage.clone();
+        if gcs_storage.bucket.is_empty() {
+          gcs_storage.bucket = first_match?.to_string();
+        }
+
+        T::from_gcs(&gcs_storage, query, &options).await
+      }
+      #[cfg(feature = "azure-storage")]
+      Storage::Azure(azure_storage) => {
+        let first_match = self.get_match(1, &_matched_id);
+        let mut azure_storage = azure_storage.clone();
+        if azure_storage.container.is_empty() {
+          azure_storage.container = first_match?.to_string();
+        }
+
+        T::from_azure(&azure_storage, query, &options).await
+      }
+    };
+
+    let response = response.map(|response| match response.server_timing {
+      Some(server_timing) => response.with_server_timing(Some(server_timing.with_resolve(resolve))),
+      None => response,
+    });
+    let response = response.map(|response| {
+      let provenance = (!self.provenance.is_empty()).then(|| self.provenance.clone());
+      response.with_provenance(provenance)
+    });
+
+    Some(
+      response
+        .map(|response| (response, age))
+        .map_err(|err| self.label_not_found(err)),
+    )
+  }
+}
+
+impl IdResolver for &[Resolver] {
+  #[instrument(level = "trace", skip(self), ret)]
+  fn resolve_id(&self, query: &Query) -> Option<ResolvedId> {
+    self.iter().find_map(|resolver| resolver.resolve_id(query))
+  }
+}
+
+#[async_trait]
+impl StorageResolver for &[Resolver] {
+  #[instrument(level = "trace", skip(self), ret)]
+  async fn resolve_request<T: ResolveResponse>(
+    &self,
+    query: &mut Query,
+  ) -> Option<Result<(Response, Option<Duration>)>> {
+    for resolver in self.iter() {
+      if let Some(resolved_storage) = resolver.resolve_request::<T>(query).await {
+        return Some(resolved_storage);
+      }
+    }
+
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use http::uri::Authority;
+
+  #[cfg(feature = "url-storage")]
+  use {
+    crate::storage::url, crate::storage::url::ValidatedUrl, http::Uri as InnerUrl,
+    reqwest::ClientBuilder, std::str::FromStr,
+  };
+
+  use crate::config::tests::{test_config_from_env, test_config_from_file};
+  #[cfg(feature = "s3-storage")]
+  use crate::storage::s3::S3;
+  use crate::types::Scheme::Http;
+  use crate::types::Url;
+
+  use super::*;
+
+  struct TestResolveResponse;
+
+  #[async_trait]
+  impl ResolveResponse for TestResolveResponse {
+    async fn from_local(local_storage: &Local, _: &Query, _: &SearchOptions) -> Result<Response> {
+      Ok(Response::new(
+        Bam,
+        vec![Url::new(local_storage.authority().to_string())],
+      ))
+    }
+
+    #[cfg(feature = "s3-storage")]
+    async fn from_s3(s3_storage: &S3, _: &Query, _: &SearchOptions) -> Result<Response> {
+      Ok(Response::new(Bam, vec![Url::new(s3_storage.bucket())]))
+    }
+
+    #[cfg(feature = "url-storage")]
+    async fn from_url(
+      url_storage: &UrlStorageClient,
+      _: &Query,
+      _: &SearchOptions,
+    ) -> Result<Response> {
+      Ok(Response::new(
+        Bam,
+        vec![Url::new(url_storage.url().to_string())],
+      ))
+    }
+
+    #[cfg(feature = "gcs-storage")]
+    async fn from_gcs(gcs_storage: &Gcs, _: &Query, _: &SearchOptions) -> Result<Response> {
+      Ok(Response::new(Bam, vec![Url::new(gcs_storage.bucket())]))
+    }
+
+    #[cfg(feature = "azure-storage")]
+    async fn from_azure(azure_storage: &Azure, _: &Query, _: &SearchOptions) -> Result<Response> {
+      Ok(Response::new(
+        Bam,
+        vec![Url::new(azure_storage.container())],
+      ))
+    }
+  }
+
+  #[tokio::test]
+  async fn resolver_resolve_local_request() {
+    let local_storage = Local::new(
+      Http,
+      Authority::from_static("127.0.0.1:8080"),
+      "data".to_string(),
+      "/data".to_string(),
+      false,
+    );
+    let resolver = Resolver::new(
+      Storage::Local(local_storage),
+      "id",
+      "$0-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "127.0.0.1:8080").await;
   }
 
   #[cfg(feature = "s3-storage")]
@@ -500,165 +1617,895 @@ mod tests {
   async fn resolver_resolve_s3_request_tagged() {
     let s3_storage = S3::new("id".to_string(), None, false);
     let resolver = Resolver::new(
-      Storage::S3(s3_storage),
-      "(id)-1",
-      "$1-test",
+      Storage::S3(s3_storage),
+      "(id)-1",
+      "$1-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "id").await;
+  }
+
+  #[cfg(feature = "s3-storage")]
+  #[tokio::test]
+  async fn resolver_resolve_s3_request() {
+    let resolver = Resolver::new(
+      Storage::S3(S3::default()),
+      "(id)-1",
+      "$1-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "id").await;
+  }
+
+  #[cfg(feature = "gcs-storage")]
+  #[tokio::test]
+  async fn resolver_resolve_gcs_request() {
+    let resolver = Resolver::new(
+      Storage::Gcs(Gcs::default()),
+      "(id)-1",
+      "$1-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "id").await;
+  }
+
+  #[cfg(feature = "azure-storage")]
+  #[tokio::test]
+  async fn resolver_resolve_azure_request() {
+    let resolver = Resolver::new(
+      Storage::Azure(Azure::default()),
+      "(id)-1",
+      "$1-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "id").await;
+  }
+
+  #[cfg(feature = "url-storage")]
+  #[tokio::test]
+  async fn resolver_resolve_url_request() {
+    let client = ClientBuilder::new().build().unwrap();
+    let url_storage = UrlStorageClient::new(
+      ValidatedUrl(url::Url {
+        inner: InnerUrl::from_str("https://example.com/").unwrap(),
+      }),
+      ValidatedUrl(url::Url {
+        inner: InnerUrl::from_str("https://example.com/").unwrap(),
+      }),
+      true,
+      vec![],
+      client,
+    );
+
+    let resolver = Resolver::new(
+      Storage::Url(url_storage),
+      "(id)-1",
+      "$1-test",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    expected_resolved_request(resolver, "https://example.com/").await;
+  }
+
+  #[test]
+  fn resolver_get_matches() {
+    let resolver = Resolver::new(
+      Storage::default(),
+      "^(id)/(?P<key>.*)$",
+      "$0",
+      AllowGuard::default(),
+    )
+    .unwrap();
+    let first_match = resolver.get_match(1, "id/key").unwrap();
+
+    assert_eq!(first_match, "id");
+  }
+
+  #[test]
+  fn resolver_get_matches_no_captures() {
+    let resolver =
+      Resolver::new(Storage::default(), "^id/id$", "$0", AllowGuard::default()).unwrap();
+    let first_match = resolver.get_match(1, "/id/key");
+
+    assert_eq!(first_match, None);
+  }
+
+  #[test]
+  fn resolver_resolve_id() {
+    let resolver =
+      Resolver::new(Storage::default(), "id", "$0-test", AllowGuard::default()).unwrap();
+    assert_eq!(
+      resolver
+        .resolve_id(&Query::new_with_default_request("id", Bam))
+        .unwrap()
+        .into_inner(),
+      "id-test"
+    );
+  }
+
+  #[test]
+  fn resolver_resolve_id_case_insensitive() {
+    let resolver = Resolver::new(
+      Storage::default(),
+      "^sample1$",
+      "$0-test",
+      AllowGuard::default(),
+    )
+    .unwrap()
+    .with_case_insensitive(true)
+    .unwrap();
+
+    let upper = resolver
+      .resolve_id(&Query::new_with_default_request("Sample1", Bam))
+      .unwrap()
+      .into_inner();
+    let lower = resolver
+      .resolve_id(&Query::new_with_default_request("sample1", Bam))
+      .unwrap()
+      .into_inner();
+
+    assert_eq!(upper, "Sample1-test");
+    assert_eq!(lower, "sample1-test");
+    assert!(resolver
+      .resolve_id(&Query::new_with_default_request("sample2", Bam))
+      .is_none());
+  }
+
+  #[test]
+  fn index_location_index_id() {
+    let index_location = IndexLocation::new("^data/(.*)$", "indexes/$1").unwrap();
+
+    assert_eq!(
+      index_location.index_id("data/vcf/sample1"),
+      "indexes/vcf/sample1"
+    );
+  }
+
+  #[test]
+  fn config_resolvers_index_location_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.index_location]
+      regex = "^data/(.*)$"
+      substitution_string = "indexes/$1"
+      "#,
+      |config| {
+        let index_location = config
+          .resolvers()
+          .first()
+          .unwrap()
+          .index_location()
+          .unwrap();
+
+        assert_eq!(index_location.regex().as_str(), "^data/(.*)$");
+        assert_eq!(index_location.substitution_string(), "indexes/$1");
+      },
+    );
+  }
+
+  #[test]
+  fn resolver_resolve_id_case_sensitive() {
+    let resolver = Resolver::new(
+      Storage::default(),
+      "^sample1$",
+      "$0-test",
       AllowGuard::default(),
     )
     .unwrap();
 
-    expected_resolved_request(resolver, "id").await;
+    assert!(resolver
+      .resolve_id(&Query::new_with_default_request("Sample1", Bam))
+      .is_none());
+  }
+
+  #[test]
+  fn resolver_array_resolve_id() {
+    let resolver = vec![
+      Resolver::new(
+        Storage::default(),
+        "^(id-1)(.*)$",
+        "$1-test-1",
+        AllowGuard::default(),
+      )
+      .unwrap(),
+      Resolver::new(
+        Storage::default(),
+        "^(id-2)(.*)$",
+        "$1-test-2",
+        AllowGuard::default(),
+      )
+      .unwrap(),
+    ];
+
+    assert_eq!(
+      resolver
+        .as_slice()
+        .resolve_id(&Query::new_with_default_request("id-1", Bam))
+        .unwrap()
+        .into_inner(),
+      "id-1-test-1"
+    );
+    assert_eq!(
+      resolver
+        .as_slice()
+        .resolve_id(&Query::new_with_default_request("id-2", Bam))
+        .unwrap()
+        .into_inner(),
+      "id-2-test-2"
+    );
+  }
+
+  #[test]
+  fn config_resolvers_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+        "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().regex().as_str(),
+          "regex"
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_guard_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.allow_guard]
+      allow_formats = ["BAM"]
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().allow_formats(),
+          &vec![Bam]
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_validate_reference_names_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      validate_reference_names = true
+      "#,
+      |config| {
+        assert!(config
+          .resolvers()
+          .first()
+          .unwrap()
+          .validate_reference_names());
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_validate_bgzf_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      validate_bgzf = true
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
+        assert!(resolver.validate_bgzf());
+        assert!(resolver.search_options().validate_bgzf());
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_whole_file_fallback_max_size_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      whole_file_fallback_max_size = 1024
+      "#,
+      |config| {
+        assert_eq!(
+          config
+            .resolvers()
+            .first()
+            .unwrap()
+            .whole_file_fallback_max_size(),
+          Some(1024)
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_index_extensions_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      index_extensions = [".crai", ".index"]
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().index_extensions(),
+          &[".crai".to_string(), ".index".to_string()]
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_include_total_size_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      include_total_size = true
+      "#,
+      |config| {
+        assert!(config.resolvers().first().unwrap().include_total_size());
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_oversized_range_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      oversized_range = "Error"
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().oversized_range(),
+          OversizedRange::Error
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_max_bytes_per_url_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      max_bytes_per_url = 1000
+      min_block_size = 100
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
+        assert_eq!(resolver.max_bytes_per_url(), Some(1000));
+        assert_eq!(resolver.min_block_size(), Some(100));
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_max_urls_per_ticket_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      max_urls_per_ticket = 100
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().max_urls_per_ticket(),
+          Some(100)
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_max_query_span_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      max_query_span = { BAM = 1000, VCF = 2000 }
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
+        assert_eq!(resolver.max_query_span().get(&Bam), Some(&1000));
+        assert_eq!(resolver.max_query_span().get(&Vcf), Some(&2000));
+        assert_eq!(resolver.max_query_span().get(&Cram), None);
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_log_index_scan_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      log_index_scan = true
+      "#,
+      |config| {
+        assert!(config.resolvers().first().unwrap().log_index_scan());
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_merge_gap_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      merge_gap = 4096
+      "#,
+      |config| {
+        assert_eq!(config.resolvers().first().unwrap().merge_gap(), Some(4096));
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_label_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "^dataset1/(?P<key>.*)$"
+      label = "Dataset One"
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().label(),
+          Some("Dataset One")
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_response_cache_ttl_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      response_cache_ttl = 60
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().response_cache_ttl(),
+          Some(60)
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn config_resolvers_response_cache_capacity_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      response_cache_ttl = 60
+      response_cache_capacity = 16
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
+        assert_eq!(resolver.response_cache_capacity(), 16);
+        assert_eq!(
+          resolver
+            .response_cache
+            .as_ref()
+            .map(ResponseCache::capacity),
+          Some(16)
+        );
+      },
+    );
+  }
+
+  #[test]
+  fn response_cache_key_includes_fields_and_tags() {
+    let cache = ResponseCache::new(Duration::from_secs(60), 10);
+    let query = Query::new_with_default_request("id", Format::Bam);
+    cache.insert(&query, Response::new(Format::Bam, vec![]));
+
+    assert!(cache.get(&query).is_some());
+    assert!(cache
+      .get(
+        &query
+          .clone()
+          .with_fields(Fields::List(HashSet::from_iter(["QNAME".to_string()])))
+      )
+      .is_none());
+    assert!(cache
+      .get(
+        &query
+          .clone()
+          .with_tags(Tags::List(HashSet::from_iter(["RG".to_string()])))
+      )
+      .is_none());
+    assert!(cache.get(&query.clone().with_no_tags(vec!["RG"])).is_none());
+  }
+
+  #[test]
+  fn response_cache_evicts_least_recently_used() {
+    let cache = ResponseCache::new(Duration::from_secs(60), 2);
+    let first = Query::new_with_default_request("first", Format::Bam);
+    let second = Query::new_with_default_request("second", Format::Bam);
+    let third = Query::new_with_default_request("third", Format::Bam);
+
+    cache.insert(&first, Response::new(Format::Bam, vec![]));
+    cache.insert(&second, Response::new(Format::Bam, vec![]));
+    assert!(cache.get(&first).is_some());
+    cache.insert(&third, Response::new(Format::Bam, vec![]));
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&first).is_some());
+    assert!(cache.get(&second).is_none());
+    assert!(cache.get(&third).is_some());
   }
 
-  #[cfg(feature = "s3-storage")]
-  #[tokio::test]
-  async fn resolver_resolve_s3_request() {
-    let resolver = Resolver::new(
-      Storage::S3(S3::default()),
-      "(id)-1",
-      "$1-test",
-      AllowGuard::default(),
-    )
-    .unwrap();
+  #[test]
+  fn config_resolvers_index_cache_capacity_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      index_cache_capacity = 16
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
 
-    expected_resolved_request(resolver, "id").await;
+        assert_eq!(resolver.index_cache_capacity(), Some(16));
+        assert_eq!(
+          resolver
+            .search_options()
+            .index_cache()
+            .map(IndexCache::capacity),
+          Some(16)
+        );
+      },
+    );
   }
 
-  #[cfg(feature = "url-storage")]
-  #[tokio::test]
-  async fn resolver_resolve_url_request() {
-    let client = ClientBuilder::new().build().unwrap();
-    let url_storage = UrlStorageClient::new(
-      ValidatedUrl(url::Url {
-        inner: InnerUrl::from_str("https://example.com/").unwrap(),
-      }),
-      ValidatedUrl(url::Url {
-        inner: InnerUrl::from_str("https://example.com/").unwrap(),
-      }),
-      true,
-      vec![],
-      client,
+  #[test]
+  fn config_resolvers_format_conflict_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      format = "BAM"
+      format_conflict = "Error"
+      "#,
+      |config| {
+        let resolver = config.resolvers().first().unwrap();
+        assert_eq!(resolver.format(), Some(Bam));
+        assert_eq!(resolver.format_conflict(), FormatConflict::Error);
+      },
     );
+  }
 
-    let resolver = Resolver::new(
-      Storage::Url(url_storage),
-      "(id)-1",
-      "$1-test",
-      AllowGuard::default(),
-    )
-    .unwrap();
+  #[test]
+  fn config_resolvers_unmapped_variants_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      unmapped_variants = "Unplaced"
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().unmapped_variants(),
+          UnmappedVariants::Unplaced
+        );
+      },
+    );
+  }
 
-    expected_resolved_request(resolver, "https://example.com/").await;
+  #[test]
+  fn config_resolvers_max_id_length_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      max_id_length = 10
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().max_id_length(),
+          Some(10)
+        );
+      },
+    );
   }
 
   #[test]
-  fn resolver_get_matches() {
-    let resolver = Resolver::new(
-      Storage::default(),
-      "^(id)/(?P<key>.*)$",
-      "$0",
-      AllowGuard::default(),
-    )
-    .unwrap();
-    let first_match = resolver.get_match(1, "id/key").unwrap();
+  fn config_resolvers_preserve_classes_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      preserve_classes = true
+      "#,
+      |config| {
+        assert!(config.resolvers().first().unwrap().preserve_classes());
+      },
+    );
+  }
 
-    assert_eq!(first_match, "id");
+  #[test]
+  fn config_resolvers_default_format_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      default_format = "CRAM"
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().default_format(),
+          Some(Cram)
+        );
+      },
+    );
   }
 
   #[test]
-  fn resolver_get_matches_no_captures() {
-    let resolver =
-      Resolver::new(Storage::default(), "^id/id$", "$0", AllowGuard::default()).unwrap();
-    let first_match = resolver.get_match(1, "/id/key");
+  fn config_resolvers_provenance_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      provenance = { reference_build = "GRCh38", pipeline_version = "1.2.0" }
+      "#,
+      |config| {
+        assert_eq!(
+          config.resolvers().first().unwrap().provenance(),
+          &BTreeMap::from([
+            ("pipeline_version".to_string(), "1.2.0".to_string()),
+            ("reference_build".to_string(), "GRCh38".to_string()),
+          ])
+        );
+      },
+    );
+  }
 
-    assert_eq!(first_match, None);
+  #[test]
+  fn config_resolvers_header_without_index_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      header_without_index = true
+      "#,
+      |config| {
+        assert!(config.resolvers().first().unwrap().header_without_index());
+      },
+    );
   }
 
   #[test]
-  fn resolver_resolve_id() {
-    let resolver =
-      Resolver::new(Storage::default(), "id", "$0-test", AllowGuard::default()).unwrap();
-    assert_eq!(
-      resolver
-        .resolve_id(&Query::new_with_default_request("id", Bam))
-        .unwrap()
-        .into_inner(),
-      "id-test"
+  fn config_resolvers_normalize_chr_prefix_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      normalize_chr_prefix = true
+      "#,
+      |config| {
+        assert!(config.resolvers().first().unwrap().normalize_chr_prefix());
+      },
     );
   }
 
   #[test]
-  fn resolver_array_resolve_id() {
-    let resolver = vec![
+  fn config_resolvers_search_options_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+      search_timeout = 5
+      merge_gap = 10
+      normalize_chr_prefix = true
+      "#,
+      |config| {
+        let options = config.resolvers().first().unwrap().search_options();
+
+        assert_eq!(options.search_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(options.merge_gap(), Some(10));
+        assert!(options.normalize_chr_prefix());
+        assert!(!options.validate_reference_names());
+      },
+    );
+  }
+
+  #[test]
+  fn resolver_default_format_endpoint() {
+    let resolver = Resolver::new(Storage::default(), ".*", "$0", Default::default())
+      .unwrap()
+      .with_default_format(Some(Cram));
+
+    let mut query = Query::new_with_default_request("id", Bam).with_format_omitted(true);
+    resolver.resolve_default_format(&mut query);
+    assert_eq!(query.format(), Cram);
+
+    let mut query = Query::new_with_default_request("id", Bam);
+    resolver.resolve_default_format(&mut query);
+    assert_eq!(query.format(), Bam);
+
+    let mut query = Query::new_with_default_request("id", Vcf).with_format_omitted(true);
+    resolver.resolve_default_format(&mut query);
+    assert_eq!(query.format(), Vcf);
+  }
+
+  #[tokio::test]
+  async fn resolver_max_id_length_exceeded() {
+    let resolver = Resolver::new(
+      Storage::Local(Default::default()),
+      "(a|aa|aaa)*(b|bb)*c",
+      "$0",
+      AllowGuard::default(),
+    )
+    .unwrap()
+    .with_max_id_length(Some(1024));
+
+    let id = format!("{}c", "a".repeat(100_000));
+    let response = resolver
+      .resolve_request::<TestResolveResponse>(&mut Query::new_with_default_request(id, Bam))
+      .await
+      .unwrap();
+
+    assert!(matches!(response, Err(HtsGetError::InvalidInput(_))));
+  }
+
+  #[tokio::test]
+  async fn resolver_max_id_length_other_resolver() {
+    let resolvers = vec![
       Resolver::new(
-        Storage::default(),
-        "^(id-1)(.*)$",
-        "$1-test-1",
+        Storage::Local(Default::default()),
+        "^short-(.*)$",
+        "$1",
         AllowGuard::default(),
       )
-      .unwrap(),
+      .unwrap()
+      .with_max_id_length(Some(16)),
       Resolver::new(
-        Storage::default(),
-        "^(id-2)(.*)$",
-        "$1-test-2",
+        Storage::Local(Default::default()),
+        "^long-(.*)$",
+        "$1",
         AllowGuard::default(),
       )
       .unwrap(),
     ];
 
-    assert_eq!(
-      resolver
-        .as_slice()
-        .resolve_id(&Query::new_with_default_request("id-1", Bam))
-        .unwrap()
-        .into_inner(),
-      "id-1-test-1"
-    );
-    assert_eq!(
-      resolver
+    let id = format!("long-{}", "a".repeat(100));
+    let mut query = Query::new_with_default_request(id, Bam);
+    assert!(resolvers
+      .as_slice()
+      .resolve_request::<TestResolveResponse>(&mut query)
+      .await
+      .unwrap()
+      .is_ok());
+    assert_eq!(query.id(), "a".repeat(100));
+
+    let id = format!("short-{}", "a".repeat(100));
+    assert!(matches!(
+      resolvers
         .as_slice()
-        .resolve_id(&Query::new_with_default_request("id-2", Bam))
-        .unwrap()
-        .into_inner(),
-      "id-2-test-2"
-    );
+        .resolve_request::<TestResolveResponse>(&mut Query::new_with_default_request(id, Bam))
+        .await
+        .unwrap(),
+      Err(HtsGetError::InvalidInput(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn resolver_max_id_length_not_exceeded() {
+    let resolver = Resolver::new(
+      Storage::Local(Default::default()),
+      "id",
+      "$0-test",
+      AllowGuard::default(),
+    )
+    .unwrap()
+    .with_max_id_length(Some(4));
+
+    assert!(resolver
+      .resolve_request::<TestResolveResponse>(&mut Query::new_with_default_request("id-1", Bam))
+      .await
+      .unwrap()
+      .is_ok());
   }
 
   #[test]
-  fn config_resolvers_file() {
+  fn config_resolvers_include_content_range_file() {
     test_config_from_file(
       r#"
-        [[resolvers]]
-        regex = "regex"
-        "#,
+      [[resolvers]]
+      regex = "regex"
+      include_content_range = true
+      "#,
       |config| {
-        assert_eq!(
-          config.resolvers().first().unwrap().regex().as_str(),
-          "regex"
-        );
+        assert!(config.resolvers().first().unwrap().include_content_range());
       },
     );
   }
 
   #[test]
-  fn config_resolvers_guard_file() {
+  fn config_resolvers_case_insensitive_file() {
     test_config_from_file(
       r#"
       [[resolvers]]
-      regex = "regex"
-
-      [resolvers.allow_guard]
-      allow_formats = ["BAM"]
+      regex = "^sample1$"
+      case_insensitive = true
       "#,
       |config| {
-        assert_eq!(
-          config.resolvers().first().unwrap().allow_formats(),
-          &vec![Bam]
-        );
+        let resolver = config.resolvers().first().unwrap();
+
+        assert!(resolver.case_insensitive());
+        assert!(resolver.regex().is_match("Sample1"));
+        assert!(resolver.regex().is_match("sample1"));
       },
     );
   }
 
+  #[test]
+  fn resolver_overlapping_id() {
+    let dataset = Resolver::new(
+      Storage::default(),
+      "^dataset/(?P<id>.*)$",
+      "$id",
+      AllowGuard::default(),
+    )
+    .unwrap();
+    let sample = Resolver::new(
+      Storage::default(),
+      "^dataset/sample_(?P<id>.+)$",
+      "$id",
+      AllowGuard::default(),
+    )
+    .unwrap();
+    let other = Resolver::new(
+      Storage::default(),
+      "^other/(?P<id>.*)$",
+      "$id",
+      AllowGuard::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+      dataset.overlapping_id(&sample),
+      Some("dataset/sample_id".to_string())
+    );
+    assert_eq!(
+      sample.overlapping_id(&dataset),
+      Some("dataset/sample_id".to_string())
+    );
+    assert_eq!(dataset.overlapping_id(&other), None);
+    assert_eq!(
+      Resolver::default().overlapping_id(&other),
+      Some("other/".to_string())
+    );
+  }
+
   #[test]
   fn config_resolvers_env() {
     test_config_from_env(vec![("HTSGET_RESOLVERS", "[{regex=regex}]")], |config| {
@@ -714,7 +2561,7 @@ mod tests {
         .await
         .unwrap()
         .unwrap(),
-      Response::new(Bam, vec![Url::new(expected_id)])
+      (Response::new(Bam, vec![Url::new(expected_id)]), None)
     );
   }
 }
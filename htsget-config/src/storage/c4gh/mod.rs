@@ -6,6 +6,8 @@ use crate::error::{Error, Result};
 use crate::storage::c4gh::local::C4GHLocal;
 #[cfg(feature = "s3-storage")]
 use crate::storage::c4gh::secrets_manager::C4GHSecretsManager;
+#[cfg(feature = "vault")]
+use crate::storage::c4gh::vault::C4GHVault;
 use crypt4gh::error::Crypt4GHError;
 use futures_util::future::{BoxFuture, Shared};
 use futures_util::FutureExt;
@@ -16,6 +18,8 @@ pub mod local;
 
 #[cfg(feature = "s3-storage")]
 pub mod secrets_manager;
+#[cfg(feature = "vault")]
+pub mod vault;
 
 /// Config for Crypt4GH keys.
 #[derive(Deserialize, Debug, Clone)]
@@ -67,6 +71,8 @@ impl TryFrom<Location> for C4GHKeys {
       Location::Local(local) => local.try_into(),
       #[cfg(feature = "s3-storage")]
       Location::SecretsManager(secrets_manager) => secrets_manager.try_into(),
+      #[cfg(feature = "vault")]
+      Location::Vault(vault) => vault.try_into(),
     }
   }
 }
@@ -81,4 +87,7 @@ pub enum Location {
   #[cfg(feature = "s3-storage")]
   #[serde(alias = "secretsmanager", alias = "SECRETSMANAGER")]
   SecretsManager(C4GHSecretsManager),
+  #[cfg(feature = "vault")]
+  #[serde(alias = "vault", alias = "VAULT")]
+  Vault(C4GHVault),
 }
@@ -0,0 +1,254 @@
+//! Obtain C4GH keys from a HashiCorp Vault KV secrets engine.
+//!
+
+use crate::error::Error::{IoError, ParseError};
+use crate::error::{Error, Result};
+use crate::storage::c4gh::C4GHKeys;
+use async_trait::async_trait;
+use crypt4gh::keys::{get_private_key, get_public_key};
+use crypt4gh::Keys;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+use std::{env, fs};
+use tempfile::TempDir;
+
+/// The environment variable used for the Vault token if it is not set in the config.
+pub const VAULT_TOKEN_ENV: &str = "VAULT_TOKEN";
+
+/// A source of secrets which returns the fields of a secret.
+#[async_trait]
+pub trait SecretsSource: Debug + Send + Sync {
+  /// Get the fields of the secret at the path.
+  async fn get_secret(&self, path: &str) -> Result<HashMap<String, String>>;
+}
+
+/// A secrets source which reads from the Vault KV version 2 HTTP API.
+#[derive(Debug, Clone)]
+pub struct VaultClient {
+  address: String,
+  mount: String,
+  token: String,
+  client: reqwest::Client,
+}
+
+impl VaultClient {
+  /// Create a new Vault client.
+  pub fn new(address: String, mount: String, token: String) -> Self {
+    Self {
+      address,
+      mount,
+      token,
+      client: reqwest::Client::new(),
+    }
+  }
+}
+
+/// The response of a Vault KV version 2 read.
+#[derive(Deserialize, Debug)]
+struct VaultResponse {
+  data: VaultData,
+}
+
+/// The data of a Vault KV version 2 read.
+#[derive(Deserialize, Debug)]
+struct VaultData {
+  data: HashMap<String, String>,
+}
+
+#[async_trait]
+impl SecretsSource for VaultClient {
+  async fn get_secret(&self, path: &str) -> Result<HashMap<String, String>> {
+    let url = format!(
+      "{}/v1/{}/data/{}",
+      self.address.trim_end_matches('/'),
+      self.mount,
+      path.trim_start_matches('/')
+    );
+
+    let response = self
+      .client
+      .get(url)
+      .header("X-Vault-Token", &self.token)
+      .send()
+      .await
+      .and_then(|response| response.error_for_status())
+      .map_err(|err| IoError(format!("failed to read Vault secret: {err}")))?
+      .bytes()
+      .await
+      .map_err(|err| IoError(format!("failed to read Vault secret: {err}")))?;
+
+    let response: VaultResponse = serde_json::from_slice(&response)
+      .map_err(|err| ParseError(format!("failed to parse Vault secret: {err}")))?;
+
+    Ok(response.data.data)
+  }
+}
+
+/// C4GH Vault key storage.
+#[derive(Deserialize, Debug, Clone)]
+pub struct C4GHVault {
+  address: String,
+  #[serde(default = "default_mount")]
+  mount: String,
+  path: String,
+  #[serde(default = "default_private_key_field")]
+  private_key_field: String,
+  #[serde(default = "default_recipient_public_key_field")]
+  recipient_public_key_field: String,
+  token: Option<String>,
+  #[serde(skip)]
+  source: Option<Arc<dyn SecretsSource>>,
+}
+
+fn default_mount() -> String {
+  "secret".to_string()
+}
+
+fn default_private_key_field() -> String {
+  "private_key".to_string()
+}
+
+fn default_recipient_public_key_field() -> String {
+  "recipient_public_key".to_string()
+}
+
+impl C4GHVault {
+  /// Create a new C4GH Vault key storage, reading the keys from the secret at the path.
+  pub fn new(address: String, path: String) -> Self {
+    Self {
+      address,
+      mount: default_mount(),
+      path,
+      private_key_field: default_private_key_field(),
+      recipient_public_key_field: default_recipient_public_key_field(),
+      token: None,
+      source: None,
+    }
+  }
+
+  /// Set the secrets source.
+  pub fn with_source(mut self, source: Arc<dyn SecretsSource>) -> Self {
+    self.source = Some(source);
+    self
+  }
+
+  fn get_field(fields: &mut HashMap<String, String>, field: &str) -> Result<String> {
+    fields
+      .remove(field)
+      .ok_or_else(|| ParseError(format!("missing `{field}` in Vault secret")))
+  }
+
+  /// Retrieve the C4GH keys from Vault.
+  pub async fn get_keys(self) -> Result<Vec<Keys>> {
+    let source = match self.source {
+      Some(source) => source,
+      None => {
+        let token = self
+          .token
+          .or_else(|| env::var(VAULT_TOKEN_ENV).ok())
+          .ok_or_else(|| ParseError("missing Vault token".to_string()))?;
+
+        Arc::new(VaultClient::new(self.address, self.mount, token))
+      }
+    };
+
+    let mut fields = source.get_secret(&self.path).await?;
+
+    // Should not have to do this, but the Crypt4GH library expects a path.
+    let tmp = TempDir::new()?;
+    let private_key = tmp.path().join("private_key");
+    write_to_file(
+      &private_key,
+      Self::get_field(&mut fields, &self.private_key_field)?,
+    )?;
+
+    let recipient_public_key = tmp.path().join("public_key");
+    write_to_file(
+      &recipient_public_key,
+      Self::get_field(&mut fields, &self.recipient_public_key_field)?,
+    )?;
+
+    let private_key = get_private_key(private_key, Ok("".to_string()))?;
+    let recipient_public_key = get_public_key(recipient_public_key)?;
+
+    Ok(C4GHKeys::from_key_pair(private_key, recipient_public_key))
+  }
+}
+
+fn write_to_file(to: &Path, data: String) -> Result<()> {
+  Ok(fs::write(to, data)?)
+}
+
+impl TryFrom<C4GHVault> for C4GHKeys {
+  type Error = Error;
+
+  fn try_from(vault: C4GHVault) -> Result<Self> {
+    Ok(C4GHKeys::from_join_handle(tokio::spawn(vault.get_keys())))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::read_to_string;
+  use std::path::PathBuf;
+
+  use super::*;
+
+  /// A secrets source which returns the test keys.
+  #[derive(Debug)]
+  struct MockSecretsSource;
+
+  #[async_trait]
+  impl SecretsSource for MockSecretsSource {
+    async fn get_secret(&self, path: &str) -> Result<HashMap<String, String>> {
+      assert_eq!(path, "htsget/keys");
+
+      let parent = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+      Ok(HashMap::from([
+        (
+          "private_key".to_string(),
+          read_to_string(parent.join("data/c4gh/keys/bob.sec")).unwrap(),
+        ),
+        (
+          "recipient_public_key".to_string(),
+          read_to_string(parent.join("data/c4gh/keys/alice.pub")).unwrap(),
+        ),
+      ]))
+    }
+  }
+
+  #[tokio::test]
+  async fn config_test_get_keys_vault() {
+    let vault = C4GHVault::new(
+      "http://127.0.0.1:8200".to_string(),
+      "htsget/keys".to_string(),
+    )
+    .with_source(Arc::new(MockSecretsSource));
+
+    let keys: C4GHKeys = vault.try_into().unwrap();
+    let keys = keys.keys().await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn config_test_get_keys_vault_missing_field() {
+    let mut vault = C4GHVault::new(
+      "http://127.0.0.1:8200".to_string(),
+      "htsget/keys".to_string(),
+    )
+    .with_source(Arc::new(MockSecretsSource));
+    vault.private_key_field = "missing".to_string();
+
+    let keys: C4GHKeys = vault.try_into().unwrap();
+
+    assert!(matches!(keys.keys().await, Err(ParseError(_))));
+  }
+}
@@ -0,0 +1,267 @@
+//! Resolve GA4GH DRS ids to access urls using a DRS server.
+//!
+
+use crate::error::Error::{IoError, ParseError};
+use crate::error::Result;
+use http::{HeaderMap, Uri};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// The path of the DRS objects endpoint, relative to the DRS server url.
+pub const DRS_OBJECTS_PATH: &str = "ga4gh/drs/v1/objects";
+
+/// The prefix of hostname-based DRS URIs, which are resolved by the DRS server at the host.
+pub const DRS_URI_PREFIX: &str = "drs://";
+
+/// The supported access method types, in order of preference.
+const ACCESS_METHOD_TYPES: [&str; 3] = ["https", "s3", "http"];
+
+/// A DRS object, with the methods which can be used to access it.
+#[derive(Deserialize, Debug)]
+struct DrsObject {
+  #[serde(default)]
+  access_methods: Vec<AccessMethod>,
+}
+
+/// A DRS access method, which contains an access url or an access id used to get the access url.
+#[derive(Deserialize, Debug)]
+struct AccessMethod {
+  #[serde(rename = "type")]
+  access_type: String,
+  access_url: Option<AccessUrl>,
+  access_id: Option<String>,
+  region: Option<String>,
+}
+
+/// A DRS access url.
+#[derive(Deserialize, Debug)]
+struct AccessUrl {
+  url: String,
+}
+
+/// The access url that a DRS id resolves to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrsAccessUrl {
+  /// An `https` or `http` access url, which data is fetched from directly.
+  Url(Uri),
+  /// An `s3` access url, which should be presigned before data is fetched from it.
+  S3 {
+    bucket: String,
+    key: String,
+    region: Option<String>,
+  },
+}
+
+impl DrsAccessUrl {
+  /// Parse an `s3://bucket/key` access url, with the region of the access method.
+  pub fn from_s3_url(url: &str, region: Option<String>) -> Result<Self> {
+    let (bucket, key) = url
+      .strip_prefix("s3://")
+      .and_then(|url| url.split_once('/'))
+      .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+      .ok_or_else(|| ParseError(format!("invalid DRS s3 access url: `{url}`")))?;
+
+    Ok(Self::S3 {
+      bucket: bucket.to_string(),
+      key: key.to_string(),
+      region,
+    })
+  }
+}
+
+/// Resolves DRS ids to access urls by calling the objects endpoint of a DRS server.
+#[derive(Debug, Clone)]
+pub struct DrsResolver {
+  client: Client,
+  url: Uri,
+  allowed_hosts: Vec<String>,
+  s3: bool,
+}
+
+impl DrsResolver {
+  /// Create a new DRS resolver for the DRS server at the url.
+  pub fn new(client: Client, url: Uri) -> Self {
+    Self {
+      client,
+      url,
+      allowed_hosts: vec![],
+      s3: true,
+    }
+  }
+
+  /// Allow hostname-based DRS URIs to be resolved by these hosts, in addition to the DRS server.
+  pub fn with_allowed_hosts(mut self, allowed_hosts: Vec<String>) -> Self {
+    self.allowed_hosts = allowed_hosts;
+    self
+  }
+
+  /// Set whether `s3` access methods are supported. Unsupported access methods are never chosen.
+  pub fn with_s3(mut self, s3: bool) -> Self {
+    self.s3 = s3;
+    self
+  }
+
+  /// Get the DRS objects endpoint url for the path. Paths which are hostname-based DRS URIs, such
+  /// as `drs://host/id`, are only resolved if the host is the DRS server, or one of the allowed
+  /// hosts, which use the objects endpoint of the host. All other hosts are rejected, so that keys
+  /// cannot make the server send requests to arbitrary hosts.
+  pub fn objects_url(&self, path: &str) -> Result<String> {
+    let server_url = self.url.to_string();
+    let server_url = server_url.trim_end_matches('/');
+
+    match path
+      .strip_prefix(DRS_URI_PREFIX)
+      .and_then(|uri| uri.split_once('/'))
+    {
+      Some((host, path)) if self.is_server_host(host) => {
+        Ok(format!("{server_url}/{DRS_OBJECTS_PATH}/{path}"))
+      }
+      Some((host, path)) if self.is_allowed_host(host) => {
+        Ok(format!("https://{host}/{DRS_OBJECTS_PATH}/{path}"))
+      }
+      Some((host, _)) => Err(ParseError(format!("DRS host `{host}` is not allowed"))),
+      None => Ok(format!("{server_url}/{DRS_OBJECTS_PATH}/{path}")),
+    }
+  }
+
+  /// Check whether the host is the host of the DRS server.
+  fn is_server_host(&self, host: &str) -> bool {
+    self
+      .url
+      .authority()
+      .is_some_and(|authority| authority.as_str().eq_ignore_ascii_case(host))
+  }
+
+  /// Check whether the host is one of the allowed hosts.
+  fn is_allowed_host(&self, host: &str) -> bool {
+    self
+      .allowed_hosts
+      .iter()
+      .any(|allowed_host| allowed_host.eq_ignore_ascii_case(host))
+  }
+
+  async fn get<T: DeserializeOwned>(&self, url: String, headers: &HeaderMap) -> Result<T> {
+    let response = self
+      .client
+      .get(url)
+      .headers(headers.clone())
+      .send()
+      .await
+      .and_then(|response| response.error_for_status())
+      .map_err(|err| IoError(format!("failed to resolve DRS id: {err}")))?
+      .bytes()
+      .await
+      .map_err(|err| IoError(format!("failed to resolve DRS id: {err}")))?;
+
+    serde_json::from_slice(&response)
+      .map_err(|err| ParseError(format!("failed to parse DRS response: {err}")))
+  }
+
+  /// Resolve the DRS id to an access url, preferring `https`, then `s3`, then `http` access
+  /// methods. If the access method only has an access id, the access url is fetched from the DRS
+  /// server.
+  pub async fn resolve(&self, id: &str, headers: &HeaderMap) -> Result<DrsAccessUrl> {
+    let object: DrsObject = self.get(self.objects_url(id)?, headers).await?;
+
+    let access_method = object
+      .access_methods
+      .into_iter()
+      .filter(|method| self.s3 || method.access_type != "s3")
+      .filter_map(|method| {
+        ACCESS_METHOD_TYPES
+          .iter()
+          .position(|access_type| *access_type == method.access_type)
+          .map(|preference| (preference, method))
+      })
+      .min_by_key(|(preference, _)| *preference)
+      .map(|(_, method)| method)
+      .ok_or_else(|| ParseError(format!("no supported access method for DRS id `{id}`")))?;
+
+    let access_url = match (access_method.access_url, access_method.access_id) {
+      (Some(access_url), _) => access_url,
+      (None, Some(access_id)) => {
+        self
+          .get(
+            self.objects_url(&format!("{id}/access/{access_id}"))?,
+            headers,
+          )
+          .await?
+      }
+      (None, None) => {
+        return Err(ParseError(format!(
+          "missing access url or access id for DRS id `{id}`"
+        )))
+      }
+    };
+
+    match access_method.access_type.as_str() {
+      "s3" => DrsAccessUrl::from_s3_url(&access_url.url, access_method.region),
+      _ => access_url
+        .url
+        .parse()
+        .map(DrsAccessUrl::Url)
+        .map_err(|err| ParseError(format!("invalid DRS access url: {err}"))),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::str::FromStr;
+
+  #[test]
+  fn objects_url() {
+    let resolver = DrsResolver::new(
+      Client::new(),
+      Uri::from_str("https://example.com/drs/").unwrap(),
+    );
+
+    assert_eq!(
+      resolver.objects_url("id.bam").unwrap(),
+      "https://example.com/drs/ga4gh/drs/v1/objects/id.bam"
+    );
+    assert_eq!(
+      resolver.objects_url("drs://example.com/id.bam").unwrap(),
+      "https://example.com/drs/ga4gh/drs/v1/objects/id.bam"
+    );
+  }
+
+  #[test]
+  fn objects_url_allowed_hosts() {
+    let resolver = DrsResolver::new(
+      Client::new(),
+      Uri::from_str("https://example.com/drs/").unwrap(),
+    )
+    .with_allowed_hosts(vec!["drs.example.org".to_string()]);
+
+    assert_eq!(
+      resolver
+        .objects_url("drs://drs.example.org/id.bam")
+        .unwrap(),
+      "https://drs.example.org/ga4gh/drs/v1/objects/id.bam"
+    );
+    assert!(resolver
+      .objects_url("drs://169.254.169.254/id.bam")
+      .is_err());
+    assert!(resolver
+      .objects_url("drs://drs.example.org.attacker.com/id.bam")
+      .is_err());
+  }
+
+  #[test]
+  fn from_s3_url() {
+    assert_eq!(
+      DrsAccessUrl::from_s3_url("s3://bucket/dir/id.bam", Some("ap-southeast-2".to_string()))
+        .unwrap(),
+      DrsAccessUrl::S3 {
+        bucket: "bucket".to_string(),
+        key: "dir/id.bam".to_string(),
+        region: Some("ap-southeast-2".to_string())
+      }
+    );
+    assert!(DrsAccessUrl::from_s3_url("https://bucket/id.bam", None).is_err());
+    assert!(DrsAccessUrl::from_s3_url("s3://bucket/", None).is_err());
+  }
+}
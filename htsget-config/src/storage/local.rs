@@ -1,11 +1,16 @@
+use std::fmt::{Debug, Formatter};
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use hmac::{Hmac, Mac};
 use http::uri::Authority;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 
 use crate::config::{default_localstorage_addr, default_path, DataServerConfig};
 #[cfg(feature = "experimental")]
 use crate::storage::c4gh::C4GHKeys;
+use crate::storage::{ConcurrencyLimit, EofCache};
 use crate::tls::KeyPairScheme;
 use crate::types::Scheme;
 
@@ -17,6 +22,97 @@ fn default_local_path() -> String {
   default_path().into()
 }
 
+fn default_url_expiry_secs() -> u64 {
+  300
+}
+
+/// A secret key used to sign local data server urls with an expiry time, and to verify those
+/// urls when the data server receives a request.
+#[derive(Deserialize, Clone)]
+#[serde(from = "String")]
+pub struct UrlSigningKey(String);
+
+impl UrlSigningKey {
+  const EXPIRES_PARAM: &'static str = "expires";
+  const SIGNATURE_PARAM: &'static str = "signature";
+
+  /// Create a new signing key.
+  pub fn new(key: impl Into<String>) -> Self {
+    Self(key.into())
+  }
+
+  /// Get the hex encoded signature of the path and expiry time, in seconds since the unix epoch.
+  pub fn sign(&self, path: &str, expires: u64) -> String {
+    hex::encode(self.mac(path, expires).finalize().into_bytes())
+  }
+
+  /// Get the query string containing the signature and expiry time for the path, with the url
+  /// expiring after `expiry`.
+  pub fn signed_query(&self, path: &str, expiry: Duration) -> String {
+    let expires = Self::now() + expiry.as_secs();
+    format!(
+      "{}={}&{}={}",
+      Self::EXPIRES_PARAM,
+      expires,
+      Self::SIGNATURE_PARAM,
+      self.sign(path, expires)
+    )
+  }
+
+  /// Verify that the query contains a valid signature for the path, which has not expired.
+  pub fn verify(&self, path: &str, query: Option<&str>) -> bool {
+    self.verify_at(path, query, Self::now())
+  }
+
+  fn verify_at(&self, path: &str, query: Option<&str>, now: u64) -> bool {
+    let mut expires = None;
+    let mut signature = None;
+    for (key, value) in query
+      .unwrap_or_default()
+      .split('&')
+      .filter_map(|pair| pair.split_once('='))
+    {
+      match key {
+        Self::EXPIRES_PARAM => expires = value.parse::<u64>().ok(),
+        Self::SIGNATURE_PARAM => signature = hex::decode(value).ok(),
+        _ => {}
+      }
+    }
+
+    let (Some(expires), Some(signature)) = (expires, signature) else {
+      return false;
+    };
+
+    expires >= now && self.mac(path, expires).verify_slice(&signature).is_ok()
+  }
+
+  fn mac(&self, path: &str, expires: u64) -> Hmac<Sha256> {
+    let mut mac =
+      Hmac::<Sha256>::new_from_slice(self.0.as_bytes()).expect("expected any key length");
+    mac.update(format!("{path}:{expires}").as_bytes());
+    mac
+  }
+
+  fn now() -> u64 {
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs()
+  }
+}
+
+impl From<String> for UrlSigningKey {
+  fn from(key: String) -> Self {
+    Self::new(key)
+  }
+}
+
+impl Debug for UrlSigningKey {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("UrlSigningKey").field(&"<redacted>").finish()
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Local {
@@ -26,9 +122,23 @@ pub struct Local {
   local_path: String,
   path_prefix: String,
   use_data_server_config: bool,
+  max_concurrent_requests: ConcurrencyLimit,
+  cache_eof: EofCache,
+  read_buffer_size: Option<usize>,
+  read_ahead: Option<u64>,
+  reject_path_traversal: bool,
+  etag: bool,
+  index_local_path: Option<String>,
+  #[serde(skip_serializing)]
+  signing_key: Option<UrlSigningKey>,
+  url_expiry_secs: u64,
   #[serde(skip_serializing)]
   #[cfg(feature = "experimental")]
   keys: Option<C4GHKeys>,
+  #[cfg(feature = "experimental")]
+  max_concurrent_decryptions: ConcurrencyLimit,
+  #[cfg(feature = "experimental")]
+  verify_edit_list: bool,
 }
 
 impl Local {
@@ -46,8 +156,21 @@ impl Local {
       local_path,
       path_prefix,
       use_data_server_config,
+      max_concurrent_requests: Default::default(),
+      cache_eof: Default::default(),
+      read_buffer_size: None,
+      read_ahead: None,
+      reject_path_traversal: false,
+      etag: false,
+      index_local_path: None,
+      signing_key: None,
+      url_expiry_secs: default_url_expiry_secs(),
       #[cfg(feature = "experimental")]
       keys: None,
+      #[cfg(feature = "experimental")]
+      max_concurrent_decryptions: Default::default(),
+      #[cfg(feature = "experimental")]
+      verify_edit_list: false,
     }
   }
 
@@ -76,6 +199,109 @@ impl Local {
     self.use_data_server_config
   }
 
+  /// Get the limit on concurrent requests to this storage.
+  pub fn max_concurrent_requests(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_requests
+  }
+
+  /// Set the limit on concurrent requests to this storage.
+  pub fn set_max_concurrent_requests(mut self, max_concurrent_requests: ConcurrencyLimit) -> Self {
+    self.max_concurrent_requests = max_concurrent_requests;
+    self
+  }
+
+  /// Get the cache of end of file positions for this storage.
+  pub fn cache_eof(&self) -> &EofCache {
+    &self.cache_eof
+  }
+
+  /// Set the cache of end of file positions for this storage.
+  pub fn set_cache_eof(mut self, cache_eof: EofCache) -> Self {
+    self.cache_eof = cache_eof;
+    self
+  }
+
+  /// Get the capacity of the buffer used when streaming reads from this storage.
+  pub fn read_buffer_size(&self) -> Option<usize> {
+    self.read_buffer_size
+  }
+
+  /// Set the capacity of the buffer used when streaming reads from this storage.
+  pub fn set_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+    self.read_buffer_size = read_buffer_size;
+    self
+  }
+
+  /// Get the number of bytes read ahead of sequential range reads from this storage.
+  pub fn read_ahead(&self) -> Option<u64> {
+    self.read_ahead
+  }
+
+  /// Set the number of bytes read ahead of sequential range reads from this storage.
+  pub fn set_read_ahead(mut self, read_ahead: Option<u64>) -> Self {
+    self.read_ahead = read_ahead;
+    self
+  }
+
+  /// Get whether ids which contain path traversal sequences, such as `../`, or absolute paths are
+  /// rejected.
+  pub fn reject_path_traversal(&self) -> bool {
+    self.reject_path_traversal
+  }
+
+  /// Set whether ids which contain path traversal sequences, such as `../`, or absolute paths are
+  /// rejected.
+  pub fn set_reject_path_traversal(mut self, reject_path_traversal: bool) -> Self {
+    self.reject_path_traversal = reject_path_traversal;
+    self
+  }
+
+  /// Get whether responses contain an ETag derived from the modification times of the file and
+  /// its index.
+  pub fn etag(&self) -> bool {
+    self.etag
+  }
+
+  /// Set whether responses contain an ETag derived from the modification times of the file and
+  /// its index.
+  pub fn set_etag(mut self, etag: bool) -> Self {
+    self.etag = etag;
+    self
+  }
+
+  /// Get the local path that index files are read from, if it differs from the local path.
+  pub fn index_local_path(&self) -> Option<&str> {
+    self.index_local_path.as_deref()
+  }
+
+  /// Set the local path that index files are read from, if it differs from the local path.
+  pub fn set_index_local_path(mut self, index_local_path: Option<String>) -> Self {
+    self.index_local_path = index_local_path;
+    self
+  }
+
+  /// Get the key used to sign urls.
+  pub fn signing_key(&self) -> Option<&UrlSigningKey> {
+    self.signing_key.as_ref()
+  }
+
+  /// Set the key used to sign urls.
+  pub fn set_signing_key(mut self, signing_key: Option<UrlSigningKey>) -> Self {
+    self.signing_key = signing_key;
+    self
+  }
+
+  /// Get the number of seconds that signed urls are valid for.
+  pub fn url_expiry_secs(&self) -> u64 {
+    self.url_expiry_secs
+  }
+
+  /// Set the number of seconds that signed urls are valid for.
+  pub fn set_url_expiry_secs(mut self, url_expiry_secs: u64) -> Self {
+    self.url_expiry_secs = url_expiry_secs;
+    self
+  }
+
   #[cfg(feature = "experimental")]
   /// Set the C4GH keys.
   pub fn set_keys(mut self, keys: Option<C4GHKeys>) -> Self {
@@ -88,6 +314,35 @@ impl Local {
   pub fn keys(&self) -> Option<&C4GHKeys> {
     self.keys.as_ref()
   }
+
+  #[cfg(feature = "experimental")]
+  /// Set the maximum number of objects that can be decrypted concurrently.
+  pub fn set_max_concurrent_decryptions(
+    mut self,
+    max_concurrent_decryptions: ConcurrencyLimit,
+  ) -> Self {
+    self.max_concurrent_decryptions = max_concurrent_decryptions;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get the maximum number of objects that can be decrypted concurrently.
+  pub fn max_concurrent_decryptions(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_decryptions
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Set whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn set_verify_edit_list(mut self, verify_edit_list: bool) -> Self {
+    self.verify_edit_list = verify_edit_list;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn verify_edit_list(&self) -> bool {
+    self.verify_edit_list
+  }
 }
 
 impl Default for Local {
@@ -111,6 +366,7 @@ impl From<&DataServerConfig> for Local {
       config.serve_at().to_string(),
       true,
     )
+    .set_signing_key(config.signing_key().cloned())
   }
 }
 
@@ -149,6 +405,107 @@ mod tests {
     );
   }
 
+  #[test]
+  fn config_storage_local_url_expiry_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "Local"
+        signing_key = "secret"
+        url_expiry_secs = 60
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Local(local_storage) if local_storage.signing_key().is_some() && local_storage.url_expiry_secs() == 60
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_local_reject_path_traversal_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "Local"
+        reject_path_traversal = true
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Local(local_storage) if local_storage.reject_path_traversal()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_local_etag_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "Local"
+        etag = true
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Local(local_storage) if local_storage.etag()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_local_index_local_path_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "Local"
+        index_local_path = "indexes"
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Local(local_storage) if local_storage.index_local_path() == Some("indexes")
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn url_signing_key_verify() {
+    let key = UrlSigningKey::new("secret");
+    let query = format!("expires=100&signature={}", key.sign("/data/key1", 100));
+
+    assert!(key.verify_at("/data/key1", Some(&query), 100));
+    assert!(!key.verify_at("/data/key1", Some(&query), 101));
+    assert!(!key.verify_at("/data/key2", Some(&query), 100));
+    assert!(!UrlSigningKey::new("other").verify_at("/data/key1", Some(&query), 100));
+    assert!(!key.verify_at("/data/key1", None, 100));
+  }
+
+  #[test]
+  fn url_signing_key_signed_query() {
+    let key = UrlSigningKey::new("secret");
+    let query = key.signed_query("/data/key1", Duration::from_secs(60));
+
+    assert!(key.verify("/data/key1", Some(&query)));
+  }
+
   #[test]
   fn local_storage_from_data_server_config() {
     let data_server_config = DataServerConfig::new(
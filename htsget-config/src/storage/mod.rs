@@ -1,12 +1,32 @@
+use crate::error::Error::ParseError;
+use crate::error::{Error, Result};
+use crate::index_cache::LruEntries;
+#[cfg(feature = "azure-storage")]
+use crate::storage::azure::Azure;
+#[cfg(feature = "gcs-storage")]
+use crate::storage::gcs::Gcs;
 use crate::storage::local::Local;
 #[cfg(feature = "s3-storage")]
 use crate::storage::s3::S3;
 #[cfg(feature = "url-storage")]
 use crate::storage::url::UrlStorageClient;
+use crate::types::Scheme;
 use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::result;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OnceCell, Semaphore};
 
+#[cfg(feature = "azure-storage")]
+pub mod azure;
 #[cfg(feature = "experimental")]
 pub mod c4gh;
+#[cfg(feature = "url-storage")]
+pub mod drs;
+#[cfg(feature = "gcs-storage")]
+pub mod gcs;
 pub mod local;
 #[cfg(feature = "s3-storage")]
 pub mod s3;
@@ -42,6 +62,249 @@ pub enum Storage {
   #[cfg(feature = "url-storage")]
   #[serde(alias = "url", alias = "URL")]
   Url(#[serde(skip_serializing)] UrlStorageClient),
+  #[cfg(feature = "gcs-storage")]
+  #[serde(alias = "gcs", alias = "GCS")]
+  Gcs(Gcs),
+  #[cfg(feature = "azure-storage")]
+  #[serde(alias = "azure")]
+  Azure(Azure),
+}
+
+impl Storage {
+  /// Get the scheme of the urls that this storage produces in tickets.
+  pub fn ticket_scheme(&self) -> Scheme {
+    match self {
+      Storage::Local(local) => local.scheme(),
+      #[cfg(feature = "s3-storage")]
+      Storage::S3(s3) => match s3.endpoint() {
+        Some(endpoint) if endpoint.starts_with("http://") => Scheme::Http,
+        _ => Scheme::Https,
+      },
+      #[cfg(feature = "url-storage")]
+      Storage::Url(url) => match url.response_url().scheme_str() {
+        Some("https") => Scheme::Https,
+        _ => Scheme::Http,
+      },
+      #[cfg(feature = "gcs-storage")]
+      Storage::Gcs(gcs) if gcs.endpoint().starts_with("http://") => Scheme::Http,
+      #[cfg(feature = "gcs-storage")]
+      Storage::Gcs(_) => Scheme::Https,
+      #[cfg(feature = "azure-storage")]
+      Storage::Azure(azure) if azure.endpoint().starts_with("http://") => Scheme::Http,
+      #[cfg(feature = "azure-storage")]
+      Storage::Azure(_) => Scheme::Https,
+    }
+  }
+}
+
+/// A limit on the number of concurrent requests made to a storage backend. Clones of this value
+/// share the same underlying limit.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(try_from = "Option<usize>", into = "Option<usize>")]
+pub struct ConcurrencyLimit {
+  limit: Option<usize>,
+  semaphore: Option<Arc<Semaphore>>,
+}
+
+impl ConcurrencyLimit {
+  /// Create a new concurrency limit. A `None` value does not limit requests.
+  pub fn new(limit: Option<usize>) -> Self {
+    Self {
+      limit,
+      semaphore: limit.map(|limit| Arc::new(Semaphore::new(limit))),
+    }
+  }
+
+  /// Get the maximum number of concurrent requests.
+  pub fn limit(&self) -> Option<usize> {
+    self.limit
+  }
+
+  /// Get the semaphore used to enforce the limit.
+  pub fn semaphore(&self) -> Option<Arc<Semaphore>> {
+    self.semaphore.clone()
+  }
+}
+
+impl TryFrom<Option<usize>> for ConcurrencyLimit {
+  type Error = Error;
+
+  fn try_from(limit: Option<usize>) -> Result<Self> {
+    if limit == Some(0) {
+      return Err(ParseError(
+        "concurrency limit must be greater than zero".to_string(),
+      ));
+    }
+
+    Ok(Self::new(limit))
+  }
+}
+
+impl From<ConcurrencyLimit> for Option<usize> {
+  fn from(limit: ConcurrencyLimit) -> Self {
+    limit.limit
+  }
+}
+
+/// A least recently used cache of end of file marker positions for keys in a storage backend,
+/// which avoids repeatedly requesting the size of the same object. Positions are not revalidated,
+/// so objects should not change while they are cached. Clones of this value share the same
+/// underlying cache.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "bool", into = "bool")]
+pub struct EofCache {
+  cache: Option<Arc<Mutex<LruEntries<String, u64>>>>,
+  capacity: usize,
+  prefix: String,
+}
+
+impl EofCache {
+  /// The default maximum number of positions held by the cache.
+  pub const DEFAULT_CAPACITY: usize = 4096;
+
+  /// Create a new eof cache, which only caches positions if it is enabled.
+  pub fn new(enabled: bool) -> Self {
+    Self {
+      cache: enabled.then(Default::default),
+      capacity: Self::DEFAULT_CAPACITY,
+      prefix: Default::default(),
+    }
+  }
+
+  /// Set the maximum number of positions held by the cache. The least recently used position is
+  /// evicted when the cache is full.
+  pub fn with_capacity(mut self, capacity: usize) -> Self {
+    self.capacity = capacity;
+    self
+  }
+
+  /// Get the maximum number of positions held by the cache.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Set a prefix which is added to all keys, so that storage backends which share this cache,
+  /// such as different S3 buckets, do not share positions.
+  pub fn with_prefix(mut self, prefix: String) -> Self {
+    self.prefix = prefix;
+    self
+  }
+
+  /// Get whether the cache is enabled.
+  pub fn enabled(&self) -> bool {
+    self.cache.is_some()
+  }
+
+  /// Get the cached end of file position for the key.
+  pub fn get(&self, key: &str) -> Option<u64> {
+    let key = format!("{}{key}", self.prefix);
+    self
+      .cache
+      .as_ref()
+      .and_then(|cache| cache.lock().ok()?.get(&key).copied())
+  }
+
+  /// Cache the end of file position for the key, if the cache is enabled, evicting the least
+  /// recently used position if the cache is full.
+  pub fn insert(&self, key: &str, position: u64) {
+    if let Some(mut cache) = self.cache.as_ref().and_then(|cache| cache.lock().ok()) {
+      cache.insert(format!("{}{key}", self.prefix), position, self.capacity);
+    }
+  }
+}
+
+impl From<bool> for EofCache {
+  fn from(enabled: bool) -> Self {
+    Self::new(enabled)
+  }
+}
+
+impl From<EofCache> for bool {
+  fn from(cache: EofCache) -> Self {
+    cache.enabled()
+  }
+}
+
+/// A storage backend which is built once from the storage config, such as the backend used to
+/// probe readiness. The backend is type-erased because it is built outside of this crate. Clones
+/// of this value share the same backend.
+#[derive(Clone, Default)]
+pub struct StorageCell(Arc<OnceCell<Arc<dyn Any + Send + Sync>>>);
+
+impl StorageCell {
+  /// Get the storage backend, building it with `init` if it has not been built yet. Failures are
+  /// not cached, so building the backend is retried on the next call. Returns `None` if the
+  /// backend was built with a different type.
+  pub async fn get_or_try_init<T, E, F, Fut>(&self, init: F) -> result::Result<Option<Arc<T>>, E>
+  where
+    T: Send + Sync + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = result::Result<T, E>>,
+  {
+    let storage = self
+      .0
+      .get_or_try_init(|| async {
+        init()
+          .await
+          .map(|storage| Arc::new(storage) as Arc<dyn Any + Send + Sync>)
+      })
+      .await?;
+
+    Ok(storage.clone().downcast::<T>().ok())
+  }
+
+  /// Get whether the storage backend has been built.
+  pub fn initialized(&self) -> bool {
+    self.0.initialized()
+  }
+}
+
+impl Debug for StorageCell {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("StorageCell")
+      .field("initialized", &self.initialized())
+      .finish()
+  }
+}
+
+/// Percent-encode the value, leaving unreserved characters and optionally `/` unencoded.
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+  value
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        (byte as char).to_string()
+      }
+      b'/' if !encode_slash => "/".to_string(),
+      _ => format!("%{byte:02X}"),
+    })
+    .collect()
+}
+
+/// Percent-decode the value, returning `None` if the decoded bytes are not valid UTF-8. Invalid
+/// percent-encodings are left as they are.
+pub fn uri_decode(value: &str) -> Option<String> {
+  let value = value.as_bytes();
+  let mut decoded = Vec::with_capacity(value.len());
+  let mut i = 0;
+  while i < value.len() {
+    let hex = value
+      .get(i + 1..i + 3)
+      .filter(|hex| value[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit));
+    match hex {
+      Some(hex) => {
+        let hex = std::str::from_utf8(hex).ok()?;
+        decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        i += 3;
+      }
+      None => {
+        decoded.push(value[i]);
+        i += 1;
+      }
+    }
+  }
+
+  String::from_utf8(decoded).ok()
 }
 
 impl Default for Storage {
@@ -56,6 +319,22 @@ pub(crate) mod tests {
 
   use super::*;
 
+  #[test]
+  fn uri_decode_round_trip() {
+    let value = "folder/key 1 é+%";
+    assert_eq!(
+      uri_decode(&uri_encode(value, false)).as_deref(),
+      Some(value)
+    );
+  }
+
+  #[test]
+  fn uri_decode_invalid_encoding() {
+    assert_eq!(uri_decode("key%2").as_deref(), Some("key%2"));
+    assert_eq!(uri_decode("key%zz").as_deref(), Some("key%zz"));
+    assert_eq!(uri_decode("key%FF"), None);
+  }
+
   #[test]
   fn config_storage_tagged_local_file() {
     test_config_from_file(
@@ -75,6 +354,173 @@ pub(crate) mod tests {
     );
   }
 
+  #[test]
+  fn config_storage_concurrency_limit_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      max_concurrent_requests = 2
+      "#,
+      |config| {
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::Local(local) if local.max_concurrent_requests().limit() == Some(2)
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn concurrency_limit_shared_between_clones() {
+    let limit = ConcurrencyLimit::new(Some(1));
+    let cloned = limit.clone();
+
+    let _permit = limit.semaphore().unwrap().try_acquire_owned().unwrap();
+    assert!(cloned.semaphore().unwrap().try_acquire_owned().is_err());
+  }
+
+  #[tokio::test]
+  async fn storage_cell_built_once() {
+    let cell = StorageCell::default();
+    let cloned = cell.clone();
+
+    let result = cell
+      .get_or_try_init(|| async { Err::<u8, _>("failed") })
+      .await;
+    assert_eq!(result, Err("failed"));
+    assert!(!cell.initialized());
+
+    let storage = cell
+      .get_or_try_init(|| async { Ok::<_, ()>(1u8) })
+      .await
+      .unwrap();
+    assert_eq!(storage, Some(Arc::new(1)));
+
+    let storage = cloned
+      .get_or_try_init(|| async { Ok::<_, ()>(2u8) })
+      .await
+      .unwrap();
+    assert_eq!(storage, Some(Arc::new(1)));
+    assert_eq!(
+      cloned.get_or_try_init(|| async { Ok::<_, ()>(2u16) }).await,
+      Ok(None)
+    );
+  }
+
+  #[test]
+  fn config_storage_cache_eof_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      cache_eof = true
+      "#,
+      |config| {
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::Local(local) if local.cache_eof().enabled()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_read_buffer_size_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      read_buffer_size = 65536
+      "#,
+      |config| {
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::Local(local) if local.read_buffer_size() == Some(65536)
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_read_ahead_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      read_ahead = 65536
+      "#,
+      |config| {
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::Local(local) if local.read_ahead() == Some(65536)
+        ));
+      },
+    );
+  }
+
+  #[cfg(feature = "experimental")]
+  #[test]
+  fn config_storage_verify_edit_list_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      [resolvers.storage]
+      backend = "Local"
+      verify_edit_list = true
+      "#,
+      |config| {
+        assert!(matches!(
+          config.resolvers().first().unwrap().storage(),
+          Storage::Local(local) if local.verify_edit_list()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn eof_cache_shared_between_clones() {
+    let cache = EofCache::new(true);
+    let cloned = cache.clone();
+
+    cache.insert("key", 1);
+    assert_eq!(cloned.get("key"), Some(1));
+    assert_eq!(cloned.with_prefix("bucket/".to_string()).get("key"), None);
+
+    let disabled = EofCache::new(false);
+    disabled.insert("key", 1);
+    assert_eq!(disabled.get("key"), None);
+  }
+
+  #[test]
+  fn eof_cache_evicts_least_recently_used() {
+    let cache = EofCache::new(true).with_capacity(2);
+    cache.insert("key1", 1);
+    cache.insert("key2", 2);
+
+    assert_eq!(cache.get("key1"), Some(1));
+    cache.insert("key3", 3);
+
+    assert_eq!(cache.get("key1"), Some(1));
+    assert_eq!(cache.get("key2"), None);
+    assert_eq!(cache.get("key3"), Some(3));
+  }
+
+  #[test]
+  fn uri_encode_path() {
+    assert_eq!(uri_encode("a b/c~d", false), "a%20b/c~d");
+    assert_eq!(uri_encode("a b/c~d", true), "a%20b%2Fc~d");
+  }
+
+  #[test]
+  fn concurrency_limit_zero() {
+    assert!(ConcurrencyLimit::try_from(Some(0)).is_err());
+  }
+
   #[test]
   fn config_storage_tagged_local_env() {
     test_config_from_env(
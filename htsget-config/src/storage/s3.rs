@@ -1,16 +1,30 @@
 #[cfg(feature = "experimental")]
 use crate::storage::c4gh::C4GHKeys;
+use crate::storage::{ConcurrencyLimit, EofCache};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct S3 {
   pub(crate) bucket: String,
+  pub(crate) index_bucket: Option<String>,
   pub(crate) endpoint: Option<String>,
   pub(crate) path_style: bool,
+  pub(crate) max_concurrent_requests: ConcurrencyLimit,
+  pub(crate) cache_eof: EofCache,
+  pub(crate) read_buffer_size: Option<usize>,
+  pub(crate) read_ahead: Option<u64>,
+  pub(crate) slow_down_retries: u32,
+  pub(crate) slow_down_backoff_ms: u64,
+  pub(crate) request_payer: bool,
+  pub(crate) presign_expiry_secs: u64,
   #[serde(skip_serializing)]
   #[cfg(feature = "experimental")]
   pub(crate) keys: Option<C4GHKeys>,
+  #[cfg(feature = "experimental")]
+  pub(crate) max_concurrent_decryptions: ConcurrencyLimit,
+  #[cfg(feature = "experimental")]
+  pub(crate) verify_edit_list: bool,
 }
 
 impl S3 {
@@ -18,10 +32,23 @@ impl S3 {
   pub fn new(bucket: String, endpoint: Option<String>, path_style: bool) -> Self {
     Self {
       bucket,
+      index_bucket: None,
       endpoint,
       path_style,
+      max_concurrent_requests: Default::default(),
+      cache_eof: Default::default(),
+      read_buffer_size: None,
+      read_ahead: None,
+      slow_down_retries: 0,
+      slow_down_backoff_ms: 100,
+      request_payer: false,
+      presign_expiry_secs: 1000,
       #[cfg(feature = "experimental")]
       keys: None,
+      #[cfg(feature = "experimental")]
+      max_concurrent_decryptions: Default::default(),
+      #[cfg(feature = "experimental")]
+      verify_edit_list: false,
     }
   }
 
@@ -30,6 +57,17 @@ impl S3 {
     &self.bucket
   }
 
+  /// Get the bucket that index files are read from, if it differs from the bucket.
+  pub fn index_bucket(&self) -> Option<&str> {
+    self.index_bucket.as_deref()
+  }
+
+  /// Set the bucket that index files are read from, if it differs from the bucket.
+  pub fn set_index_bucket(mut self, index_bucket: Option<String>) -> Self {
+    self.index_bucket = index_bucket;
+    self
+  }
+
   /// Get the endpoint
   pub fn endpoint(&self) -> Option<&str> {
     self.endpoint.as_deref()
@@ -40,6 +78,93 @@ impl S3 {
     self.path_style
   }
 
+  /// Get the number of times a request is retried when S3 responds with `SlowDown`. These retries
+  /// are in addition to the standard retries of the AWS SDK, which already retries `SlowDown` as a
+  /// throttling error, so each retry may send up to the SDK's maximum attempts.
+  pub fn slow_down_retries(&self) -> u32 {
+    self.slow_down_retries
+  }
+
+  /// Get the initial backoff in milliseconds before retrying a `SlowDown` response. This doubles
+  /// after each retry.
+  pub fn slow_down_backoff_ms(&self) -> u64 {
+    self.slow_down_backoff_ms
+  }
+
+  /// Set the `SlowDown` retry options.
+  pub fn set_slow_down_retry(mut self, retries: u32, backoff_ms: u64) -> Self {
+    self.slow_down_retries = retries;
+    self.slow_down_backoff_ms = backoff_ms;
+    self
+  }
+
+  /// Get whether requests are sent with `x-amz-request-payer: requester`, so that the requester
+  /// is charged for requests to requester pays buckets.
+  pub fn request_payer(&self) -> bool {
+    self.request_payer
+  }
+
+  /// Set whether requests are sent with `x-amz-request-payer: requester`.
+  pub fn set_request_payer(mut self, request_payer: bool) -> Self {
+    self.request_payer = request_payer;
+    self
+  }
+
+  /// Get the number of seconds that presigned ticket URLs are valid for.
+  pub fn presign_expiry_secs(&self) -> u64 {
+    self.presign_expiry_secs
+  }
+
+  /// Set the number of seconds that presigned ticket URLs are valid for.
+  pub fn set_presign_expiry_secs(mut self, presign_expiry_secs: u64) -> Self {
+    self.presign_expiry_secs = presign_expiry_secs;
+    self
+  }
+
+  /// Get the limit on concurrent requests to this storage.
+  pub fn max_concurrent_requests(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_requests
+  }
+
+  /// Set the limit on concurrent requests to this storage.
+  pub fn set_max_concurrent_requests(mut self, max_concurrent_requests: ConcurrencyLimit) -> Self {
+    self.max_concurrent_requests = max_concurrent_requests;
+    self
+  }
+
+  /// Get the cache of end of file positions for this storage.
+  pub fn cache_eof(&self) -> &EofCache {
+    &self.cache_eof
+  }
+
+  /// Set the cache of end of file positions for this storage.
+  pub fn set_cache_eof(mut self, cache_eof: EofCache) -> Self {
+    self.cache_eof = cache_eof;
+    self
+  }
+
+  /// Get the capacity of the buffer used when streaming reads from this storage.
+  pub fn read_buffer_size(&self) -> Option<usize> {
+    self.read_buffer_size
+  }
+
+  /// Set the capacity of the buffer used when streaming reads from this storage.
+  pub fn set_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+    self.read_buffer_size = read_buffer_size;
+    self
+  }
+
+  /// Get the number of bytes read ahead of sequential range reads from this storage.
+  pub fn read_ahead(&self) -> Option<u64> {
+    self.read_ahead
+  }
+
+  /// Set the number of bytes read ahead of sequential range reads from this storage.
+  pub fn set_read_ahead(mut self, read_ahead: Option<u64>) -> Self {
+    self.read_ahead = read_ahead;
+    self
+  }
+
   #[cfg(feature = "experimental")]
   /// Set the C4GH keys.
   pub fn set_keys(mut self, keys: Option<C4GHKeys>) -> Self {
@@ -52,6 +177,41 @@ impl S3 {
   pub fn keys(&self) -> Option<&C4GHKeys> {
     self.keys.as_ref()
   }
+
+  #[cfg(feature = "experimental")]
+  /// Set the maximum number of objects that can be decrypted concurrently.
+  pub fn set_max_concurrent_decryptions(
+    mut self,
+    max_concurrent_decryptions: ConcurrencyLimit,
+  ) -> Self {
+    self.max_concurrent_decryptions = max_concurrent_decryptions;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get the maximum number of objects that can be decrypted concurrently.
+  pub fn max_concurrent_decryptions(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_decryptions
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Set whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn set_verify_edit_list(mut self, verify_edit_list: bool) -> Self {
+    self.verify_edit_list = verify_edit_list;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn verify_edit_list(&self) -> bool {
+    self.verify_edit_list
+  }
+}
+
+impl Default for S3 {
+  fn default() -> Self {
+    Self::new(Default::default(), None, false)
+  }
 }
 
 #[cfg(test)]
@@ -79,4 +239,88 @@ mod tests {
       },
     );
   }
+
+  #[test]
+  fn config_storage_s3_slow_down_retry_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "S3"
+        slow_down_retries = 5
+        slow_down_backoff_ms = 10
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::S3(s3_storage) if s3_storage.slow_down_retries() == 5 && s3_storage.slow_down_backoff_ms() == 10
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_s3_request_payer_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "S3"
+        bucket = "bucket"
+        request_payer = true
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::S3(s3_storage) if s3_storage.request_payer()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_s3_presign_expiry_secs_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "S3"
+        bucket = "bucket"
+        presign_expiry_secs = 3600
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::S3(s3_storage) if s3_storage.presign_expiry_secs() == 3600
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_s3_index_bucket_file() {
+    test_config_from_file(
+      r#"
+        [[resolvers]]
+        regex = "regex"
+
+        [resolvers.storage]
+        backend = "S3"
+        bucket = "bucket"
+        index_bucket = "indexes"
+        "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::S3(s3_storage) if s3_storage.index_bucket() == Some("indexes")
+        ));
+      },
+    );
+  }
 }
@@ -1,4 +1,5 @@
 use cfg_if::cfg_if;
+use http::HeaderMap;
 use http::Uri as InnerUrl;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,7 @@ use crate::error::{Error, Result};
 #[cfg(feature = "experimental")]
 use crate::storage::c4gh::C4GHKeys;
 use crate::storage::local::default_authority;
+use crate::storage::{ConcurrencyLimit, EofCache};
 use crate::tls::client::TlsClientConfig;
 
 fn default_url() -> InnerUrl {
@@ -25,11 +27,27 @@ pub struct UrlStorage {
   response_url: ValidatedUrl,
   forward_headers: bool,
   header_blacklist: Vec<String>,
+  max_concurrent_requests: ConcurrencyLimit,
+  cache_eof: EofCache,
+  read_buffer_size: Option<usize>,
+  read_ahead: Option<u64>,
+  probe_key: Option<String>,
+  retries: u32,
+  retry_backoff_ms: u64,
+  retry_jitter_ms: u64,
+  drs: bool,
+  drs_allowed_hosts: Vec<String>,
+  path_template: Option<String>,
+  forward_headers_override: Option<String>,
   #[serde(skip_serializing)]
   tls: TlsClientConfig,
   #[serde(skip_serializing)]
   #[cfg(feature = "experimental")]
   keys: Option<C4GHKeys>,
+  #[cfg(feature = "experimental")]
+  max_concurrent_decryptions: ConcurrencyLimit,
+  #[cfg(feature = "experimental")]
+  verify_edit_list: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -39,9 +57,26 @@ pub struct UrlStorageClient {
   response_url: ValidatedUrl,
   forward_headers: bool,
   header_blacklist: Vec<String>,
+  max_concurrent_requests: ConcurrencyLimit,
+  cache_eof: EofCache,
+  read_buffer_size: Option<usize>,
+  read_ahead: Option<u64>,
+  probe_key: Option<String>,
+  retries: u32,
+  retry_backoff_ms: u64,
+  retry_jitter_ms: u64,
+  drs: bool,
+  drs_allowed_hosts: Vec<String>,
+  path_template: Option<String>,
+  forward_headers_override: Option<String>,
+  request_ids: Option<(String, String)>,
   client: Client,
   #[cfg(feature = "experimental")]
   keys: Option<C4GHKeys>,
+  #[cfg(feature = "experimental")]
+  max_concurrent_decryptions: ConcurrencyLimit,
+  #[cfg(feature = "experimental")]
+  verify_edit_list: bool,
 }
 
 impl TryFrom<UrlStorage> for UrlStorageClient {
@@ -71,11 +106,27 @@ impl TryFrom<UrlStorage> for UrlStorageClient {
       storage.forward_headers,
       storage.header_blacklist,
       client,
-    );
+    )
+    .set_max_concurrent_requests(storage.max_concurrent_requests)
+    .set_cache_eof(storage.cache_eof)
+    .set_read_buffer_size(storage.read_buffer_size)
+    .set_read_ahead(storage.read_ahead)
+    .set_probe_key(storage.probe_key)
+    .set_retry(storage.retries, storage.retry_backoff_ms)
+    .set_retry_jitter_ms(storage.retry_jitter_ms)
+    .set_drs(storage.drs)
+    .set_drs_allowed_hosts(storage.drs_allowed_hosts)
+    .set_path_template(storage.path_template)
+    .set_forward_headers_override(storage.forward_headers_override);
 
     cfg_if! {
       if #[cfg(feature = "experimental")] {
-        Ok(url_storage.set_keys(storage.keys))
+        Ok(
+          url_storage
+            .set_keys(storage.keys)
+            .set_max_concurrent_decryptions(storage.max_concurrent_decryptions)
+            .set_verify_edit_list(storage.verify_edit_list),
+        )
       } else {
         Ok(url_storage)
       }
@@ -97,9 +148,26 @@ impl UrlStorageClient {
       response_url,
       forward_headers,
       header_blacklist,
+      max_concurrent_requests: Default::default(),
+      cache_eof: Default::default(),
+      read_buffer_size: None,
+      read_ahead: None,
+      probe_key: None,
+      retries: 0,
+      retry_backoff_ms: 100,
+      retry_jitter_ms: 0,
+      drs: false,
+      drs_allowed_hosts: vec![],
+      path_template: None,
+      forward_headers_override: None,
+      request_ids: None,
       client,
       #[cfg(feature = "experimental")]
       keys: None,
+      #[cfg(feature = "experimental")]
+      max_concurrent_decryptions: Default::default(),
+      #[cfg(feature = "experimental")]
+      verify_edit_list: false,
     }
   }
 
@@ -128,6 +196,173 @@ impl UrlStorageClient {
     self.client.clone()
   }
 
+  /// Get the limit on concurrent requests to this storage.
+  pub fn max_concurrent_requests(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_requests
+  }
+
+  /// Set the limit on concurrent requests to this storage.
+  pub fn set_max_concurrent_requests(mut self, max_concurrent_requests: ConcurrencyLimit) -> Self {
+    self.max_concurrent_requests = max_concurrent_requests;
+    self
+  }
+
+  /// Get the cache of end of file positions for this storage.
+  pub fn cache_eof(&self) -> &EofCache {
+    &self.cache_eof
+  }
+
+  /// Set the cache of end of file positions for this storage.
+  pub fn set_cache_eof(mut self, cache_eof: EofCache) -> Self {
+    self.cache_eof = cache_eof;
+    self
+  }
+
+  /// Get the capacity of the buffer used when streaming reads from this storage.
+  pub fn read_buffer_size(&self) -> Option<usize> {
+    self.read_buffer_size
+  }
+
+  /// Set the capacity of the buffer used when streaming reads from this storage.
+  pub fn set_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+    self.read_buffer_size = read_buffer_size;
+    self
+  }
+
+  /// Get the number of bytes read ahead of sequential range reads from this storage.
+  pub fn read_ahead(&self) -> Option<u64> {
+    self.read_ahead
+  }
+
+  /// Set the number of bytes read ahead of sequential range reads from this storage.
+  pub fn set_read_ahead(mut self, read_ahead: Option<u64>) -> Self {
+    self.read_ahead = read_ahead;
+    self
+  }
+
+  /// Get the key which is requested with a HEAD request to check that the storage is reachable.
+  /// The storage is not probed if this is not set.
+  pub fn probe_key(&self) -> Option<&str> {
+    self.probe_key.as_deref()
+  }
+
+  /// Set the key which is requested with a HEAD request to check that the storage is reachable.
+  pub fn set_probe_key(mut self, probe_key: Option<String>) -> Self {
+    self.probe_key = probe_key;
+    self
+  }
+
+  /// Get the number of times a failed `GET` or `HEAD` request is retried. Requests with other
+  /// methods are never retried.
+  pub fn retries(&self) -> u32 {
+    self.retries
+  }
+
+  /// Get the initial backoff in milliseconds before retrying a failed request. This doubles after
+  /// each retry.
+  pub fn retry_backoff_ms(&self) -> u64 {
+    self.retry_backoff_ms
+  }
+
+  /// Set the retry options.
+  pub fn set_retry(mut self, retries: u32, retry_backoff_ms: u64) -> Self {
+    self.retries = retries;
+    self.retry_backoff_ms = retry_backoff_ms;
+    self
+  }
+
+  /// Get the maximum random delay in milliseconds which is added to each retry backoff.
+  pub fn retry_jitter_ms(&self) -> u64 {
+    self.retry_jitter_ms
+  }
+
+  /// Set the maximum random delay in milliseconds which is added to each retry backoff.
+  pub fn set_retry_jitter_ms(mut self, retry_jitter_ms: u64) -> Self {
+    self.retry_jitter_ms = retry_jitter_ms;
+    self
+  }
+
+  /// Get whether the url is a DRS server which resolves ids to access urls.
+  pub fn drs(&self) -> bool {
+    self.drs
+  }
+
+  /// Set whether the url is a DRS server which resolves ids to access urls.
+  pub fn set_drs(mut self, drs: bool) -> Self {
+    self.drs = drs;
+    self
+  }
+
+  /// Get the hosts, other than the DRS server, which `drs://<host>/<id>` keys can be resolved by.
+  pub fn drs_allowed_hosts(&self) -> &[String] {
+    &self.drs_allowed_hosts
+  }
+
+  /// Set the hosts, other than the DRS server, which `drs://<host>/<id>` keys can be resolved by.
+  pub fn set_drs_allowed_hosts(mut self, drs_allowed_hosts: Vec<String>) -> Self {
+    self.drs_allowed_hosts = drs_allowed_hosts;
+    self
+  }
+
+  /// Get the template of the path requested from the url. `{id}` is replaced by the original
+  /// request id, `{key}` by the storage key and `{extension}` by the part of the key which follows
+  /// the resolved id.
+  pub fn path_template(&self) -> Option<&str> {
+    self.path_template.as_deref()
+  }
+
+  /// Set the template of the path requested from the url.
+  pub fn set_path_template(mut self, path_template: Option<String>) -> Self {
+    self.path_template = path_template;
+    self
+  }
+
+  /// Get the name of the trusted header which overrides whether headers are forwarded for a
+  /// request.
+  pub fn forward_headers_override(&self) -> Option<&str> {
+    self.forward_headers_override.as_deref()
+  }
+
+  /// Set the name of the trusted header which overrides whether headers are forwarded for a
+  /// request.
+  pub fn set_forward_headers_override(mut self, forward_headers_override: Option<String>) -> Self {
+    self.forward_headers_override = forward_headers_override;
+    self
+  }
+
+  /// Override whether headers are forwarded for a request, using a `true` or `false` value of the
+  /// override header in the request headers. The override header itself is never forwarded.
+  pub fn apply_forward_headers_override(mut self, headers: &HeaderMap) -> Self {
+    let Some(name) = self.forward_headers_override.clone() else {
+      return self;
+    };
+
+    if let Some(forward_headers) = headers
+      .get(&name)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<bool>().ok())
+    {
+      self.forward_headers = forward_headers;
+    }
+    self.header_blacklist.push(name);
+
+    self
+  }
+
+  /// Get the original request id and the resolved id of the request this storage is used for.
+  pub fn request_ids(&self) -> Option<(&str, &str)> {
+    self
+      .request_ids
+      .as_ref()
+      .map(|(id, resolved_id)| (id.as_str(), resolved_id.as_str()))
+  }
+
+  /// Set the original request id and the resolved id of the request this storage is used for.
+  pub fn set_request_ids(mut self, id: String, resolved_id: String) -> Self {
+    self.request_ids = Some((id, resolved_id));
+    self
+  }
+
   #[cfg(feature = "experimental")]
   /// Set the C4GH keys.
   pub fn set_keys(mut self, keys: Option<C4GHKeys>) -> Self {
@@ -140,6 +375,35 @@ impl UrlStorageClient {
   pub fn keys(&self) -> Option<&C4GHKeys> {
     self.keys.as_ref()
   }
+
+  #[cfg(feature = "experimental")]
+  /// Set the maximum number of objects that can be decrypted concurrently.
+  pub fn set_max_concurrent_decryptions(
+    mut self,
+    max_concurrent_decryptions: ConcurrencyLimit,
+  ) -> Self {
+    self.max_concurrent_decryptions = max_concurrent_decryptions;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get the maximum number of objects that can be decrypted concurrently.
+  pub fn max_concurrent_decryptions(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_decryptions
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Set whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn set_verify_edit_list(mut self, verify_edit_list: bool) -> Self {
+    self.verify_edit_list = verify_edit_list;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn verify_edit_list(&self) -> bool {
+    self.verify_edit_list
+  }
 }
 
 /// A wrapper around `http::Uri` type which implements serialize and deserialize.
@@ -189,9 +453,25 @@ impl UrlStorage {
       }),
       forward_headers,
       header_blacklist,
+      max_concurrent_requests: Default::default(),
+      cache_eof: Default::default(),
+      read_buffer_size: None,
+      read_ahead: None,
+      probe_key: None,
+      retries: 0,
+      retry_backoff_ms: 100,
+      retry_jitter_ms: 0,
+      drs: false,
+      drs_allowed_hosts: vec![],
+      path_template: None,
+      forward_headers_override: None,
       tls,
       #[cfg(feature = "experimental")]
       keys: None,
+      #[cfg(feature = "experimental")]
+      max_concurrent_decryptions: Default::default(),
+      #[cfg(feature = "experimental")]
+      verify_edit_list: false,
     }
   }
 
@@ -216,6 +496,140 @@ impl UrlStorage {
     &self.tls
   }
 
+  /// Get the limit on concurrent requests to this storage.
+  pub fn max_concurrent_requests(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_requests
+  }
+
+  /// Set the limit on concurrent requests to this storage.
+  pub fn set_max_concurrent_requests(mut self, max_concurrent_requests: ConcurrencyLimit) -> Self {
+    self.max_concurrent_requests = max_concurrent_requests;
+    self
+  }
+
+  /// Get the cache of end of file positions for this storage.
+  pub fn cache_eof(&self) -> &EofCache {
+    &self.cache_eof
+  }
+
+  /// Set the cache of end of file positions for this storage.
+  pub fn set_cache_eof(mut self, cache_eof: EofCache) -> Self {
+    self.cache_eof = cache_eof;
+    self
+  }
+
+  /// Get the capacity of the buffer used when streaming reads from this storage.
+  pub fn read_buffer_size(&self) -> Option<usize> {
+    self.read_buffer_size
+  }
+
+  /// Set the capacity of the buffer used when streaming reads from this storage.
+  pub fn set_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+    self.read_buffer_size = read_buffer_size;
+    self
+  }
+
+  /// Get the number of bytes read ahead of sequential range reads from this storage.
+  pub fn read_ahead(&self) -> Option<u64> {
+    self.read_ahead
+  }
+
+  /// Set the number of bytes read ahead of sequential range reads from this storage.
+  pub fn set_read_ahead(mut self, read_ahead: Option<u64>) -> Self {
+    self.read_ahead = read_ahead;
+    self
+  }
+
+  /// Get the key which is requested with a HEAD request to check that the storage is reachable.
+  /// The storage is not probed if this is not set.
+  pub fn probe_key(&self) -> Option<&str> {
+    self.probe_key.as_deref()
+  }
+
+  /// Set the key which is requested with a HEAD request to check that the storage is reachable.
+  pub fn set_probe_key(mut self, probe_key: Option<String>) -> Self {
+    self.probe_key = probe_key;
+    self
+  }
+
+  /// Get the number of times a failed `GET` or `HEAD` request is retried. Requests with other
+  /// methods are never retried.
+  pub fn retries(&self) -> u32 {
+    self.retries
+  }
+
+  /// Get the initial backoff in milliseconds before retrying a failed request. This doubles after
+  /// each retry.
+  pub fn retry_backoff_ms(&self) -> u64 {
+    self.retry_backoff_ms
+  }
+
+  /// Set the retry options.
+  pub fn set_retry(mut self, retries: u32, retry_backoff_ms: u64) -> Self {
+    self.retries = retries;
+    self.retry_backoff_ms = retry_backoff_ms;
+    self
+  }
+
+  /// Get the maximum random delay in milliseconds which is added to each retry backoff.
+  pub fn retry_jitter_ms(&self) -> u64 {
+    self.retry_jitter_ms
+  }
+
+  /// Set the maximum random delay in milliseconds which is added to each retry backoff.
+  pub fn set_retry_jitter_ms(mut self, retry_jitter_ms: u64) -> Self {
+    self.retry_jitter_ms = retry_jitter_ms;
+    self
+  }
+
+  /// Get whether the url is a DRS server which resolves ids to access urls.
+  pub fn drs(&self) -> bool {
+    self.drs
+  }
+
+  /// Set whether the url is a DRS server which resolves ids to access urls.
+  pub fn set_drs(mut self, drs: bool) -> Self {
+    self.drs = drs;
+    self
+  }
+
+  /// Get the hosts, other than the DRS server, which `drs://<host>/<id>` keys can be resolved by.
+  pub fn drs_allowed_hosts(&self) -> &[String] {
+    &self.drs_allowed_hosts
+  }
+
+  /// Set the hosts, other than the DRS server, which `drs://<host>/<id>` keys can be resolved by.
+  pub fn set_drs_allowed_hosts(mut self, drs_allowed_hosts: Vec<String>) -> Self {
+    self.drs_allowed_hosts = drs_allowed_hosts;
+    self
+  }
+
+  /// Get the template of the path requested from the url. `{id}` is replaced by the original
+  /// request id, `{key}` by the storage key and `{extension}` by the part of the key which follows
+  /// the resolved id.
+  pub fn path_template(&self) -> Option<&str> {
+    self.path_template.as_deref()
+  }
+
+  /// Set the template of the path requested from the url.
+  pub fn set_path_template(mut self, path_template: Option<String>) -> Self {
+    self.path_template = path_template;
+    self
+  }
+
+  /// Get the name of the trusted header which overrides whether headers are forwarded for a
+  /// request.
+  pub fn forward_headers_override(&self) -> Option<&str> {
+    self.forward_headers_override.as_deref()
+  }
+
+  /// Set the name of the trusted header which overrides whether headers are forwarded for a
+  /// request.
+  pub fn set_forward_headers_override(mut self, forward_headers_override: Option<String>) -> Self {
+    self.forward_headers_override = forward_headers_override;
+    self
+  }
+
   #[cfg(feature = "experimental")]
   /// Set the C4GH keys.
   pub fn set_keys(mut self, keys: Option<C4GHKeys>) -> Self {
@@ -228,6 +642,35 @@ impl UrlStorage {
   pub fn keys(&self) -> Option<&C4GHKeys> {
     self.keys.as_ref()
   }
+
+  #[cfg(feature = "experimental")]
+  /// Set the maximum number of objects that can be decrypted concurrently.
+  pub fn set_max_concurrent_decryptions(
+    mut self,
+    max_concurrent_decryptions: ConcurrencyLimit,
+  ) -> Self {
+    self.max_concurrent_decryptions = max_concurrent_decryptions;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get the maximum number of objects that can be decrypted concurrently.
+  pub fn max_concurrent_decryptions(&self) -> &ConcurrencyLimit {
+    &self.max_concurrent_decryptions
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Set whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn set_verify_edit_list(mut self, verify_edit_list: bool) -> Self {
+    self.verify_edit_list = verify_edit_list;
+    self
+  }
+
+  #[cfg(feature = "experimental")]
+  /// Get whether the Crypt4GH edit list is verified against the decrypted data before responding.
+  pub fn verify_edit_list(&self) -> bool {
+    self.verify_edit_list
+  }
 }
 
 impl Default for UrlStorage {
@@ -305,4 +748,137 @@ mod tests {
       );
     });
   }
+
+  #[test]
+  fn config_storage_url_retry_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.storage]
+      backend = "Url"
+      url = "https://example.com/"
+      retries = 2
+      retry_backoff_ms = 50
+      retry_jitter_ms = 20
+      "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Url(url_storage) if url_storage.retries() == 2
+              && url_storage.retry_backoff_ms() == 50
+              && url_storage.retry_jitter_ms() == 20
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_url_drs_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.storage]
+      backend = "Url"
+      url = "https://drs.example.com/"
+      drs = true
+      "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Url(url_storage) if url_storage.drs()
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_url_drs_allowed_hosts_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.storage]
+      backend = "Url"
+      url = "https://drs.example.com/"
+      drs = true
+      drs_allowed_hosts = ["drs.example.org"]
+      "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Url(url_storage) if url_storage.drs_allowed_hosts() == ["drs.example.org"]
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_url_path_template_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.storage]
+      backend = "Url"
+      url = "https://example.com/"
+      path_template = "{id}{extension}"
+      "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Url(url_storage) if url_storage.path_template() == Some("{id}{extension}")
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn config_storage_url_forward_headers_override_file() {
+    test_config_from_file(
+      r#"
+      [[resolvers]]
+      regex = "regex"
+
+      [resolvers.storage]
+      backend = "Url"
+      url = "https://example.com/"
+      forward_headers_override = "X-Htsget-Forward-Headers"
+      "#,
+      |config| {
+        assert!(matches!(
+            config.resolvers().first().unwrap().storage(),
+            Storage::Url(url_storage) if url_storage.forward_headers_override() == Some("X-Htsget-Forward-Headers")
+        ));
+      },
+    );
+  }
+
+  #[test]
+  fn apply_forward_headers_override() {
+    let url_storage = UrlStorageClient::try_from(UrlStorage::new(
+      "https://example.com".parse::<InnerUrl>().unwrap(),
+      "https://example.com".parse::<InnerUrl>().unwrap(),
+      true,
+      vec![],
+      Default::default(),
+    ))
+    .unwrap()
+    .set_forward_headers_override(Some("X-Htsget-Forward-Headers".to_string()));
+
+    let mut headers = HeaderMap::default();
+    let not_set = url_storage.clone().apply_forward_headers_override(&headers);
+    assert!(not_set.forward_headers());
+    assert_eq!(not_set.header_blacklist(), ["X-Htsget-Forward-Headers"]);
+
+    headers.insert("x-htsget-forward-headers", "false".parse().unwrap());
+    let overridden = url_storage.apply_forward_headers_override(&headers);
+    assert!(!overridden.forward_headers());
+    assert_eq!(overridden.header_blacklist(), ["X-Htsget-Forward-Headers"]);
+  }
 }
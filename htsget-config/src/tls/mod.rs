@@ -40,6 +40,18 @@ impl TlsServerConfig {
     Self { server_config }
   }
 
+  /// Set whether HTTP/2 is advertised to clients using ALPN. If it is not, clients negotiate
+  /// HTTP/1.1.
+  pub fn with_http2(mut self, http2: bool) -> Self {
+    self.server_config.alpn_protocols = alpn_protocols(http2);
+    self
+  }
+
+  /// Get the protocols advertised to clients using ALPN.
+  pub fn alpn_protocols(&self) -> &[Vec<u8>] {
+    &self.server_config.alpn_protocols
+  }
+
   /// Get the inner server config.
   pub fn into_inner(self) -> ServerConfig {
     self.server_config
@@ -191,11 +203,20 @@ pub fn tls_server_config(key_pair: CertificateKeyPair) -> Result<ServerConfig> {
     .with_single_cert(certs, key)
     .map_err(|err| ParseError(err.to_string()))?;
 
-  config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+  config.alpn_protocols = alpn_protocols(true);
 
   Ok(config)
 }
 
+/// Get the ALPN protocols, in order of preference.
+fn alpn_protocols(http2: bool) -> Vec<Vec<u8>> {
+  if http2 {
+    vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+  } else {
+    vec![b"http/1.1".to_vec()]
+  }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
   use std::fs::write;
@@ -242,6 +263,17 @@ pub(crate) mod tests {
     });
   }
 
+  #[test]
+  fn test_tls_server_config_without_http2() {
+    with_test_certificates(|_, key, cert| {
+      let server_config =
+        TlsServerConfig::new(tls_server_config(CertificateKeyPair::new(vec![cert], key)).unwrap())
+          .with_http2(false);
+
+      assert_eq!(server_config.alpn_protocols(), vec![b"http/1.1".to_vec()]);
+    });
+  }
+
   pub(crate) fn with_test_certificates<F>(test: F)
   where
     F: FnOnce(&Path, PrivateKeyDer<'static>, CertificateDer<'static>),
@@ -1,22 +1,28 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::io::ErrorKind::Other;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, io, result};
 
 use http::HeaderMap;
 use noodles::core::region::Interval as NoodlesInterval;
 use noodles::core::Position;
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use thiserror::Error;
+use tokio::fs;
 use tracing::instrument;
 
 use crate::error::Error;
 use crate::error::Error::ParseError;
+use crate::index_cache::IndexCache;
 
 pub type Result<T> = result::Result<T, HtsGetError>;
 
 /// An enumeration with all the possible formats.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "UPPERCASE"))]
 pub enum Format {
   #[serde(alias = "bam", alias = "BAM")]
@@ -29,6 +35,260 @@ pub enum Format {
   Bcf,
 }
 
+/// Aliases which clients may send in place of a canonical format name, such as `gvcf` for `vcf`.
+/// Aliases are matched case-insensitively.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(from = "HashMap<String, Format>", into = "HashMap<String, Format>")]
+pub struct FormatAliases(HashMap<String, Format>);
+
+impl FormatAliases {
+  /// Create new format aliases.
+  pub fn new(aliases: HashMap<String, Format>) -> Self {
+    Self(
+      aliases
+        .into_iter()
+        .map(|(alias, format)| (alias.to_lowercase(), format))
+        .collect(),
+    )
+  }
+
+  /// Get the canonical format for the alias.
+  pub fn get(&self, alias: &str) -> Option<Format> {
+    self.0.get(&alias.to_lowercase()).copied()
+  }
+}
+
+impl From<HashMap<String, Format>> for FormatAliases {
+  fn from(aliases: HashMap<String, Format>) -> Self {
+    Self::new(aliases)
+  }
+}
+
+impl From<FormatAliases> for HashMap<String, Format> {
+  fn from(aliases: FormatAliases) -> Self {
+    aliases.0
+  }
+}
+
+/// Route patterns which are deprecated, responses to requests with a path matching any of the
+/// patterns include a `Deprecation` header.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct DeprecatedRoutes(#[serde(with = "serde_regex")] Vec<Regex>);
+
+impl DeprecatedRoutes {
+  /// Create new deprecated routes from regex patterns.
+  pub fn new(routes: &[&str]) -> result::Result<Self, regex::Error> {
+    Ok(Self(
+      routes
+        .iter()
+        .map(|route| Regex::new(route))
+        .collect::<result::Result<_, _>>()?,
+    ))
+  }
+
+  /// Check whether there are no deprecated routes.
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// Check whether the path matches any of the deprecated routes.
+  pub fn is_deprecated(&self, path: &str) -> bool {
+    self.0.iter().any(|route| route.is_match(path))
+  }
+}
+
+/// The format of the `Retry-After` header.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAfterFormat {
+  /// A number of seconds to wait.
+  #[default]
+  #[serde(alias = "seconds", alias = "SECONDS")]
+  Seconds,
+  /// An HTTP-date after which to retry.
+  #[serde(alias = "httpdate", alias = "HTTPDATE")]
+  HttpDate,
+}
+
+/// Audit logging of requests which are denied with 401 Unauthorized or 403 Forbidden. Entries are
+/// logged separately from other logs, with the subject of the request read from a header.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct AuditLog {
+  enabled: bool,
+  subject_header: Option<String>,
+}
+
+impl AuditLog {
+  /// Create a new audit log which is enabled.
+  pub fn new(subject_header: Option<String>) -> Self {
+    Self {
+      enabled: true,
+      subject_header,
+    }
+  }
+
+  /// Get whether denied requests are audit logged.
+  pub fn enabled(&self) -> bool {
+    self.enabled
+  }
+
+  /// Get the header which contains the subject of the request, such as a header set by an
+  /// authenticating proxy.
+  pub fn subject_header(&self) -> Option<&str> {
+    self.subject_header.as_deref()
+  }
+}
+
+/// Maintenance mode, which is active while the maintenance file exists. During maintenance, queries
+/// are rejected with a 503 Service Unavailable and a `Retry-After` header. The file is checked on
+/// each request, so maintenance mode can be toggled without restarting the server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Maintenance {
+  file: Option<PathBuf>,
+  retry_after: u64,
+  retry_after_format: RetryAfterFormat,
+}
+
+impl Default for Maintenance {
+  fn default() -> Self {
+    Self {
+      file: None,
+      retry_after: 60,
+      retry_after_format: RetryAfterFormat::default(),
+    }
+  }
+}
+
+impl Maintenance {
+  /// Create a new maintenance mode which is active while the file exists.
+  pub fn new(file: PathBuf) -> Self {
+    Self {
+      file: Some(file),
+      ..Default::default()
+    }
+  }
+
+  /// Set the number of seconds returned in the `Retry-After` header during maintenance.
+  pub fn with_retry_after(mut self, retry_after: u64) -> Self {
+    self.retry_after = retry_after;
+    self
+  }
+
+  /// Set whether the `Retry-After` header is a number of seconds or an HTTP-date.
+  pub fn with_retry_after_format(mut self, retry_after_format: RetryAfterFormat) -> Self {
+    self.retry_after_format = retry_after_format;
+    self
+  }
+
+  /// Get the file which enables maintenance mode while it exists.
+  pub fn file(&self) -> Option<&Path> {
+    self.file.as_deref()
+  }
+
+  /// Get the number of seconds returned in the `Retry-After` header during maintenance.
+  pub fn retry_after(&self) -> u64 {
+    self.retry_after
+  }
+
+  /// Get whether the `Retry-After` header is a number of seconds or an HTTP-date.
+  pub fn retry_after_format(&self) -> RetryAfterFormat {
+    self.retry_after_format
+  }
+
+  /// Get the value of the `Retry-After` header, in the configured format.
+  pub fn retry_after_header_value(&self) -> String {
+    self.retry_after_header_value_at(SystemTime::now())
+  }
+
+  fn retry_after_header_value_at(&self, now: SystemTime) -> String {
+    match self.retry_after_format {
+      RetryAfterFormat::Seconds => self.retry_after.to_string(),
+      RetryAfterFormat::HttpDate => fmt_http_date(now + Duration::from_secs(self.retry_after)),
+    }
+  }
+
+  /// Check whether a maintenance file is configured.
+  pub fn is_enabled(&self) -> bool {
+    self.file.is_some()
+  }
+
+  /// Check whether maintenance mode is currently active. The file is checked without blocking the
+  /// async runtime, and maintenance mode is inactive if its existence cannot be determined.
+  pub async fn is_active(&self) -> bool {
+    match &self.file {
+      Some(file) => fs::try_exists(file).await.unwrap_or(false),
+      None => false,
+    }
+  }
+}
+
+/// A UTC date and time, used to format timestamps without a date-time dependency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UtcDateTime {
+  pub(crate) year: u64,
+  pub(crate) month: u64,
+  pub(crate) day: u64,
+  pub(crate) hour: u64,
+  pub(crate) minute: u64,
+  pub(crate) second: u64,
+  days_since_epoch: u64,
+}
+
+impl From<SystemTime> for UtcDateTime {
+  fn from(time: SystemTime) -> Self {
+    let secs = time
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Convert days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days_since_era = days + 719468;
+    let era = days_since_era / 146097;
+    let day_of_era = days_since_era - era * 146097;
+    let year_of_era =
+      (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = (month_index + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    Self {
+      year,
+      month,
+      day,
+      hour: secs_of_day / 3600,
+      minute: secs_of_day / 60 % 60,
+      second: secs_of_day % 60,
+      days_since_epoch: days,
+    }
+  }
+}
+
+/// Format the time as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn fmt_http_date(time: SystemTime) -> String {
+  const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+  const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+  ];
+
+  let date = UtcDateTime::from(time);
+  format!(
+    "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+    WEEKDAYS[(date.days_since_epoch % 7) as usize],
+    date.day,
+    MONTHS[(date.month - 1) as usize],
+    date.year,
+    date.hour,
+    date.minute,
+    date.second
+  )
+}
+
 /// Todo allow these to be configurable.
 impl Format {
   pub fn file_ending(&self) -> &str {
@@ -101,7 +361,7 @@ impl Display for Format {
 }
 
 /// Class component of htsget response.
-#[derive(Copy, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Copy, Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "lowercase"))]
 pub enum Class {
   #[serde(alias = "header", alias = "HEADER")]
@@ -112,7 +372,7 @@ pub enum Class {
 
 /// An interval represents the start (0-based, inclusive) and end (0-based exclusive) ranges of the
 /// query.
-#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Interval {
   start: Option<u32>,
   end: Option<u32>,
@@ -191,6 +451,47 @@ impl Interval {
   }
 }
 
+/// How to handle byte ranges which extend past the end of the file, for example because the
+/// index is stale.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OversizedRange {
+  /// Clamp the byte ranges to the file size and log a warning.
+  #[default]
+  #[serde(alias = "clamp", alias = "CLAMP")]
+  Clamp,
+  /// Return an error.
+  #[serde(alias = "error", alias = "ERROR")]
+  Error,
+}
+
+/// How to handle a requested format which conflicts with the format of the objects that a resolver
+/// serves.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FormatConflict {
+  /// Use the requested format.
+  #[default]
+  #[serde(alias = "request", alias = "REQUEST")]
+  Request,
+  /// Use the format of the resolver.
+  #[serde(alias = "resolver", alias = "RESOLVER")]
+  Resolver,
+  /// Return an error.
+  #[serde(alias = "error", alias = "ERROR")]
+  Error,
+}
+
+/// How the `*` reference name, which requests unplaced unmapped records, is handled for variants.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnmappedVariants {
+  /// Return an invalid input error.
+  #[default]
+  #[serde(alias = "reject", alias = "REJECT")]
+  Reject,
+  /// Return the records after the last record in the index, in the same way as unmapped reads.
+  #[serde(alias = "unplaced", alias = "UNPLACED")]
+  Unplaced,
+}
+
 /// Schemes that can be used with htsget.
 #[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
@@ -286,6 +587,8 @@ impl Request {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Query {
   id: String,
+  /// The id used to locate index files, if it differs from the id.
+  index_id: Option<String>,
   format: Format,
   class: Class,
   /// Reference name
@@ -297,6 +600,8 @@ pub struct Query {
   no_tags: NoTags,
   /// The raw HTTP request information.
   request: Request,
+  /// Whether the format was omitted from the request, so that the endpoint default was used.
+  format_omitted: bool,
 }
 
 impl Query {
@@ -304,6 +609,7 @@ impl Query {
   pub fn new(id: impl Into<String>, format: Format, request: Request) -> Self {
     Self {
       id: id.into(),
+      index_id: None,
       format,
       class: Class::Body,
       reference_name: None,
@@ -312,6 +618,7 @@ impl Query {
       tags: Tags::Tagged(TaggedTypeAll::All),
       no_tags: NoTags(None),
       request,
+      format_omitted: false,
     }
   }
 
@@ -332,9 +639,25 @@ impl Query {
     self
   }
 
+  /// Set the id used to locate index files.
+  pub fn set_index_id(&mut self, index_id: Option<String>) {
+    self.index_id = index_id;
+  }
+
+  /// Set the id used to locate index files and return self.
+  pub fn with_index_id(mut self, index_id: Option<String>) -> Self {
+    self.set_index_id(index_id);
+    self
+  }
+
   /// Set the format.
-  pub fn with_format(mut self, format: Format) -> Self {
+  pub fn set_format(&mut self, format: Format) {
     self.format = format;
+  }
+
+  /// Set the format.
+  pub fn with_format(mut self, format: Format) -> Self {
+    self.set_format(format);
     self
   }
 
@@ -382,10 +705,26 @@ impl Query {
     self
   }
 
+  /// Set whether the format was omitted from the request.
+  pub fn set_format_omitted(&mut self, format_omitted: bool) {
+    self.format_omitted = format_omitted;
+  }
+
+  /// Set whether the format was omitted from the request and return self.
+  pub fn with_format_omitted(mut self, format_omitted: bool) -> Self {
+    self.set_format_omitted(format_omitted);
+    self
+  }
+
   pub fn id(&self) -> &str {
     &self.id
   }
 
+  /// Get the id used to locate index files, which is the id unless set separately.
+  pub fn index_id(&self) -> &str {
+    self.index_id.as_deref().unwrap_or(&self.id)
+  }
+
   pub fn format(&self) -> Format {
     self.format
   }
@@ -417,6 +756,298 @@ impl Query {
   pub fn request(&self) -> &Request {
     &self.request
   }
+
+  pub fn format_omitted(&self) -> bool {
+    self.format_omitted
+  }
+}
+
+/// Options which control how a query is searched. These are set per resolver, and are passed
+/// alongside the query to the search.
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptions {
+  /// Whether the reference name should be validated against the file header before searching.
+  validate_reference_names: bool,
+  /// Whether VCF files should be checked to be BGZF compressed before searching.
+  validate_bgzf: bool,
+  /// The maximum file size for which the whole file is returned if the index is missing.
+  whole_file_fallback_max_size: Option<u64>,
+  /// Whether the response should include the total size of the byte ranges.
+  include_total_size: bool,
+  /// The maximum total size of the byte ranges that a response may contain.
+  max_total_response_bytes: Option<u64>,
+  /// Additional index file extensions which are tried after the default index file ending.
+  index_extensions: Vec<String>,
+  /// Whether the response should include the public key of the sender of encrypted data.
+  include_sender_public_key: bool,
+  /// The maximum duration of the search before it is aborted.
+  search_timeout: Option<Duration>,
+  /// Whether each url in the response should include its byte range within the concatenated file.
+  include_content_range: bool,
+  /// How byte ranges which extend past the end of the file are handled.
+  oversized_range: OversizedRange,
+  /// The maximum number of bytes a single url in the response may cover.
+  max_bytes_per_url: Option<u64>,
+  /// The minimum size of a fragment produced when splitting ranges by `max_bytes_per_url`.
+  min_block_size: Option<u64>,
+  /// The maximum number of urls in the response, which is reached by coalescing ranges across gaps.
+  max_urls_per_ticket: Option<usize>,
+  /// Whether the number of index bins and chunks scanned by the search is logged.
+  log_index_scan: bool,
+  /// The maximum number of bytes between two byte ranges which are merged into one range.
+  merge_gap: Option<u64>,
+  /// How the `*` reference name is handled for variants.
+  unmapped_variants: UnmappedVariants,
+  /// Whether the durations of the stages of the search are recorded in the response.
+  server_timing: bool,
+  /// Whether header and body ranges are kept separate when merging ranges.
+  preserve_classes: bool,
+  /// Whether header queries are served by reading the header when the index is missing.
+  header_without_index: bool,
+  /// Whether a `chr` prefix is added to or stripped from the reference name to match the header.
+  normalize_chr_prefix: bool,
+  /// The cache of parsed indexes which is shared between searches.
+  index_cache: Option<IndexCache>,
+}
+
+impl SearchOptions {
+  /// Set whether the reference name should be validated against the file header.
+  pub fn with_validate_reference_names(mut self, validate_reference_names: bool) -> Self {
+    self.validate_reference_names = validate_reference_names;
+    self
+  }
+
+  /// Set whether VCF files should be checked to be BGZF compressed before searching.
+  pub fn with_validate_bgzf(mut self, validate_bgzf: bool) -> Self {
+    self.validate_bgzf = validate_bgzf;
+    self
+  }
+
+  /// Set the maximum file size for which the whole file is returned if the index is missing.
+  pub fn with_whole_file_fallback_max_size(
+    mut self,
+    whole_file_fallback_max_size: Option<u64>,
+  ) -> Self {
+    self.whole_file_fallback_max_size = whole_file_fallback_max_size;
+    self
+  }
+
+  /// Set whether the response should include the total size of the byte ranges.
+  pub fn with_include_total_size(mut self, include_total_size: bool) -> Self {
+    self.include_total_size = include_total_size;
+    self
+  }
+
+  /// Set the maximum total size of the byte ranges that a response may contain.
+  pub fn with_max_total_response_bytes(mut self, max_total_response_bytes: Option<u64>) -> Self {
+    self.max_total_response_bytes = max_total_response_bytes;
+    self
+  }
+
+  /// Set the additional index file extensions which are tried after the default index file ending.
+  pub fn with_index_extensions(mut self, index_extensions: Vec<String>) -> Self {
+    self.index_extensions = index_extensions;
+    self
+  }
+
+  /// Set whether the response should include the public key of the sender of encrypted data.
+  pub fn with_include_sender_public_key(mut self, include_sender_public_key: bool) -> Self {
+    self.include_sender_public_key = include_sender_public_key;
+    self
+  }
+
+  /// Set the maximum duration of the search before it is aborted.
+  pub fn with_search_timeout(mut self, search_timeout: Option<Duration>) -> Self {
+    self.search_timeout = search_timeout;
+    self
+  }
+
+  /// Set whether each url in the response should include its byte range within the concatenated
+  /// file.
+  pub fn with_include_content_range(mut self, include_content_range: bool) -> Self {
+    self.include_content_range = include_content_range;
+    self
+  }
+
+  /// Set how byte ranges which extend past the end of the file are handled.
+  pub fn with_oversized_range(mut self, oversized_range: OversizedRange) -> Self {
+    self.oversized_range = oversized_range;
+    self
+  }
+
+  /// Set the maximum number of bytes a single url in the response may cover.
+  pub fn with_max_bytes_per_url(mut self, max_bytes_per_url: Option<u64>) -> Self {
+    self.max_bytes_per_url = max_bytes_per_url;
+    self
+  }
+
+  /// Set the minimum size of a fragment produced when splitting ranges.
+  pub fn with_min_block_size(mut self, min_block_size: Option<u64>) -> Self {
+    self.min_block_size = min_block_size;
+    self
+  }
+
+  /// Set the maximum number of urls in the response.
+  pub fn with_max_urls_per_ticket(mut self, max_urls_per_ticket: Option<usize>) -> Self {
+    self.max_urls_per_ticket = max_urls_per_ticket;
+    self
+  }
+
+  /// Set whether the number of index bins and chunks scanned by the search is logged.
+  pub fn with_log_index_scan(mut self, log_index_scan: bool) -> Self {
+    self.log_index_scan = log_index_scan;
+    self
+  }
+
+  /// Set the maximum number of bytes between two byte ranges which are merged into one range.
+  pub fn with_merge_gap(mut self, merge_gap: Option<u64>) -> Self {
+    self.merge_gap = merge_gap;
+    self
+  }
+
+  /// Set how the `*` reference name is handled for variants.
+  pub fn with_unmapped_variants(mut self, unmapped_variants: UnmappedVariants) -> Self {
+    self.unmapped_variants = unmapped_variants;
+    self
+  }
+
+  /// Set whether the durations of the stages of the search are recorded in the response.
+  pub fn with_server_timing(mut self, server_timing: bool) -> Self {
+    self.server_timing = server_timing;
+    self
+  }
+
+  /// Set whether header and body ranges are kept separate when merging ranges.
+  pub fn with_preserve_classes(mut self, preserve_classes: bool) -> Self {
+    self.preserve_classes = preserve_classes;
+    self
+  }
+
+  /// Set whether header queries are served by reading the header when the index is missing.
+  pub fn with_header_without_index(mut self, header_without_index: bool) -> Self {
+    self.header_without_index = header_without_index;
+    self
+  }
+
+  /// Set whether a `chr` prefix is added to or stripped from the reference name to match the
+  /// header.
+  pub fn with_normalize_chr_prefix(mut self, normalize_chr_prefix: bool) -> Self {
+    self.normalize_chr_prefix = normalize_chr_prefix;
+    self
+  }
+
+  /// Set the cache of parsed indexes which is shared between searches.
+  pub fn with_index_cache(mut self, index_cache: Option<IndexCache>) -> Self {
+    self.index_cache = index_cache;
+    self
+  }
+
+  /// Get whether the reference name should be validated against the file header.
+  pub fn validate_reference_names(&self) -> bool {
+    self.validate_reference_names
+  }
+
+  /// Get whether VCF files should be checked to be BGZF compressed before searching.
+  pub fn validate_bgzf(&self) -> bool {
+    self.validate_bgzf
+  }
+
+  /// Get the maximum file size for which the whole file is returned if the index is missing.
+  pub fn whole_file_fallback_max_size(&self) -> Option<u64> {
+    self.whole_file_fallback_max_size
+  }
+
+  /// Get whether the response should include the total size of the byte ranges.
+  pub fn include_total_size(&self) -> bool {
+    self.include_total_size
+  }
+
+  /// Get the maximum total size of the byte ranges that a response may contain.
+  pub fn max_total_response_bytes(&self) -> Option<u64> {
+    self.max_total_response_bytes
+  }
+
+  /// Get the additional index file extensions which are tried after the default index file ending.
+  pub fn index_extensions(&self) -> &[String] {
+    &self.index_extensions
+  }
+
+  /// Get whether the response should include the public key of the sender of encrypted data.
+  pub fn include_sender_public_key(&self) -> bool {
+    self.include_sender_public_key
+  }
+
+  /// Get the maximum duration of the search before it is aborted.
+  pub fn search_timeout(&self) -> Option<Duration> {
+    self.search_timeout
+  }
+
+  /// Get whether each url in the response should include its byte range within the concatenated
+  /// file.
+  pub fn include_content_range(&self) -> bool {
+    self.include_content_range
+  }
+
+  /// Get how byte ranges which extend past the end of the file are handled.
+  pub fn oversized_range(&self) -> OversizedRange {
+    self.oversized_range
+  }
+
+  /// Get the maximum number of bytes a single url in the response may cover.
+  pub fn max_bytes_per_url(&self) -> Option<u64> {
+    self.max_bytes_per_url
+  }
+
+  /// Get the minimum size of a fragment produced when splitting ranges.
+  pub fn min_block_size(&self) -> Option<u64> {
+    self.min_block_size
+  }
+
+  /// Get the maximum number of urls in the response.
+  pub fn max_urls_per_ticket(&self) -> Option<usize> {
+    self.max_urls_per_ticket
+  }
+
+  /// Get whether the number of index bins and chunks scanned by the search is logged.
+  pub fn log_index_scan(&self) -> bool {
+    self.log_index_scan
+  }
+
+  /// Get the maximum number of bytes between two byte ranges which are merged into one range.
+  pub fn merge_gap(&self) -> Option<u64> {
+    self.merge_gap
+  }
+
+  /// Get how the `*` reference name is handled for variants.
+  pub fn unmapped_variants(&self) -> UnmappedVariants {
+    self.unmapped_variants
+  }
+
+  /// Get whether the durations of the stages of the search are recorded in the response.
+  pub fn server_timing(&self) -> bool {
+    self.server_timing
+  }
+
+  /// Get whether header and body ranges are kept separate when merging ranges.
+  pub fn preserve_classes(&self) -> bool {
+    self.preserve_classes
+  }
+
+  /// Get whether header queries are served by reading the header when the index is missing.
+  pub fn header_without_index(&self) -> bool {
+    self.header_without_index
+  }
+
+  /// Get whether a `chr` prefix is added to or stripped from the reference name to match the
+  /// header.
+  pub fn normalize_chr_prefix(&self) -> bool {
+    self.normalize_chr_prefix
+  }
+
+  /// Get the cache of parsed indexes which is shared between searches.
+  pub fn index_cache(&self) -> Option<&IndexCache> {
+    self.index_cache.as_ref()
+  }
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -433,6 +1064,12 @@ pub enum HtsGetError {
   #[error("invalid range: {0}")]
   InvalidRange(String),
 
+  #[error("payload too large: {0}")]
+  PayloadTooLarge(String),
+
+  #[error("service unavailable: {0}")]
+  Unavailable(String),
+
   #[error("io error: {0}")]
   IoError(String),
 
@@ -460,6 +1097,14 @@ impl HtsGetError {
     Self::InvalidRange(message.into())
   }
 
+  pub fn payload_too_large<S: Into<String>>(message: S) -> Self {
+    Self::PayloadTooLarge(message.into())
+  }
+
+  pub fn unavailable<S: Into<String>>(message: S) -> Self {
+    Self::Unavailable(message.into())
+  }
+
   pub fn io_error<S: Into<String>>(message: S) -> Self {
     Self::IoError(message.into())
   }
@@ -486,7 +1131,7 @@ impl From<io::Error> for HtsGetError {
 }
 
 /// The headers that need to be supplied when requesting data from a url.
-#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Headers(HashMap<String, String>);
 
 impl Headers {
@@ -555,13 +1200,17 @@ impl TryFrom<&HeaderMap> for Headers {
 }
 
 /// A url from which raw data can be retrieved.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Url {
   pub url: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub headers: Option<Headers>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub class: Option<Class>,
+  /// The byte range of this url within the concatenated file, formatted like a `Content-Range`
+  /// header, e.g. `bytes 0-99/1000`.
+  #[serde(rename = "contentRange", skip_serializing_if = "Option::is_none")]
+  pub content_range: Option<String>,
 }
 
 impl Url {
@@ -571,6 +1220,7 @@ impl Url {
       url: url.into(),
       headers: None,
       class: None,
+      content_range: None,
     }
   }
 
@@ -602,6 +1252,12 @@ impl Url {
   pub fn with_class(self, class: Class) -> Self {
     self.set_class(Some(class))
   }
+
+  /// Set the byte range of the Url within the concatenated file.
+  pub fn with_content_range(mut self, content_range: Option<String>) -> Self {
+    self.content_range = content_range;
+    self
+  }
 }
 
 /// Wrapped json response for htsget.
@@ -623,15 +1279,223 @@ impl From<Response> for JsonResponse {
 }
 
 /// The response for a HtsGet query.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Response {
   pub format: Format,
   pub urls: Vec<Url>,
+  /// The total number of bytes across all byte range urls, excluding inline data urls.
+  #[serde(rename = "totalSize", skip_serializing_if = "Option::is_none")]
+  pub total_size: Option<u64>,
+  /// The base64 encoded public key of the sender which encrypted the data.
+  #[serde(rename = "senderPublicKey", skip_serializing_if = "Option::is_none")]
+  pub sender_public_key: Option<String>,
+  /// The durations of the stages of the search, if they were recorded.
+  #[serde(skip)]
+  pub server_timing: Option<ServerTiming>,
+  /// An entity tag derived from the modification times of the file and its index, if enabled.
+  #[serde(skip)]
+  pub etag: Option<String>,
+  /// Provenance metadata of the dataset, such as the reference build or pipeline version.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub provenance: Option<BTreeMap<String, String>>,
 }
 
 impl Response {
   pub fn new(format: Format, urls: Vec<Url>) -> Self {
-    Self { format, urls }
+    Self {
+      format,
+      urls,
+      total_size: None,
+      sender_public_key: None,
+      server_timing: None,
+      etag: None,
+      provenance: None,
+    }
+  }
+
+  /// Set the total number of bytes across all byte range urls.
+  pub fn with_total_size(mut self, total_size: Option<u64>) -> Self {
+    self.total_size = total_size;
+    self
+  }
+
+  /// Set the base64 encoded public key of the sender which encrypted the data.
+  pub fn with_sender_public_key(mut self, sender_public_key: Option<String>) -> Self {
+    self.sender_public_key = sender_public_key;
+    self
+  }
+
+  /// Set the durations of the stages of the search.
+  pub fn with_server_timing(mut self, server_timing: Option<ServerTiming>) -> Self {
+    self.server_timing = server_timing;
+    self
+  }
+
+  /// Set the entity tag derived from the modification times of the file and its index.
+  pub fn with_etag(mut self, etag: Option<String>) -> Self {
+    self.etag = etag;
+    self
+  }
+
+  /// Set the provenance metadata of the dataset.
+  pub fn with_provenance(mut self, provenance: Option<BTreeMap<String, String>>) -> Self {
+    self.provenance = provenance;
+    self
+  }
+
+  /// Get the value of the weak `ETag` header for the entity tag, if it is present.
+  pub fn etag_header_value(&self) -> Option<String> {
+    self.etag.as_ref().map(|etag| format!("W/\"{etag}\""))
+  }
+
+  /// Make the urls relative by removing their scheme and authority, leaving the path and query.
+  /// Clients resolve these against the origin of the request. Inline data urls are not changed.
+  pub fn with_relative_urls(mut self) -> Self {
+    for url in &mut self.urls {
+      let path_and_query = url
+        .url
+        .parse::<http::Uri>()
+        .ok()
+        .filter(|uri| uri.authority().is_some())
+        .and_then(|uri| uri.path_and_query().cloned());
+
+      if let Some(path_and_query) = path_and_query {
+        url.url = path_and_query.to_string();
+      }
+    }
+    self
+  }
+}
+
+/// The durations of the stages of resolving a query, which are reported using the `Server-Timing`
+/// header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ServerTiming {
+  /// The time taken to resolve the id and format of the query.
+  pub resolve: Option<Duration>,
+  /// The time taken to get the size of the file.
+  pub head: Option<Duration>,
+  /// The time taken to read the index.
+  pub index: Option<Duration>,
+}
+
+impl ServerTiming {
+  /// Set the time taken to resolve the id and format of the query.
+  pub fn with_resolve(mut self, resolve: Duration) -> Self {
+    self.resolve = Some(resolve);
+    self
+  }
+
+  /// Set the time taken to get the size of the file.
+  pub fn with_head(mut self, head: Duration) -> Self {
+    self.head = Some(head);
+    self
+  }
+
+  /// Set the time taken to read the index.
+  pub fn with_index(mut self, index: Duration) -> Self {
+    self.index = Some(index);
+    self
+  }
+
+  /// Merge the durations of another search into these, adding the durations of each stage.
+  pub fn merge(self, other: Self) -> Self {
+    let add = |a: Option<Duration>, b: Option<Duration>| match (a, b) {
+      (Some(a), Some(b)) => Some(a + b),
+      (a, b) => a.or(b),
+    };
+
+    Self {
+      resolve: add(self.resolve, other.resolve),
+      head: add(self.head, other.head),
+      index: add(self.index, other.index),
+    }
+  }
+
+  /// Format the durations as a `Server-Timing` header value, in milliseconds.
+  pub fn header_value(&self) -> String {
+    [
+      ("resolve", self.resolve),
+      ("head", self.head),
+      ("index", self.index),
+    ]
+    .into_iter()
+    .filter_map(|(name, duration)| {
+      duration.map(|duration| format!("{name};dur={:.3}", duration.as_secs_f64() * 1000.0))
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+  }
+}
+
+/// Determines how ticket responses are serialized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ResponseStyle {
+  /// Pretty printed json.
+  #[default]
+  Pretty,
+  /// Json without any whitespace.
+  Compact,
+  /// Pretty printed json, with fields in the order recommended by the htsget spec.
+  Strict,
+}
+
+/// A json response which serializes fields in the order recommended by the htsget spec. This is
+/// `htsget`, `format` and `urls`, where each url contains `url`, `headers` and `class`. Fields that
+/// are not part of the spec are serialized after these.
+#[derive(Debug)]
+pub struct StrictJsonResponse<'a>(pub &'a JsonResponse);
+
+impl Serialize for StrictJsonResponse<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("JsonResponse", 1)?;
+    state.serialize_field("htsget", &StrictResponse(&self.0.htsget))?;
+    state.end()
+  }
+}
+
+struct StrictResponse<'a>(&'a Response);
+
+impl Serialize for StrictResponse<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+    let response = self.0;
+    let mut state = serializer.serialize_struct("Response", 4)?;
+
+    state.serialize_field("format", &response.format)?;
+    state.serialize_field(
+      "urls",
+      &response.urls.iter().map(StrictUrl).collect::<Vec<_>>(),
+    )?;
+    if let Some(total_size) = response.total_size {
+      state.serialize_field("totalSize", &total_size)?;
+    }
+    if let Some(sender_public_key) = &response.sender_public_key {
+      state.serialize_field("senderPublicKey", sender_public_key)?;
+    }
+
+    state.end()
+  }
+}
+
+struct StrictUrl<'a>(&'a Url);
+
+impl Serialize for StrictUrl<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+    let url = self.0;
+    let mut state = serializer.serialize_struct("Url", 4)?;
+
+    state.serialize_field("url", &url.url)?;
+    if let Some(headers) = &url.headers {
+      state.serialize_field("headers", headers)?;
+    }
+    if let Some(class) = &url.class {
+      state.serialize_field("class", class)?;
+    }
+    if let Some(content_range) = &url.content_range {
+      state.serialize_field("contentRange", content_range)?;
+    }
+
+    state.end()
   }
 }
 
@@ -639,15 +1503,66 @@ impl Response {
 mod tests {
   use std::collections::{HashMap, HashSet};
   use std::str::FromStr;
+  use std::time::{Duration, UNIX_EPOCH};
 
   use http::{HeaderMap, HeaderName, HeaderValue};
   use serde_json::{json, to_value};
 
   use crate::types::{
-    Class, Fields, Format, Headers, HtsGetError, Interval, NoTags, Query, Response, TaggedTypeAll,
-    Tags, Url,
+    fmt_http_date, Class, Fields, Format, Headers, HtsGetError, Interval, JsonResponse,
+    Maintenance, NoTags, Query, Response, RetryAfterFormat, ServerTiming, StrictJsonResponse,
+    TaggedTypeAll, Tags, Url,
   };
 
+  #[test]
+  fn server_timing_header_value() {
+    let server_timing = ServerTiming::default()
+      .with_resolve(Duration::from_micros(1500))
+      .with_index(Duration::from_millis(2));
+
+    assert_eq!(
+      server_timing.header_value(),
+      "resolve;dur=1.500, index;dur=2.000"
+    );
+    assert_eq!(
+      server_timing.merge(server_timing).header_value(),
+      "resolve;dur=3.000, index;dur=4.000"
+    );
+  }
+
+  #[tokio::test]
+  async fn maintenance_is_active() {
+    let tmp = tempfile::TempDir::new().unwrap();
+    let file = tmp.path().join("maintenance");
+    let maintenance = Maintenance::new(file.clone());
+
+    assert!(maintenance.is_enabled());
+    assert!(!maintenance.is_active().await);
+
+    std::fs::write(&file, "").unwrap();
+    assert!(maintenance.is_active().await);
+
+    assert!(!Maintenance::default().is_active().await);
+  }
+
+  #[test]
+  fn maintenance_retry_after_header_value() {
+    let now = UNIX_EPOCH + Duration::from_secs(784111657);
+    let maintenance = Maintenance::default().with_retry_after(120);
+
+    assert_eq!(maintenance.retry_after_header_value_at(now), "120");
+    assert_eq!(
+      maintenance
+        .with_retry_after_format(RetryAfterFormat::HttpDate)
+        .retry_after_header_value_at(now),
+      "Sun, 06 Nov 1994 08:49:37 GMT"
+    );
+    assert_eq!(
+      fmt_http_date(UNIX_EPOCH + Duration::from_secs(951782400)),
+      "Tue, 29 Feb 2000 00:00:00 GMT"
+    );
+  }
+
   #[test]
   fn interval_contains() {
     let interval = Interval {
@@ -735,6 +1650,12 @@ mod tests {
     assert!(matches!(result, HtsGetError::InvalidRange(message) if message == "error"));
   }
 
+  #[test]
+  fn htsget_error_payload_too_large() {
+    let result = HtsGetError::payload_too_large("error");
+    assert!(matches!(result, HtsGetError::PayloadTooLarge(message) if message == "error"));
+  }
+
   #[test]
   fn htsget_error_io_error() {
     let result = HtsGetError::io_error("error");
@@ -934,6 +1855,56 @@ mod tests {
     );
   }
 
+  #[test]
+  fn response_with_relative_urls() {
+    let response = Response::new(
+      Format::Bam,
+      vec![
+        Url::new("https://example.com/data/key.bam?query=value"),
+        Url::new("data:;base64,AAAA"),
+      ],
+    )
+    .with_relative_urls();
+
+    assert_eq!(response.urls[0].url, "/data/key.bam?query=value");
+    assert_eq!(response.urls[1].url, "data:;base64,AAAA");
+  }
+
+  #[test]
+  fn serialize_strict_json_response() {
+    let response = JsonResponse::new(
+      Response::new(
+        Format::Bam,
+        vec![
+          Url::new("http://localhost:8081/data/htsnexus_test_NA12878.bam")
+            .with_headers(Headers::new(HashMap::new()).with_header("Range", "bytes=0-1023"))
+            .with_class(Class::Header)
+            .with_content_range(Some("bytes 0-1023/2596770".to_string())),
+        ],
+      )
+      .with_total_size(Some(1024)),
+    );
+
+    let result = serde_json::to_string(&StrictJsonResponse(&response)).unwrap();
+    let positions = [
+      "\"htsget\"",
+      "\"format\"",
+      "\"urls\"",
+      "\"url\"",
+      "\"headers\"",
+      "\"class\"",
+      "\"contentRange\"",
+      "\"totalSize\"",
+    ]
+    .map(|field| result.find(field).unwrap());
+
+    assert!(positions.windows(2).all(|window| window[0] < window[1]));
+    assert_eq!(
+      serde_json::from_str::<JsonResponse>(&result).unwrap(),
+      response
+    );
+  }
+
   #[test]
   fn url_with_headers() {
     let result = Url::new("data:application/vnd.ga4gh.bam;base64,QkFNAQ==")
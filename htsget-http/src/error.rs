@@ -18,6 +18,8 @@ pub enum HtsGetError {
   NotFound(String),
   #[error("PayloadTooLarge")]
   PayloadTooLarge(String),
+  #[error("ServiceUnavailable")]
+  ServiceUnavailable(String),
   #[error("UnsupportedFormat")]
   UnsupportedFormat(String),
   #[error("InvalidInput")]
@@ -43,6 +45,21 @@ pub struct WrappedHtsGetError {
 }
 
 impl HtsGetError {
+  /// Get the message describing the error.
+  pub fn message(&self) -> &str {
+    match self {
+      HtsGetError::InvalidAuthentication(err)
+      | HtsGetError::PermissionDenied(err)
+      | HtsGetError::NotFound(err)
+      | HtsGetError::PayloadTooLarge(err)
+      | HtsGetError::ServiceUnavailable(err)
+      | HtsGetError::UnsupportedFormat(err)
+      | HtsGetError::InvalidInput(err)
+      | HtsGetError::InvalidRange(err)
+      | HtsGetError::InternalError(err) => err,
+    }
+  }
+
   /// Allows converting the error to JSON and the correspondent
   /// status code
   pub fn to_json_representation(&self) -> (WrappedHtsGetError, StatusCode) {
@@ -51,6 +68,7 @@ impl HtsGetError {
       HtsGetError::PermissionDenied(err) => (err, StatusCode::FORBIDDEN),
       HtsGetError::NotFound(err) => (err, StatusCode::NOT_FOUND),
       HtsGetError::PayloadTooLarge(err) => (err, StatusCode::PAYLOAD_TOO_LARGE),
+      HtsGetError::ServiceUnavailable(err) => (err, StatusCode::SERVICE_UNAVAILABLE),
       HtsGetError::UnsupportedFormat(err)
       | HtsGetError::InvalidInput(err)
       | HtsGetError::InvalidRange(err) => (err, StatusCode::BAD_REQUEST),
@@ -76,8 +94,22 @@ impl From<HtsGetSearchError> for HtsGetError {
       HtsGetSearchError::UnsupportedFormat(err) => Self::UnsupportedFormat(err),
       HtsGetSearchError::InvalidInput(err) => Self::InvalidInput(err),
       HtsGetSearchError::InvalidRange(err) => Self::InvalidRange(err),
+      HtsGetSearchError::PayloadTooLarge(err) => Self::PayloadTooLarge(err),
+      HtsGetSearchError::Unavailable(err) => Self::ServiceUnavailable(err),
       HtsGetSearchError::IoError(err) | HtsGetSearchError::ParseError(err) => Self::NotFound(err),
       HtsGetSearchError::InternalError(err) => Self::InternalError(err),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unavailable_to_json_representation() {
+    let (_, status_code) =
+      HtsGetError::from(HtsGetSearchError::unavailable("error")).to_json_representation();
+    assert_eq!(status_code, StatusCode::SERVICE_UNAVAILABLE);
+  }
+}
@@ -1,73 +1,268 @@
-use futures::stream::FuturesOrdered;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use futures::stream;
 use futures::StreamExt;
-use tokio::select;
-use tracing::debug;
+use http::header::{AGE, AUTHORIZATION, LINK, WWW_AUTHENTICATE};
+use http::{HeaderMap, HeaderName, StatusCode};
 use tracing::instrument;
+use tracing::{debug, info};
 
-use htsget_config::types::{JsonResponse, Request, Response};
+use htsget_config::types::{
+  AuditLog, Format, FormatAliases, JsonResponse, Maintenance, Request, Response,
+};
 use htsget_search::HtsGet;
 
-use crate::HtsGetError::InvalidInput;
+use crate::HtsGetError::{InvalidAuthentication, InvalidInput, ServiceUnavailable};
 use crate::{
   convert_to_query, match_format, merge_responses, Endpoint, HtsGetError, PostRequest, Result,
 };
 
+/// The header which reports the server-side search duration in milliseconds.
+pub const ELAPSED_MS_HEADER: &str = "x-htsget-elapsed-ms";
+
+/// The header which marks responses to deprecated routes.
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+/// The header which reports the durations of the stages of resolving a query.
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+/// The header which reports the format of the file in responses to HEAD requests.
+pub const FORMAT_HEADER: &str = "htsget-format";
+
+/// The tracing target of audit log entries for denied requests, which can be used to filter them
+/// separately from other logs.
+pub const AUDIT_LOG_TARGET: &str = "htsget_audit";
+
+/// The static `robots.txt` response, which disallows crawling all paths.
+pub const ROBOTS_TXT: &str = "User-agent: *\nDisallow: /\n";
+
+/// The query parameters which take a single value.
+const SCALAR_QUERY_KEYS: [&str; 5] = ["format", "class", "referenceName", "start", "end"];
+
+/// Get the custom headers that ticket responses may contain with the enabled options. These should
+/// be exposed to browsers using CORS.
+pub fn exposed_ticket_headers(
+  service_info_link: bool,
+  elapsed_time_header: bool,
+  require_authorization: bool,
+  age_header: bool,
+  deprecation_header: bool,
+) -> Vec<HeaderName> {
+  [
+    service_info_link.then_some(LINK),
+    elapsed_time_header.then(|| HeaderName::from_static(ELAPSED_MS_HEADER)),
+    require_authorization.then_some(WWW_AUTHENTICATE),
+    age_header.then_some(AGE),
+    deprecation_header.then(|| HeaderName::from_static(DEPRECATION_HEADER)),
+  ]
+  .into_iter()
+  .flatten()
+  .collect()
+}
+
 /// Gets a JSON response for a GET request. The GET request parameters must
 /// be in a HashMap. The "id" field is the only mandatory one. The rest can be
-/// consulted [here](https://samtools.github.io/hts-specs/htsget.html)
+/// consulted [here](https://samtools.github.io/hts-specs/htsget.html). The time since the
+/// response was computed is returned alongside it if it was served from a response cache.
 #[instrument(level = "debug", skip_all, ret)]
 pub async fn get(
   searcher: impl HtsGet + Send + Sync + 'static,
   request: Request,
   endpoint: Endpoint,
-) -> Result<JsonResponse> {
-  let format = match_format(&endpoint, request.query().get("format"))?;
+  format_aliases: &FormatAliases,
+) -> Result<(JsonResponse, Option<Duration>)> {
+  let format = match_format(&endpoint, request.query().get("format"), format_aliases)?;
   let query = convert_to_query(request, format)?;
 
   debug!(endpoint = ?endpoint, query = ?query, "getting GET response");
 
   searcher
-    .search(query)
+    .search_with_age(query)
     .await
     .map_err(Into::into)
-    .map(JsonResponse::from)
+    .map(|(response, age)| (JsonResponse::from(response), age))
+}
+
+/// Checks that the file of a HEAD request exists, without searching it. The request parameters are
+/// the same as for a GET request. Returns the format of the file.
+#[instrument(level = "debug", skip_all, ret)]
+pub async fn head(
+  searcher: impl HtsGet + Send + Sync + 'static,
+  request: Request,
+  endpoint: Endpoint,
+  format_aliases: &FormatAliases,
+) -> Result<Format> {
+  let format = match_format(&endpoint, request.query().get("format"), format_aliases)?;
+  let query = convert_to_query(request, format)?;
+
+  debug!(endpoint = ?endpoint, query = ?query, "getting HEAD response");
+
+  searcher.head(query).await.map_err(Into::into)
+}
+
+/// Checks that the body of a GET request is empty, if GET requests with a body should be rejected.
+pub fn check_get_body(reject_get_body: bool, body: &[u8]) -> Result<()> {
+  if reject_get_body && !body.is_empty() {
+    return Err(InvalidInput(
+      "body should be empty for a GET request".to_string(),
+    ));
+  }
+
+  Ok(())
+}
+
+/// Checks that no scalar query parameter appears more than once in the query string, if requests
+/// with duplicate query parameters should be rejected. Otherwise, the last value is used.
+pub fn check_duplicate_query_keys(reject_duplicate_query_keys: bool, query: &str) -> Result<()> {
+  if !reject_duplicate_query_keys {
+    return Ok(());
+  }
+
+  let mut seen = HashSet::new();
+  for key in query
+    .split('&')
+    .map(|pair| pair.split_once('=').map_or(pair, |(key, _)| key))
+    .filter(|key| SCALAR_QUERY_KEYS.contains(key))
+  {
+    if !seen.insert(key) {
+      return Err(InvalidInput(format!(
+        "query parameter `{key}` should not be repeated"
+      )));
+    }
+  }
+
+  Ok(())
+}
+
+/// Checks that the request contains an `Authorization` header, if requests without one should be
+/// rejected.
+pub fn check_authorization(require_authorization: bool, headers: &HeaderMap) -> Result<()> {
+  if require_authorization && !headers.contains_key(AUTHORIZATION) {
+    return Err(InvalidAuthentication(
+      "missing `Authorization` header".to_string(),
+    ));
+  }
+
+  Ok(())
+}
+
+/// An audit log entry for a request which was denied with 401 Unauthorized or 403 Forbidden.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeniedRequest {
+  pub subject: Option<String>,
+  pub id: String,
+  pub reason: String,
+  pub status: StatusCode,
+}
+
+impl DeniedRequest {
+  /// Get the audit log entry for the request if the error denied it. The subject is read from the
+  /// subject header of the audit log.
+  pub fn from_error(audit_log: &AuditLog, request: &Request, error: &HtsGetError) -> Option<Self> {
+    let (_, status) = error.to_json_representation();
+    if status != StatusCode::UNAUTHORIZED && status != StatusCode::FORBIDDEN {
+      return None;
+    }
+
+    let subject = audit_log
+      .subject_header()
+      .and_then(|header| request.headers().get(header))
+      .and_then(|subject| subject.to_str().ok())
+      .map(str::to_string);
+
+    Some(Self {
+      subject,
+      id: request.path().to_string(),
+      reason: error.message().to_string(),
+      status,
+    })
+  }
+}
+
+/// Logs an audit entry if audit logging is enabled and the error denied the request. Returns the
+/// error.
+pub fn audit_denied(audit_log: &AuditLog, request: &Request, error: HtsGetError) -> HtsGetError {
+  if let Some(denied) = audit_log
+    .enabled()
+    .then(|| DeniedRequest::from_error(audit_log, request, &error))
+    .flatten()
+  {
+    info!(
+      target: AUDIT_LOG_TARGET,
+      subject = denied.subject.as_deref(),
+      id = denied.id.as_str(),
+      reason = denied.reason.as_str(),
+      status = denied.status.as_u16(),
+      "request denied"
+    );
+  }
+
+  error
+}
+
+/// Checks that maintenance mode is not active, if the path is a query. Other endpoints, such as
+/// service-info, are still served during maintenance.
+pub async fn check_maintenance(maintenance: &Maintenance, path: &str) -> Result<()> {
+  let is_query = ["/reads/", "/variants/"].into_iter().any(|prefix| {
+    path
+      .strip_prefix(prefix)
+      .is_some_and(|id| id != "service-info")
+  });
+
+  if is_query && maintenance.is_active().await {
+    return Err(ServiceUnavailable(
+      "the server is undergoing maintenance".to_string(),
+    ));
+  }
+
+  Ok(())
 }
 
 /// Gets a response in JSON for a POST request.
-/// The parameters can be consulted [here](https://samtools.github.io/hts-specs/htsget.html)
+/// The parameters can be consulted [here](https://samtools.github.io/hts-specs/htsget.html).
+/// The age of the oldest response served from a response cache is returned alongside it.
 #[instrument(level = "debug", skip_all, ret)]
 pub async fn post(
   searcher: impl HtsGet + Clone + Send + Sync + 'static,
   body: PostRequest,
   request: Request,
   endpoint: Endpoint,
-) -> Result<JsonResponse> {
+  format_aliases: &FormatAliases,
+  max_distinct_references: Option<usize>,
+  query_concurrency: Option<usize>,
+) -> Result<(JsonResponse, Option<Duration>)> {
   if !request.query().is_empty() {
     return Err(InvalidInput(
       "query parameters should be empty for a POST request".to_string(),
     ));
   }
 
-  let queries = body.get_queries(request, &endpoint)?;
+  body.validate_distinct_references(max_distinct_references)?;
+
+  let queries = body.get_queries(request, &endpoint, format_aliases)?;
 
   debug!(endpoint = ?endpoint, queries = ?queries, "getting POST response");
 
-  let mut futures = FuturesOrdered::new();
-  for query in queries {
-    let owned_searcher = searcher.clone();
-    futures.push_back(tokio::spawn(
-      async move { owned_searcher.search(query).await },
-    ));
-  }
+  // Responses are buffered in order, so that they are merged the same as a serial search.
+  let mut futures = stream::iter(queries)
+    .map(|query| {
+      let owned_searcher = searcher.clone();
+      tokio::spawn(async move { owned_searcher.search_with_age(query).await })
+    })
+    .buffered(query_concurrency.unwrap_or(usize::MAX).max(1));
   let mut responses: Vec<Response> = Vec::new();
-  loop {
-    select! {
-      Some(next) = futures.next() => responses.push(next.map_err(|err| HtsGetError::InternalError(err.to_string()))?.map_err(HtsGetError::from)?),
-      else => break
-    }
+  let mut age = None;
+  while let Some(next) = futures.next().await {
+    let (response, response_age) = next
+      .map_err(|err| HtsGetError::InternalError(err.to_string()))?
+      .map_err(HtsGetError::from)?;
+    responses.push(response);
+    age = age.max(response_age);
   }
 
-  Ok(JsonResponse::from(
-    merge_responses(responses).expect("expected at least one response"),
+  Ok((
+    JsonResponse::from(merge_responses(responses).expect("expected at least one response")),
+    age,
   ))
 }
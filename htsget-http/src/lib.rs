@@ -1,3 +1,4 @@
+use std::fmt::{self, Display, Formatter};
 use std::result;
 use std::str::FromStr;
 
@@ -7,18 +8,27 @@ pub use htsget_config::config::{
 };
 pub use htsget_config::storage::Storage;
 use htsget_config::types::Format::{Bam, Bcf, Cram, Vcf};
-use htsget_config::types::{Format, Query, Request, Response};
-pub use http_core::{get, post};
+use htsget_config::types::{Format, FormatAliases, Query, Request, Response};
+pub use http_core::{
+  audit_denied, check_authorization, check_duplicate_query_keys, check_get_body, check_maintenance,
+  exposed_ticket_headers, get, head, post, DeniedRequest, AUDIT_LOG_TARGET, DEPRECATION_HEADER,
+  ELAPSED_MS_HEADER, FORMAT_HEADER, ROBOTS_TXT, SERVER_TIMING_HEADER,
+};
 pub use post_request::{PostRequest, Region};
 use query_builder::QueryBuilder;
+pub use readiness::{get_readiness, BackendReadiness, Readiness};
+pub use service_info::get_root_json;
 pub use service_info::get_service_info_json;
 pub use service_info::get_service_info_with;
-pub use service_info::{Htsget, Organisation, ServiceInfo, Type};
+pub use service_info::{
+  Capabilities, Htsget, Organisation, RootResponse, ServiceInfo, ServiceInfoLinks, Type,
+};
 
 mod error;
 mod http_core;
 mod post_request;
 mod query_builder;
+mod readiness;
 mod service_info;
 
 /// A enum to distinguish between the two endpoint defined in the
@@ -29,6 +39,30 @@ pub enum Endpoint {
   Variants,
 }
 
+impl Endpoint {
+  /// Get the value of a `Link` header which points to the service-info of this endpoint.
+  pub fn service_info_link(&self) -> String {
+    format!("</{self}/service-info>; rel=\"service-desc\"")
+  }
+
+  /// Get the formats which are valid for this endpoint.
+  pub fn formats(&self) -> &'static [Format] {
+    match self {
+      Self::Reads => &[Bam, Cram],
+      Self::Variants => &[Vcf, Bcf],
+    }
+  }
+}
+
+impl Display for Endpoint {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Reads => write!(f, "reads"),
+      Self::Variants => write!(f, "variants"),
+    }
+  }
+}
+
 impl FromStr for Endpoint {
   type Err = ();
 
@@ -41,9 +75,19 @@ impl FromStr for Endpoint {
   }
 }
 
-/// Get the format from the string
-pub fn match_format(endpoint: &Endpoint, format: Option<impl Into<String>>) -> Result<Format> {
-  let format = format.map(Into::into).map(|format| format.to_lowercase());
+/// Get the format from the string, mapping any aliases to their canonical format first.
+pub fn match_format(
+  endpoint: &Endpoint,
+  format: Option<impl Into<String>>,
+  format_aliases: &FormatAliases,
+) -> Result<Format> {
+  let format = format.map(Into::into).map(|format| {
+    format_aliases
+      .get(&format)
+      .map(|alias| alias.to_string())
+      .unwrap_or(format)
+      .to_lowercase()
+  });
 
   match (endpoint, format) {
     (Endpoint::Reads, None) => Ok(Bam),
@@ -52,8 +96,14 @@ pub fn match_format(endpoint: &Endpoint, format: Option<impl Into<String>>) -> R
     (Endpoint::Reads, Some(s)) if s == "cram" => Ok(Cram),
     (Endpoint::Variants, Some(s)) if s == "vcf" => Ok(Vcf),
     (Endpoint::Variants, Some(s)) if s == "bcf" => Ok(Bcf),
-    (_, Some(format)) => Err(HtsGetError::UnsupportedFormat(format!(
-      "{format} isn't a supported format for this endpoint"
+    (endpoint, Some(format)) => Err(HtsGetError::UnsupportedFormat(format!(
+      "{format} isn't a supported format for this endpoint, {endpoint} supports: {}",
+      endpoint
+        .formats()
+        .iter()
+        .map(|format| format.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
     ))),
   }
 }
@@ -63,8 +113,9 @@ fn convert_to_query(request: Request, format: Format) -> Result<Query> {
 
   Ok(
     QueryBuilder::new(request, format)
+      .with_format_omitted(query.get("format").is_none())
       .with_class(query.get("class"))?
-      .with_reference_name(query.get("referenceName"))
+      .with_reference_name(query.get("referenceName"))?
       .with_range(query.get("start"), query.get("end"))?
       .with_fields(query.get("fields"))
       .with_tags(query.get("tags"), query.get("notags"))?
@@ -75,6 +126,23 @@ fn convert_to_query(request: Request, format: Format) -> Result<Query> {
 fn merge_responses(responses: Vec<Response>) -> Option<Response> {
   responses.into_iter().reduce(|mut acc, mut response| {
     acc.urls.append(&mut response.urls);
+    acc.total_size = acc
+      .total_size
+      .zip(response.total_size)
+      .map(|(acc_size, size)| acc_size + size);
+    acc.sender_public_key = acc.sender_public_key.or(response.sender_public_key);
+    acc.provenance = acc.provenance.or(response.provenance);
+    acc.server_timing = match (acc.server_timing, response.server_timing) {
+      (Some(acc_timing), Some(timing)) => Some(acc_timing.merge(timing)),
+      (acc_timing, timing) => acc_timing.or(timing),
+    };
+    acc.etag = acc.etag.zip(response.etag).map(|(acc_etag, etag)| {
+      if acc_etag == etag {
+        acc_etag
+      } else {
+        format!("{acc_etag}-{etag}")
+      }
+    });
     acc
   })
 }
@@ -86,8 +154,10 @@ mod tests {
 
   use http::uri::Authority;
 
+  use htsget_config::resolver::Resolver;
   use htsget_config::storage::local::Local as ConfigLocalStorage;
-  use htsget_config::types::{Headers, JsonResponse, Request, Scheme, Url};
+  use htsget_config::storage::Storage as ConfigStorage;
+  use htsget_config::types::{AuditLog, Headers, JsonResponse, Request, Scheme, Url};
   use htsget_search::from_storage::HtsGetFromStorage;
   use htsget_search::HtsGet;
   use htsget_search::LocalStorage;
@@ -98,7 +168,12 @@ mod tests {
   #[test]
   fn match_with_invalid_format() {
     assert!(matches!(
-      match_format(&Endpoint::Reads, Some("Invalid".to_string())).unwrap_err(),
+      match_format(
+        &Endpoint::Reads,
+        Some("Invalid".to_string()),
+        &Default::default()
+      )
+      .unwrap_err(),
       HtsGetError::UnsupportedFormat(_)
     ));
   }
@@ -106,7 +181,12 @@ mod tests {
   #[test]
   fn match_with_invalid_endpoint() {
     assert!(matches!(
-      match_format(&Endpoint::Variants, Some("bam".to_string())).unwrap_err(),
+      match_format(
+        &Endpoint::Variants,
+        Some("bam".to_string()),
+        &Default::default()
+      )
+      .unwrap_err(),
       HtsGetError::UnsupportedFormat(_)
     ));
   }
@@ -114,11 +194,95 @@ mod tests {
   #[test]
   fn match_with_valid_format() {
     assert!(matches!(
-      match_format(&Endpoint::Reads, Some("bam".to_string())).unwrap(),
+      match_format(
+        &Endpoint::Reads,
+        Some("bam".to_string()),
+        &Default::default()
+      )
+      .unwrap(),
       Bam,
     ));
   }
 
+  #[test]
+  fn match_with_format_alias() {
+    let format_aliases = FormatAliases::new(HashMap::from([("gvcf".to_string(), Vcf)]));
+
+    assert!(matches!(
+      match_format(
+        &Endpoint::Variants,
+        Some("GVCF".to_string()),
+        &format_aliases
+      )
+      .unwrap(),
+      Vcf,
+    ));
+  }
+
+  #[test]
+  fn match_with_unknown_format_alias() {
+    let format_aliases = FormatAliases::new(HashMap::from([("gvcf".to_string(), Vcf)]));
+
+    assert!(matches!(
+      match_format(
+        &Endpoint::Variants,
+        Some("sam".to_string()),
+        &format_aliases
+      )
+      .unwrap_err(),
+      HtsGetError::UnsupportedFormat(_)
+    ));
+    assert!(matches!(
+      match_format(&Endpoint::Reads, Some("gvcf".to_string()), &format_aliases).unwrap_err(),
+      HtsGetError::UnsupportedFormat(_)
+    ));
+  }
+
+  #[test]
+  fn match_with_invalid_format_lists_valid_formats() {
+    let err = match_format(&Endpoint::Reads, Some("VCF"), &Default::default()).unwrap_err();
+
+    assert_eq!(
+      err,
+      HtsGetError::UnsupportedFormat(
+        "vcf isn't a supported format for this endpoint, reads supports: BAM, CRAM".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn denied_request_audit_entry() {
+    let mut headers = http::HeaderMap::new();
+    headers.insert("x-forwarded-user", "user".parse().unwrap());
+    let request = Request::new(
+      "bam/htsnexus_test_NA12878".to_string(),
+      HashMap::new(),
+      headers,
+    );
+    let audit_log = AuditLog::new(Some("x-forwarded-user".to_string()));
+
+    let error = check_authorization(true, request.headers()).unwrap_err();
+    assert_eq!(
+      DeniedRequest::from_error(&audit_log, &request, &error),
+      Some(DeniedRequest {
+        subject: Some("user".to_string()),
+        id: "bam/htsnexus_test_NA12878".to_string(),
+        reason: "missing `Authorization` header".to_string(),
+        status: http::StatusCode::UNAUTHORIZED,
+      })
+    );
+    assert!(matches!(
+      audit_denied(&audit_log, &request, error),
+      HtsGetError::InvalidAuthentication(_)
+    ));
+
+    let not_found = HtsGetError::NotFound("not found".to_string());
+    assert_eq!(
+      DeniedRequest::from_error(&audit_log, &request, &not_found),
+      None
+    );
+  }
+
   #[tokio::test]
   async fn get_request() {
     let request = HashMap::new();
@@ -133,11 +297,57 @@ mod tests {
     );
 
     assert_eq!(
-      get(get_searcher(), request, Endpoint::Reads).await,
-      Ok(expected_bam_json_response(expected_response_headers))
+      get(
+        get_searcher(),
+        request,
+        Endpoint::Reads,
+        &Default::default()
+      )
+      .await,
+      Ok((expected_bam_json_response(expected_response_headers), None))
     );
   }
 
+  #[tokio::test]
+  async fn head_request() {
+    let request = Request::new(
+      "vcf/sample1-bcbio-cancer".to_string(),
+      HashMap::new(),
+      Default::default(),
+    );
+
+    assert_eq!(
+      head(
+        get_searcher(),
+        request,
+        Endpoint::Variants,
+        &Default::default()
+      )
+      .await,
+      Ok(Format::Vcf)
+    );
+  }
+
+  #[tokio::test]
+  async fn head_request_not_found() {
+    let request = Request::new(
+      "vcf/non-existent-id".to_string(),
+      HashMap::new(),
+      Default::default(),
+    );
+
+    assert!(matches!(
+      head(
+        get_searcher(),
+        request,
+        Endpoint::Variants,
+        &Default::default()
+      )
+      .await,
+      Err(HtsGetError::NotFound(_))
+    ));
+  }
+
   #[tokio::test]
   async fn get_reads_request_with_variants_format() {
     let mut request = HashMap::new();
@@ -150,7 +360,13 @@ mod tests {
     );
 
     assert!(matches!(
-      get(get_searcher(), request, Endpoint::Reads).await,
+      get(
+        get_searcher(),
+        request,
+        Endpoint::Reads,
+        &Default::default()
+      )
+      .await,
       Err(HtsGetError::UnsupportedFormat(_))
     ));
   }
@@ -172,8 +388,14 @@ mod tests {
     );
 
     assert_eq!(
-      get(get_searcher(), request, Endpoint::Variants).await,
-      Ok(expected_vcf_json_response(expected_response_headers))
+      get(
+        get_searcher(),
+        request,
+        Endpoint::Variants,
+        &Default::default()
+      )
+      .await,
+      Ok((expected_vcf_json_response(expected_response_headers), None))
     );
   }
 
@@ -193,8 +415,17 @@ mod tests {
     expected_response_headers.insert("Range".to_string(), "bytes=0-2596798".to_string());
 
     assert_eq!(
-      post(get_searcher(), body, request, Endpoint::Reads).await,
-      Ok(expected_bam_json_response(expected_response_headers))
+      post(
+        get_searcher(),
+        body,
+        request,
+        Endpoint::Reads,
+        &Default::default(),
+        None,
+        None
+      )
+      .await,
+      Ok((expected_bam_json_response(expected_response_headers), None))
     );
   }
 
@@ -211,11 +442,108 @@ mod tests {
     };
 
     assert!(matches!(
-      post(get_searcher(), body, request, Endpoint::Variants).await,
+      post(
+        get_searcher(),
+        body,
+        request,
+        Endpoint::Variants,
+        &Default::default(),
+        None,
+        None
+      )
+      .await,
       Err(HtsGetError::UnsupportedFormat(_))
     ));
   }
 
+  #[tokio::test]
+  async fn post_request_with_resolver_default_format() {
+    let request = Request::new_with_id("cram/htsnexus_test_NA12878".to_string());
+    let body = PostRequest {
+      format: None,
+      class: None,
+      fields: None,
+      tags: None,
+      notags: None,
+      regions: None,
+    };
+
+    let resolvers = vec![Resolver::new(
+      ConfigStorage::Local(ConfigLocalStorage::new(
+        Scheme::Http,
+        Authority::from_static("127.0.0.1:8081"),
+        get_base_path().to_str().unwrap().to_string(),
+        "/data".to_string(),
+        false,
+      )),
+      ".*",
+      "$0",
+      Default::default(),
+    )
+    .unwrap()
+    .with_default_format(Some(Cram))];
+
+    let (response, _) = post(
+      resolvers,
+      body,
+      request,
+      Endpoint::Reads,
+      &Default::default(),
+      None,
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.htsget.format, Cram);
+  }
+
+  #[tokio::test]
+  async fn post_request_with_query_concurrency() {
+    let post_regions = |query_concurrency| async move {
+      let body = PostRequest {
+        format: None,
+        class: None,
+        fields: None,
+        tags: None,
+        notags: None,
+        regions: Some(vec![
+          Region {
+            reference_name: "11".to_string(),
+            start: Some(4999976),
+            end: Some(5002147),
+          },
+          Region {
+            reference_name: "20".to_string(),
+            start: None,
+            end: None,
+          },
+          Region {
+            reference_name: "11".to_string(),
+            start: Some(5015000),
+            end: Some(5050000),
+          },
+        ]),
+      };
+
+      post(
+        get_searcher(),
+        body,
+        Request::new_with_id("bam/htsnexus_test_NA12878".to_string()),
+        Endpoint::Reads,
+        &Default::default(),
+        None,
+        query_concurrency,
+      )
+      .await
+      .unwrap()
+    };
+
+    let serial = post_regions(Some(1)).await;
+    assert_eq!(post_regions(Some(2)).await, serial);
+    assert_eq!(post_regions(None).await, serial);
+  }
+
   #[tokio::test]
   async fn post_request_with_range() {
     let request = Request::new_with_id("vcf/sample1-bcbio-cancer".to_string());
@@ -236,8 +564,17 @@ mod tests {
     expected_response_headers.insert("Range".to_string(), "bytes=0-3493".to_string());
 
     assert_eq!(
-      post(get_searcher(), body, request, Endpoint::Variants).await,
-      Ok(expected_vcf_json_response(expected_response_headers))
+      post(
+        get_searcher(),
+        body,
+        request,
+        Endpoint::Variants,
+        &Default::default(),
+        None,
+        None
+      )
+      .await,
+      Ok((expected_vcf_json_response(expected_response_headers), None))
     );
   }
 
@@ -1,9 +1,11 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use htsget_config::types::{Format, Query, Request};
+use htsget_config::types::{Format, FormatAliases, Query, Request};
 
-use crate::{match_format, Endpoint, QueryBuilder, Result};
+use crate::{match_format, Endpoint, HtsGetError, QueryBuilder, Result};
 
 /// A struct to represent a POST request according to the
 /// [HtsGet specification](https://samtools.github.io/hts-specs/htsget.html). It implements
@@ -28,20 +30,40 @@ pub struct Region {
   pub end: Option<u32>,
 }
 
+impl Region {
+  /// Check that the start of the region is less than its end, naming the region index if it is not.
+  fn validate_range(&self, index: usize) -> Result<()> {
+    match (self.start, self.end) {
+      (Some(start), Some(end)) if start >= end => Err(HtsGetError::InvalidInput(format!(
+        "region {index} must have a start less than its end (`{start}` >= `{end}`)"
+      ))),
+      _ => Ok(()),
+    }
+  }
+}
+
 impl PostRequest {
   /// Converts the `PostRequest` into one or more equivalent [Queries](Query)
   #[instrument(level = "trace", skip_all, ret)]
-  pub(crate) fn get_queries(self, request: Request, endpoint: &Endpoint) -> Result<Vec<Query>> {
-    let format = match_format(endpoint, self.format.clone())?;
+  pub(crate) fn get_queries(
+    self,
+    request: Request,
+    endpoint: &Endpoint,
+    format_aliases: &FormatAliases,
+  ) -> Result<Vec<Query>> {
+    let format = match_format(endpoint, self.format.clone(), format_aliases)?;
 
     if let Some(ref regions) = self.regions {
       regions
         .iter()
-        .map(|region| {
+        .enumerate()
+        .map(|(index, region)| {
+          region.validate_range(index)?;
+
           Ok(
             self
               .get_base_query_builder(request.clone(), format)?
-              .with_reference_name(Some(region.reference_name.clone()))
+              .with_reference_name(Some(region.reference_name.clone()))?
               .with_range_from_u32(region.start, region.end)?
               .build(),
           )
@@ -52,8 +74,35 @@ impl PostRequest {
     }
   }
 
+  /// Check that the regions do not span more than `max_distinct_references` distinct reference
+  /// names.
+  pub(crate) fn validate_distinct_references(
+    &self,
+    max_distinct_references: Option<usize>,
+  ) -> Result<()> {
+    let (Some(max_distinct_references), Some(regions)) = (max_distinct_references, &self.regions)
+    else {
+      return Ok(());
+    };
+
+    let distinct_references = regions
+      .iter()
+      .map(|region| region.reference_name.as_str())
+      .collect::<HashSet<_>>()
+      .len();
+    if distinct_references > max_distinct_references {
+      return Err(HtsGetError::InvalidInput(format!(
+        "regions span {distinct_references} distinct reference names, which exceeds the maximum of \
+        {max_distinct_references}"
+      )));
+    }
+
+    Ok(())
+  }
+
   fn get_base_query_builder(&self, request: Request, format: Format) -> Result<QueryBuilder> {
     QueryBuilder::new(request, format)
+      .with_format_omitted(self.format.is_none())
       .with_class(self.class.clone())?
       .with_fields_from_vec(self.fields.clone())
       .with_tags_from_vec(self.tags.clone(), self.notags.clone())
@@ -79,7 +128,7 @@ mod tests {
         notags: None,
         regions: None,
       }
-      .get_queries(request.clone(), &Endpoint::Variants)
+      .get_queries(request.clone(), &Endpoint::Variants, &Default::default())
       .unwrap(),
       vec![Query::new("id", Format::Vcf, request).with_class(Class::Header)]
     );
@@ -102,7 +151,7 @@ mod tests {
           end: Some(153),
         }]),
       }
-      .get_queries(request.clone(), &Endpoint::Variants)
+      .get_queries(request.clone(), &Endpoint::Variants, &Default::default())
       .unwrap(),
       vec![Query::new("id", Format::Vcf, request)
         .with_class(Class::Header)
@@ -136,7 +185,7 @@ mod tests {
           }
         ]),
       }
-      .get_queries(request.clone(), &Endpoint::Variants)
+      .get_queries(request.clone(), &Endpoint::Variants, &Default::default())
       .unwrap(),
       vec![
         Query::new("id", Format::Vcf, request.clone())
@@ -152,4 +201,54 @@ mod tests {
       ]
     );
   }
+
+  #[test]
+  fn post_request_with_invalid_region() {
+    let request = PostRequest {
+      format: Some("VCF".to_string()),
+      regions: Some(vec![
+        Region {
+          reference_name: "20".to_string(),
+          start: Some(150),
+          end: Some(153),
+        },
+        Region {
+          reference_name: "20".to_string(),
+          start: Some(153),
+          end: Some(150),
+        },
+      ]),
+      ..Default::default()
+    };
+
+    let result = request.get_queries(
+      Request::new_with_id("id".to_string()),
+      &Endpoint::Variants,
+      &Default::default(),
+    );
+    assert!(matches!(
+      result,
+      Err(HtsGetError::InvalidInput(message)) if message.starts_with("region 1 ")
+    ));
+  }
+
+  #[test]
+  fn post_request_with_too_many_distinct_references() {
+    let region = |reference_name: &str| Region {
+      reference_name: reference_name.to_string(),
+      start: None,
+      end: None,
+    };
+    let request = PostRequest {
+      regions: Some(vec![region("20"), region("11"), region("20"), region("X")]),
+      ..Default::default()
+    };
+
+    assert!(request.validate_distinct_references(None).is_ok());
+    assert!(request.validate_distinct_references(Some(3)).is_ok());
+    assert!(matches!(
+      request.validate_distinct_references(Some(2)),
+      Err(HtsGetError::InvalidInput(_))
+    ));
+  }
 }
@@ -25,6 +25,13 @@ impl QueryBuilder {
     self.query
   }
 
+  /// Set whether the format was omitted from the request, so that a resolver default format can
+  /// be applied.
+  pub fn with_format_omitted(mut self, format_omitted: bool) -> Self {
+    self.query = self.query.with_format_omitted(format_omitted);
+    self
+  }
+
   #[instrument(level = "trace", skip_all, ret)]
   pub fn with_class(mut self, class: Option<impl Into<String>>) -> Result<Self> {
     let class = class.map(Into::into);
@@ -43,11 +50,23 @@ impl QueryBuilder {
   }
 
   #[instrument(level = "trace", skip_all, ret)]
-  pub fn with_reference_name(mut self, reference_name: Option<impl Into<String>>) -> Self {
+  pub fn with_reference_name(mut self, reference_name: Option<impl Into<String>>) -> Result<Self> {
     if let Some(reference_name) = reference_name {
+      let reference_name = reference_name.into();
+
+      // Query strings are decoded lossily, so invalid UTF-8 or invalid percent-encoded
+      // sequences show up as replacement characters.
+      if reference_name.contains(char::REPLACEMENT_CHARACTER) {
+        return Err(HtsGetError::InvalidInput(format!(
+          "`{}` isn't a valid UTF-8 reference name",
+          reference_name.escape_default()
+        )));
+      }
+
       self.query = self.query.with_reference_name(reference_name);
     }
-    self
+
+    Ok(self)
   }
 
   #[instrument(level = "trace", skip_all, ret)]
@@ -103,6 +122,12 @@ impl QueryBuilder {
           "end is greater than start (`{start}` > `{end}`)"
         )));
       }
+
+      if start == end {
+        return Err(HtsGetError::InvalidInput(format!(
+          "start must be less than end, the range is empty (`{start}` == `{end}`)"
+        )));
+      }
     }
 
     Ok(self)
@@ -208,18 +233,34 @@ mod tests {
     assert_eq!(
       QueryBuilder::new(request, Bam)
         .with_reference_name(Some("ValidName"))
+        .unwrap()
         .build()
         .reference_name(),
       Some("ValidName")
     );
   }
 
+  #[test]
+  fn query_with_invalid_utf8_reference_name() {
+    let request = Request::new_with_id("ValidId".to_string());
+    // `%FF` decoded lossily, as it is by the query string extractors.
+    let reference_name = String::from_utf8_lossy(b"chr\xFF").to_string();
+
+    assert!(matches!(
+      QueryBuilder::new(request, Bam)
+        .with_reference_name(Some(reference_name))
+        .unwrap_err(),
+      HtsGetError::InvalidInput(_)
+    ));
+  }
+
   #[test]
   fn query_with_range() {
     let request = Request::new_with_id("ValidId".to_string());
 
     let query = QueryBuilder::new(request, Bam)
       .with_reference_name(Some("ValidName"))
+      .unwrap()
       .with_range(Some("3"), Some("5"))
       .unwrap()
       .build();
@@ -248,6 +289,7 @@ mod tests {
     assert!(matches!(
       QueryBuilder::new(request, Bam)
         .with_reference_name(Some("ValidName"))
+        .unwrap()
         .with_range(Some("a"), Some("5"))
         .unwrap_err(),
       HtsGetError::InvalidInput(_)
@@ -261,6 +303,7 @@ mod tests {
     assert!(matches!(
       QueryBuilder::new(request, Bam)
         .with_reference_name(Some("ValidName"))
+        .unwrap()
         .with_range(Some("5"), Some("a"))
         .unwrap_err(),
       HtsGetError::InvalidInput(_)
@@ -274,12 +317,27 @@ mod tests {
     assert!(matches!(
       QueryBuilder::new(request, Bam)
         .with_reference_name(Some("ValidName"))
+        .unwrap()
         .with_range(Some("5"), Some("3"))
         .unwrap_err(),
       HtsGetError::InvalidRange(_)
     ));
   }
 
+  #[test]
+  fn query_with_empty_range() {
+    let request = Request::new_with_id("ValidId".to_string());
+
+    assert!(matches!(
+      QueryBuilder::new(request, Bam)
+        .with_reference_name(Some("ValidName"))
+        .unwrap()
+        .with_range(Some("5"), Some("5"))
+        .unwrap_err(),
+      HtsGetError::InvalidInput(_)
+    ));
+  }
+
   #[test]
   fn query_with_fields() {
     let request = Request::new_with_id("ValidId".to_string());
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use tracing::instrument;
+
+use htsget_search::{HtsGet, StorageProbe};
+
+/// A struct representing the readiness of the server, and the status of each storage backend.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+  pub ready: bool,
+  pub backends: Vec<BackendReadiness>,
+}
+
+/// The readiness of a single storage backend.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendReadiness {
+  pub name: String,
+  pub backend: String,
+  pub ready: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+impl From<StorageProbe> for BackendReadiness {
+  fn from(probe: StorageProbe) -> Self {
+    Self {
+      name: probe.name().to_string(),
+      backend: probe.backend().to_string(),
+      ready: probe.is_ready(),
+      error: probe.error().map(str::to_string),
+    }
+  }
+}
+
+/// Probe each storage backend and get the readiness of the server. The server is only ready if
+/// all backends are reachable.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_readiness<H: HtsGet + Send + Sync + 'static>(searcher: &H) -> Readiness {
+  let backends: Vec<BackendReadiness> = searcher
+    .probe_storage()
+    .await
+    .into_iter()
+    .map(BackendReadiness::from)
+    .collect();
+  let ready = backends.iter().all(|backend| backend.ready);
+
+  debug!(ready, backends = ?backends, "readiness");
+
+  Readiness { ready, backends }
+}
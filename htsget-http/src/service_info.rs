@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 use tracing::instrument;
 
 use htsget_config::types::Format;
-use htsget_search::HtsGet;
+use htsget_search::{FormatCapabilities, HtsGet};
 
 use crate::ConfigServiceInfo;
 use crate::Endpoint;
@@ -11,6 +13,10 @@ use crate::Endpoint;
 const READS_FORMATS: [&str; 2] = ["BAM", "CRAM"];
 const VARIANTS_FORMATS: [&str; 2] = ["VCF", "BCF"];
 
+const TYPE_GROUP: &str = "org.ga4gh";
+const TYPE_ARTIFACT: &str = "htsget";
+const TYPE_VERSION: &str = "1.3.0";
+
 /// A struct representing the information that should be present in a service-info response.
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -51,6 +57,60 @@ pub struct Htsget {
   pub formats: Vec<String>,
   pub fields_parameters_effective: bool,
   pub tags_parameters_effective: bool,
+  pub format_capabilities: BTreeMap<String, Capabilities>,
+}
+
+/// The capabilities of a single format in this deployment.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+  pub range_queries: bool,
+  pub header_class: bool,
+}
+
+impl From<FormatCapabilities> for Capabilities {
+  fn from(capabilities: FormatCapabilities) -> Self {
+    Self {
+      range_queries: capabilities.range_queries(),
+      header_class: capabilities.header_class(),
+    }
+  }
+}
+
+/// The landing response for the root path, which describes the service and links to the
+/// service-info of each endpoint.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RootResponse {
+  pub id: String,
+  pub name: String,
+  pub version: String,
+  pub documentation_url: String,
+  pub service_info: ServiceInfoLinks,
+}
+
+/// The links to the service-info of each endpoint.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceInfoLinks {
+  pub reads: String,
+  pub variants: String,
+}
+
+/// Get the landing response for the root path, filled out with the data from the server config.
+#[instrument(level = "debug", skip_all)]
+pub fn get_root_json(config: &ConfigServiceInfo) -> RootResponse {
+  debug!("getting root response");
+  RootResponse {
+    id: config.id().unwrap_or_default().to_string(),
+    name: config.name().unwrap_or_default().to_string(),
+    version: config.version().unwrap_or_default().to_string(),
+    documentation_url: config.documentation_url().unwrap_or_default().to_string(),
+    service_info: ServiceInfoLinks {
+      reads: format!("/{}/service-info", Endpoint::Reads),
+      variants: format!("/{}/service-info", Endpoint::Variants),
+    },
+  }
 }
 
 pub fn get_service_info_with(
@@ -58,23 +118,29 @@ pub fn get_service_info_with(
   supported_formats: &[Format],
   fields_effective: bool,
   tags_effective: bool,
+  format_capabilities: impl Fn(Format) -> FormatCapabilities,
 ) -> ServiceInfo {
+  let formats: Vec<_> = supported_formats
+    .iter()
+    .filter(|format| match endpoint {
+      Endpoint::Reads => READS_FORMATS.contains(&format.to_string().as_str()),
+      Endpoint::Variants => VARIANTS_FORMATS.contains(&format.to_string().as_str()),
+    })
+    .collect();
+
   let htsget_info = Htsget {
     datatype: match endpoint {
       Endpoint::Reads => "reads",
       Endpoint::Variants => "variants",
     }
     .to_string(),
-    formats: supported_formats
-      .iter()
-      .map(|format| format.to_string())
-      .filter(|format| match endpoint {
-        Endpoint::Reads => READS_FORMATS.contains(&format.as_str()),
-        Endpoint::Variants => VARIANTS_FORMATS.contains(&format.as_str()),
-      })
-      .collect(),
+    formats: formats.iter().map(|format| format.to_string()).collect(),
     fields_parameters_effective: fields_effective,
     tags_parameters_effective: tags_effective,
+    format_capabilities: formats
+      .iter()
+      .map(|format| (format.to_string(), format_capabilities(**format).into()))
+      .collect(),
   };
 
   ServiceInfo {
@@ -82,7 +148,11 @@ pub fn get_service_info_with(
     name: "".to_string(),
     version: "".to_string(),
     organization: Default::default(),
-    service_type: Default::default(),
+    service_type: Type {
+      group: TYPE_GROUP.to_string(),
+      artifact: TYPE_ARTIFACT.to_string(),
+      version: TYPE_VERSION.to_string(),
+    },
     htsget: htsget_info,
     contact_url: "".to_string(),
     documentation_url: "".to_string(),
@@ -105,6 +175,7 @@ pub fn get_service_info_json(
       &searcher.get_supported_formats(),
       searcher.are_field_parameters_effective(),
       searcher.are_tag_parameters_effective(),
+      |format| searcher.get_format_capabilities(format),
     ),
     config,
   )
@@ -130,6 +201,15 @@ fn fill_out_service_info_json(
   if let Some(organization_url) = config.organization_url() {
     service_info_json.organization.url = organization_url.to_string();
   }
+  if let Some(type_group) = config.type_group() {
+    service_info_json.service_type.group = type_group.to_string();
+  }
+  if let Some(type_artifact) = config.type_artifact() {
+    service_info_json.service_type.artifact = type_artifact.to_string();
+  }
+  if let Some(type_version) = config.type_version() {
+    service_info_json.service_type.version = type_version.to_string();
+  }
   if let Some(contact_url) = config.contact_url() {
     service_info_json.contact_url = contact_url.to_string();
   }
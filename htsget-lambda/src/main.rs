@@ -25,8 +25,8 @@ async fn main() -> Result<(), Error> {
     debug!(config = ?config, "config parsed");
 
     let service_info = config.service_info().clone();
-    let cors = config.ticket_server().cors().clone();
-    let router = TicketServer::router(config.owned_resolvers(), service_info, cors);
+    let ticket_server = config.ticket_server().clone();
+    let router = TicketServer::router(config.owned_resolvers(), service_info, &ticket_server);
 
     run(router).await
   } else {
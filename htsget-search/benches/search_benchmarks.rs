@@ -9,7 +9,7 @@ use htsget_config::resolver::ResolveResponse;
 use htsget_config::storage::local::Local as ConfigLocalStorage;
 use htsget_config::types::Class::Header;
 use htsget_config::types::Format::{Bam, Bcf, Cram, Vcf};
-use htsget_config::types::{HtsGetError, Query, Scheme};
+use htsget_config::types::{HtsGetError, Query, Scheme, SearchOptions};
 use htsget_search::from_storage::HtsGetFromStorage;
 
 const BENCHMARK_DURATION_SECONDS: u64 = 30;
@@ -25,6 +25,7 @@ async fn perform_query(query: Query) -> Result<(), HtsGetError> {
       false,
     ),
     &query,
+    &SearchOptions::default(),
   )
   .await?;
 
@@ -1,14 +1,16 @@
-//! Module providing the search capability using BAM/BAI files
+//! Module providing the search capability using BAM files, indexed by BAI or CSI files.
 //!
 
+use std::marker::PhantomData;
+
 use async_trait::async_trait;
 use noodles::bam;
 use noodles::bam::bai;
-use noodles::bam::bai::Index;
 use noodles::bgzf;
 use noodles::bgzf::VirtualPosition;
-use noodles::csi::binning_index::index::reference_sequence::index::LinearIndex;
-use noodles::csi::binning_index::index::ReferenceSequence;
+use noodles::csi;
+use noodles::csi::binning_index::index::reference_sequence::index::{BinnedIndex, LinearIndex};
+use noodles::csi::binning_index::index::{reference_sequence, Index, ReferenceSequence};
 use noodles::csi::BinningIndex;
 use noodles::sam::Header;
 use tokio::io;
@@ -19,23 +21,66 @@ use crate::search::{BgzfSearch, Search, SearchAll, SearchReads};
 use crate::Class::Body;
 use crate::HtsGetError;
 use crate::{Format, Query, Result};
-use htsget_storage::types::BytesPosition;
-use htsget_storage::{Storage, Streamable};
+use htsget_config::types::Fields;
+use htsget_config::types::SearchOptions;
+use htsget_storage::error::StorageError;
+use htsget_storage::types::{BytesPosition, HeadOptions};
+use htsget_storage::{Storage, StorageTrait, Streamable};
 
 type AsyncReader = bam::AsyncReader<bgzf::AsyncReader<Streamable>>;
 
-/// Allows searching through bam files.
-pub struct BamSearch {
+/// The fields of a SAM record which can be requested using the `fields` parameter.
+const SAM_FIELDS: [&str; 11] = [
+  "QNAME", "FLAG", "RNAME", "POS", "MAPQ", "CIGAR", "RNEXT", "PNEXT", "TLEN", "SEQ", "QUAL",
+];
+
+/// The index types that bam files can be searched with. BAI indexes use a [LinearIndex], and CSI
+/// indexes use a [BinnedIndex].
+#[async_trait]
+pub trait BamIndex: reference_sequence::Index + Clone + Send + Sync + 'static {
+  /// The file ending of the index, which is appended to the id of the query.
+  const INDEX_FILE_ENDING: &'static str;
+
+  /// Read the index.
+  async fn read_bam_index<T: AsyncRead + Unpin + Send>(inner: T) -> io::Result<Index<Self>>;
+}
+
+#[async_trait]
+impl BamIndex for LinearIndex {
+  const INDEX_FILE_ENDING: &'static str = ".bam.bai";
+
+  async fn read_bam_index<T: AsyncRead + Unpin + Send>(inner: T) -> io::Result<Index<Self>> {
+    let mut reader = bai::AsyncReader::new(BufReader::new(inner));
+    reader.read_index().await
+  }
+}
+
+#[async_trait]
+impl BamIndex for BinnedIndex {
+  const INDEX_FILE_ENDING: &'static str = ".bam.csi";
+
+  async fn read_bam_index<T: AsyncRead + Unpin + Send>(inner: T) -> io::Result<Index<Self>> {
+    csi::AsyncReader::new(inner).read_index().await
+  }
+}
+
+/// Allows searching through bam files, using a BAI index by default.
+pub struct BamSearch<I = LinearIndex> {
   storage: Storage,
+  options: SearchOptions,
+  index: PhantomData<I>,
 }
 
+/// Allows searching through bam files which are indexed by a CSI index.
+pub type BamCsiSearch = BamSearch<BinnedIndex>;
+
 #[async_trait]
-impl BgzfSearch<LinearIndex, AsyncReader, Header> for BamSearch {
+impl<I: BamIndex> BgzfSearch<I, AsyncReader, Header> for BamSearch<I> {
   #[instrument(level = "trace", skip(self, index))]
   async fn get_byte_ranges_for_unmapped(
     &self,
     query: &Query,
-    index: &Index,
+    index: &Index<I>,
   ) -> Result<Vec<BytesPosition>> {
     trace!("getting byte ranges for unmapped reads");
     let last_interval = index.last_first_record_start_position();
@@ -66,7 +111,7 @@ impl BgzfSearch<LinearIndex, AsyncReader, Header> for BamSearch {
 }
 
 #[async_trait]
-impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for BamSearch {
+impl<I: BamIndex> Search<ReferenceSequence<I>, Index<I>, AsyncReader, Header> for BamSearch<I> {
   fn init_reader(inner: Streamable) -> AsyncReader {
     AsyncReader::new(inner)
   }
@@ -75,16 +120,15 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for BamS
     reader.read_header().await
   }
 
-  async fn read_index_inner<T: AsyncRead + Unpin + Send>(inner: T) -> io::Result<Index> {
-    let mut reader = bai::AsyncReader::new(BufReader::new(inner));
-    reader.read_index().await
+  async fn read_index_inner<T: AsyncRead + Unpin + Send>(inner: T) -> io::Result<Index<I>> {
+    I::read_bam_index(inner).await
   }
 
   #[instrument(level = "trace", skip(self, index, header, query))]
   async fn get_byte_ranges_for_reference_name(
     &self,
     reference_name: String,
-    index: &Index,
+    index: &Index<I>,
     header: &Header,
     query: &Query,
   ) -> Result<Vec<BytesPosition>> {
@@ -94,10 +138,45 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for BamS
       .await
   }
 
+  /// Check that the requested fields are SAM fields. BGZF byte ranges always contain whole
+  /// records, so the response still contains every field, which the spec allows.
+  fn validate_fields(query: &Query) -> Result<()> {
+    let Fields::List(fields) = query.fields() else {
+      return Ok(());
+    };
+
+    let mut unsupported = fields
+      .iter()
+      .filter(|field| !SAM_FIELDS.contains(&field.as_str()))
+      .map(String::as_str)
+      .collect::<Vec<_>>();
+    if unsupported.is_empty() {
+      return Ok(());
+    }
+
+    unsupported.sort_unstable();
+    Err(HtsGetError::invalid_input(format!(
+      "unsupported fields for `{}`: `{}`",
+      Format::Bam,
+      unsupported.join("`, `")
+    )))
+  }
+
+  fn header_contains_reference_name(header: &Header, reference_name: &str) -> bool {
+    reference_name == "*"
+      || header
+        .reference_sequences()
+        .contains_key(reference_name.as_bytes())
+  }
+
   fn get_storage(&self) -> &Storage {
     &self.storage
   }
 
+  fn options(&self) -> &SearchOptions {
+    &self.options
+  }
+
   fn mut_storage(&mut self) -> &mut Storage {
     &mut self.storage
   }
@@ -105,10 +184,24 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for BamS
   fn get_format(&self) -> Format {
     Format::Bam
   }
+
+  fn index_keys(&self, query: &Query) -> Vec<String> {
+    let mut keys = vec![format!("{}{}", query.index_id(), I::INDEX_FILE_ENDING)];
+    keys.extend(
+      self
+        .options()
+        .index_extensions()
+        .iter()
+        .map(|extension| format!("{}{extension}", query.index_id())),
+    );
+    keys
+  }
 }
 
 #[async_trait]
-impl SearchReads<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for BamSearch {
+impl<I: BamIndex> SearchReads<ReferenceSequence<I>, Index<I>, AsyncReader, Header>
+  for BamSearch<I>
+{
   async fn get_reference_sequence_from_name<'a>(
     &self,
     header: &'a Header,
@@ -120,16 +213,16 @@ impl SearchReads<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for
   async fn get_byte_ranges_for_unmapped_reads(
     &self,
     query: &Query,
-    bai_index: &Index,
+    index: &Index<I>,
   ) -> Result<Vec<BytesPosition>> {
-    self.get_byte_ranges_for_unmapped(query, bai_index).await
+    self.get_byte_ranges_for_unmapped(query, index).await
   }
 
   async fn get_byte_ranges_for_reference_sequence(
     &self,
     ref_seq_id: usize,
     query: &Query,
-    index: &Index,
+    index: &Index<I>,
   ) -> Result<Vec<BytesPosition>> {
     self
       .get_byte_ranges_for_reference_sequence_bgzf(query, ref_seq_id, index)
@@ -140,7 +233,50 @@ impl SearchReads<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for
 impl BamSearch {
   /// Create the bam search.
   pub fn new(storage: Storage) -> Self {
-    Self { storage }
+    Self {
+      storage,
+      options: Default::default(),
+      index: PhantomData,
+    }
+  }
+
+  /// Get whether the query should be searched using a CSI index. This is the case when the BAI
+  /// index of the query is not found, but the CSI index is.
+  pub async fn uses_csi_index(storage: &Storage, query: &Query) -> bool {
+    let index_storage = storage.index_storage();
+    let options = HeadOptions::new(query.request().headers());
+
+    matches!(
+      index_storage
+        .head(&query.format().fmt_index(query.index_id()), options.clone())
+        .await,
+      Err(StorageError::KeyNotFound(_))
+    ) && index_storage
+      .head(
+        &format!("{}{}", query.index_id(), BinnedIndex::INDEX_FILE_ENDING),
+        options,
+      )
+      .await
+      .is_ok()
+  }
+}
+
+impl BamCsiSearch {
+  /// Create the bam search using a CSI index.
+  pub fn new_csi(storage: Storage) -> Self {
+    Self {
+      storage,
+      options: Default::default(),
+      index: PhantomData,
+    }
+  }
+}
+
+impl<I> BamSearch<I> {
+  /// Set the options which control how the query is searched.
+  pub fn with_options(mut self, options: SearchOptions) -> Self {
+    self.options = options;
+    self
   }
 }
 
@@ -151,16 +287,51 @@ pub(crate) mod tests {
   use crate::from_storage::tests::with_aws_storage_fn;
   use crate::from_storage::tests::with_local_storage_fn;
   use crate::{Class::Body, Class::Header, Headers, HtsGetError::NotFound, Response, Url};
+  use htsget_config::storage::ConcurrencyLimit;
+  use htsget_config::types::{OversizedRange, TaggedTypeAll};
+  use htsget_storage::error::Result as StorageResult;
+  use htsget_storage::types::{GetOptions, HeadOptions, RangeUrlOptions};
+  use htsget_storage::{StorageMiddleware, StorageTrait};
   use htsget_test::http::concat::ConcatResponse;
+  use htsget_test::util::TestWriter;
+  use std::collections::HashSet;
   use std::future::Future;
+  use std::time::Duration;
+  use tokio::time::timeout;
   #[cfg(feature = "experimental")]
   use {
     crate::from_storage::tests::with_local_storage_c4gh,
     htsget_storage::c4gh::storage::C4GHStorage, htsget_test::c4gh::get_decryption_keys,
   };
 
+  /// Storage which reports a smaller file size than the actual file, simulating a stale index.
+  #[derive(Debug, Clone)]
+  struct TruncatedStorage {
+    inner: Storage,
+    size: u64,
+  }
+
+  impl StorageMiddleware for TruncatedStorage {}
+
+  #[async_trait]
+  impl StorageTrait for TruncatedStorage {
+    async fn get(&self, key: &str, options: GetOptions<'_>) -> StorageResult<Streamable> {
+      self.inner.get(key, options).await
+    }
+
+    async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> StorageResult<Url> {
+      self.inner.range_url(key, options).await
+    }
+
+    async fn head(&self, _key: &str, _options: HeadOptions<'_>) -> StorageResult<u64> {
+      Ok(self.size)
+    }
+  }
+
   const DATA_LOCATION: &str = "data/bam";
   const INDEX_FILE_LOCATION: &str = "htsnexus_test_NA12878.bam.bai";
+  const CSI_INDEX_FILE_LOCATION: &str = "htsnexus_test_NA12878.bam.csi";
+  const GZI_INDEX_FILE_LOCATION: &str = "htsnexus_test_NA12878.bam.gzi";
   pub(crate) const BAM_FILE_NAME: &str = "htsnexus_test_NA12878.bam";
 
   #[tokio::test]
@@ -210,6 +381,118 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_unmapped_reads_content_range() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage)
+        .with_options(SearchOptions::default().with_include_content_range(true));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("*");
+      let response = search.search(query).await.unwrap();
+      println!("{response:#?}");
+
+      let total = (4667 + 1) + (2596798 - 2060795 + 1);
+      let content_ranges = response
+        .urls
+        .iter()
+        .map(|url| url.content_range.clone())
+        .collect::<Vec<_>>();
+      assert_eq!(
+        content_ranges,
+        vec![
+          Some(format!("bytes 0-4667/{total}")),
+          Some(format!("bytes 4668-{}/{total}", total - 1)),
+        ]
+      );
+
+      let mut next_start = 0;
+      for content_range in content_ranges.into_iter().flatten() {
+        let (range, size) = content_range
+          .strip_prefix("bytes ")
+          .unwrap()
+          .split_once('/')
+          .unwrap();
+        let (start, end) = range.split_once('-').unwrap();
+        let (start, end) = (start.parse::<u64>().unwrap(), end.parse::<u64>().unwrap());
+
+        assert_eq!(start, next_start);
+        assert_eq!(size.parse::<u64>().unwrap(), total);
+        next_start = end + 1;
+      }
+      assert_eq!(next_start, total);
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_unmapped_reads_total_size() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage)
+        .with_options(SearchOptions::default().with_include_total_size(true));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("*");
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      let expected_response = Ok(
+        Response::new(
+          Format::Bam,
+          vec![
+            Url::new(expected_url())
+              .with_headers(Headers::default().with_header("Range", "bytes=0-4667"))
+              .with_class(Header),
+            Url::new(expected_url())
+              .with_headers(Headers::default().with_header("Range", "bytes=2060795-2596798"))
+              .with_class(Body),
+          ],
+        )
+        .with_total_size(Some((4667 + 1) + (2596798 - 2060795 + 1))),
+      );
+      assert_eq!(response, expected_response);
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_all_reads_above_max_total_response_bytes() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage)
+        .with_options(SearchOptions::default().with_max_total_response_bytes(Some(1000)));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam);
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      assert!(matches!(response, Err(HtsGetError::PayloadTooLarge(_))));
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_eof_served_from_data_server() {
+    with_local_storage(|storage| async move {
+      let search = BamSearch::new(storage);
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("20");
+      let response = search.search(query).await.unwrap();
+      println!("{response:#?}");
+
+      assert!(response
+        .urls
+        .iter()
+        .all(|url| !url.url.starts_with("data:")));
+      assert_eq!(response.urls.last(), Some(&expected_eof_url()));
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_reference_name_without_seq_range_chr11() {
     with_local_storage(|storage| async move {
@@ -234,6 +517,28 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_with_concurrency_limit_of_one() {
+    with_local_storage(|storage| async move {
+      let mut search =
+        BamSearch::new(storage.with_concurrency_limit(&ConcurrencyLimit::new(Some(1))));
+
+      for query in [
+        Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+          .with_reference_name("11"),
+        Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam).with_class(Header),
+      ] {
+        let response = timeout(Duration::from_secs(10), search.search(query))
+          .await
+          .expect("search should not wait on its own concurrency permit");
+        assert!(response.is_ok());
+      }
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_reference_name_without_seq_range_chr20() {
     with_local_storage(|storage| async move {
@@ -298,6 +603,166 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_unmapped_reads_min_block_size() {
+    with_local_storage(|storage| async move {
+      let search = BamSearch::new(storage.clone()).with_options(
+        SearchOptions::default()
+          .with_max_bytes_per_url(Some(100000))
+          .with_min_block_size(Some(50000)),
+      );
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("*");
+      let response = search.search(query).await.unwrap();
+      println!("{response:#?}");
+
+      let ranges = response
+        .urls
+        .iter()
+        .filter_map(|url| url.headers.as_ref())
+        .map(|headers| headers.as_ref_inner()["Range"].clone())
+        .collect::<Vec<_>>();
+      assert_eq!(
+        ranges,
+        vec![
+          "bytes=0-4667",
+          "bytes=2060795-2160794",
+          "bytes=2160795-2260794",
+          "bytes=2260795-2360794",
+          "bytes=2360795-2460794",
+          "bytes=2460795-2596798",
+        ]
+      );
+      for range in ranges.iter().skip(1) {
+        let (start, end) = range
+          .strip_prefix("bytes=")
+          .unwrap()
+          .split_once('-')
+          .unwrap();
+        let size = end.parse::<u64>().unwrap() - start.parse::<u64>().unwrap() + 1;
+        assert!(size >= 50000);
+      }
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_range_exceeds_file_size() {
+    with_local_storage(|storage| async move {
+      let file_size = 840000;
+      let storage = Storage::new(TruncatedStorage {
+        inner: storage,
+        size: file_size,
+      });
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(5015000)
+        .with_end(5050000);
+
+      let response = BamSearch::new(storage.clone())
+        .with_options(SearchOptions::default().with_oversized_range(OversizedRange::Error))
+        .search(query.clone())
+        .await;
+      assert!(matches!(response, Err(HtsGetError::InternalError(_))));
+
+      let response = BamSearch::new(storage).search(query).await.unwrap();
+      println!("{response:#?}");
+
+      let ranges = response
+        .urls
+        .iter()
+        .map(|url| url.headers.as_ref().unwrap().as_ref_inner()["Range"].clone())
+        .collect::<Vec<_>>();
+      assert!(ranges.contains(&"bytes=0-4667".to_string()));
+      assert!(ranges.contains(&"bytes=256721-647345".to_string()));
+      assert!(!ranges.contains(&"bytes=977196-996014".to_string()));
+      for range in ranges {
+        let (_, end) = range.split_once('-').unwrap();
+        assert!(end.parse::<u64>().unwrap() < file_size);
+      }
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_reference_name_with_seq_range_logs_summary() {
+    let writer = TestWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+      .with_max_level(tracing::Level::DEBUG)
+      .with_ansi(false)
+      .with_writer(writer.make_writer())
+      .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage);
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(5015000)
+        .with_end(5050000);
+      let response = search.search(query).await;
+      assert!(response.is_ok());
+
+      None
+    })
+    .await;
+
+    let logs = writer.logs();
+    assert!(logs.contains("byte range summary"));
+    assert!(logs.contains("htsnexus_test_NA12878"));
+    assert!(logs.contains("count=5"));
+    assert!(logs.contains("total_bytes=431880"));
+  }
+
+  #[tokio::test]
+  async fn search_reference_name_with_seq_range_logs_index_scan() {
+    let writer = TestWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+      .with_max_level(tracing::Level::INFO)
+      .with_ansi(false)
+      .with_writer(writer.make_writer())
+      .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    with_local_storage(|storage| async move {
+      let mut search =
+        BamSearch::new(storage).with_options(SearchOptions::default().with_log_index_scan(true));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(5015000)
+        .with_end(5050000);
+      let response = search.search(query).await;
+      assert!(response.is_ok());
+
+      None
+    })
+    .await;
+
+    let logs = writer.logs();
+    let summary = logs
+      .lines()
+      .find(|line| line.contains("index scan summary"))
+      .unwrap();
+    let count = |field: &str| {
+      summary
+        .split_whitespace()
+        .find_map(|value| value.strip_prefix(field))
+        .unwrap()
+        .parse::<usize>()
+        .unwrap()
+    };
+
+    // The range is covered by one bin at each of the five levels above the leaf level, and three
+    // leaf bins of 16kbp.
+    let bins = count("bins=");
+    assert!(bins > 0 && bins <= 8);
+    assert!(count("chunks=") > 0);
+  }
+
   #[tokio::test]
   async fn search_reference_name_no_end_position() {
     with_local_storage(|storage| async move {
@@ -361,6 +826,37 @@ pub(crate) mod tests {
     .await
   }
 
+  #[tokio::test]
+  async fn search_many_response_urls_max_urls_per_ticket() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage)
+        .with_options(SearchOptions::default().with_max_urls_per_ticket(Some(4)));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(4999976)
+        .with_end(5003981);
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      let expected_response = Ok(Response::new(
+        Format::Bam,
+        vec![
+          Url::new(expected_url())
+            .with_headers(Headers::default().with_header("Range", "bytes=0-273085")),
+          Url::new(expected_url())
+            .with_headers(Headers::default().with_header("Range", "bytes=499249-647345")),
+          Url::new(expected_url())
+            .with_headers(Headers::default().with_header("Range", "bytes=824361-996014")),
+          expected_eof_url().set_class(None),
+        ],
+      ));
+      assert_eq!(response, expected_response);
+
+      Some((BAM_FILE_NAME.to_string(), (response.unwrap(), Body).into()))
+    })
+    .await
+  }
+
   #[tokio::test]
   async fn search_no_gzi() {
     with_local_storage_fn(
@@ -420,6 +916,79 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_with_fields() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage);
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_fields(Fields::List(HashSet::from_iter([
+          "QNAME".to_string(),
+          "FLAG".to_string(),
+        ])));
+      let response = search.search(query.clone()).await;
+      println!("{response:#?}");
+
+      let expected_response = search
+        .search(query.with_fields(Fields::Tagged(TaggedTypeAll::All)))
+        .await;
+      assert_eq!(response, expected_response);
+
+      Some((BAM_FILE_NAME.to_string(), (response.unwrap(), Body).into()))
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_header_with_fields() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage);
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_class(Header)
+        .with_fields(Fields::List(HashSet::from_iter(["SEQ".to_string()])));
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      let expected_response = Ok(Response::new(
+        Format::Bam,
+        vec![Url::new(expected_url())
+          .with_headers(Headers::default().with_header("Range", "bytes=0-4667"))
+          .with_class(Header)],
+      ));
+      assert_eq!(response, expected_response);
+
+      Some((
+        BAM_FILE_NAME.to_string(),
+        (response.unwrap(), Header).into(),
+      ))
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_with_unsupported_fields() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage);
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_fields(Fields::List(HashSet::from_iter([
+          "QNAME".to_string(),
+          "CHROM".to_string(),
+        ])));
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      assert_eq!(
+        response,
+        Err(HtsGetError::invalid_input(
+          "unsupported fields for `BAM`: `CHROM`"
+        ))
+      );
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_header_with_no_mapped_reads() {
     with_local_storage(|storage| async move {
@@ -461,6 +1030,28 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_validate_non_existent_reference_name() {
+    with_local_storage(|storage| async move {
+      let mut search = BamSearch::new(storage)
+        .with_options(SearchOptions::default().with_validate_reference_names(true));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("bogus");
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      assert_eq!(
+        response,
+        Err(NotFound(
+          "reference name `bogus` not found in `BAM` header".to_string()
+        ))
+      );
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_non_existent_id_reference_name() {
     with_local_storage_fn(
@@ -478,6 +1069,62 @@ pub(crate) mod tests {
     .await
   }
 
+  #[tokio::test]
+  async fn search_csi_index() {
+    let queries = vec![
+      Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam),
+      Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam).with_class(Header),
+      Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("*"),
+      Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11"),
+      Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(5015000)
+        .with_end(5050000),
+    ];
+
+    for query in queries {
+      let mut bai_response = None;
+      let bai_response_ref = &mut bai_response;
+      let bai_query = query.clone();
+      with_local_storage(move |storage| async move {
+        *bai_response_ref = Some(BamSearch::new(storage).search(bai_query).await);
+        None
+      })
+      .await;
+
+      with_local_storage_fn(
+        move |storage| async move {
+          assert!(BamSearch::uses_csi_index(&storage, &query).await);
+
+          let response = BamSearch::new_csi(storage).search(query).await;
+          assert_eq!(Some(response), bai_response);
+
+          None
+        },
+        DATA_LOCATION,
+        &[
+          BAM_FILE_NAME,
+          CSI_INDEX_FILE_LOCATION,
+          GZI_INDEX_FILE_LOCATION,
+        ],
+      )
+      .await;
+    }
+  }
+
+  #[tokio::test]
+  async fn uses_csi_index_with_bai_index() {
+    with_local_storage(|storage| async move {
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam);
+      assert!(!BamSearch::uses_csi_index(&storage, &query).await);
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_non_existent_id_all_reads() {
     with_local_storage_fn(
@@ -632,6 +1279,27 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[cfg(feature = "experimental")]
+  #[tokio::test]
+  async fn search_narrow_range_c4gh_verify_edit_list() {
+    with_local_storage_c4gh(|storage| async move {
+      let storage =
+        C4GHStorage::new(get_decryption_keys().await, storage).with_verify_edit_list(true);
+      let mut search = BamSearch::new(Storage::new(storage));
+      let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+        .with_reference_name("11")
+        .with_start(5015000)
+        .with_end(5015100);
+      let response = search.search(query).await.unwrap();
+
+      Some((
+        "htsnexus_test_NA12878.bam.c4gh".to_string(),
+        (response, Body).into(),
+      ))
+    })
+    .await;
+  }
+
   pub(crate) async fn with_local_storage<F, Fut>(test: F)
   where
     F: FnOnce(Storage) -> Fut,
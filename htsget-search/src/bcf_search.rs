@@ -16,6 +16,7 @@ use tracing::{instrument, trace};
 
 use crate::search::{find_first, BgzfSearch, Search};
 use crate::{Format, Query, Result};
+use htsget_config::types::SearchOptions;
 use htsget_storage::types::BytesPosition;
 use htsget_storage::{Storage, Streamable};
 
@@ -24,6 +25,7 @@ type AsyncReader = bcf::AsyncReader<bgzf::AsyncReader<Streamable>>;
 /// Allows searching through bcf files.
 pub struct BcfSearch {
   storage: Storage,
+  options: SearchOptions,
 }
 
 #[async_trait]
@@ -60,6 +62,12 @@ impl Search<ReferenceSequence<BinnedIndex>, Index, AsyncReader, Header> for BcfS
     query: &Query,
   ) -> Result<Vec<BytesPosition>> {
     trace!("getting byte ranges for reference name");
+    if reference_name == "*" {
+      return self
+        .get_byte_ranges_for_unmapped_variants(query, index)
+        .await;
+    }
+
     // We are assuming the order of the contigs in the header and the references sequences
     // in the index is the same
     let mut futures = FuturesOrdered::new();
@@ -86,10 +94,18 @@ impl Search<ReferenceSequence<BinnedIndex>, Index, AsyncReader, Header> for BcfS
     Ok(byte_ranges)
   }
 
+  fn header_contains_reference_name(header: &Header, reference_name: &str) -> bool {
+    reference_name == "*" || header.contigs().contains_key(reference_name)
+  }
+
   fn get_storage(&self) -> &Storage {
     &self.storage
   }
 
+  fn options(&self) -> &SearchOptions {
+    &self.options
+  }
+
   fn mut_storage(&mut self) -> &mut Storage {
     &mut self.storage
   }
@@ -102,7 +118,16 @@ impl Search<ReferenceSequence<BinnedIndex>, Index, AsyncReader, Header> for BcfS
 impl BcfSearch {
   /// Create the bcf search.
   pub fn new(storage: Storage) -> Self {
-    Self { storage }
+    Self {
+      storage,
+      options: Default::default(),
+    }
+  }
+
+  /// Set the options which control how the query is searched.
+  pub fn with_options(mut self, options: SearchOptions) -> Self {
+    self.options = options;
+    self
   }
 }
 
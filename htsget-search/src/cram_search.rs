@@ -23,6 +23,7 @@ use crate::search::{Search, SearchAll, SearchReads};
 use crate::Class::Body;
 use crate::{ConcurrencyError, ParsedHeader};
 use crate::{Format, HtsGetError, Query, Result};
+use htsget_config::types::SearchOptions;
 use htsget_storage::types::{BytesPosition, DataBlock};
 use htsget_storage::{Storage, Streamable};
 
@@ -38,6 +39,7 @@ type AsyncReader = cram::AsyncReader<BufReader<Streamable>>;
 /// Allows searching through cram files.
 pub struct CramSearch {
   storage: Storage,
+  options: SearchOptions,
 }
 
 #[async_trait]
@@ -67,7 +69,7 @@ impl SearchAll<PhantomData<Self>, Index, AsyncReader, Header> for CramSearch {
   async fn get_byte_ranges_for_header(
     &self,
     index: &Index,
-    _reader: &mut AsyncReader,
+    _reader: AsyncReader,
     _query: &Query,
   ) -> Result<BytesPosition> {
     Ok(
@@ -77,6 +79,18 @@ impl SearchAll<PhantomData<Self>, Index, AsyncReader, Header> for CramSearch {
     )
   }
 
+  async fn get_byte_ranges_for_header_without_index(
+    &self,
+    _reader: AsyncReader,
+    query: &Query,
+  ) -> Result<BytesPosition> {
+    Err(HtsGetError::not_found(format!(
+      "{} index for `{}` not found, the end of the header cannot be found without an index",
+      self.get_format(),
+      query.index_id()
+    )))
+  }
+
   fn get_eof_marker(&self) -> &[u8] {
     CRAM_EOF
   }
@@ -164,10 +178,21 @@ impl Search<PhantomData<Self>, Index, AsyncReader, Header> for CramSearch {
       .await
   }
 
+  fn header_contains_reference_name(header: &Header, reference_name: &str) -> bool {
+    reference_name == "*"
+      || header
+        .reference_sequences()
+        .contains_key(reference_name.as_bytes())
+  }
+
   fn get_storage(&self) -> &Storage {
     &self.storage
   }
 
+  fn options(&self) -> &SearchOptions {
+    &self.options
+  }
+
   fn mut_storage(&mut self) -> &mut Storage {
     &mut self.storage
   }
@@ -180,7 +205,16 @@ impl Search<PhantomData<Self>, Index, AsyncReader, Header> for CramSearch {
 impl CramSearch {
   /// Create the cram search.
   pub fn new(storage: Storage) -> Self {
-    Self { storage }
+    Self {
+      storage,
+      options: Default::default(),
+    }
+  }
+
+  /// Set the options which control how the query is searched.
+  pub fn with_options(mut self, options: SearchOptions) -> Self {
+    self.options = options;
+    self
   }
 
   /// Get bytes ranges using the index.
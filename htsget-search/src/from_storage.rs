@@ -1,16 +1,25 @@
 //! Module providing an implementation of the [HtsGet] trait using a [StorageTrait].
 //!
 
+use std::time::Duration;
+
 use async_trait::async_trait;
+use tokio::time::timeout;
 use tracing::debug;
 use tracing::instrument;
 
-use htsget_config::resolver::{ResolveResponse, StorageResolver};
+use htsget_config::index_cache::IndexCache;
+use htsget_config::resolver::{ReferenceNames, ResolveResponse, StorageResolver};
+#[cfg(feature = "azure-storage")]
+use htsget_config::storage::azure::Azure as AzureStorageConfig;
+#[cfg(feature = "gcs-storage")]
+use htsget_config::storage::gcs::Gcs as GcsStorageConfig;
 use htsget_config::storage::local::Local as LocalStorageConfig;
 #[cfg(feature = "s3-storage")]
 use htsget_config::storage::s3::S3 as S3StorageConfig;
 #[cfg(feature = "url-storage")]
 use htsget_config::storage::url::UrlStorageClient as UrlStorageConfig;
+use htsget_config::types::SearchOptions;
 
 use crate::search::Search;
 use crate::Resolver;
@@ -21,13 +30,17 @@ use crate::{
   vcf_search::VcfSearch,
   {HtsGet, Query, Response, Result},
 };
-use crate::{Format, HtsGetError};
-use htsget_storage::Storage;
+use crate::{Class, ConfigStorage, Format, FormatCapabilities, HtsGetError, StorageProbe};
+use futures::future::join_all;
+use htsget_storage::error::{Result as StorageResult, StorageError};
+use htsget_storage::types::HeadOptions;
+use htsget_storage::{Storage, StorageTrait};
 
 /// Implementation of the [HtsGet] trait using a [StorageTrait].
 #[derive(Debug, Clone)]
 pub struct HtsGetFromStorage {
   storage: Storage,
+  options: SearchOptions,
 }
 
 #[async_trait]
@@ -35,16 +48,154 @@ impl HtsGet for Vec<Resolver> {
   async fn search(self, query: Query) -> Result<Response> {
     self.as_slice().search(query).await
   }
+
+  async fn search_with_age(self, query: Query) -> Result<(Response, Option<Duration>)> {
+    self.as_slice().search_with_age(query).await
+  }
+
+  async fn head(self, query: Query) -> Result<Format> {
+    self.as_slice().head(query).await
+  }
+
+  async fn probe_storage(&self) -> Vec<StorageProbe> {
+    self.as_slice().probe_storage().await
+  }
+
+  fn get_format_capabilities(&self, format: Format) -> FormatCapabilities {
+    self.as_slice().get_format_capabilities(format)
+  }
 }
 
 #[async_trait]
 impl HtsGet for &[Resolver] {
-  async fn search(self, mut query: Query) -> Result<Response> {
+  async fn search(self, query: Query) -> Result<Response> {
+    self
+      .search_with_age(query)
+      .await
+      .map(|(response, _)| response)
+  }
+
+  async fn search_with_age(self, mut query: Query) -> Result<(Response, Option<Duration>)> {
     self
       .resolve_request::<HtsGetFromStorage>(&mut query)
       .await
       .ok_or_else(|| HtsGetError::not_found("failed to match query with storage"))?
   }
+
+  async fn head(self, mut query: Query) -> Result<Format> {
+    self
+      .resolve_request::<HeadFromStorage>(&mut query)
+      .await
+      .ok_or_else(|| HtsGetError::not_found("failed to match query with storage"))?
+      .map(|(response, _)| response.format)
+  }
+
+  async fn probe_storage(&self) -> Vec<StorageProbe> {
+    join_all(self.iter().map(probe_resolver_storage)).await
+  }
+
+  fn get_format_capabilities(&self, format: Format) -> FormatCapabilities {
+    let resolvers = self
+      .iter()
+      .filter(|resolver| resolver.allow_formats().contains(&format));
+
+    let (range_queries, header_class) =
+      resolvers.fold((false, false), |(range_queries, header_class), resolver| {
+        (
+          range_queries
+            || !matches!(
+              resolver.allow_reference_names(),
+              ReferenceNames::List(reference_names) if reference_names.is_empty()
+            ),
+          header_class || resolver.allow_classes().contains(&Class::Header),
+        )
+      });
+
+    FormatCapabilities::new(range_queries, header_class)
+  }
+}
+
+/// Probe the storage backend of a resolver. The backend is built on the first probe and reused by
+/// later probes.
+async fn probe_resolver_storage(resolver: &Resolver) -> StorageProbe {
+  let backend = match resolver.storage() {
+    ConfigStorage::Local(_) => "Local",
+    #[cfg(feature = "s3-storage")]
+    ConfigStorage::S3(s3_storage) => {
+      // The bucket is resolved from the query id, so there is nothing to probe up front.
+      if s3_storage.bucket().is_empty() {
+        return StorageProbe::new(resolver.regex().to_string(), "S3".to_string(), None);
+      }
+
+      "S3"
+    }
+    #[cfg(feature = "url-storage")]
+    ConfigStorage::Url(url_storage) => {
+      // There is no key to request without a probe key, so there is nothing to probe up front.
+      if url_storage.probe_key().is_none() {
+        return StorageProbe::new(resolver.regex().to_string(), "Url".to_string(), None);
+      }
+
+      "Url"
+    }
+    #[cfg(feature = "gcs-storage")]
+    ConfigStorage::Gcs(gcs_storage) => {
+      // The bucket is resolved from the query id, so there is nothing to probe up front.
+      if gcs_storage.bucket().is_empty() {
+        return StorageProbe::new(resolver.regex().to_string(), "Gcs".to_string(), None);
+      }
+
+      "Gcs"
+    }
+    #[cfg(feature = "azure-storage")]
+    ConfigStorage::Azure(azure_storage) => {
+      // The container is resolved from the query id, so there is nothing to probe up front.
+      if azure_storage.container().is_empty() {
+        return StorageProbe::new(resolver.regex().to_string(), "Azure".to_string(), None);
+      }
+
+      "Azure"
+    }
+    _ => "Unknown",
+  };
+
+  let storage = resolver
+    .probe_storage()
+    .get_or_try_init(|| storage_from_config(resolver.storage()))
+    .await
+    .and_then(|storage| {
+      storage.ok_or_else(|| {
+        StorageError::InternalError("probe storage has an unexpected type".to_string())
+      })
+    });
+  let result = match storage {
+    Ok(storage) => storage.probe().await,
+    Err(err) => Err(err),
+  };
+
+  StorageProbe::new(
+    resolver.regex().to_string(),
+    backend.to_string(),
+    result.err().map(|err| err.to_string()),
+  )
+}
+
+/// Build the storage backend from the storage config of a resolver.
+async fn storage_from_config(storage: &ConfigStorage) -> StorageResult<Storage> {
+  match storage {
+    ConfigStorage::Local(local_storage) => Storage::from_local(local_storage).await,
+    #[cfg(feature = "s3-storage")]
+    ConfigStorage::S3(s3_storage) => Storage::from_s3(s3_storage).await,
+    #[cfg(feature = "url-storage")]
+    ConfigStorage::Url(url_storage) => Storage::from_url(url_storage).await,
+    #[cfg(feature = "gcs-storage")]
+    ConfigStorage::Gcs(gcs_storage) => Storage::from_gcs(gcs_storage).await,
+    #[cfg(feature = "azure-storage")]
+    ConfigStorage::Azure(azure_storage) => Storage::from_azure(azure_storage).await,
+    _ => Err(StorageError::InternalError(
+      "unsupported storage backend".to_string(),
+    )),
+  }
 }
 
 #[async_trait]
@@ -52,13 +203,89 @@ impl HtsGet for HtsGetFromStorage {
   #[instrument(level = "debug", skip(self))]
   async fn search(self, query: Query) -> Result<Response> {
     debug!(format = ?query.format(), ?query, "searching {:?}, with query {:?}", query.format(), query);
-    match query.format() {
-      Format::Bam => BamSearch::new(self.into_inner()).search(query).await,
-      Format::Cram => CramSearch::new(self.into_inner()).search(query).await,
-      Format::Vcf => VcfSearch::new(self.into_inner()).search(query).await,
-      Format::Bcf => BcfSearch::new(self.into_inner()).search(query).await,
+    match self.options.search_timeout() {
+      Some(search_timeout) => timeout(search_timeout, self.search_format(query))
+        .await
+        .map_err(|_| {
+          HtsGetError::internal_error(format!("search timed out after {search_timeout:?}"))
+        })?,
+      None => self.search_format(query).await,
     }
   }
+
+  async fn head(self, query: Query) -> Result<Format> {
+    self
+      .storage
+      .head(
+        &query.format().fmt_file(query.id()),
+        HeadOptions::new(query.request().headers()),
+      )
+      .await?;
+
+    Ok(query.format())
+  }
+}
+
+/// Resolves requests by checking that the file exists in storage, without searching it. Used to
+/// answer `HEAD` requests.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadFromStorage;
+
+impl HeadFromStorage {
+  /// Check that the file for the query exists in the storage.
+  async fn head(storage: Storage, query: &Query) -> Result<Response> {
+    HtsGetFromStorage::new(storage).head(query.clone()).await?;
+    Ok(Response::new(query.format(), vec![]))
+  }
+}
+
+#[async_trait]
+impl ResolveResponse for HeadFromStorage {
+  const CACHEABLE: bool = false;
+
+  async fn from_local(
+    local_storage_config: &LocalStorageConfig,
+    query: &Query,
+    _: &SearchOptions,
+  ) -> Result<Response> {
+    Self::head(Storage::from_local(local_storage_config).await?, query).await
+  }
+
+  #[cfg(feature = "s3-storage")]
+  async fn from_s3(
+    s3_storage: &S3StorageConfig,
+    query: &Query,
+    _: &SearchOptions,
+  ) -> Result<Response> {
+    Self::head(Storage::from_s3(s3_storage).await?, query).await
+  }
+
+  #[cfg(feature = "url-storage")]
+  async fn from_url(
+    url_storage_config: &UrlStorageConfig,
+    query: &Query,
+    _: &SearchOptions,
+  ) -> Result<Response> {
+    Self::head(Storage::from_url(url_storage_config).await?, query).await
+  }
+
+  #[cfg(feature = "gcs-storage")]
+  async fn from_gcs(
+    gcs_storage_config: &GcsStorageConfig,
+    query: &Query,
+    _: &SearchOptions,
+  ) -> Result<Response> {
+    Self::head(Storage::from_gcs(gcs_storage_config).await?, query).await
+  }
+
+  #[cfg(feature = "azure-storage")]
+  async fn from_azure(
+    azure_storage_config: &AzureStorageConfig,
+    query: &Query,
+    _: &SearchOptions,
+  ) -> Result<Response> {
+    Self::head(Storage::from_azure(azure_storage_config).await?, query).await
+  }
 }
 
 #[async_trait]
@@ -66,30 +293,104 @@ impl ResolveResponse for HtsGetFromStorage {
   async fn from_local(
     local_storage_config: &LocalStorageConfig,
     query: &Query,
+    options: &SearchOptions,
   ) -> Result<Response> {
     let storage = Storage::from_local(local_storage_config).await?;
-    let searcher = HtsGetFromStorage::new(storage);
+    let searcher = HtsGetFromStorage::new(storage)
+      .with_prefixed_options(options, format!("{}/", local_storage_config.local_path()));
     searcher.search(query.clone()).await
   }
 
   #[cfg(feature = "s3-storage")]
-  async fn from_s3(s3_storage: &S3StorageConfig, query: &Query) -> Result<Response> {
+  async fn from_s3(
+    s3_storage: &S3StorageConfig,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response> {
     let storage = Storage::from_s3(s3_storage).await;
-    let searcher = HtsGetFromStorage::new(storage?);
+    let searcher = HtsGetFromStorage::new(storage?)
+      .with_prefixed_options(options, format!("{}/", s3_storage.bucket()));
     searcher.search(query.clone()).await
   }
 
   #[cfg(feature = "url-storage")]
-  async fn from_url(url_storage_config: &UrlStorageConfig, query: &Query) -> Result<Response> {
+  async fn from_url(
+    url_storage_config: &UrlStorageConfig,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response> {
     let storage = Storage::from_url(url_storage_config).await;
-    let searcher = HtsGetFromStorage::new(storage?);
+    let searcher = HtsGetFromStorage::new(storage?)
+      .with_prefixed_options(options, format!("{}/", url_storage_config.url()));
+    searcher.search(query.clone()).await
+  }
+
+  #[cfg(feature = "gcs-storage")]
+  async fn from_gcs(
+    gcs_storage_config: &GcsStorageConfig,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response> {
+    let storage = Storage::from_gcs(gcs_storage_config).await;
+    let searcher = HtsGetFromStorage::new(storage?)
+      .with_prefixed_options(options, format!("{}/", gcs_storage_config.bucket()));
+    searcher.search(query.clone()).await
+  }
+
+  #[cfg(feature = "azure-storage")]
+  async fn from_azure(
+    azure_storage_config: &AzureStorageConfig,
+    query: &Query,
+    options: &SearchOptions,
+  ) -> Result<Response> {
+    let storage = Storage::from_azure(azure_storage_config).await;
+    let searcher = HtsGetFromStorage::new(storage?).with_prefixed_options(
+      options,
+      format!(
+        "{}/{}/",
+        azure_storage_config.account(),
+        azure_storage_config.container()
+      ),
+    );
     searcher.search(query.clone()).await
   }
 }
 
 impl HtsGetFromStorage {
   pub fn new(storage: Storage) -> Self {
-    Self { storage }
+    Self {
+      storage,
+      options: Default::default(),
+    }
+  }
+
+  /// Set the options which control how queries are searched.
+  pub fn with_options(mut self, options: SearchOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  /// Cache up to `capacity` parsed indexes, evicting the least recently used index when full.
+  /// Cached indexes are read again if the size of their data file changes.
+  pub fn with_index_cache(mut self, capacity: usize) -> Self {
+    self.options = self
+      .options
+      .with_index_cache(Some(IndexCache::new(capacity)));
+    self
+  }
+
+  /// Set the options, prefixing the keys of the index cache so that different storage backends
+  /// which share the cache of a resolver do not share indexes.
+  fn with_prefixed_options(self, options: &SearchOptions, prefix: String) -> Self {
+    let index_cache = options
+      .index_cache()
+      .map(|index_cache| index_cache.clone().with_prefix(prefix));
+    self.with_options(options.clone().with_index_cache(index_cache))
+  }
+
+  /// Get the options which control how queries are searched.
+  pub fn options(&self) -> &SearchOptions {
+    &self.options
   }
 
   pub fn storage(&self) -> &Storage {
@@ -99,10 +400,48 @@ impl HtsGetFromStorage {
   pub fn into_inner(self) -> Storage {
     self.storage
   }
+
+  /// Search using the searcher for the format of the query.
+  async fn search_format(self, query: Query) -> Result<Response> {
+    let options = self.options.clone();
+    match query.format() {
+      Format::Bam if BamSearch::uses_csi_index(self.storage(), &query).await => {
+        BamSearch::new_csi(self.into_inner())
+          .with_options(options)
+          .search(query)
+          .await
+      }
+      Format::Bam => {
+        BamSearch::new(self.into_inner())
+          .with_options(options)
+          .search(query)
+          .await
+      }
+      Format::Cram => {
+        CramSearch::new(self.into_inner())
+          .with_options(options)
+          .search(query)
+          .await
+      }
+      Format::Vcf => {
+        VcfSearch::new(self.into_inner())
+          .with_options(options)
+          .search(query)
+          .await
+      }
+      Format::Bcf => {
+        BcfSearch::new(self.into_inner())
+          .with_options(options)
+          .search(query)
+          .await
+      }
+    }
+  }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
+  use std::collections::{BTreeMap, HashMap, HashSet};
   use std::fs;
   use std::future::Future;
   use std::path::{Path, PathBuf};
@@ -111,11 +450,18 @@ pub(crate) mod tests {
     htsget_storage::s3::S3Storage, htsget_test::aws_mocks::with_s3_test_server, std::fs::create_dir,
   };
 
+  use std::time::Duration;
+
+  use htsget_storage::types::{GetOptions, HeadOptions, RangeUrlOptions};
+  use htsget_storage::{StorageMiddleware, Streamable};
   use http::uri::Authority;
   use tempfile::TempDir;
+  use tokio::time::sleep;
 
+  use htsget_config::resolver::{AllowGuard, IndexLocation};
   use htsget_config::storage;
   use htsget_config::types::Class::Body;
+  use htsget_config::types::FormatConflict;
   use htsget_config::types::Scheme::Http;
   use htsget_storage::local::LocalStorage;
   #[cfg(feature = "experimental")]
@@ -133,6 +479,32 @@ pub(crate) mod tests {
 
   use super::*;
 
+  /// Storage which delays every request, simulating a slow backend.
+  #[derive(Debug, Clone)]
+  struct SlowStorage {
+    inner: Storage,
+    delay: Duration,
+  }
+
+  impl StorageMiddleware for SlowStorage {}
+
+  #[async_trait]
+  impl StorageTrait for SlowStorage {
+    async fn get(&self, key: &str, options: GetOptions<'_>) -> StorageResult<Streamable> {
+      sleep(self.delay).await;
+      self.inner.get(key, options).await
+    }
+
+    async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> StorageResult<Url> {
+      self.inner.range_url(key, options).await
+    }
+
+    async fn head(&self, key: &str, options: HeadOptions<'_>) -> StorageResult<u64> {
+      sleep(self.delay).await;
+      self.inner.head(key, options).await
+    }
+  }
+
   #[tokio::test]
   async fn search_bam() {
     with_bam_local_storage(|storage| async move {
@@ -172,6 +544,111 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_bam_csi_index() {
+    with_local_storage_fn(
+      |storage| async move {
+        let htsget = HtsGetFromStorage::new(storage);
+        let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+          .with_reference_name("11");
+        let response = htsget.search(query).await;
+
+        assert!(response.is_ok());
+        Some((BAM_FILE_NAME.to_string(), (response.unwrap(), Body).into()))
+      },
+      "data/bam",
+      &[BAM_FILE_NAME, "htsnexus_test_NA12878.bam.csi"],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_vcf_index_cache() {
+    with_vcf_local_storage(|storage| async move {
+      let htsget = HtsGetFromStorage::new(storage)
+        .with_options(SearchOptions::default().with_index_cache(Some(IndexCache::new(1))));
+      let filename = "spec-v4.3";
+      let query = Query::new_with_default_request(filename, Format::Vcf);
+
+      let response = htsget.clone().search(query.clone()).await;
+      assert_eq!(htsget.options().index_cache().unwrap().len(), 1);
+
+      let cached_response = htsget.clone().search(query).await;
+      assert_eq!(cached_response, response);
+      assert_eq!(response, expected_vcf_response(filename));
+
+      Some((
+        VCF_FILE_NAME_SPEC.to_string(),
+        (response.unwrap(), Body).into(),
+      ))
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_vcf_index_cache_data_size() {
+    with_vcf_local_storage(|storage| async move {
+      let filename = "spec-v4.3";
+      let headers = Default::default();
+      let size = storage
+        .head(&format!("{filename}.vcf.gz"), HeadOptions::new(&headers))
+        .await
+        .unwrap();
+
+      let htsget = HtsGetFromStorage::new(storage).with_index_cache(1);
+      let query = Query::new_with_default_request(filename, Format::Vcf);
+      let response = htsget.clone().search(query).await;
+      assert_eq!(response, expected_vcf_response(filename));
+
+      let cache = htsget.options().index_cache().unwrap();
+      let key = format!("{filename}.vcf.gz.tbi");
+      assert_eq!(cache.capacity(), 1);
+      assert!(cache
+        .get::<noodles::tabix::Index>(&key, Format::Vcf, size)
+        .is_some());
+      assert!(cache
+        .get::<noodles::tabix::Index>(&key, Format::Vcf, size + 1)
+        .is_none());
+      assert!(cache.is_empty());
+
+      Some((
+        VCF_FILE_NAME_SPEC.to_string(),
+        (response.unwrap(), Body).into(),
+      ))
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_timeout_slow_backend() {
+    with_vcf_local_storage(|storage| async move {
+      let storage = Storage::new(SlowStorage {
+        inner: storage,
+        delay: Duration::from_millis(100),
+      });
+      let filename = "spec-v4.3";
+
+      let query = Query::new_with_default_request(filename, Format::Vcf);
+      let response = HtsGetFromStorage::new(storage.clone())
+        .with_options(SearchOptions::default().with_search_timeout(Some(Duration::from_millis(10))))
+        .search(query.clone())
+        .await;
+      assert!(matches!(response, Err(HtsGetError::InternalError(_))));
+
+      let response = HtsGetFromStorage::new(storage)
+        .with_options(SearchOptions::default().with_search_timeout(Some(Duration::from_secs(10))))
+        .search(query)
+        .await;
+      assert_eq!(response, expected_vcf_response(filename));
+
+      Some((
+        VCF_FILE_NAME_SPEC.to_string(),
+        (response.unwrap(), Body).into(),
+      ))
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn from_local_storage() {
     with_config_local_storage(
@@ -222,6 +699,532 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn head_resolvers() {
+    with_config_local_storage(
+      |_, local_storage| async {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()];
+
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+        assert!(matches!(
+          resolvers.clone().head(query).await,
+          Ok(Format::Vcf)
+        ));
+
+        let query = Query::new_with_default_request("non-existent-id", Format::Vcf);
+        assert!(matches!(
+          resolvers.head(query).await,
+          Err(HtsGetError::NotFound(_))
+        ));
+
+        None
+      },
+      "data/vcf",
+      &[],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn probe_storage_resolvers() {
+    with_config_local_storage(
+      |_, local_storage| async {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()];
+        assert!(!resolvers[0].probe_storage().initialized());
+
+        let probes = resolvers.probe_storage().await;
+        assert!(probes.iter().all(StorageProbe::is_ready));
+        assert!(resolvers[0].probe_storage().initialized());
+
+        let probes = resolvers.clone().probe_storage().await;
+        assert!(probes.iter().all(StorageProbe::is_ready));
+
+        None
+      },
+      "data/vcf",
+      &[],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_max_query_span() {
+    with_config_local_storage(
+      |_, local_storage| async {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()
+        .with_max_query_span(HashMap::from([(Format::Vcf, 2000)]))];
+
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf)
+          .with_reference_name("20")
+          .with_start(14000)
+          .with_end(15000);
+        assert!(resolvers.clone().search(query).await.is_ok());
+
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf)
+          .with_reference_name("20")
+          .with_start(14000)
+          .with_end(1000000);
+        assert!(matches!(
+          resolvers.search(query).await,
+          Err(HtsGetError::InvalidRange(_))
+        ));
+
+        None
+      },
+      "data/vcf",
+      &[],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_provenance() {
+    with_config_local_storage(
+      |_, local_storage| async {
+        let provenance = BTreeMap::from([
+          ("pipeline_version".to_string(), "1.2.0".to_string()),
+          ("reference_build".to_string(), "GRCh38".to_string()),
+        ]);
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()
+        .with_provenance(provenance.clone())];
+
+        let filename = "spec-v4.3";
+        let query = Query::new_with_default_request(filename, Format::Vcf);
+        let response = resolvers.search(query).await;
+
+        assert_eq!(
+          response,
+          expected_vcf_response(filename)
+            .map(|response| response.with_provenance(Some(provenance)))
+        );
+
+        Some((
+          VCF_FILE_NAME_SPEC.to_string(),
+          (response.unwrap(), Body).into(),
+        ))
+      },
+      "data/vcf",
+      &[],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_index_location() {
+    let tmp_dir = TempDir::new().unwrap();
+    let from_path = std::env::current_dir()
+      .unwrap()
+      .parent()
+      .unwrap()
+      .join("data/vcf");
+    let index_file_name = Format::Vcf.fmt_index("spec-v4.3");
+
+    fs::create_dir(tmp_dir.path().join("data")).unwrap();
+    fs::create_dir(tmp_dir.path().join("indexes")).unwrap();
+    fs::copy(
+      from_path.join(VCF_FILE_NAME_SPEC),
+      tmp_dir.path().join("data").join(VCF_FILE_NAME_SPEC),
+    )
+    .unwrap();
+    fs::copy(
+      from_path.join(&index_file_name),
+      tmp_dir.path().join("indexes").join(&index_file_name),
+    )
+    .unwrap();
+
+    let local_storage = LocalStorageConfig::new(
+      Http,
+      Authority::from_static("127.0.0.1:8081"),
+      tmp_dir.path().to_str().unwrap().to_string(),
+      "/data".to_string(),
+      false,
+    );
+    let resolvers = vec![Resolver::new(
+      storage::Storage::Local(local_storage),
+      "^(.*)$",
+      "data/$1",
+      Default::default(),
+    )
+    .unwrap()
+    .with_index_location(Some(
+      IndexLocation::new("^data/(.*)$", "indexes/$1").unwrap(),
+    ))];
+
+    let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+    let response = resolvers.search(query).await;
+
+    assert_eq!(response, expected_vcf_response("data/spec-v4.3"));
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_index_local_path() {
+    let tmp_dir = TempDir::new().unwrap();
+    let from_path = std::env::current_dir()
+      .unwrap()
+      .parent()
+      .unwrap()
+      .join("data/vcf");
+    let index_file_name = Format::Vcf.fmt_index("spec-v4.3");
+
+    fs::create_dir(tmp_dir.path().join("data")).unwrap();
+    fs::create_dir(tmp_dir.path().join("indexes")).unwrap();
+    fs::copy(
+      from_path.join(VCF_FILE_NAME_SPEC),
+      tmp_dir.path().join("data").join(VCF_FILE_NAME_SPEC),
+    )
+    .unwrap();
+    fs::copy(
+      from_path.join(&index_file_name),
+      tmp_dir.path().join("indexes").join(&index_file_name),
+    )
+    .unwrap();
+
+    let local_storage = LocalStorageConfig::new(
+      Http,
+      Authority::from_static("127.0.0.1:8081"),
+      tmp_dir.path().join("data").to_str().unwrap().to_string(),
+      "/data".to_string(),
+      false,
+    )
+    .set_index_local_path(Some(
+      tmp_dir.path().join("indexes").to_str().unwrap().to_string(),
+    ));
+    let resolvers = vec![Resolver::new(
+      storage::Storage::Local(local_storage),
+      "^(.*)$",
+      "$1",
+      Default::default(),
+    )
+    .unwrap()];
+
+    let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+    let response = resolvers.search(query).await;
+
+    assert_eq!(response, expected_vcf_response("spec-v4.3"));
+  }
+
+  #[cfg(feature = "s3-storage")]
+  #[tokio::test]
+  async fn search_index_storage_different_bucket_aws() {
+    let tmp_dir = TempDir::new().unwrap();
+    let from_path = std::env::current_dir()
+      .unwrap()
+      .parent()
+      .unwrap()
+      .join("data/vcf");
+    let index_file_name = Format::Vcf.fmt_index("spec-v4.3");
+
+    create_dir(tmp_dir.path().join("data")).unwrap();
+    create_dir(tmp_dir.path().join("indexes")).unwrap();
+    fs::copy(
+      from_path.join(VCF_FILE_NAME_SPEC),
+      tmp_dir.path().join("data").join(VCF_FILE_NAME_SPEC),
+    )
+    .unwrap();
+    fs::copy(
+      from_path.join(&index_file_name),
+      tmp_dir.path().join("indexes").join(&index_file_name),
+    )
+    .unwrap();
+
+    with_s3_test_server(tmp_dir.path(), |client| async move {
+      let storage = Storage::new(S3Storage::new(client.clone(), "data".to_string()));
+      let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+
+      let response = HtsGetFromStorage::new(storage.clone())
+        .search(query.clone())
+        .await;
+      assert!(response.is_err());
+
+      let storage = storage.with_index_storage(Some(Storage::new(S3Storage::new(
+        client,
+        "indexes".to_string(),
+      ))));
+      let response = HtsGetFromStorage::new(storage).search(query).await.unwrap();
+
+      assert_eq!(response.format, Format::Vcf);
+      assert_eq!(response.urls.len(), 1);
+      assert!(response.urls[0].url.contains("spec-v4.3.vcf.gz"));
+    })
+    .await;
+  }
+
+  #[test]
+  fn resolvers_format_capabilities() {
+    let resolvers = vec![
+      Resolver::new(
+        Default::default(),
+        "^body/(.*)$",
+        "$1",
+        AllowGuard::default()
+          .with_allow_formats(vec![Format::Bam])
+          .with_allow_classes(vec![Body])
+          .with_allow_reference_names(ReferenceNames::List(HashSet::new())),
+      )
+      .unwrap(),
+      Resolver::new(
+        Default::default(),
+        "^header/(.*)$",
+        "$1",
+        AllowGuard::default().with_allow_formats(vec![Format::Vcf]),
+      )
+      .unwrap(),
+    ];
+
+    assert_eq!(
+      resolvers.get_format_capabilities(Format::Bam),
+      FormatCapabilities::new(false, false)
+    );
+    assert_eq!(
+      resolvers.get_format_capabilities(Format::Vcf),
+      FormatCapabilities::new(true, true)
+    );
+    assert_eq!(
+      resolvers.get_format_capabilities(Format::Cram),
+      FormatCapabilities::new(false, false)
+    );
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_index_extensions() {
+    let tmp_dir = TempDir::new().unwrap();
+    let from_path = std::env::current_dir()
+      .unwrap()
+      .parent()
+      .unwrap()
+      .join("data/cram");
+
+    fs::copy(
+      from_path.join("htsnexus_test_NA12878.cram"),
+      tmp_dir.path().join("htsnexus_test_NA12878.cram"),
+    )
+    .unwrap();
+    fs::copy(
+      from_path.join("htsnexus_test_NA12878.cram.crai"),
+      tmp_dir.path().join("htsnexus_test_NA12878.crai"),
+    )
+    .unwrap();
+
+    let local_storage = LocalStorageConfig::new(
+      Http,
+      Authority::from_static("127.0.0.1:8081"),
+      tmp_dir.path().to_str().unwrap().to_string(),
+      "/data".to_string(),
+      false,
+    );
+    let resolver = Resolver::new(
+      storage::Storage::Local(local_storage),
+      ".*",
+      "$0",
+      Default::default(),
+    )
+    .unwrap();
+
+    let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Cram);
+    let response = vec![resolver.clone()].search(query.clone()).await;
+    assert_eq!(
+      response,
+      Err(HtsGetError::NotFound(
+        "CRAM index for `htsnexus_test_NA12878` not found, tried: htsnexus_test_NA12878.cram.crai"
+          .to_string()
+      ))
+    );
+
+    let resolvers = vec![resolver.with_index_extensions(vec![".crai".to_string()])];
+    let response = resolvers.search(query).await;
+    assert_eq!(
+      response,
+      Ok(Response::new(
+        Format::Cram,
+        vec![
+          Url::new("http://127.0.0.1:8081/data/htsnexus_test_NA12878.cram")
+            .with_headers(Headers::default().with_header("Range", "bytes=0-1672447"))
+        ],
+      ))
+    );
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_label_not_found() {
+    with_config_local_storage(
+      |_, local_storage| async move {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          "^dataset1/(?P<key>.*)$",
+          "$key",
+          Default::default(),
+        )
+        .unwrap()
+        .with_label(Some("Dataset One".to_string()))];
+
+        let query = Query::new_with_default_request("dataset1/non-existent-id", Format::Bam);
+        let response = resolvers.search(query).await;
+
+        assert!(matches!(
+          response,
+          Err(HtsGetError::NotFound(message)) if message.ends_with("in dataset `Dataset One`")
+        ));
+
+        None
+      },
+      "data/bam",
+      &[],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_response_cache() {
+    with_config_local_storage(
+      |base_path, local_storage| async move {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()
+        .with_response_cache_ttl(Some(60))];
+
+        let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+          .with_reference_name("11");
+        let (response, age) = resolvers
+          .clone()
+          .search_with_age(query.clone())
+          .await
+          .unwrap();
+        assert_eq!(age, None);
+
+        // A cache hit does not read the index again.
+        fs::remove_file(base_path.join("htsnexus_test_NA12878.bam.bai")).unwrap();
+        let (cached, age) = resolvers
+          .clone()
+          .search_with_age(query.clone())
+          .await
+          .unwrap();
+        assert!(age.is_some());
+        assert_eq!(cached, response);
+
+        let uncached = resolvers
+          .into_iter()
+          .map(|resolver| resolver.with_response_cache_ttl(None))
+          .collect::<Vec<_>>();
+        assert!(matches!(
+          uncached.search(query).await,
+          Err(HtsGetError::NotFound(_))
+        ));
+
+        None
+      },
+      "data/bam",
+      &["htsnexus_test_NA12878.bam", "htsnexus_test_NA12878.bam.bai"],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_response_cache_server_timing() {
+    with_config_local_storage(
+      |_, local_storage| async move {
+        let resolvers = vec![Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()
+        .with_server_timing(true)
+        .with_response_cache_ttl(Some(60))];
+
+        let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Bam)
+          .with_reference_name("11");
+        let response = resolvers.clone().search(query.clone()).await.unwrap();
+        assert!(response.server_timing.unwrap().index.is_some());
+
+        let (cached, age) = resolvers.search_with_age(query).await.unwrap();
+        assert!(age.is_some());
+
+        let server_timing = cached.server_timing.unwrap();
+        assert!(server_timing.resolve.is_some());
+        assert_eq!(server_timing.head, None);
+        assert_eq!(server_timing.index, None);
+
+        None
+      },
+      "data/bam",
+      &["htsnexus_test_NA12878.bam", "htsnexus_test_NA12878.bam.bai"],
+    )
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_resolvers_format_conflict() {
+    with_config_local_storage(
+      |_, local_storage| async move {
+        let resolver = Resolver::new(
+          storage::Storage::Local(local_storage),
+          ".*",
+          "$0",
+          Default::default(),
+        )
+        .unwrap()
+        .with_format(Some(Format::Bam));
+        let query = Query::new_with_default_request("htsnexus_test_NA12878", Format::Cram);
+
+        let resolvers = vec![resolver
+          .clone()
+          .with_format_conflict(FormatConflict::Request)];
+        assert!(matches!(
+          resolvers.search(query.clone()).await,
+          Err(HtsGetError::NotFound(_))
+        ));
+
+        let resolvers = vec![resolver
+          .clone()
+          .with_format_conflict(FormatConflict::Resolver)];
+        assert!(matches!(
+          resolvers.search(query.clone()).await,
+          Ok(response) if response.format == Format::Bam
+        ));
+
+        let resolvers = vec![resolver.with_format_conflict(FormatConflict::Error)];
+        assert!(matches!(
+          resolvers.search(query).await,
+          Err(HtsGetError::UnsupportedFormat(_))
+        ));
+
+        None
+      },
+      "data/bam",
+      &[],
+    )
+    .await;
+  }
+
   fn expected_vcf_response(filename: &str) -> Result<Response> {
     Ok(Response::new(
       Format::Vcf,
@@ -17,6 +17,7 @@ pub use htsget_storage::local::LocalStorage;
 
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use tokio::task::JoinError;
@@ -33,6 +34,23 @@ pub mod vcf_search;
 pub trait HtsGet {
   async fn search(self, query: Query) -> Result<Response>;
 
+  /// Search the query, also returning the time since the response was computed if it was served
+  /// from a response cache.
+  async fn search_with_age(self, query: Query) -> Result<(Response, Option<Duration>)>
+  where
+    Self: Sized,
+  {
+    self.search(query).await.map(|response| (response, None))
+  }
+
+  /// Check that the file for the query exists without searching it, returning its format.
+  async fn head(self, query: Query) -> Result<Format>
+  where
+    Self: Sized,
+  {
+    self.search(query).await.map(|response| response.format)
+  }
+
   fn get_supported_formats(&self) -> Vec<Format> {
     vec![Format::Bam, Format::Cram, Format::Vcf, Format::Bcf]
   }
@@ -44,6 +62,82 @@ pub trait HtsGet {
   fn are_tag_parameters_effective(&self) -> bool {
     false
   }
+
+  /// Get the capabilities of this search for a format.
+  fn get_format_capabilities(&self, _format: Format) -> FormatCapabilities {
+    FormatCapabilities::new(true, true)
+  }
+
+  /// Probe the storage backends used by this search, checking whether they are reachable.
+  async fn probe_storage(&self) -> Vec<StorageProbe> {
+    vec![]
+  }
+}
+
+/// The capabilities of a search for a single format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatCapabilities {
+  range_queries: bool,
+  header_class: bool,
+}
+
+impl FormatCapabilities {
+  /// Create new format capabilities.
+  pub fn new(range_queries: bool, header_class: bool) -> Self {
+    Self {
+      range_queries,
+      header_class,
+    }
+  }
+
+  /// Whether queries for a reference name and interval are supported.
+  pub fn range_queries(&self) -> bool {
+    self.range_queries
+  }
+
+  /// Whether requests for the header class are supported.
+  pub fn header_class(&self) -> bool {
+    self.header_class
+  }
+}
+
+/// The result of probing a storage backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageProbe {
+  name: String,
+  backend: String,
+  error: Option<String>,
+}
+
+impl StorageProbe {
+  /// Create a new storage probe result.
+  pub fn new(name: String, backend: String, error: Option<String>) -> Self {
+    Self {
+      name,
+      backend,
+      error,
+    }
+  }
+
+  /// Get the name of the probed storage, which is the regex of the resolver that uses it.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Get the type of the storage backend.
+  pub fn backend(&self) -> &str {
+    &self.backend
+  }
+
+  /// Get the error if the storage backend is unreachable.
+  pub fn error(&self) -> Option<&str> {
+    self.error.as_deref()
+  }
+
+  /// Whether the storage backend is reachable.
+  pub fn is_ready(&self) -> bool {
+    self.error.is_none()
+  }
 }
 
 /// A struct to represent a parsed header
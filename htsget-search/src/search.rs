@@ -6,6 +6,8 @@
 //!
 
 use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -17,12 +19,16 @@ use noodles::csi::binning_index::index::{reference_sequence, ReferenceSequence};
 use noodles::csi::binning_index::ReferenceSequence as ReferenceSequenceExt;
 use noodles::csi::BinningIndex;
 use tokio::io;
-use tokio::io::{AsyncRead, BufReader};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
 use tokio::select;
 use tokio::task::JoinHandle;
-use tracing::{instrument, trace, trace_span, Instrument};
+use tracing::{debug, info, instrument, trace, trace_span, warn, Instrument};
 
+use htsget_config::index_cache::IndexCache;
 use htsget_config::types::Class::Header;
+use htsget_config::types::{
+  Interval, OversizedRange, SearchOptions, ServerTiming, UnmappedVariants,
+};
 
 use crate::ConcurrencyError;
 use crate::{Class, Class::Body, Format, HtsGetError, Query, Response, Result};
@@ -39,6 +45,18 @@ pub(crate) static BGZF_EOF: &[u8] = &[
 
 pub(crate) const MAX_BGZF_ISIZE: u64 = 1 << 16;
 
+/// The length of a BGZF block header, including the `BC` extra subfield.
+pub(crate) const BGZF_HEADER_LEN: u64 = 18;
+
+/// Check whether the bytes start with a BGZF block header. This is a gzip header with the `FEXTRA`
+/// flag set, containing the `BC` extra subfield.
+pub(crate) fn is_bgzf_header(header: &[u8]) -> bool {
+  matches!(
+    header,
+    [0x1f, 0x8b, 0x08, flags, _, _, _, _, _, _, _, _, b'B', b'C', 0x02, 0x00, ..] if flags & 0x04 != 0
+  )
+}
+
 /// Helper function to find the first non-none value from a set of futures.
 pub(crate) async fn find_first<T>(
   msg: &str,
@@ -59,6 +77,69 @@ pub(crate) async fn find_first<T>(
   result.ok_or_else(|| HtsGetError::not_found(msg))
 }
 
+/// Get the byte range of each block within the concatenated file, formatted like a `Content-Range`
+/// header. Returns `None` if the size of any block is unknown.
+pub(crate) fn content_ranges(blocks: &[DataBlock]) -> Option<Vec<String>> {
+  let sizes = blocks
+    .iter()
+    .map(DataBlock::byte_count)
+    .collect::<Option<Vec<_>>>()?;
+  let total = sizes.iter().sum::<u64>();
+
+  let mut start = 0;
+  Some(
+    sizes
+      .into_iter()
+      .map(|size| {
+        let content_range = format!("bytes {start}-{}/{total}", (start + size).saturating_sub(1));
+        start += size;
+        content_range
+      })
+      .collect(),
+  )
+}
+
+/// Check that the byte ranges do not extend past the end of the file, which can happen if the
+/// index is stale. Depending on the search options, ranges are either clamped to the file size, or
+/// an error is returned.
+pub(crate) fn check_ranges_within_file(
+  query: &Query,
+  options: &SearchOptions,
+  byte_ranges: Vec<BytesPosition>,
+  file_size: u64,
+) -> Result<Vec<BytesPosition>> {
+  let mut checked_ranges = Vec::with_capacity(byte_ranges.len());
+  for range in byte_ranges {
+    let start = range.get_start().unwrap_or_default();
+    let end = range.get_end().unwrap_or(file_size);
+    if start < file_size && end <= file_size {
+      checked_ranges.push(range);
+      continue;
+    }
+
+    match options.oversized_range() {
+      OversizedRange::Clamp => {
+        warn!(
+          id = query.id(),
+          start, end, file_size, "byte range exceeds the file size, the index may be stale"
+        );
+        if start < file_size {
+          checked_ranges.push(range.with_end(file_size));
+        }
+      }
+      OversizedRange::Error => {
+        return Err(HtsGetError::InternalError(format!(
+          "byte range {start}-{end} for `{}` exceeds the file size of {file_size} bytes, the \
+          index may be stale",
+          query.id()
+        )));
+      }
+    }
+  }
+
+  Ok(checked_ranges)
+}
+
 /// [SearchAll] represents searching bytes ranges that are applicable to all formats. Specifically,
 /// range for the whole file, and the header.
 ///
@@ -79,11 +160,20 @@ where
   /// Get the offset in the file of the end of the header.
   async fn get_header_end_offset(&self, index: &Index) -> Result<u64>;
 
-  /// Returns the header bytes range.
+  /// Returns the header bytes range. The reader is consumed so that it, and the storage
+  /// concurrency permit it holds, can be dropped before any other storage request is made.
   async fn get_byte_ranges_for_header(
     &self,
     index: &Index,
-    reader: &mut Reader,
+    reader: Reader,
+    query: &Query,
+  ) -> Result<BytesPosition>;
+
+  /// Returns the header bytes range of a file without an index, using the reader positioned after
+  /// the header. The reader is consumed in the same way as `get_byte_ranges_for_header`.
+  async fn get_byte_ranges_for_header_without_index(
+    &self,
+    reader: Reader,
     query: &Query,
   ) -> Result<BytesPosition>;
 
@@ -130,7 +220,7 @@ pub trait SearchReads<ReferenceSequence, Index, Reader, Header>:
 where
   Reader: Send,
   Header: Send + Sync,
-  Index: Send + Sync,
+  Index: Clone + Send + Sync + 'static,
 {
   /// Get reference sequence from name.
   async fn get_reference_sequence_from_name<'b>(
@@ -194,7 +284,7 @@ where
 pub trait Search<ReferenceSequence, Index, Reader, Header>:
   SearchAll<ReferenceSequence, Index, Reader, Header>
 where
-  Index: Send + Sync,
+  Index: Clone + Send + Sync + 'static,
   Header: Send + Sync,
   Reader: Send,
   Self: Sync + Send,
@@ -212,9 +302,53 @@ where
     query: &Query,
   ) -> Result<Vec<BytesPosition>>;
 
+  /// Check whether the reference name is present in the header.
+  fn header_contains_reference_name(header: &Header, reference_name: &str) -> bool;
+
+  /// Get the reference name to search for. If the search normalizes the `chr` prefix and the
+  /// reference name is not present in the header, the prefix is added or stripped to match the
+  /// convention of the header.
+  fn normalize_reference_name(&self, header: &Header, reference_name: &str) -> String {
+    if !self.options().normalize_chr_prefix()
+      || Self::header_contains_reference_name(header, reference_name)
+    {
+      return reference_name.to_string();
+    }
+
+    let normalized = match reference_name.strip_prefix("chr") {
+      Some(stripped) => stripped.to_string(),
+      None => format!("chr{reference_name}"),
+    };
+    if Self::header_contains_reference_name(header, &normalized) {
+      normalized
+    } else {
+      reference_name.to_string()
+    }
+  }
+
+  /// Check that the `fields` of the query can be handled by this search, before any data is
+  /// read. By default, all fields are accepted.
+  fn validate_fields(_query: &Query) -> Result<()> {
+    Ok(())
+  }
+
+  /// Check that the file can be read by this search, after the storage has been preprocessed.
+  /// By default, no check is performed.
+  async fn validate_file(&self, _query: &Query) -> Result<()> {
+    Ok(())
+  }
+
   /// Get the storage of this format.
   fn get_storage(&self) -> &Storage;
 
+  /// Get the options which control how the query is searched.
+  fn options(&self) -> &SearchOptions;
+
+  /// Get the cache of parsed indexes, if indexes are cached.
+  fn index_cache(&self) -> Option<&IndexCache> {
+    self.options().index_cache()
+  }
+
   /// Get the mutable storage of this format.
   fn mut_storage(&mut self) -> &mut Storage;
 
@@ -224,32 +358,100 @@ where
   /// Get the position at the end of file marker.
   #[instrument(level = "trace", skip(self), ret)]
   async fn position_at_eof(&self, query: &Query) -> Result<u64> {
-    let file_size = self.file_size(query).await?;
+    let eof_marker_len = u64::try_from(self.get_eof_marker().len())
+      .map_err(|err| HtsGetError::InvalidInput(err.to_string()))?;
+
     Ok(
-      file_size
-        - u64::try_from(self.get_eof_marker().len())
-          .map_err(|err| HtsGetError::InvalidInput(err.to_string()))?,
+      self
+        .get_storage()
+        .eof_position(
+          &query.format().fmt_file(query.id()),
+          eof_marker_len,
+          HeadOptions::new(query.request().headers()),
+        )
+        .await?,
     )
   }
 
-  /// Read the index from the key.
+  /// Read the index from the key. If indexes are cached, the cached index is returned when the
+  /// size of the data file has not changed, so the index is shared rather than owned.
   #[instrument(level = "trace", skip(self))]
-  async fn read_index(&self, query: &Query) -> Result<Index> {
+  async fn read_index(&self, query: &Query) -> Result<Arc<Index>> {
     trace!("reading index");
-    let storage = self
-      .get_storage()
-      .get(
-        &query.format().fmt_index(query.id()),
-        GetOptions::new_with_default_range(query.request().headers()),
-      )
-      .await?;
-    Self::read_index_inner(storage)
-      .await
-      .map_err(|err| HtsGetError::io_error(format!("reading {} index: {}", self.get_format(), err)))
+    let keys = self.index_keys(query);
+
+    let size = match self.index_cache() {
+      Some(_) => Some(
+        self
+          .get_storage()
+          .head(
+            &query.format().fmt_file(query.id()),
+            HeadOptions::new(query.request().headers()),
+          )
+          .await?,
+      ),
+      None => None,
+    };
+
+    for key in &keys {
+      if let (Some(cache), Some(size)) = (self.index_cache(), size) {
+        if let Some(index) = cache.get(key, self.get_format(), size) {
+          trace!(key, "using cached index");
+          return Ok(index);
+        }
+      }
+
+      let storage = match self
+        .get_storage()
+        .index_storage()
+        .get(
+          key,
+          GetOptions::new_with_default_range(query.request().headers()),
+        )
+        .await
+        .map_err(HtsGetError::from)
+      {
+        Ok(storage) => storage,
+        Err(HtsGetError::NotFound(_)) => continue,
+        Err(err) => return Err(err),
+      };
+
+      let index = Arc::new(Self::read_index_inner(storage).await.map_err(|err| {
+        HtsGetError::io_error(format!("reading {} index: {}", self.get_format(), err))
+      })?);
+
+      if let (Some(cache), Some(size)) = (self.index_cache(), size) {
+        cache.insert(key, self.get_format(), size, index.clone());
+      }
+
+      return Ok(index);
+    }
+
+    Err(HtsGetError::not_found(format!(
+      "{} index for `{}` not found, tried: {}",
+      self.get_format(),
+      query.index_id(),
+      keys.join(", ")
+    )))
+  }
+
+  /// Get the keys that the index may be found at, in the order that they are tried.
+  fn index_keys(&self, query: &Query) -> Vec<String> {
+    let mut keys = vec![query.format().fmt_index(query.index_id())];
+    keys.extend(
+      self
+        .options()
+        .index_extensions()
+        .iter()
+        .map(|extension| format!("{}{extension}", query.index_id())),
+    );
+    keys
   }
 
   /// Search based on the query.
   async fn search(&mut self, query: Query) -> Result<Response> {
+    Self::validate_fields(&query)?;
+
     match query.class() {
       Body => {
         let format = self.get_format();
@@ -261,36 +463,60 @@ where
           )));
         }
 
-        let index = self.read_index(&query).await?;
+        let start = Instant::now();
+        let index = self.read_index(&query).await;
+        let mut server_timing = ServerTiming::default().with_index(start.elapsed());
+
+        let index = match index {
+          Ok(index) => index,
+          Err(HtsGetError::NotFound(err)) => {
+            return match self.whole_file_fallback(&query).await? {
+              Some(response) => Ok(response),
+              None => Err(HtsGetError::NotFound(err)),
+            };
+          }
+          Err(err) => return Err(err),
+        };
         let header_end = self.get_header_end_offset(&index).await?;
 
         self.preprocess(&query, header_end).await?;
+        self.validate_file(&query).await?;
 
-        let mut byte_ranges = match query.reference_name().as_ref() {
+        let byte_ranges = match query.reference_name().as_ref() {
           None => self.get_byte_ranges_for_all(&query).await?,
           Some(reference_name) => {
-            let (header, mut reader) = self.get_header(&query, header_end).await?;
+            let (header, reader) = self.get_header(&query, header_end).await?;
+            let reference_name = self.normalize_reference_name(&header, reference_name);
+
+            if self.options().validate_reference_names()
+              && !Self::header_contains_reference_name(&header, &reference_name)
+            {
+              return Err(HtsGetError::not_found(format!(
+                "reference name `{}` not found in `{}` header",
+                reference_name,
+                self.get_format()
+              )));
+            }
+
+            let header_byte_ranges = self
+              .get_byte_ranges_for_header(&index, reader, &query)
+              .await?;
 
             let mut byte_ranges = self
-              .get_byte_ranges_for_reference_name(
-                reference_name.to_string(),
-                &index,
-                &header,
-                &query,
-              )
+              .get_byte_ranges_for_reference_name(reference_name, &index, &header, &query)
               .await?;
-
-            byte_ranges.push(
-              self
-                .get_byte_ranges_for_header(&index, &mut reader, &query)
-                .await?,
-            );
+            byte_ranges.push(header_byte_ranges);
 
             byte_ranges
           }
         };
 
+        let start = Instant::now();
         let file_size = self.file_size(&query).await?;
+        server_timing = server_timing.with_head(start.elapsed());
+
+        let mut byte_ranges =
+          check_ranges_within_file(&query, self.options(), byte_ranges, file_size)?;
         if let Some(eof) = self.get_eof_byte_positions(file_size) {
           byte_ranges.push(eof?);
         }
@@ -299,37 +525,112 @@ where
           .get_storage()
           .postprocess(
             &query.format().fmt_file(query.id()),
-            BytesPositionOptions::new(byte_ranges, query.request().headers()),
+            BytesPositionOptions::new(byte_ranges, query.request().headers())
+              .with_preserve_classes(self.options().preserve_classes())
+              .with_merge_gap(self.options().merge_gap().unwrap_or_default()),
           )
           .await?;
 
-        self.build_response(&query, blocks).await
+        self
+          .build_response(&query, blocks)
+          .await
+          .map(|response| self.with_server_timing(response, server_timing))
       }
       Class::Header => {
-        let index = self.read_index(&query).await?;
+        let start = Instant::now();
+        let index = match self.read_index(&query).await {
+          Ok(index) => index,
+          Err(HtsGetError::NotFound(_)) if self.options().header_without_index() => {
+            return self.search_header_without_index(&query).await;
+          }
+          Err(err) => return Err(err),
+        };
+        let server_timing = ServerTiming::default().with_index(start.elapsed());
+
         let header_end = self.get_header_end_offset(&index).await?;
 
         self.preprocess(&query, header_end).await?;
+        self.validate_file(&query).await?;
 
-        let (_, mut reader) = self.get_header(&query, header_end).await?;
+        let (_, reader) = self.get_header(&query, header_end).await?;
 
         let header_byte_ranges = self
-          .get_byte_ranges_for_header(&index, &mut reader, &query)
+          .get_byte_ranges_for_header(&index, reader, &query)
           .await?;
 
         let blocks = self
           .get_storage()
           .postprocess(
             &query.format().fmt_file(query.id()),
-            BytesPositionOptions::new(vec![header_byte_ranges], query.request().headers()),
+            BytesPositionOptions::new(vec![header_byte_ranges], query.request().headers())
+              .with_preserve_classes(self.options().preserve_classes()),
           )
           .await?;
 
-        self.build_response(&query, blocks).await
+        self
+          .build_response(&query, blocks)
+          .await
+          .map(|response| self.with_server_timing(response, server_timing))
       }
     }
   }
 
+  /// Set the server timing of the response, if the search records server timings.
+  fn with_server_timing(&self, response: Response, server_timing: ServerTiming) -> Response {
+    response.with_server_timing(self.options().server_timing().then_some(server_timing))
+  }
+
+  /// Get a response for the header of a file without an index, by reading the header from the
+  /// start of the file.
+  #[instrument(level = "trace", skip(self))]
+  async fn search_header_without_index(&mut self, query: &Query) -> Result<Response> {
+    debug!(id = query.id(), "index not found, reading the header");
+    let file_size = self.file_size(query).await?;
+
+    self.preprocess(query, file_size).await?;
+    self.validate_file(query).await?;
+
+    let (_, reader) = self.get_header(query, file_size).await?;
+
+    let header_byte_ranges = self
+      .get_byte_ranges_for_header_without_index(reader, query)
+      .await?;
+
+    let blocks = self
+      .get_storage()
+      .postprocess(
+        &query.format().fmt_file(query.id()),
+        BytesPositionOptions::new(vec![header_byte_ranges], query.request().headers())
+          .with_preserve_classes(self.options().preserve_classes()),
+      )
+      .await?;
+
+    self.build_response(query, blocks).await
+  }
+
+  /// Get a response containing the whole file if the search allows falling back to the whole file
+  /// when the index is missing, and the file is small enough.
+  #[instrument(level = "trace", skip(self))]
+  async fn whole_file_fallback(&self, query: &Query) -> Result<Option<Response>> {
+    let Some(max_size) = self.options().whole_file_fallback_max_size() else {
+      return Ok(None);
+    };
+
+    let file_size = self.file_size(query).await?;
+    if file_size > max_size {
+      return Ok(None);
+    }
+
+    debug!(
+      id = query.id(),
+      file_size, "index not found, returning the whole file"
+    );
+    self
+      .build_response(query, vec![DataBlock::Range(BytesPosition::default())])
+      .await
+      .map(Some)
+  }
+
   async fn preprocess(&mut self, query: &Query, header_end: u64) -> Result<()> {
     Ok(
       self
@@ -363,8 +664,49 @@ where
     trace!("building response");
     let mut urls = vec![];
     let storage = self.get_storage();
+    let options = self.options();
 
-    for block in DataBlock::update_classes(byte_ranges) {
+    let mut blocks = DataBlock::update_classes(byte_ranges);
+    if let Some(max_urls_per_ticket) = options.max_urls_per_ticket() {
+      blocks = DataBlock::coalesce_ranges(blocks, max_urls_per_ticket);
+    }
+    if let Some(max_bytes_per_url) = options.max_bytes_per_url() {
+      blocks = DataBlock::split_ranges(
+        blocks,
+        max_bytes_per_url,
+        options.min_block_size().unwrap_or_default(),
+      );
+    }
+    debug!(
+      id = query.id(),
+      count = blocks.len(),
+      total_bytes = blocks.iter().filter_map(DataBlock::byte_count).sum::<u64>(),
+      "byte range summary"
+    );
+
+    let range_bytes = blocks
+      .iter()
+      .filter(|block| matches!(block, DataBlock::Range(_)))
+      .filter_map(DataBlock::byte_count)
+      .sum::<u64>();
+    if let Some(max_total_response_bytes) = options.max_total_response_bytes() {
+      if range_bytes > max_total_response_bytes {
+        return Err(HtsGetError::PayloadTooLarge(format!(
+          "the response for `{}` would contain {range_bytes} bytes, which exceeds the maximum of \
+          {max_total_response_bytes} bytes, try narrowing the query using a reference name or range",
+          query.id()
+        )));
+      }
+    }
+    let total_size = options.include_total_size().then_some(range_bytes);
+    let mut content_ranges = options
+      .include_content_range()
+      .then(|| content_ranges(&blocks))
+      .flatten()
+      .map(Vec::into_iter);
+
+    for block in blocks {
+      let content_range = content_ranges.as_mut().and_then(Iterator::next);
       match block {
         DataBlock::Range(range) => {
           trace!(range = ?range, "range");
@@ -376,17 +718,40 @@ where
                 &query_owned.format().fmt_file(query_owned.id()),
                 RangeUrlOptions::new(range, query_owned.request().headers()),
               )
-              .await?,
+              .await?
+              .with_content_range(content_range),
           );
         }
         DataBlock::Data(data, class) => {
-          let data_url = self.get_storage().data_url(data, class);
+          let data_url = self
+            .get_storage()
+            .data_url(data, class)
+            .with_content_range(content_range);
           urls.push(data_url);
         }
       }
     }
 
-    Ok(Response::new(query.format(), urls))
+    let sender_public_key = if options.include_sender_public_key() {
+      self.get_storage().sender_public_key()?
+    } else {
+      None
+    };
+
+    let etag = storage
+      .etag(
+        &query.format().fmt_file(query.id()),
+        &self.index_keys(query),
+        HeadOptions::new(query.request().headers()),
+      )
+      .await?;
+
+    Ok(
+      Response::new(query.format(), urls)
+        .with_total_size(total_size)
+        .with_sender_public_key(sender_public_key)
+        .with_etag(etag),
+    )
   }
 
   /// Get the header from the file specified by the id and format.
@@ -427,10 +792,40 @@ where
 pub trait BgzfSearch<I, Reader, Header>:
   Search<ReferenceSequence<I>, Index<I>, Reader, Header>
 where
-  I: reference_sequence::Index + Send + Sync,
+  I: reference_sequence::Index + Clone + Send + Sync + 'static,
   Reader: Send + Sync,
   Header: Send + Sync,
 {
+  /// Count the bins of the reference sequence which overlap the interval. These are the bins which
+  /// are scanned for chunks when querying the index.
+  fn overlapping_bins(index: &Index<I>, ref_seq_id: usize, interval: Interval) -> usize {
+    let Some(reference_sequence) = index.reference_sequences().get(ref_seq_id) else {
+      return 0;
+    };
+
+    let min_shift = usize::from(index.min_shift());
+    let depth = usize::from(index.depth());
+    let max_position = 1usize << (min_shift + 3 * depth);
+    let start = interval
+      .start()
+      .map_or(0, |start| start as usize)
+      .min(max_position - 1);
+    let end = interval
+      .end()
+      .map_or(max_position, |end| end as usize)
+      .clamp(start + 1, max_position);
+
+    (0..=depth)
+      .map(|level| {
+        let shift = min_shift + 3 * (depth - level);
+        let offset = ((1 << (3 * level)) - 1) / 7;
+        ((start >> shift)..=((end - 1) >> shift))
+          .filter(|bin| reference_sequence.bins().contains_key(&(offset + bin)))
+          .count()
+      })
+      .sum()
+  }
+
   #[instrument(level = "trace", skip_all)]
   fn index_positions(index: &Index<I>) -> BTreeSet<u64> {
     trace!("getting possible index positions");
@@ -482,10 +877,21 @@ where
       Ok(chunks)
     });
 
+    if let (true, Ok(chunks)) = (self.options().log_index_scan(), &chunks) {
+      info!(
+        id = query.id(),
+        ref_seq_id,
+        bins = Self::overlapping_bins(index, ref_seq_id, query.interval()),
+        chunks = chunks.len(),
+        "index scan summary"
+      );
+    }
+
     let gzi_data = self
       .get_storage()
+      .index_storage()
       .get(
-        &query.format().fmt_gzi(query.id())?,
+        &query.format().fmt_gzi(query.index_id())?,
         GetOptions::new_with_default_range(query.request().headers()),
       )
       .await;
@@ -578,13 +984,100 @@ where
     Ok(bytes_positions)
   }
 
-  /// Get unmapped bytes ranges.
+  /// Get unmapped bytes ranges. By default, these are the bytes after the last chunk in the index.
+  /// If the last chunk ends inside a BGZF block, that block still contains placed records, so the
+  /// range starts at the next block.
   async fn get_byte_ranges_for_unmapped(
     &self,
-    _query: &Query,
-    _index: &Index<I>,
+    query: &Query,
+    index: &Index<I>,
   ) -> Result<Vec<BytesPosition>> {
-    Ok(Vec::new())
+    let last_chunk_end = index
+      .reference_sequences()
+      .iter()
+      .flat_map(|ref_seq| ref_seq.bins())
+      .flat_map(|(_, bin)| bin.chunks())
+      .map(|chunk| chunk.end())
+      .max();
+    let start = match last_chunk_end {
+      Some(end) if end.uncompressed() == 0 => end.compressed(),
+      Some(end) => self.block_end_position(query, end.compressed()).await?,
+      None => self.get_header_end_offset(index).await?,
+    };
+
+    Ok(vec![BytesPosition::default()
+      .with_start(start)
+      .with_end(self.position_at_eof(query).await?)
+      .with_class(Body)])
+  }
+
+  /// Get the byte ranges for the `*` reference name of variants, which are handled according to
+  /// the unmapped variants option of the search.
+  async fn get_byte_ranges_for_unmapped_variants(
+    &self,
+    query: &Query,
+    index: &Index<I>,
+  ) -> Result<Vec<BytesPosition>> {
+    match self.options().unmapped_variants() {
+      UnmappedVariants::Reject => Err(HtsGetError::InvalidInput(format!(
+        "the `*` reference name is not supported for `{}`",
+        self.get_format()
+      ))),
+      UnmappedVariants::Unplaced => self.get_byte_ranges_for_unmapped(query, index).await,
+    }
+  }
+
+  /// Get the compressed position of the end of the BGZF block starting at the position, by reading
+  /// the block size from the block header.
+  #[instrument(level = "trace", skip(self), ret)]
+  async fn block_end_position(&self, query: &Query, position: u64) -> Result<u64> {
+    let key = query.format().fmt_file(query.id());
+    let get_options = GetOptions::new(
+      BytesPosition::default()
+        .with_start(position)
+        .with_end(position + BGZF_HEADER_LEN),
+      query.request().headers(),
+    );
+
+    let mut header = Vec::new();
+    self
+      .get_storage()
+      .get(&key, get_options)
+      .await?
+      .take(BGZF_HEADER_LEN)
+      .read_to_end(&mut header)
+      .await
+      .map_err(|err| HtsGetError::io_error(format!("reading `{key}`: {err}")))?;
+
+    match header.as_slice() {
+      [.., bsize_low, bsize_high]
+        if header.len() as u64 == BGZF_HEADER_LEN && is_bgzf_header(&header) =>
+      {
+        Ok(position + u64::from(u16::from_le_bytes([*bsize_low, *bsize_high])) + 1)
+      }
+      _ => Err(HtsGetError::io_error(format!(
+        "invalid BGZF block header at `{position}` in `{key}`"
+      ))),
+    }
+  }
+
+  /// Get the compressed position of the start of the BGZF block after the current position of the
+  /// reader. Returns zero if the position cannot be determined.
+  async fn next_block_position(&self, reader: &mut Reader) -> u64 {
+    let current_block_index = self.virtual_position(reader);
+
+    if current_block_index.uncompressed() == 0 {
+      current_block_index.compressed()
+    } else {
+      loop {
+        let bytes_read = Self::read_bytes(reader).await.unwrap_or_default();
+        let actual_block_index = self.virtual_position(reader).compressed();
+
+        if bytes_read == 0 || actual_block_index > current_block_index.compressed() {
+          break actual_block_index;
+        }
+      }
+    }
   }
 
   /// Get the virtual position of the underlying reader.
@@ -597,7 +1090,7 @@ where
 #[async_trait]
 impl<I, Reader, Header, T> SearchAll<ReferenceSequence<I>, Index<I>, Reader, Header> for T
 where
-  I: reference_sequence::Index + Send + Sync,
+  I: reference_sequence::Index + Clone + Send + Sync + 'static,
   Reader: Send + Sync,
   Header: Send + Sync,
   T: BgzfSearch<I, Reader, Header> + Send + Sync,
@@ -630,23 +1123,11 @@ where
   async fn get_byte_ranges_for_header(
     &self,
     index: &Index<I>,
-    reader: &mut Reader,
+    mut reader: Reader,
     query: &Query,
   ) -> Result<BytesPosition> {
-    let current_block_index = self.virtual_position(reader);
-
-    let mut next_block_index = if current_block_index.uncompressed() == 0 {
-      current_block_index.compressed()
-    } else {
-      loop {
-        let bytes_read = Self::read_bytes(reader).await.unwrap_or_default();
-        let actual_block_index = self.virtual_position(reader).compressed();
-
-        if bytes_read == 0 || actual_block_index > current_block_index.compressed() {
-          break actual_block_index;
-        }
-      }
-    };
+    let mut next_block_index = self.next_block_position(&mut reader).await;
+    drop(reader);
 
     next_block_index = if next_block_index == 0 {
       // if for some reason that fails, get the second position from the index.
@@ -673,6 +1154,27 @@ where
     )
   }
 
+  async fn get_byte_ranges_for_header_without_index(
+    &self,
+    mut reader: Reader,
+    query: &Query,
+  ) -> Result<BytesPosition> {
+    let next_block_position = self.next_block_position(&mut reader).await;
+    drop(reader);
+
+    let next_block_index = match next_block_position {
+      0 => self.position_at_eof(query).await?,
+      position => position,
+    };
+
+    Ok(
+      BytesPosition::default()
+        .with_start(0)
+        .with_end(next_block_index)
+        .with_class(Header),
+    )
+  }
+
   fn get_eof_marker(&self) -> &[u8] {
     BGZF_EOF
   }
@@ -13,14 +13,15 @@ use noodles::tabix::Index;
 use noodles::vcf;
 use noodles::vcf::Header;
 use tokio::io;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tracing::{instrument, trace};
 
 use htsget_config::types::HtsGetError;
 
-use crate::search::{find_first, BgzfSearch, Search};
+use crate::search::{find_first, is_bgzf_header, BgzfSearch, Search, BGZF_HEADER_LEN};
 use crate::{Format, Query, Result};
-use htsget_storage::types::BytesPosition;
+use htsget_config::types::SearchOptions;
+use htsget_storage::types::{BytesPosition, GetOptions};
 use htsget_storage::{Storage, Streamable};
 
 type AsyncReader = vcf::AsyncReader<bgzf::AsyncReader<Streamable>>;
@@ -28,6 +29,7 @@ type AsyncReader = vcf::AsyncReader<bgzf::AsyncReader<Streamable>>;
 /// Allows searching through vcf files.
 pub struct VcfSearch {
   storage: Storage,
+  options: SearchOptions,
 }
 
 #[async_trait]
@@ -64,6 +66,12 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for VcfS
     query: &Query,
   ) -> Result<Vec<BytesPosition>> {
     trace!("getting byte ranges for reference name");
+    if reference_name == "*" {
+      return self
+        .get_byte_ranges_for_unmapped_variants(query, index)
+        .await;
+    }
+
     // We are assuming the order of the names and the references sequences
     // in the index is the same
     let mut futures = FuturesOrdered::new();
@@ -97,10 +105,51 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for VcfS
     Ok(byte_ranges)
   }
 
+  fn header_contains_reference_name(header: &Header, reference_name: &str) -> bool {
+    reference_name == "*" || header.contigs().contains_key(reference_name)
+  }
+
+  /// Check that the file is BGZF compressed, rather than plain gzip compressed, if BGZF validation
+  /// is enabled.
+  #[instrument(level = "trace", skip(self))]
+  async fn validate_file(&self, query: &Query) -> Result<()> {
+    if !self.options().validate_bgzf() {
+      return Ok(());
+    }
+
+    let key = query.format().fmt_file(query.id());
+    let get_options = GetOptions::new(
+      BytesPosition::default().with_end(BGZF_HEADER_LEN),
+      query.request().headers(),
+    );
+
+    let mut header = Vec::new();
+    self
+      .get_storage()
+      .get(&key, get_options)
+      .await?
+      .take(BGZF_HEADER_LEN)
+      .read_to_end(&mut header)
+      .await
+      .map_err(|err| HtsGetError::io_error(format!("reading `{key}`: {err}")))?;
+
+    if is_bgzf_header(&header) {
+      Ok(())
+    } else {
+      Err(HtsGetError::unsupported_format(format!(
+        "`{key}` is not BGZF compressed"
+      )))
+    }
+  }
+
   fn get_storage(&self) -> &Storage {
     &self.storage
   }
 
+  fn options(&self) -> &SearchOptions {
+    &self.options
+  }
+
   fn mut_storage(&mut self) -> &mut Storage {
     &mut self.storage
   }
@@ -113,13 +162,23 @@ impl Search<ReferenceSequence<LinearIndex>, Index, AsyncReader, Header> for VcfS
 impl VcfSearch {
   /// Create the vcf search.
   pub fn new(storage: Storage) -> Self {
-    Self { storage }
+    Self {
+      storage,
+      options: Default::default(),
+    }
+  }
+
+  /// Set the options which control how the query is searched.
+  pub fn with_options(mut self, options: SearchOptions) -> Self {
+    self.options = options;
+    self
   }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
   use htsget_config::types::Class::Body;
+  use htsget_config::types::UnmappedVariants;
   use htsget_test::http::concat::ConcatResponse;
   use std::future::Future;
 
@@ -132,7 +191,8 @@ pub(crate) mod tests {
   #[cfg(feature = "experimental")]
   use {
     crate::from_storage::tests::with_local_storage_c4gh,
-    htsget_storage::c4gh::storage::C4GHStorage, htsget_test::c4gh::get_decryption_keys,
+    htsget_storage::c4gh::storage::C4GHStorage,
+    htsget_test::c4gh::{get_decryption_keys, get_sender_public_key},
   };
 
   const VCF_LOCATION: &str = "data/vcf";
@@ -184,6 +244,39 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn search_reference_name_normalize_chr_prefix() {
+    with_local_storage(|storage| async move {
+      let mut search = VcfSearch::new(storage.clone())
+        .with_options(SearchOptions::default().with_normalize_chr_prefix(true));
+      let mut validating_search = VcfSearch::new(storage)
+        .with_options(SearchOptions::default().with_validate_reference_names(true));
+
+      for (filename, reference_name, normalized) in [
+        ("spec-v4.3", "chr20", "20"),
+        ("sample1-bcbio-cancer", "M", "chrM"),
+      ] {
+        let query =
+          Query::new_with_default_request(filename, Format::Vcf).with_reference_name(normalized);
+        let expected_response = search.search(query.clone()).await;
+        assert!(expected_response.is_ok());
+
+        let response = search
+          .search(query.clone().with_reference_name(reference_name))
+          .await;
+        assert_eq!(response, expected_response);
+
+        let response = validating_search
+          .search(query.with_reference_name(reference_name))
+          .await;
+        assert!(matches!(response, Err(NotFound(_))));
+      }
+
+      None
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn search_reference_name_with_seq_range() {
     with_local_storage(|storage| async move { test_reference_name_with_seq_range(storage).await })
@@ -303,6 +396,192 @@ pub(crate) mod tests {
     .await
   }
 
+  #[tokio::test]
+  async fn search_unmapped_variants_reject() {
+    with_local_storage(|storage| async move {
+      let mut search = VcfSearch::new(storage)
+        .with_options(SearchOptions::default().with_validate_reference_names(true));
+      let query =
+        Query::new_with_default_request("spec-v4.3", Format::Vcf).with_reference_name("*");
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      assert_eq!(
+        response,
+        Err(HtsGetError::InvalidInput(
+          "the `*` reference name is not supported for `VCF`".to_string()
+        ))
+      );
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_unmapped_variants_unplaced() {
+    with_local_storage(|storage| async move {
+      let mut search = VcfSearch::new(storage)
+        .with_options(SearchOptions::default().with_unmapped_variants(UnmappedVariants::Unplaced));
+      let filename = "spec-v4.3";
+      let query = Query::new_with_default_request(filename, Format::Vcf).with_reference_name("*");
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      let expected_response = Ok(Response::new(
+        Format::Vcf,
+        vec![Url::new(expected_url(filename))
+          .with_headers(Headers::default().with_header("Range", "bytes=0-850"))],
+      ));
+      assert_eq!(response, expected_response);
+
+      Some((
+        VCF_FILE_NAME_SPEC.to_string(),
+        (response.unwrap(), Body).into(),
+      ))
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_unmapped_variants_block_end_position() {
+    with_local_storage(|storage| async move {
+      let search = VcfSearch::new(storage);
+      let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+
+      // All records are in the first block, which is followed by the end-of-file marker.
+      assert_eq!(search.block_end_position(&query, 0).await, Ok(823));
+      assert!(search.block_end_position(&query, 1).await.is_err());
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_validate_non_existent_reference_name() {
+    with_local_storage(|storage| async move {
+      let mut search = VcfSearch::new(storage)
+        .with_options(SearchOptions::default().with_validate_reference_names(true));
+      let query =
+        Query::new_with_default_request("spec-v4.3", Format::Vcf).with_reference_name("bogus");
+      let response = search.search(query).await;
+      println!("{response:#?}");
+
+      assert_eq!(
+        response,
+        Err(NotFound(
+          "reference name `bogus` not found in `VCF` header".to_string()
+        ))
+      );
+
+      None
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn search_plain_gzip() {
+    with_local_storage_fn(
+      |storage| async move {
+        let mut search =
+          VcfSearch::new(storage).with_options(SearchOptions::default().with_validate_bgzf(true));
+        let query = Query::new_with_default_request("spec-v4.3-gzip", Format::Vcf);
+        let response = search.search(query).await;
+        println!("{response:#?}");
+
+        assert_eq!(
+          response,
+          Err(HtsGetError::UnsupportedFormat(
+            "`spec-v4.3-gzip.vcf.gz` is not BGZF compressed".to_string()
+          ))
+        );
+
+        None
+      },
+      VCF_LOCATION,
+      &["spec-v4.3-gzip.vcf.gz", "spec-v4.3-gzip.vcf.gz.tbi"],
+    )
+    .await
+  }
+
+  #[tokio::test]
+  async fn search_whole_file_fallback_missing_index() {
+    with_local_storage_fn(
+      |storage| async move {
+        let mut search = VcfSearch::new(storage)
+          .with_options(SearchOptions::default().with_whole_file_fallback_max_size(Some(1024)));
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+        let response = search.search(query).await;
+        println!("{response:#?}");
+
+        assert_eq!(
+          response,
+          Ok(Response::new(
+            Format::Vcf,
+            vec![Url::new(expected_url("spec-v4.3"))]
+          ))
+        );
+
+        None
+      },
+      VCF_LOCATION,
+      &[VCF_FILE_NAME_SPEC],
+    )
+    .await
+  }
+
+  #[tokio::test]
+  async fn search_whole_file_fallback_above_max_size() {
+    with_local_storage_fn(
+      |storage| async move {
+        let mut search = VcfSearch::new(storage)
+          .with_options(SearchOptions::default().with_whole_file_fallback_max_size(Some(100)));
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+        let response = search.search(query).await;
+        assert!(matches!(response, Err(NotFound(_))));
+
+        None
+      },
+      VCF_LOCATION,
+      &[VCF_FILE_NAME_SPEC],
+    )
+    .await
+  }
+
+  #[tokio::test]
+  async fn search_header_without_index() {
+    with_local_storage_fn(
+      |storage| async move {
+        let mut search = VcfSearch::new(storage)
+          .with_options(SearchOptions::default().with_header_without_index(true));
+        let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+
+        let response = search.search(query.clone()).await;
+        assert!(matches!(response, Err(NotFound(_))));
+
+        let response = search.search(query.with_class(Header)).await;
+        assert_eq!(
+          response,
+          Ok(Response::new(
+            Format::Vcf,
+            vec![Url::new(expected_url("spec-v4.3"))
+              .with_headers(Headers::default().with_header("Range", "bytes=0-822"))
+              .with_class(Header)],
+          ))
+        );
+
+        Some((
+          VCF_FILE_NAME_SPEC.to_string(),
+          (response.unwrap(), Header).into(),
+        ))
+      },
+      VCF_LOCATION,
+      &[VCF_FILE_NAME_SPEC],
+    )
+    .await
+  }
+
   #[tokio::test]
   async fn search_header_with_non_existent_reference_name() {
     with_local_storage(|storage| async move {
@@ -429,6 +708,23 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[cfg(feature = "experimental")]
+  #[tokio::test]
+  async fn search_all_c4gh_sender_public_key() {
+    with_local_storage_c4gh(|storage| async move {
+      let storage = C4GHStorage::new(get_decryption_keys().await, storage);
+      let mut search = VcfSearch::new(Storage::new(storage))
+        .with_options(SearchOptions::default().with_include_sender_public_key(true));
+      let query = Query::new_with_default_request("spec-v4.3", Format::Vcf);
+      let response = search.search(query).await.unwrap();
+
+      assert_eq!(response.sender_public_key, Some(get_sender_public_key()));
+
+      Some(("spec-v4.3.vcf.gz.c4gh".to_string(), (response, Body).into()))
+    })
+    .await;
+  }
+
   async fn test_reference_name_with_seq_range(
     storage: Storage,
   ) -> Option<(String, ConcatResponse)> {
@@ -0,0 +1,374 @@
+//! Module providing an implementation for the [StorageTrait] trait using Azure Blob Storage.
+//!
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use http::header::{CONTENT_LENGTH, RANGE};
+use http::{Method, StatusCode, Uri};
+use reqwest::{Client, ClientBuilder};
+use tokio_util::io::StreamReader;
+use tracing::{debug, instrument};
+
+use htsget_config::storage::azure::AzureAccountKey;
+use htsget_config::storage::uri_encode;
+
+use crate::types::BytesRange;
+use crate::StorageError::{InternalError, InvalidUri, KeyNotFound, ResponseError};
+use crate::{
+  BytesPosition, GetOptions, HeadOptions, RangeUrlOptions, Result, StorageMiddleware, StorageTrait,
+  Streamable, Url,
+};
+
+/// Implementation for the [StorageTrait] trait using Azure Blob Storage. Blobs are read using
+/// read-only service SAS urls when an account key is set.
+#[derive(Debug, Clone)]
+pub struct AzureBlobStorage {
+  client: Client,
+  endpoint: Uri,
+  account: String,
+  container: String,
+  account_key: Option<AzureAccountKey>,
+  url_expiry: Duration,
+}
+
+impl AzureBlobStorage {
+  /// The default number of seconds that SAS urls are valid for.
+  pub const SAS_URL_EXPIRY: u64 = 1000;
+
+  /// Create a new Azure Blob Storage storage.
+  pub fn new(
+    client: Client,
+    endpoint: Uri,
+    account: String,
+    container: String,
+    account_key: Option<AzureAccountKey>,
+  ) -> Self {
+    Self {
+      client,
+      endpoint,
+      account,
+      container,
+      account_key,
+      url_expiry: Duration::from_secs(Self::SAS_URL_EXPIRY),
+    }
+  }
+
+  /// Create a new Azure Blob Storage storage with a default client.
+  pub fn new_with_default_config(
+    account: String,
+    container: String,
+    endpoint: String,
+    account_key: Option<AzureAccountKey>,
+  ) -> Result<Self> {
+    let endpoint = endpoint
+      .parse::<Uri>()
+      .map_err(|err| InvalidUri(err.to_string()))?;
+    let client = ClientBuilder::new()
+      .build()
+      .map_err(|err| InternalError(format!("failed to build reqwest client: {}", err)))?;
+
+    Ok(Self::new(client, endpoint, account, container, account_key))
+  }
+
+  /// Set the time that SAS urls are valid for.
+  pub fn with_url_expiry(mut self, url_expiry: Duration) -> Self {
+    self.url_expiry = url_expiry;
+    self
+  }
+
+  /// Get the url of the blob for the key, which is signed with a SAS token if the account key
+  /// is set.
+  pub fn blob_url(&self, key: &str) -> String {
+    let url = format!(
+      "{}/{}/{}",
+      self.endpoint.to_string().trim_end_matches('/'),
+      self.container,
+      uri_encode(key, false)
+    );
+
+    match &self.account_key {
+      Some(account_key) => format!(
+        "{url}?{}",
+        account_key.sas_query(&self.account, &self.container, key, self.url_expiry)
+      ),
+      None => url,
+    }
+  }
+
+  /// Send a request with the method for the key, requesting only the range if it is set.
+  async fn send_request(
+    &self,
+    key: &str,
+    method: Method,
+    range: Option<&BytesPosition>,
+  ) -> Result<reqwest::Response> {
+    let mut request = self.client.request(method, self.blob_url(key));
+
+    let range = range
+      .map(|range| String::from(&BytesRange::from(range)))
+      .unwrap_or_default();
+    if !range.is_empty() {
+      request = request.header(RANGE, range);
+    }
+
+    let response = request
+      .send()
+      .await
+      .map_err(|err| ResponseError(format!("{} with key {}", err, key)))?;
+
+    match response.status() {
+      status if status.is_success() => Ok(response),
+      StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => Err(KeyNotFound(key.to_string())),
+      status => Err(ResponseError(format!(
+        "azure returned {} for key {}",
+        status, key
+      ))),
+    }
+  }
+}
+
+#[async_trait]
+impl StorageMiddleware for AzureBlobStorage {}
+
+#[async_trait]
+impl StorageTrait for AzureBlobStorage {
+  /// Gets the blob as a buffered reader, using a ranged blob read.
+  #[instrument(level = "trace", skip(self))]
+  async fn get(&self, key: &str, options: GetOptions<'_>) -> Result<Streamable> {
+    debug!(calling_from = ?self, key, "getting file with key {:?}", key);
+
+    let response = self
+      .send_request(key, Method::GET, Some(options.range()))
+      .await?;
+
+    Ok(Streamable::from_async_read(StreamReader::new(Box::pin(
+      response
+        .bytes_stream()
+        .map_err(|err| ResponseError(format!("reading body from response: {}", err))),
+    ))))
+  }
+
+  /// Return a SAS url to the blob.
+  #[instrument(level = "trace", skip(self))]
+  async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> Result<Url> {
+    let url = self.blob_url(key);
+    debug!(calling_from = ?self, key, ?url, "getting url with key {:?}", key);
+
+    Ok(options.apply(Url::new(url)))
+  }
+
+  /// Returns the size of the blob in bytes, using the blob properties.
+  #[instrument(level = "trace", skip(self))]
+  async fn head(&self, key: &str, _options: HeadOptions<'_>) -> Result<u64> {
+    let head = self.send_request(key, Method::HEAD, None).await?;
+
+    let len = head
+      .headers()
+      .get(CONTENT_LENGTH)
+      .and_then(|content_length| content_length.to_str().ok())
+      .and_then(|content_length| content_length.parse().ok())
+      .ok_or_else(|| {
+        ResponseError(format!(
+          "failed to get content length from head response for key: {}",
+          key
+        ))
+      })?;
+
+    debug!(calling_from = ?self, key, len, "size of key {:?} is {}", key, len);
+    Ok(len)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::future::Future;
+  use std::result;
+  use std::str::FromStr;
+
+  use axum::body::Body;
+  use axum::middleware::Next;
+  use axum::response::Response;
+  use axum::{middleware, Router};
+  use base64::engine::general_purpose;
+  use base64::Engine;
+  use http::{HeaderMap, Request};
+  use tokio::io::AsyncReadExt;
+  use tokio::net::TcpListener;
+  use tower_http::services::ServeDir;
+
+  use crate::local::tests::create_local_test_files;
+  use crate::Headers;
+
+  use super::*;
+
+  fn test_key() -> AzureAccountKey {
+    general_purpose::STANDARD
+      .encode("secret")
+      .try_into()
+      .unwrap()
+  }
+
+  /// Reject requests which do not have a SAS token.
+  async fn test_sas(request: Request<Body>, next: Next) -> result::Result<Response, StatusCode> {
+    let query = request.uri().query().unwrap_or_default();
+
+    if query.contains("sp=r") && query.contains("sig=") {
+      Ok(next.run(request).await)
+    } else {
+      Err(StatusCode::FORBIDDEN)
+    }
+  }
+
+  async fn with_azure_test_server<F, Fut>(test: F)
+  where
+    F: FnOnce(AzureBlobStorage, String) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    let (_, base_path) = create_local_test_files().await;
+    let router = Router::new()
+      .nest_service("/container", ServeDir::new(base_path.path()))
+      .route_layer(middleware::from_fn(test_sas));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router.into_make_service()).await });
+
+    let url = format!("http://{}", addr);
+    test(
+      AzureBlobStorage::new(
+        Client::new(),
+        Uri::from_str(&url).unwrap(),
+        "account".to_string(),
+        "container".to_string(),
+        Some(test_key()),
+      ),
+      url,
+    )
+    .await;
+  }
+
+  #[test]
+  fn blob_url_sas() {
+    let storage = AzureBlobStorage::new_with_default_config(
+      "account".to_string(),
+      "container".to_string(),
+      "https://account.blob.core.windows.net".to_string(),
+      Some(test_key()),
+    )
+    .unwrap();
+
+    let url = storage.blob_url("folder/key 1.bam");
+    assert!(url.starts_with(
+      "https://account.blob.core.windows.net/container/folder/key%201.bam?sv=2022-11-02&sr=b&sp=r&se="
+    ));
+    assert!(url.contains("&sig="));
+  }
+
+  #[test]
+  fn blob_url_unsigned() {
+    let storage = AzureBlobStorage::new_with_default_config(
+      "account".to_string(),
+      "container".to_string(),
+      "https://account.blob.core.windows.net".to_string(),
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(
+      storage.blob_url("key1"),
+      "https://account.blob.core.windows.net/container/key1"
+    );
+  }
+
+  #[tokio::test]
+  async fn get_storage_range() {
+    with_azure_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let mut reader = storage
+        .get(
+          "folder/key2",
+          GetOptions::new(BytesPosition::new(Some(1), Some(3), None), &headers),
+        )
+        .await
+        .unwrap();
+
+      let mut response = vec![];
+      reader.read_to_end(&mut response).await.unwrap();
+      assert_eq!(response, b"al");
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_non_existing_key() {
+    with_azure_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let result = storage
+        .get(
+          "non-existing-key",
+          GetOptions::new_with_default_range(&headers),
+        )
+        .await;
+
+      assert!(matches!(result, Err(KeyNotFound(_))));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn range_url_storage() {
+    with_azure_test_server(|storage, url| async move {
+      let headers = HeaderMap::default();
+      let result = storage
+        .range_url(
+          "key1",
+          RangeUrlOptions::new(BytesPosition::new(Some(1), Some(3), None), &headers),
+        )
+        .await
+        .unwrap();
+
+      assert!(result
+        .url
+        .starts_with(&format!("{url}/container/key1?sv=2022-11-02&")));
+      assert_eq!(
+        result.headers,
+        Some(Headers::default().with_header("Range", "bytes=1-2"))
+      );
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn range_url_storage_default_range() {
+    with_azure_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let result = storage
+        .range_url("key1", RangeUrlOptions::new_with_default_range(&headers))
+        .await
+        .unwrap();
+
+      assert_eq!(result.headers, None);
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn head_storage() {
+    with_azure_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+
+      assert_eq!(
+        storage
+          .head("folder/key2", HeadOptions::new(&headers))
+          .await
+          .unwrap(),
+        6
+      );
+    })
+    .await;
+  }
+}
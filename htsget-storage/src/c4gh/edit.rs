@@ -2,11 +2,13 @@
 //!
 
 use crate::c4gh::DeserializedHeader;
+use crate::error::StorageError::InternalError;
 use crate::error::{Result, StorageError};
 use crypt4gh::error::Crypt4GHError;
 use crypt4gh::error::Crypt4GHError::InvalidPacketType;
 use crypt4gh::header::{encrypt, make_packet_data_edit_list, make_packet_data_enc, HeaderInfo};
 use crypt4gh::Keys;
+use std::cmp::min;
 use std::collections::HashSet;
 use tokio::io;
 
@@ -78,6 +80,7 @@ pub struct EditHeader<'a> {
   clamped_positions: Vec<ClampedPosition>,
   keys: &'a [Keys],
   current_header: &'a DeserializedHeader,
+  error_positions: bool,
 }
 
 impl<'a> EditHeader<'a> {
@@ -93,9 +96,31 @@ impl<'a> EditHeader<'a> {
       clamped_positions,
       keys,
       current_header,
+      error_positions: false,
     }
   }
 
+  /// Include the unencrypted positions of the query in edit list error messages.
+  pub fn with_error_positions(mut self, error_positions: bool) -> Self {
+    self.error_positions = error_positions;
+    self
+  }
+
+  /// Format an error message, appending the unencrypted positions if they should be included.
+  fn error_message(&self, message: &str) -> String {
+    if !self.error_positions {
+      return message.to_string();
+    }
+
+    let positions = self
+      .unencrypted_positions
+      .iter()
+      .map(|pos| format!("{}-{}", pos.start, pos.end))
+      .collect::<Vec<_>>()
+      .join(", ");
+    format!("{message}, for unencrypted positions: `{positions}`")
+  }
+
   /// Encrypt the header packet.
   pub fn encrypt_header_packet(&self, header_packet: Vec<u8>) -> Result<Vec<u8>> {
     Ok(
@@ -163,11 +188,37 @@ impl<'a> EditHeader<'a> {
     edit_list
   }
 
+  /// Verify the edit list by applying it to the decrypted data of the clamped positions, and
+  /// checking that the output matches the plaintext at the unencrypted positions. The plaintext
+  /// is expected to start at the beginning of the unencrypted object.
+  pub fn verify_edit_list(&self, clamped_data: &[u8], plaintext: &[u8]) -> Result<()> {
+    let expected = self
+      .unencrypted_positions
+      .iter()
+      .map(|pos| {
+        plaintext
+          .get(pos.start as usize..pos.end as usize)
+          .ok_or_else(|| {
+            InternalError(self.error_message("edit list position is outside the plaintext"))
+          })
+      })
+      .collect::<Result<Vec<_>>>()?
+      .concat();
+
+    if apply_edit_list(clamped_data, &self.create_edit_list()) != expected {
+      return Err(InternalError(
+        self.error_message("edit list does not match the expected plaintext"),
+      ));
+    }
+
+    Ok(())
+  }
+
   /// Add edit lists and return a header packet.
   pub fn reencrypt_header(self) -> Result<Header> {
     if self.current_header.contains_edit_list() {
       return Err(StorageError::IoError(
-        "edit lists already exist".to_string(),
+        self.error_message("edit lists already exist"),
         io::Error::other(Crypt4GHError::TooManyEditListPackets),
       ));
     }
@@ -226,6 +277,26 @@ impl<'a> EditHeader<'a> {
   }
 }
 
+/// Apply an edit list to data, alternately discarding and keeping the number of bytes in each
+/// edit. If the edit list ends with a discard, the remaining data is kept.
+pub fn apply_edit_list(data: &[u8], edit_list: &[u64]) -> Vec<u8> {
+  let mut output = vec![];
+  let mut position = 0;
+  for (i, edit) in edit_list.iter().enumerate() {
+    let end = min(position + *edit as usize, data.len());
+    if i % 2 == 1 {
+      output.extend_from_slice(&data[position..end]);
+    }
+    position = end;
+  }
+
+  if edit_list.len() % 2 == 1 {
+    output.extend_from_slice(&data[position..]);
+  }
+
+  output
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -255,6 +326,89 @@ mod tests {
     assert_eq!(edit, expected_edit_list());
   }
 
+  #[test]
+  fn test_apply_edit_list() {
+    let data: Vec<u8> = (0..10).collect();
+
+    assert_eq!(apply_edit_list(&data, &[2, 3, 1, 2]), vec![2, 3, 4, 6, 7]);
+    assert_eq!(apply_edit_list(&data, &[0, 2, 6]), vec![0, 1, 8, 9]);
+  }
+
+  #[tokio::test]
+  async fn test_verify_edit_list() {
+    let mut src =
+      File::open(default_dir().join("data/c4gh/htsnexus_test_NA12878.bam.c4gh")).unwrap();
+    let mut buf = vec![];
+    src.read_to_end(&mut buf).unwrap();
+
+    let mut buf = BufReader::new(Cursor::new(buf));
+    let keys = get_decryption_keys().await;
+    let header = DeserializedHeader::from_buffer(&mut buf, &keys).unwrap();
+
+    let edit = EditHeader::new(
+      vec![UnencryptedPosition::new(10, 20)],
+      vec![ClampedPosition::new(0, 65536)],
+      &keys,
+      &header,
+    );
+
+    let plaintext: Vec<u8> = (0..70000).map(|i| (i % 251) as u8).collect();
+    assert!(edit
+      .verify_edit_list(&plaintext[..65536], &plaintext)
+      .is_ok());
+    assert!(matches!(
+      edit.verify_edit_list(&plaintext[1..65537], &plaintext),
+      Err(InternalError(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_edit_list_error_positions() {
+    let mut src =
+      File::open(default_dir().join("data/c4gh/htsnexus_test_NA12878.bam.c4gh")).unwrap();
+    let mut buf = vec![];
+    src.read_to_end(&mut buf).unwrap();
+
+    let mut buf = BufReader::new(Cursor::new(buf));
+    let keys = get_decryption_keys().await;
+    let mut header = DeserializedHeader::from_buffer(&mut buf, &keys).unwrap();
+    header.edit_list = Some(vec![0, 7853]);
+
+    let edit = || {
+      EditHeader::new(
+        test_unencrypted_positions(),
+        test_clamped_positions(),
+        &keys,
+        &header,
+      )
+    };
+
+    let err = edit().reencrypt_header().unwrap_err().to_string();
+    assert!(!err.contains("unencrypted positions"));
+
+    let err = edit()
+      .with_error_positions(true)
+      .reencrypt_header()
+      .unwrap_err()
+      .to_string();
+    assert!(err.contains(
+      "edit lists already exist, for unencrypted positions: `0-7853, 145110-453039, 5485074-5485112`"
+    ));
+
+    let plaintext: Vec<u8> = (0..70000).map(|i| (i % 251) as u8).collect();
+    let err = EditHeader::new(
+      vec![UnencryptedPosition::new(10, 20)],
+      vec![ClampedPosition::new(0, 65536)],
+      &keys,
+      &header,
+    )
+    .with_error_positions(true)
+    .verify_edit_list(&plaintext[1..65537], &plaintext)
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("for unencrypted positions: `10-20`"));
+  }
+
   fn test_unencrypted_positions() -> Vec<UnencryptedPosition> {
     vec![
       UnencryptedPosition::new(0, 7853),
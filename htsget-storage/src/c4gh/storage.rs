@@ -6,7 +6,7 @@ use crate::c4gh::{
   to_unencrypted_file_size, unencrypted_clamp, unencrypted_clamp_next, unencrypted_to_data_block,
   unencrypted_to_next_data_block, DecryptedData, DeserializedHeader,
 };
-use crate::error::StorageError::{InternalError, IoError};
+use crate::error::StorageError::{InternalError, IoError, Unavailable};
 use crate::error::{Result, StorageError};
 use crate::types::BytesPosition;
 use crate::{
@@ -14,15 +14,22 @@ use crate::{
   StorageTrait, Streamable,
 };
 use async_trait::async_trait;
+use base64::engine::general_purpose;
+use base64::Engine;
 use crypt4gh::error::Crypt4GHError;
+use crypt4gh::keys::get_public_key_from_private_key;
 use crypt4gh::Keys;
+use htsget_config::storage::ConcurrencyLimit;
 use htsget_config::types::{Class, Format, Url};
+use http::HeaderMap;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::io;
 use std::io::{BufReader, Cursor, Read};
+use std::sync::Arc;
 use tokio::io::AsyncReadExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 /// Max C4GH header size in bytes. Supports 50 regular sized encrypted packets. 16 + (108 * 50).
 const MAX_C4GH_HEADER_SIZE: u64 = 5416;
@@ -35,6 +42,8 @@ pub struct C4GHState {
   unencrypted_file_size: u64,
   deserialized_header: DeserializedHeader,
   decrypted_data: DecryptedData,
+  // Held for as long as the decrypted data is buffered.
+  _permit: Option<Arc<OwnedSemaphorePermit>>,
 }
 
 /// Implementation for the [StorageTrait] trait using the local file system for accessing Crypt4GH
@@ -43,6 +52,9 @@ pub struct C4GHStorage {
   keys: Vec<Keys>,
   inner: Box<dyn StorageTrait + Send + Sync + 'static>,
   state: HashMap<String, C4GHState>,
+  decryption_limit: Option<Arc<Semaphore>>,
+  verify_edit_list: bool,
+  edit_list_error_positions: bool,
 }
 
 impl Clone for C4GHStorage {
@@ -51,6 +63,9 @@ impl Clone for C4GHStorage {
       keys: self.keys.clone(),
       inner: self.inner.clone_box(),
       state: self.state.clone(),
+      decryption_limit: self.decryption_limit.clone(),
+      verify_edit_list: self.verify_edit_list,
+      edit_list_error_positions: self.edit_list_error_positions,
     }
   }
 }
@@ -73,9 +88,46 @@ impl C4GHStorage {
       keys,
       inner,
       state: Default::default(),
+      decryption_limit: None,
+      verify_edit_list: false,
+      edit_list_error_positions: false,
     }
   }
 
+  /// Limit the number of objects which can be decrypted and buffered concurrently. Objects which
+  /// exceed the limit are rejected rather than queued.
+  pub fn with_decryption_limit(mut self, limit: &ConcurrencyLimit) -> Self {
+    self.decryption_limit = limit.semaphore();
+    self
+  }
+
+  /// Verify the edit list against the decrypted data before returning data blocks. This reads
+  /// and decrypts the whole object, so it is intended for testing.
+  pub fn with_verify_edit_list(mut self, verify_edit_list: bool) -> Self {
+    self.verify_edit_list = verify_edit_list;
+    self
+  }
+
+  /// Include the unencrypted positions of the query in edit list error messages, which helps
+  /// with debugging failed requests.
+  pub fn with_edit_list_error_positions(mut self, edit_list_error_positions: bool) -> Self {
+    self.edit_list_error_positions = edit_list_error_positions;
+    self
+  }
+
+  /// Try to get a permit to decrypt an object, if there is a decryption limit.
+  fn try_acquire_decryption_permit(&self) -> Result<Option<Arc<OwnedSemaphorePermit>>> {
+    self
+      .decryption_limit
+      .clone()
+      .map(|semaphore| {
+        semaphore.try_acquire_owned().map(Arc::new).map_err(|_| {
+          Unavailable("too many concurrent Crypt4GH decryptions, try again later".to_string())
+        })
+      })
+      .transpose()
+  }
+
   /// Format a C4GH key.
   pub fn format_key(key: &str) -> String {
     format!("{}.c4gh", key)
@@ -109,6 +161,7 @@ impl C4GHStorage {
     }
 
     let key = Self::format_key(key);
+    let permit = self.try_acquire_decryption_permit()?;
 
     // Get the file size.
     let encrypted_file_size = self.inner.head(&key, (&options).into()).await?;
@@ -165,6 +218,7 @@ impl C4GHStorage {
       unencrypted_file_size,
       deserialized_header,
       decrypted_data,
+      _permit: permit,
     };
 
     self.state.insert(key, state);
@@ -183,6 +237,7 @@ impl C4GHStorage {
       .get(&Self::format_key(key))
       .ok_or_else(|| InternalError("missing key from state".to_string()))?;
 
+    let headers = options.headers();
     let default_start = |pos: &BytesPosition| pos.start.unwrap_or_default();
     let default_end = |pos: &BytesPosition| pos.end.unwrap_or(state.unencrypted_file_size);
 
@@ -233,14 +288,23 @@ impl C4GHStorage {
       .map(|pos| ClampedPosition::new(default_start(&pos), default_end(&pos)))
       .collect::<Vec<_>>();
 
-    let (header_info, reencrypted_bytes, edit_list_packet) = EditHeader::new(
+    let encrypted_positions = BytesPosition::merge_all(encrypted_positions);
+
+    let edit_header = EditHeader::new(
       unencrypted_positions,
       clamped_positions,
       &self.keys,
       &state.deserialized_header,
     )
-    .reencrypt_header()?
-    .into_inner();
+    .with_error_positions(self.edit_list_error_positions);
+    if self.verify_edit_list {
+      self
+        .verify_edit_list(key, state, &edit_header, &encrypted_positions, headers)
+        .await?;
+    }
+
+    let (header_info, reencrypted_bytes, edit_list_packet) =
+      edit_header.reencrypt_header()?.into_inner();
 
     let header_info_size = header_info.len() as u64;
     let current_header_size = state.deserialized_header.header_size;
@@ -257,12 +321,63 @@ impl C4GHStorage {
       ),
     ];
 
-    blocks.extend(DataBlock::from_bytes_positions(BytesPosition::merge_all(
-      encrypted_positions,
-    )));
+    blocks.extend(DataBlock::from_bytes_positions(encrypted_positions));
 
     Ok(blocks)
   }
+
+  /// Verify the edit list by decrypting the encrypted positions and applying the edit list, and
+  /// comparing the output to the plaintext of the whole object.
+  async fn verify_edit_list(
+    &self,
+    key: &str,
+    state: &C4GHState,
+    edit_header: &EditHeader<'_>,
+    encrypted_positions: &[BytesPosition],
+    headers: &HeaderMap,
+  ) -> Result<()> {
+    let key = Self::format_key(key);
+
+    let mut clamped_data = vec![];
+    for pos in encrypted_positions {
+      clamped_data.extend(self.decrypt_range(&key, state, pos, headers).await?);
+    }
+
+    let whole_object = BytesPosition::default()
+      .with_start(state.deserialized_header.header_size)
+      .with_end(state.encrypted_file_size);
+    let plaintext = self
+      .decrypt_range(&key, state, &whole_object, headers)
+      .await?;
+
+    edit_header.verify_edit_list(&clamped_data, &plaintext)
+  }
+
+  /// Get and decrypt the data blocks within the encrypted range.
+  async fn decrypt_range(
+    &self,
+    key: &str,
+    state: &C4GHState,
+    range: &BytesPosition,
+    headers: &HeaderMap,
+  ) -> Result<Vec<u8>> {
+    let start = range.start.unwrap_or_default();
+    let end = range.end.unwrap_or(state.encrypted_file_size);
+
+    let mut encrypted = vec![];
+    self
+      .inner
+      .get(key, GetOptions::new(range.clone(), headers))
+      .await?
+      .take(end - start)
+      .read_to_end(&mut encrypted)
+      .await?;
+
+    Ok(
+      DecryptedData::from_header(&mut encrypted.as_slice(), state.deserialized_header.clone())?
+        .into_inner(),
+    )
+  }
 }
 
 #[async_trait]
@@ -303,6 +418,22 @@ impl StorageTrait for C4GHStorage {
         .unencrypted_file_size,
     )
   }
+
+  /// Probe the underlying `StorageTrait`.
+  async fn probe(&self) -> Result<()> {
+    self.inner.probe().await
+  }
+
+  /// Get the public key derived from the private key which re-encrypts the header.
+  fn sender_public_key(&self) -> Result<Option<String>> {
+    self
+      .keys
+      .first()
+      .map(|keys| {
+        Ok(general_purpose::STANDARD.encode(get_public_key_from_private_key(&keys.privkey)?))
+      })
+      .transpose()
+  }
 }
 
 impl From<Crypt4GHError> for StorageError {
@@ -319,9 +450,8 @@ mod tests {
   use crate::s3::tests::with_aws_s3_storage;
   #[cfg(feature = "url-storage")]
   use crate::url::tests::{test_headers, with_url_test_server};
-  use htsget_config::types::Headers;
+  use htsget_config::types::{Headers, HtsGetError};
   use htsget_test::c4gh::{encrypt_data, get_decryption_keys};
-  use http::HeaderMap;
   use std::future::Future;
   use std::path::Path;
   use tokio::fs::{read, File};
@@ -411,6 +541,33 @@ mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn test_preprocess_decryption_limit() {
+    with_local_c4gh_storage(|storage| async move {
+      let headers = HeaderMap::default();
+      let options = GetOptions::new_with_default_range(&headers);
+
+      let mut storage = storage.with_decryption_limit(&ConcurrencyLimit::new(Some(1)));
+      let mut saturated = storage.clone();
+      storage
+        .preprocess("folder/key", options.clone())
+        .await
+        .unwrap();
+
+      let result = saturated.preprocess("folder/key", options.clone()).await;
+      assert!(matches!(result, Err(Unavailable(_))));
+      assert!(matches!(
+        HtsGetError::from(result.unwrap_err()),
+        HtsGetError::Unavailable(_)
+      ));
+
+      // The permit is released once the decrypted data is dropped.
+      drop(storage);
+      assert!(saturated.preprocess("folder/key", options).await.is_ok());
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn test_postprocess_local_storage() {
     with_local_c4gh_storage(|mut storage| async move {
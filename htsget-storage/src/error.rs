@@ -40,6 +40,9 @@ pub enum StorageError {
   #[error("response error: {0}")]
   ResponseError(String),
 
+  #[error("unavailable: {0}")]
+  Unavailable(String),
+
   #[cfg(feature = "s3-storage")]
   #[error("aws error: {0}, with key: `{1}`")]
   AwsS3Error(String, String),
@@ -63,6 +66,7 @@ impl From<StorageError> for HtsGetError {
       #[cfg(feature = "s3-storage")]
       err @ StorageError::AwsS3Error(_, _) => Self::IoError(err.to_string()),
       err @ StorageError::UrlParseError(_) => Self::ParseError(err.to_string()),
+      err @ StorageError::Unavailable(_) => Self::Unavailable(err.to_string()),
     }
   }
 }
@@ -93,6 +97,12 @@ mod tests {
     assert!(matches!(result, HtsGetError::NotFound(_)));
   }
 
+  #[test]
+  fn htsget_error_from_storage_unavailable() {
+    let result = HtsGetError::from(StorageError::Unavailable("error".to_string()));
+    assert!(matches!(result, HtsGetError::Unavailable(_)));
+  }
+
   #[test]
   fn htsget_error_from_storage_invalid_key() {
     let result = HtsGetError::from(StorageError::InvalidKey("error".to_string()));
@@ -0,0 +1,420 @@
+//! Module providing an implementation for the [StorageTrait] trait using Google Cloud Storage.
+//!
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use http::header::{CONTENT_LENGTH, RANGE};
+use http::{Method, StatusCode, Uri};
+use reqwest::{Client, ClientBuilder};
+use tokio_util::io::StreamReader;
+use tracing::{debug, instrument};
+
+use htsget_config::storage::gcs::{GcsHmacKey, GcsServiceAccountKey, DEFAULT_GCS_ENDPOINT};
+use htsget_config::storage::uri_encode;
+
+use crate::types::BytesRange;
+use crate::StorageError::{InternalError, InvalidUri, KeyNotFound, ResponseError};
+use crate::{
+  BytesPosition, GetOptions, HeadOptions, RangeUrlOptions, Result, StorageMiddleware, StorageTrait,
+  Streamable, Url,
+};
+
+/// Implementation for the [StorageTrait] trait using Google Cloud Storage. Objects are read
+/// using the XML API, with V4 signed urls when a service account key or HMAC key is set. The
+/// same signed urls authenticate the requests made by the storage, so private buckets are read
+/// without an OAuth access token.
+#[derive(Debug, Clone)]
+pub struct GcsStorage {
+  client: Client,
+  endpoint: Uri,
+  bucket: String,
+  service_account_key: Option<GcsServiceAccountKey>,
+  hmac_key: Option<GcsHmacKey>,
+  url_expiry: Duration,
+}
+
+impl GcsStorage {
+  /// The default number of seconds that signed urls are valid for.
+  pub const SIGNED_URL_EXPIRY: u64 = 1000;
+
+  /// Create a new Google Cloud Storage storage.
+  pub fn new(client: Client, endpoint: Uri, bucket: String, hmac_key: Option<GcsHmacKey>) -> Self {
+    Self {
+      client,
+      endpoint,
+      bucket,
+      service_account_key: None,
+      hmac_key,
+      url_expiry: Duration::from_secs(Self::SIGNED_URL_EXPIRY),
+    }
+  }
+
+  /// Create a new Google Cloud Storage storage with a default client, using the default endpoint
+  /// if it is not set.
+  pub fn new_with_default_config(
+    bucket: String,
+    endpoint: Option<String>,
+    hmac_key: Option<GcsHmacKey>,
+  ) -> Result<Self> {
+    let endpoint = endpoint
+      .as_deref()
+      .unwrap_or(DEFAULT_GCS_ENDPOINT)
+      .parse::<Uri>()
+      .map_err(|err| InvalidUri(err.to_string()))?;
+    let client = ClientBuilder::new()
+      .build()
+      .map_err(|err| InternalError(format!("failed to build reqwest client: {}", err)))?;
+
+    Ok(Self::new(client, endpoint, bucket, hmac_key))
+  }
+
+  /// Set the time that signed urls are valid for.
+  pub fn with_url_expiry(mut self, url_expiry: Duration) -> Self {
+    self.url_expiry = url_expiry;
+    self
+  }
+
+  /// Set the service account key used to sign urls. This takes precedence over the HMAC key.
+  pub fn with_service_account_key(
+    mut self,
+    service_account_key: Option<GcsServiceAccountKey>,
+  ) -> Self {
+    self.service_account_key = service_account_key;
+    self
+  }
+
+  /// Get the url of the key for a request with the method, which is signed if the service account
+  /// key or HMAC key is set.
+  pub fn object_url(&self, key: &str, method: &Method) -> String {
+    let path = format!("/{}/{}", self.bucket, uri_encode(key, false));
+    let url = format!("{}{path}", self.endpoint.to_string().trim_end_matches('/'));
+
+    let Some(host) = self.endpoint.authority() else {
+      return url;
+    };
+    let (method, host) = (method.as_str(), host.as_str());
+    match (&self.service_account_key, &self.hmac_key) {
+      (Some(service_account_key), _) => format!(
+        "{url}?{}",
+        service_account_key.signed_query(method, host, &path, self.url_expiry)
+      ),
+      (None, Some(hmac_key)) => format!(
+        "{url}?{}",
+        hmac_key.signed_query(method, host, &path, self.url_expiry)
+      ),
+      (None, None) => url,
+    }
+  }
+
+  /// Send a request with the method for the key, requesting only the range if it is set.
+  async fn send_request(
+    &self,
+    key: &str,
+    method: Method,
+    range: Option<&BytesPosition>,
+  ) -> Result<reqwest::Response> {
+    let mut request = self
+      .client
+      .request(method.clone(), self.object_url(key, &method));
+
+    let range = range
+      .map(|range| String::from(&BytesRange::from(range)))
+      .unwrap_or_default();
+    if !range.is_empty() {
+      request = request.header(RANGE, range);
+    }
+
+    let response = request
+      .send()
+      .await
+      .map_err(|err| ResponseError(format!("{} with key {}", err, key)))?;
+
+    match response.status() {
+      status if status.is_success() => Ok(response),
+      StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => Err(KeyNotFound(key.to_string())),
+      status => Err(ResponseError(format!(
+        "gcs returned {} for key {}",
+        status, key
+      ))),
+    }
+  }
+}
+
+#[async_trait]
+impl StorageMiddleware for GcsStorage {}
+
+#[async_trait]
+impl StorageTrait for GcsStorage {
+  /// Gets the actual GCS object as a buffered reader.
+  #[instrument(level = "trace", skip(self))]
+  async fn get(&self, key: &str, options: GetOptions<'_>) -> Result<Streamable> {
+    debug!(calling_from = ?self, key, "getting file with key {:?}", key);
+
+    let response = self
+      .send_request(key, Method::GET, Some(options.range()))
+      .await?;
+
+    Ok(Streamable::from_async_read(StreamReader::new(Box::pin(
+      response
+        .bytes_stream()
+        .map_err(|err| ResponseError(format!("reading body from response: {}", err))),
+    ))))
+  }
+
+  /// Return a signed url to the GCS object.
+  #[instrument(level = "trace", skip(self))]
+  async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> Result<Url> {
+    let url = self.object_url(key, &Method::GET);
+    debug!(calling_from = ?self, key, ?url, "getting url with key {:?}", key);
+
+    Ok(options.apply(Url::new(url)))
+  }
+
+  /// Returns the size of the GCS object in bytes, using the metadata of a `HEAD` request.
+  #[instrument(level = "trace", skip(self))]
+  async fn head(&self, key: &str, _options: HeadOptions<'_>) -> Result<u64> {
+    let head = self.send_request(key, Method::HEAD, None).await?;
+
+    let len = head
+      .headers()
+      .get(CONTENT_LENGTH)
+      .and_then(|content_length| content_length.to_str().ok())
+      .and_then(|content_length| content_length.parse().ok())
+      .ok_or_else(|| {
+        ResponseError(format!(
+          "failed to get content length from head response for key: {}",
+          key
+        ))
+      })?;
+
+    debug!(calling_from = ?self, key, len, "size of key {:?} is {}", key, len);
+    Ok(len)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::future::Future;
+  use std::result;
+  use std::str::FromStr;
+
+  use axum::body::Body;
+  use axum::middleware::Next;
+  use axum::response::Response;
+  use axum::{middleware, Router};
+  use htsget_test::util::TEST_RSA_PRIVATE_KEY;
+  use http::{HeaderMap, Request};
+  use tokio::io::AsyncReadExt;
+  use tokio::net::TcpListener;
+  use tower_http::services::ServeDir;
+
+  use crate::local::tests::create_local_test_files;
+  use crate::types::BytesPosition;
+  use crate::Headers;
+
+  use super::*;
+
+  /// Reject requests which are not signed by the test HMAC key.
+  async fn test_signature(
+    request: Request<Body>,
+    next: Next,
+  ) -> result::Result<Response, StatusCode> {
+    let query = request.uri().query().unwrap_or_default();
+
+    let credential = [
+      "X-Goog-Credential=access-id%2F",
+      "X-Goog-Credential=htsget%40",
+    ]
+    .into_iter()
+    .any(|credential| query.contains(credential));
+    if credential && query.contains("X-Goog-Signature=") {
+      Ok(next.run(request).await)
+    } else {
+      Err(StatusCode::FORBIDDEN)
+    }
+  }
+
+  async fn with_gcs_test_server<F, Fut>(test: F)
+  where
+    F: FnOnce(GcsStorage, String) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    let (_, base_path) = create_local_test_files().await;
+    let router = Router::new()
+      .nest_service("/bucket", ServeDir::new(base_path.path()))
+      .route_layer(middleware::from_fn(test_signature));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router.into_make_service()).await });
+
+    let url = format!("http://{}", addr);
+    test(
+      GcsStorage::new(
+        Client::new(),
+        Uri::from_str(&url).unwrap(),
+        "bucket".to_string(),
+        Some(GcsHmacKey::new("access-id", "secret")),
+      ),
+      url,
+    )
+    .await;
+  }
+
+  #[test]
+  fn object_url_unsigned() {
+    let storage = GcsStorage::new_with_default_config("bucket".to_string(), None, None).unwrap();
+
+    assert_eq!(
+      storage.object_url("folder/key 1.bam", &Method::GET),
+      "https://storage.googleapis.com/bucket/folder/key%201.bam"
+    );
+  }
+
+  #[tokio::test]
+  async fn get_storage() {
+    with_gcs_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let mut reader = storage
+        .get("key1", GetOptions::new_with_default_range(&headers))
+        .await
+        .unwrap();
+
+      let mut response = vec![];
+      reader.read_to_end(&mut response).await.unwrap();
+      assert_eq!(response, b"value1");
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_range() {
+    with_gcs_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let mut reader = storage
+        .get(
+          "folder/key2",
+          GetOptions::new(BytesPosition::new(Some(1), Some(3), None), &headers),
+        )
+        .await
+        .unwrap();
+
+      let mut response = vec![];
+      reader.read_to_end(&mut response).await.unwrap();
+      assert_eq!(response, b"al");
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_non_existing_key() {
+    with_gcs_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+      let result = storage
+        .get(
+          "non-existing-key",
+          GetOptions::new_with_default_range(&headers),
+        )
+        .await;
+
+      assert!(matches!(result, Err(KeyNotFound(_))));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_unsigned() {
+    with_gcs_test_server(|storage, url| async move {
+      let storage = GcsStorage::new(
+        Client::new(),
+        Uri::from_str(&url).unwrap(),
+        storage.bucket,
+        None,
+      );
+
+      let headers = HeaderMap::default();
+      let result = storage
+        .get("key1", GetOptions::new_with_default_range(&headers))
+        .await;
+
+      assert!(matches!(result, Err(KeyNotFound(_))));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_service_account_key() {
+    with_gcs_test_server(|storage, url| async move {
+      let storage = GcsStorage::new(
+        Client::new(),
+        Uri::from_str(&url).unwrap(),
+        storage.bucket,
+        None,
+      )
+      .with_service_account_key(Some(
+        GcsServiceAccountKey::new(
+          "htsget@project.iam.gserviceaccount.com",
+          TEST_RSA_PRIVATE_KEY,
+        )
+        .unwrap(),
+      ));
+
+      let headers = HeaderMap::default();
+      let mut reader = storage
+        .get("key1", GetOptions::new_with_default_range(&headers))
+        .await
+        .unwrap();
+
+      let mut response = vec![];
+      reader.read_to_end(&mut response).await.unwrap();
+      assert_eq!(response, b"value1");
+      assert!(storage
+        .object_url("key1", &Method::GET)
+        .contains("X-Goog-Algorithm=GOOG4-RSA-SHA256"));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn range_url_storage() {
+    with_gcs_test_server(|storage, url| async move {
+      let headers = HeaderMap::default();
+      let result = storage
+        .range_url(
+          "key1",
+          RangeUrlOptions::new(BytesPosition::new(Some(1), Some(3), None), &headers),
+        )
+        .await
+        .unwrap();
+
+      assert!(result.url.starts_with(&format!(
+        "{url}/bucket/key1?X-Goog-Algorithm=GOOG4-HMAC-SHA256&"
+      )));
+      assert_eq!(
+        result.headers,
+        Some(Headers::default().with_header("Range", "bytes=1-2"))
+      );
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn head_storage() {
+    with_gcs_test_server(|storage, _| async move {
+      let headers = HeaderMap::default();
+
+      assert_eq!(
+        storage
+          .head("folder/key2", HeadOptions::new(&headers))
+          .await
+          .unwrap(),
+        6
+      );
+    })
+    .await;
+  }
+}
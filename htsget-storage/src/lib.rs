@@ -9,40 +9,60 @@ pub use htsget_config::types::{
   Class, Format, Headers, HtsGetError, JsonResponse, Query, Response, Url,
 };
 
+#[cfg(feature = "azure-storage")]
+use crate::azure::AzureBlobStorage;
 #[cfg(feature = "experimental")]
 use crate::c4gh::storage::C4GHStorage;
 use crate::error::Result;
 use crate::error::StorageError;
+#[cfg(feature = "gcs-storage")]
+use crate::gcs::GcsStorage;
 use crate::local::LocalStorage;
+use crate::read_ahead::ReadAhead;
 #[cfg(feature = "s3-storage")]
 use crate::s3::S3Storage;
-use crate::types::{BytesPositionOptions, DataBlock, GetOptions, HeadOptions, RangeUrlOptions};
+use crate::types::{
+  BytesPosition, BytesPositionOptions, DataBlock, GetOptions, HeadOptions, RangeUrlOptions,
+};
 #[cfg(feature = "url-storage")]
-use crate::url::UrlStorage;
+use crate::url::{PathTemplate, UrlStorage};
 use async_trait::async_trait;
 use base64::engine::general_purpose;
 use base64::Engine;
-use cfg_if::cfg_if;
+#[cfg(feature = "azure-storage")]
+use htsget_config::storage::azure::Azure as AzureStorageConfig;
 #[cfg(feature = "experimental")]
 use htsget_config::storage::c4gh::C4GHKeys;
+#[cfg(feature = "gcs-storage")]
+use htsget_config::storage::gcs::Gcs as GcsStorageConfig;
 use htsget_config::storage::local::Local as LocalStorageConfig;
 #[cfg(feature = "s3-storage")]
 use htsget_config::storage::s3::S3 as S3StorageConfig;
 #[cfg(feature = "url-storage")]
 use htsget_config::storage::url::UrlStorageClient as UrlStorageConfig;
+use htsget_config::storage::{ConcurrencyLimit, EofCache};
 use htsget_config::types::Scheme;
 use http::uri;
 use pin_project_lite::pin_project;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::io::Cursor;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
-use tokio::io::{AsyncRead, ReadBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader, ReadBuf};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
+#[cfg(feature = "azure-storage")]
+pub mod azure;
 #[cfg(feature = "experimental")]
 pub mod c4gh;
 pub mod error;
+#[cfg(feature = "gcs-storage")]
+pub mod gcs;
 pub mod local;
+pub mod read_ahead;
 #[cfg(feature = "s3-storage")]
 pub mod s3;
 pub mod types;
@@ -54,6 +74,8 @@ pin_project! {
   pub struct Streamable {
     #[pin]
     inner: Box<dyn AsyncRead + Send + Sync + Unpin + 'static>,
+    buffer_size: Option<usize>,
+    permit: Option<OwnedSemaphorePermit>,
   }
 }
 
@@ -62,8 +84,31 @@ impl Streamable {
   pub fn from_async_read(inner: impl AsyncRead + Send + Sync + Unpin + 'static) -> Self {
     Self {
       inner: Box::new(inner),
+      buffer_size: None,
+      permit: None,
     }
   }
+
+  /// Buffer reads from the inner AsyncRead using a buffer with the capacity `buffer_size`.
+  pub fn with_buffer_size(self, buffer_size: usize) -> Self {
+    Self {
+      inner: Box::new(BufReader::with_capacity(buffer_size, self.inner)),
+      buffer_size: Some(buffer_size),
+      permit: self.permit,
+    }
+  }
+
+  /// Hold the concurrency permit until the stream is dropped, so that the concurrency limit covers
+  /// reading the stream rather than only opening it.
+  pub fn with_permit(mut self, permit: Option<OwnedSemaphorePermit>) -> Self {
+    self.permit = permit;
+    self
+  }
+
+  /// Get the capacity of the read buffer, if reads are buffered.
+  pub fn buffer_size(&self) -> Option<usize> {
+    self.buffer_size
+  }
 }
 
 impl AsyncRead for Streamable {
@@ -79,6 +124,12 @@ impl AsyncRead for Streamable {
 /// The top-level storage type is created from any `StorageTrait`.
 pub struct Storage {
   inner: Box<dyn StorageTrait + Send + Sync + 'static>,
+  semaphore: Option<Arc<Semaphore>>,
+  eof_cache: EofCache,
+  read_buffer_size: Option<usize>,
+  read_ahead: Option<ReadAhead>,
+  etag: bool,
+  index_storage: Option<Box<Storage>>,
 }
 
 impl Storage {
@@ -86,12 +137,169 @@ impl Storage {
   pub fn into_inner(self) -> Box<dyn StorageTrait + Send + Sync> {
     self.inner
   }
+
+  /// Limit the number of concurrent `get` and `head` calls made to the inner storage.
+  pub fn with_concurrency_limit(mut self, limit: &ConcurrencyLimit) -> Self {
+    self.semaphore = limit.semaphore();
+    self
+  }
+
+  /// Cache the end of file positions of keys, so that they are only requested once.
+  pub fn with_eof_cache(mut self, eof_cache: &EofCache) -> Self {
+    self.eof_cache = eof_cache.clone();
+    self
+  }
+
+  /// Buffer the data returned by `get` using a buffer with the capacity `read_buffer_size`.
+  pub fn with_read_buffer_size(mut self, read_buffer_size: Option<usize>) -> Self {
+    self.read_buffer_size = read_buffer_size;
+    self
+  }
+
+  /// Read `read_ahead` bytes past the end of sequential range reads, serving the following
+  /// adjacent ranges from a buffer.
+  pub fn with_read_ahead(mut self, read_ahead: Option<u64>) -> Self {
+    self.read_ahead = read_ahead.map(ReadAhead::new);
+    self
+  }
+
+  /// Compute an ETag from the modification times of the keys.
+  pub fn with_etag(mut self, etag: bool) -> Self {
+    self.etag = etag;
+    self
+  }
+
+  /// Read index files from a separate storage, for layouts which keep indexes apart from the data.
+  pub fn with_index_storage(mut self, index_storage: Option<Storage>) -> Self {
+    self.index_storage = index_storage.map(Box::new);
+    self
+  }
+
+  /// Get the storage that index files are read from. This is the storage itself unless a separate
+  /// index storage is set.
+  pub fn index_storage(&self) -> &Storage {
+    self.index_storage.as_deref().unwrap_or(self)
+  }
+
+  /// Get an ETag derived from the modification times of the key and its index keys, if enabled.
+  /// Keys which are not found are skipped, and no ETag is returned if the storage does not report
+  /// modification times.
+  pub async fn etag(
+    &self,
+    key: &str,
+    index_keys: &[String],
+    options: HeadOptions<'_>,
+  ) -> Result<Option<String>> {
+    if !self.etag {
+      return Ok(None);
+    }
+
+    let keys = [(self, key)].into_iter().chain(
+      index_keys
+        .iter()
+        .map(|index_key| (self.index_storage(), index_key.as_str())),
+    );
+
+    let mut modified = Vec::with_capacity(index_keys.len() + 1);
+    for (storage, key) in keys {
+      match storage.inner.modified(key, options.clone()).await {
+        Ok(Some(time)) => modified.push(
+          time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+        ),
+        Ok(None) => return Ok(None),
+        Err(StorageError::KeyNotFound(_)) => continue,
+        Err(err) => return Err(err),
+      }
+    }
+
+    Ok((!modified.is_empty()).then(|| {
+      modified
+        .iter()
+        .map(|time| format!("{time:x}"))
+        .collect::<Vec<_>>()
+        .join("-")
+    }))
+  }
+
+  /// Get the range extended by read-ahead, buffering the bytes past the end of the requested range.
+  async fn get_read_ahead(
+    &self,
+    read_ahead: &ReadAhead,
+    key: &str,
+    options: GetOptions<'_>,
+    range: BytesPosition,
+  ) -> Result<Streamable> {
+    let start = range.get_start().unwrap_or_default();
+    let end = range.get_end().unwrap_or_default();
+    let requested_end = options.range().get_end().unwrap_or(end);
+
+    let mut data = Vec::new();
+    {
+      let _permit = self.acquire_permit().await?;
+      self
+        .inner
+        .get(key, options.with_range(range))
+        .await?
+        .take(end - start)
+        .read_to_end(&mut data)
+        .await?;
+    }
+
+    let requested_len = usize::try_from(requested_end - start).unwrap_or(usize::MAX);
+    let requested = data[..data.len().min(requested_len)].to_vec();
+    read_ahead.insert(key, start, data);
+
+    Ok(Streamable::from_async_read(Cursor::new(requested)))
+  }
+
+  /// Get the position of the end of file marker for the key, using the cached position if there
+  /// is one.
+  pub async fn eof_position(
+    &self,
+    key: &str,
+    eof_marker_len: u64,
+    options: HeadOptions<'_>,
+  ) -> Result<u64> {
+    if let Some(position) = self.eof_cache.get(key) {
+      return Ok(position);
+    }
+
+    let position = self
+      .head(key, options)
+      .await?
+      .checked_sub(eof_marker_len)
+      .ok_or_else(|| {
+        StorageError::InvalidInput(format!("`{key}` is smaller than its end of file marker"))
+      })?;
+    self.eof_cache.insert(key, position);
+
+    Ok(position)
+  }
+
+  /// Wait for a permit to make a request, if the storage has a concurrency limit.
+  async fn acquire_permit(&self) -> Result<Option<OwnedSemaphorePermit>> {
+    match &self.semaphore {
+      Some(semaphore) => Ok(Some(semaphore.clone().acquire_owned().await.map_err(
+        |err| StorageError::InternalError(format!("acquiring concurrency permit: {}", err)),
+      )?)),
+      None => Ok(None),
+    }
+  }
 }
 
 impl Clone for Storage {
   fn clone(&self) -> Self {
     Self {
       inner: self.inner.clone_box(),
+      semaphore: self.semaphore.clone(),
+      eof_cache: self.eof_cache.clone(),
+      read_buffer_size: self.read_buffer_size,
+      read_ahead: self.read_ahead.clone(),
+      etag: self.etag,
+      index_storage: self.index_storage.clone(),
     }
   }
 }
@@ -120,7 +328,22 @@ impl StorageMiddleware for Storage {
 #[async_trait]
 impl StorageTrait for Storage {
   async fn get(&self, key: &str, options: GetOptions<'_>) -> Result<Streamable> {
-    self.inner.get(key, options).await
+    if let Some(read_ahead) = &self.read_ahead {
+      if let Some(data) = read_ahead.get_buffered(key, options.range()) {
+        return Ok(Streamable::from_async_read(Cursor::new(data)));
+      }
+      if let Some(range) = read_ahead.next_range(key, options.range()) {
+        return self.get_read_ahead(read_ahead, key, options, range).await;
+      }
+    }
+
+    let permit = self.acquire_permit().await?;
+    let streamable = self.inner.get(key, options).await?.with_permit(permit);
+
+    Ok(match self.read_buffer_size {
+      Some(read_buffer_size) => streamable.with_buffer_size(read_buffer_size),
+      None => streamable,
+    })
   }
 
   async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> Result<Url> {
@@ -128,9 +351,22 @@ impl StorageTrait for Storage {
   }
 
   async fn head(&self, key: &str, options: HeadOptions<'_>) -> Result<u64> {
+    let _permit = self.acquire_permit().await?;
     self.inner.head(key, options).await
   }
 
+  async fn probe(&self) -> Result<()> {
+    self.inner.probe().await
+  }
+
+  async fn modified(&self, key: &str, options: HeadOptions<'_>) -> Result<Option<SystemTime>> {
+    self.inner.modified(key, options).await
+  }
+
+  fn sender_public_key(&self) -> Result<Option<String>> {
+    self.inner.sender_public_key()
+  }
+
   fn data_url(&self, data: Vec<u8>, class: Option<Class>) -> Url {
     self.inner.data_url(data, class)
   }
@@ -139,16 +375,25 @@ impl StorageTrait for Storage {
 impl Storage {
   #[cfg(feature = "experimental")]
   /// Wrap an existing storage with C4GH storage
-  pub async fn from_c4gh_keys(keys: Option<&C4GHKeys>, storage: Storage) -> Result<Storage> {
+  pub async fn from_c4gh_keys(
+    keys: Option<&C4GHKeys>,
+    max_concurrent_decryptions: &ConcurrencyLimit,
+    verify_edit_list: bool,
+    storage: Storage,
+  ) -> Result<Storage> {
     if let Some(keys) = keys {
-      Ok(Storage::new(C4GHStorage::new_box(
-        keys
-          .clone()
-          .keys()
-          .await
-          .map_err(|err| StorageError::InternalError(err.to_string()))?,
-        storage.into_inner(),
-      )))
+      Ok(Storage::new(
+        C4GHStorage::new_box(
+          keys
+            .clone()
+            .keys()
+            .await
+            .map_err(|err| StorageError::InternalError(err.to_string()))?,
+          storage.into_inner(),
+        )
+        .with_decryption_limit(max_concurrent_decryptions)
+        .with_verify_edit_list(verify_edit_list),
+      ))
     } else {
       Ok(storage)
     }
@@ -156,64 +401,222 @@ impl Storage {
 
   /// Create from local storage config.
   pub async fn from_local(local_storage: &LocalStorageConfig) -> Result<Storage> {
-    let storage = Storage::new(LocalStorage::new(
-      local_storage.local_path(),
-      local_storage.clone(),
-    )?);
-
-    cfg_if! {
-      if #[cfg(feature = "experimental")] {
-        Self::from_c4gh_keys(local_storage.keys(), storage).await
-      } else {
-        Ok(storage)
-      }
-    }
+    let storage = Storage::new(
+      LocalStorage::new(local_storage.local_path(), local_storage.clone())?
+        .with_reject_path_traversal(local_storage.reject_path_traversal()),
+    );
+    let index_storage = local_storage
+      .index_local_path()
+      .map(|index_local_path| {
+        Ok::<_, StorageError>(
+          Storage::new(
+            LocalStorage::new(index_local_path, local_storage.clone())?
+              .with_reject_path_traversal(local_storage.reject_path_traversal()),
+          )
+          .with_concurrency_limit(local_storage.max_concurrent_requests())
+          .with_read_buffer_size(local_storage.read_buffer_size()),
+        )
+      })
+      .transpose()?;
+
+    #[cfg(feature = "experimental")]
+    let storage = Self::from_c4gh_keys(
+      local_storage.keys(),
+      local_storage.max_concurrent_decryptions(),
+      local_storage.verify_edit_list(),
+      storage,
+    )
+    .await?;
+
+    Ok(
+      storage
+        .with_concurrency_limit(local_storage.max_concurrent_requests())
+        .with_eof_cache(local_storage.cache_eof())
+        .with_read_buffer_size(local_storage.read_buffer_size())
+        .with_read_ahead(local_storage.read_ahead())
+        .with_etag(local_storage.etag())
+        .with_index_storage(index_storage),
+    )
   }
 
   /// Create from s3 config.
   #[cfg(feature = "s3-storage")]
   pub async fn from_s3(s3_storage: &S3StorageConfig) -> Result<Storage> {
+    let storage = Storage::new(Self::s3_storage(s3_storage, s3_storage.bucket()).await?);
+    let index_storage = match s3_storage.index_bucket() {
+      Some(index_bucket) => Some(
+        Storage::new(Self::s3_storage(s3_storage, index_bucket).await?)
+          .with_concurrency_limit(s3_storage.max_concurrent_requests())
+          .with_read_buffer_size(s3_storage.read_buffer_size()),
+      ),
+      None => None,
+    };
+
+    #[cfg(feature = "experimental")]
+    let storage = Self::from_c4gh_keys(
+      s3_storage.keys(),
+      s3_storage.max_concurrent_decryptions(),
+      s3_storage.verify_edit_list(),
+      storage,
+    )
+    .await?;
+
+    Ok(
+      storage
+        .with_concurrency_limit(s3_storage.max_concurrent_requests())
+        .with_eof_cache(
+          &s3_storage
+            .cache_eof()
+            .clone()
+            .with_prefix(format!("{}/", s3_storage.bucket())),
+        )
+        .with_read_buffer_size(s3_storage.read_buffer_size())
+        .with_read_ahead(s3_storage.read_ahead())
+        .with_index_storage(index_storage),
+    )
+  }
+
+  /// Create the S3 storage for the bucket from the s3 config.
+  #[cfg(feature = "s3-storage")]
+  async fn s3_storage(s3_storage: &S3StorageConfig, bucket: &str) -> Result<S3Storage> {
+    S3Storage::new_with_default_config(
+      bucket.to_string(),
+      s3_storage.endpoint().map(str::to_string),
+      s3_storage.path_style(),
+    )
+    .await
+    .with_slow_down_retry(
+      s3_storage.slow_down_retries(),
+      Duration::from_millis(s3_storage.slow_down_backoff_ms()),
+    )
+    .with_request_payer(s3_storage.request_payer())
+    .with_presign_expiry(Duration::from_secs(s3_storage.presign_expiry_secs()))
+  }
+
+  /// Create from gcs config.
+  #[cfg(feature = "gcs-storage")]
+  pub async fn from_gcs(gcs_storage: &GcsStorageConfig) -> Result<Storage> {
     let storage = Storage::new(
-      S3Storage::new_with_default_config(
-        s3_storage.bucket().to_string(),
-        s3_storage.endpoint().map(str::to_string),
-        s3_storage.path_style(),
-      )
-      .await,
+      GcsStorage::new_with_default_config(
+        gcs_storage.bucket().to_string(),
+        Some(gcs_storage.endpoint().to_string()),
+        gcs_storage.hmac_key().cloned(),
+      )?
+      .with_service_account_key(gcs_storage.service_account_key().cloned())
+      .with_url_expiry(Duration::from_secs(gcs_storage.url_expiry_secs())),
     );
 
-    cfg_if! {
-      if #[cfg(feature = "experimental")] {
-        Self::from_c4gh_keys(s3_storage.keys(), storage).await
-      } else {
-        Ok(storage)
-      }
-    }
+    #[cfg(feature = "experimental")]
+    let storage = Self::from_c4gh_keys(
+      gcs_storage.keys(),
+      gcs_storage.max_concurrent_decryptions(),
+      gcs_storage.verify_edit_list(),
+      storage,
+    )
+    .await?;
+
+    Ok(
+      storage
+        .with_concurrency_limit(gcs_storage.max_concurrent_requests())
+        .with_eof_cache(
+          &gcs_storage
+            .cache_eof()
+            .clone()
+            .with_prefix(format!("{}/", gcs_storage.bucket())),
+        )
+        .with_read_buffer_size(gcs_storage.read_buffer_size())
+        .with_read_ahead(gcs_storage.read_ahead()),
+    )
+  }
+
+  /// Create from azure config.
+  #[cfg(feature = "azure-storage")]
+  pub async fn from_azure(azure_storage: &AzureStorageConfig) -> Result<Storage> {
+    let storage = Storage::new(
+      AzureBlobStorage::new_with_default_config(
+        azure_storage.account().to_string(),
+        azure_storage.container().to_string(),
+        azure_storage.endpoint(),
+        azure_storage.account_key().cloned(),
+      )?
+      .with_url_expiry(Duration::from_secs(azure_storage.url_expiry_secs())),
+    );
+
+    #[cfg(feature = "experimental")]
+    let storage = Self::from_c4gh_keys(
+      azure_storage.keys(),
+      azure_storage.max_concurrent_decryptions(),
+      azure_storage.verify_edit_list(),
+      storage,
+    )
+    .await?;
+
+    Ok(
+      storage
+        .with_concurrency_limit(azure_storage.max_concurrent_requests())
+        .with_eof_cache(&azure_storage.cache_eof().clone().with_prefix(format!(
+          "{}/{}/",
+          azure_storage.account(),
+          azure_storage.container()
+        )))
+        .with_read_buffer_size(azure_storage.read_buffer_size())
+        .with_read_ahead(azure_storage.read_ahead()),
+    )
   }
 
   /// Create from url config.
   #[cfg(feature = "url-storage")]
   pub async fn from_url(url_storage: &UrlStorageConfig) -> Result<Storage> {
-    let storage = Storage::new(UrlStorage::new(
-      url_storage.client_cloned(),
-      url_storage.url().clone(),
-      url_storage.response_url().clone(),
-      url_storage.forward_headers(),
-      url_storage.header_blacklist().to_vec(),
-    ));
-
-    cfg_if! {
-      if #[cfg(feature = "experimental")] {
-        Self::from_c4gh_keys(url_storage.keys(), storage).await
-      } else {
-        Ok(storage)
-      }
-    }
+    let storage = Storage::new(
+      UrlStorage::new(
+        url_storage.client_cloned(),
+        url_storage.url().clone(),
+        url_storage.response_url().clone(),
+        url_storage.forward_headers(),
+        url_storage.header_blacklist().to_vec(),
+      )
+      .with_probe_key(url_storage.probe_key())
+      .with_retry(
+        url_storage.retries(),
+        Duration::from_millis(url_storage.retry_backoff_ms()),
+      )
+      .with_retry_jitter(Duration::from_millis(url_storage.retry_jitter_ms()))
+      .with_drs(url_storage.drs(), url_storage.drs_allowed_hosts().to_vec())
+      .with_path_template(
+        url_storage
+          .path_template()
+          .zip(url_storage.request_ids())
+          .map(|(template, (id, resolved_id))| PathTemplate::new(template, id, resolved_id)),
+      ),
+    );
+
+    #[cfg(feature = "experimental")]
+    let storage = Self::from_c4gh_keys(
+      url_storage.keys(),
+      url_storage.max_concurrent_decryptions(),
+      url_storage.verify_edit_list(),
+      storage,
+    )
+    .await?;
+
+    Ok(
+      storage
+        .with_concurrency_limit(url_storage.max_concurrent_requests())
+        .with_eof_cache(url_storage.cache_eof())
+        .with_read_buffer_size(url_storage.read_buffer_size())
+        .with_read_ahead(url_storage.read_ahead()),
+    )
   }
 
   pub fn new(inner: impl StorageTrait + Send + Sync + 'static) -> Self {
     Self {
       inner: Box::new(inner),
+      semaphore: None,
+      eof_cache: Default::default(),
+      read_buffer_size: None,
+      read_ahead: None,
+      etag: false,
+      index_storage: None,
     }
   }
 }
@@ -232,6 +635,23 @@ pub trait StorageTrait: StorageMiddleware + StorageClone {
   /// Get the size of the object represented by the key.
   async fn head(&self, key: &str, options: HeadOptions<'_>) -> Result<u64>;
 
+  /// Check that the storage backend is reachable.
+  async fn probe(&self) -> Result<()> {
+    Ok(())
+  }
+
+  /// Get the last modification time of the object represented by the key, if the storage reports
+  /// modification times.
+  async fn modified(&self, _key: &str, _options: HeadOptions<'_>) -> Result<Option<SystemTime>> {
+    Ok(None)
+  }
+
+  /// Get the base64 encoded public key of the sender which encrypted the data, if the storage
+  /// returns encrypted data.
+  fn sender_public_key(&self) -> Result<Option<String>> {
+    Ok(None)
+  }
+
   /// Get the url of the object using an inline data uri.
   fn data_url(&self, data: Vec<u8>, class: Option<Class>) -> Url {
     Url::new(format!(
@@ -271,9 +691,14 @@ pub trait StorageMiddleware {
     _key: &str,
     positions_options: BytesPositionOptions<'_>,
   ) -> Result<Vec<DataBlock>> {
-    Ok(DataBlock::from_bytes_positions(
-      positions_options.merge_all().into_inner(),
-    ))
+    Ok(
+      positions_options
+        .merge_all()
+        .into_inner()
+        .into_iter()
+        .map(DataBlock::Range)
+        .collect(),
+    )
   }
 }
 
@@ -285,13 +710,22 @@ pub trait UrlFormatter {
 
 impl UrlFormatter for htsget_config::storage::local::Local {
   fn format_url<K: AsRef<str>>(&self, key: K) -> Result<String> {
+    let path = format!("{}/{}", self.path_prefix(), key.as_ref());
+    let path_and_query = match self.signing_key() {
+      Some(signing_key) => {
+        let query = signing_key.signed_query(&path, Duration::from_secs(self.url_expiry_secs()));
+        format!("{path}?{query}")
+      }
+      None => path,
+    };
+
     uri::Builder::new()
       .scheme(match self.scheme() {
         Scheme::Http => uri::Scheme::HTTP,
         Scheme::Https => uri::Scheme::HTTPS,
       })
       .authority(self.authority().to_string())
-      .path_and_query(format!("{}/{}", self.path_prefix(), key.as_ref()))
+      .path_and_query(path_and_query)
       .build()
       .map_err(|err| StorageError::InvalidUri(err.to_string()))
       .map(|value| value.to_string())
@@ -300,14 +734,239 @@ impl UrlFormatter for htsget_config::storage::local::Local {
 
 #[cfg(test)]
 mod tests {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
   use http::uri::Authority;
+  use tokio::task::yield_now;
 
+  use crate::local::tests::{create_local_test_files, test_local_storage};
   use crate::local::LocalStorage;
-  use htsget_config::storage::local::Local as ConfigLocalStorage;
+  use htsget_config::storage::local::{Local as ConfigLocalStorage, UrlSigningKey};
   use htsget_test::util::default_dir;
 
   use super::*;
 
+  fn test_data() -> Vec<u8> {
+    (0..=255).cycle().take(1024).collect()
+  }
+
+  #[derive(Debug, Clone, Default)]
+  struct CountingStorage {
+    current: Arc<AtomicUsize>,
+    max: Arc<AtomicUsize>,
+    heads: Arc<AtomicUsize>,
+    gets: Arc<AtomicUsize>,
+  }
+
+  impl CountingStorage {
+    async fn count(&self) {
+      let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+      self.max.fetch_max(current, Ordering::SeqCst);
+      yield_now().await;
+      self.current.fetch_sub(1, Ordering::SeqCst);
+    }
+  }
+
+  impl StorageMiddleware for CountingStorage {}
+
+  #[async_trait]
+  impl StorageTrait for CountingStorage {
+    async fn get(&self, _key: &str, options: GetOptions<'_>) -> Result<Streamable> {
+      self.gets.fetch_add(1, Ordering::SeqCst);
+      self.count().await;
+
+      let start = options.range().get_start().unwrap_or_default() as usize;
+      Ok(Streamable::from_async_read(Cursor::new(
+        test_data()[start..].to_vec(),
+      )))
+    }
+
+    async fn range_url(&self, _key: &str, _options: RangeUrlOptions<'_>) -> Result<Url> {
+      Ok(Url::new(""))
+    }
+
+    async fn head(&self, _key: &str, _options: HeadOptions<'_>) -> Result<u64> {
+      self.heads.fetch_add(1, Ordering::SeqCst);
+      self.count().await;
+      Ok(0)
+    }
+  }
+
+  #[tokio::test]
+  async fn concurrency_limit() {
+    let counting = CountingStorage::default();
+    let storage =
+      Storage::new(counting.clone()).with_concurrency_limit(&ConcurrencyLimit::new(Some(2)));
+
+    let tasks: Vec<_> = (0..10)
+      .map(|_| {
+        let storage = storage.clone();
+        tokio::spawn(async move {
+          let headers = Default::default();
+          storage
+            .head("key", HeadOptions::new(&headers))
+            .await
+            .unwrap();
+          storage
+            .get("key", GetOptions::new_with_default_range(&headers))
+            .await
+            .unwrap();
+        })
+      })
+      .collect();
+    for task in tasks {
+      task.await.unwrap();
+    }
+
+    assert_eq!(counting.max.load(Ordering::SeqCst), 2);
+  }
+
+  #[tokio::test]
+  async fn eof_cache() {
+    let counting = CountingStorage::default();
+    let storage = Storage::new(counting.clone()).with_eof_cache(&EofCache::new(true));
+
+    let headers = Default::default();
+    for _ in 0..3 {
+      assert_eq!(
+        storage
+          .eof_position("key", 0, HeadOptions::new(&headers))
+          .await
+          .unwrap(),
+        0
+      );
+    }
+
+    assert_eq!(counting.heads.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn no_eof_cache() {
+    let counting = CountingStorage::default();
+    let storage = Storage::new(counting.clone());
+
+    let headers = Default::default();
+    for _ in 0..3 {
+      storage
+        .eof_position("key", 0, HeadOptions::new(&headers))
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(counting.heads.load(Ordering::SeqCst), 3);
+  }
+
+  #[tokio::test]
+  async fn read_buffer_size() {
+    let headers = Default::default();
+
+    let storage = Storage::new(CountingStorage::default()).with_read_buffer_size(Some(1024));
+    let streamable = storage
+      .get("key", GetOptions::new_with_default_range(&headers))
+      .await
+      .unwrap();
+    assert_eq!(streamable.buffer_size(), Some(1024));
+
+    let storage = Storage::new(CountingStorage::default());
+    let streamable = storage
+      .get("key", GetOptions::new_with_default_range(&headers))
+      .await
+      .unwrap();
+    assert_eq!(streamable.buffer_size(), None);
+  }
+
+  #[tokio::test]
+  async fn read_ahead() {
+    let counting = CountingStorage::default();
+    let storage = Storage::new(counting.clone()).with_read_ahead(Some(100));
+
+    let headers = Default::default();
+    for start in (0..50).step_by(10) {
+      let range = BytesPosition::default()
+        .with_start(start)
+        .with_end(start + 10);
+
+      let mut data = Vec::new();
+      storage
+        .get("key", GetOptions::new(range, &headers))
+        .await
+        .unwrap()
+        .read_to_end(&mut data)
+        .await
+        .unwrap();
+      assert_eq!(data, test_data()[start as usize..][..10]);
+    }
+
+    // The first range is read directly, the second is read ahead, and the rest are buffered.
+    assert_eq!(counting.gets.load(Ordering::SeqCst), 2);
+  }
+
+  #[tokio::test]
+  async fn no_read_ahead() {
+    let counting = CountingStorage::default();
+    let storage = Storage::new(counting.clone());
+
+    let headers = Default::default();
+    for start in (0..50).step_by(10) {
+      let range = BytesPosition::default()
+        .with_start(start)
+        .with_end(start + 10);
+
+      storage
+        .get("key", GetOptions::new(range, &headers))
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(counting.gets.load(Ordering::SeqCst), 5);
+  }
+
+  #[tokio::test]
+  async fn concurrency_limit_held_by_stream() {
+    let storage = Storage::new(CountingStorage::default())
+      .with_concurrency_limit(&ConcurrencyLimit::new(Some(1)));
+    let semaphore = storage.semaphore.clone().unwrap();
+    let headers = Default::default();
+
+    let mut stream = storage
+      .get("key", GetOptions::new_with_default_range(&headers))
+      .await
+      .unwrap();
+    assert_eq!(semaphore.available_permits(), 0);
+
+    let mut data = vec![];
+    stream.read_to_end(&mut data).await.unwrap();
+    assert_eq!(data, test_data());
+    assert_eq!(semaphore.available_permits(), 0);
+
+    drop(stream);
+    assert_eq!(semaphore.available_permits(), 1);
+  }
+
+  #[tokio::test]
+  async fn no_concurrency_limit() {
+    let counting = CountingStorage::default();
+    let storage = Storage::new(counting.clone());
+
+    let tasks: Vec<_> = (0..10)
+      .map(|_| {
+        let storage = storage.clone();
+        tokio::spawn(async move {
+          let headers = Default::default();
+          storage
+            .head("key", HeadOptions::new(&headers))
+            .await
+            .unwrap();
+        })
+      })
+      .collect();
+    for task in tasks {
+      task.await.unwrap();
+    }
+
+    assert!(counting.max.load(Ordering::SeqCst) > 2);
+  }
+
   #[test]
   fn data_url() {
     let result = LocalStorage::<ConfigLocalStorage>::new(
@@ -333,6 +992,50 @@ mod tests {
     test_formatter_authority(formatter, "http");
   }
 
+  #[tokio::test]
+  async fn etag_changes_with_index_modified() {
+    let (folder, base_path) = create_local_test_files().await;
+    let index_keys = vec![format!("{folder}/key2")];
+    let headers = Default::default();
+
+    let storage = Storage::new(test_local_storage(base_path.path()));
+    assert_eq!(
+      storage
+        .etag("key1", &index_keys, HeadOptions::new(&headers))
+        .await
+        .unwrap(),
+      None
+    );
+
+    let storage = storage.with_etag(true);
+    let etag = storage
+      .etag("key1", &index_keys, HeadOptions::new(&headers))
+      .await
+      .unwrap();
+    assert!(etag.is_some());
+    assert_eq!(
+      storage
+        .etag("key1", &index_keys, HeadOptions::new(&headers))
+        .await
+        .unwrap(),
+      etag
+    );
+
+    std::fs::File::options()
+      .write(true)
+      .open(base_path.path().join(folder).join("key2"))
+      .unwrap()
+      .set_modified(UNIX_EPOCH + Duration::from_secs(1))
+      .unwrap();
+
+    let modified_etag = storage
+      .etag("key1", &index_keys, HeadOptions::new(&headers))
+      .await
+      .unwrap();
+    assert!(modified_etag.is_some());
+    assert_ne!(modified_etag, etag);
+  }
+
   #[test]
   fn https_formatter_authority() {
     let formatter = ConfigLocalStorage::new(
@@ -345,6 +1048,25 @@ mod tests {
     test_formatter_authority(formatter, "https");
   }
 
+  #[test]
+  fn signed_formatter() {
+    let signing_key = UrlSigningKey::new("secret");
+    let formatter = ConfigLocalStorage::new(
+      Scheme::Http,
+      Authority::from_static("127.0.0.1:8080"),
+      "data".to_string(),
+      "/data".to_string(),
+      false,
+    )
+    .set_signing_key(Some(signing_key.clone()));
+
+    let url = formatter.format_url("path").unwrap();
+    let (url, query) = url.split_once('?').unwrap();
+
+    assert_eq!(url, "http://127.0.0.1:8080/data/path");
+    assert!(signing_key.verify("/data/path", Some(query)));
+  }
+
   fn test_formatter_authority(formatter: ConfigLocalStorage, scheme: &str) {
     assert_eq!(
       formatter.format_url("path").unwrap(),
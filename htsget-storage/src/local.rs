@@ -4,6 +4,7 @@
 use std::fmt::Debug;
 use std::io::{ErrorKind, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use crate::{HeadOptions, StorageMiddleware, StorageTrait, UrlFormatter};
 use crate::{Streamable, Url as HtsGetUrl};
@@ -23,6 +24,7 @@ use super::{GetOptions, RangeUrlOptions, Result, StorageError};
 pub struct LocalStorage<T> {
   base_path: PathBuf,
   url_formatter: T,
+  reject_path_traversal: bool,
 }
 
 impl<T: UrlFormatter + Send + Sync> LocalStorage<T> {
@@ -35,9 +37,17 @@ impl<T: UrlFormatter + Send + Sync> LocalStorage<T> {
       .map(|canonicalized_base_path| Self {
         base_path: canonicalized_base_path,
         url_formatter,
+        reject_path_traversal: false,
       })
   }
 
+  /// Set whether keys containing path traversal sequences, such as `../`, or absolute paths are
+  /// rejected before accessing any files.
+  pub fn with_reject_path_traversal(mut self, reject_path_traversal: bool) -> Self {
+    self.reject_path_traversal = reject_path_traversal;
+    self
+  }
+
   pub fn base_path(&self) -> &Path {
     self.base_path.as_path()
   }
@@ -45,6 +55,12 @@ impl<T: UrlFormatter + Send + Sync> LocalStorage<T> {
   pub(crate) fn get_path_from_key<K: AsRef<str>>(&self, key: K) -> Result<PathBuf> {
     let key: &str = key.as_ref();
 
+    if self.reject_path_traversal && is_path_traversal(key) {
+      return Err(StorageError::InvalidInput(format!(
+        "`{key}` contains a path traversal sequence"
+      )));
+    }
+
     self
       .base_path
       .join(key)
@@ -78,6 +94,19 @@ impl<T: UrlFormatter + Send + Sync> LocalStorage<T> {
   }
 }
 
+/// Check whether the key is an absolute path or contains a `..` component, including
+/// percent-encoded separators and dots.
+fn is_path_traversal(key: &str) -> bool {
+  let key = key
+    .to_ascii_lowercase()
+    .replace("%2f", "/")
+    .replace("%5c", "/")
+    .replace("%2e", ".")
+    .replace('\\', "/");
+
+  key.starts_with('/') || key.split('/').any(|component| component == "..")
+}
+
 #[async_trait]
 impl<T: UrlFormatter + Send + Sync + Debug> StorageMiddleware for LocalStorage<T> {}
 
@@ -136,6 +165,28 @@ impl<T: UrlFormatter + Send + Sync + Debug + Clone + 'static> StorageTrait for L
     debug!(calling_from = ?self, key = key, len, "size of key {:?} is {}", key, len);
     Ok(len)
   }
+
+  /// Get the modification time of the file.
+  #[instrument(level = "debug", skip(self))]
+  async fn modified(&self, key: &str, _options: HeadOptions<'_>) -> Result<Option<SystemTime>> {
+    let path = self.get_path_from_key(key)?;
+    let modified = fs::metadata(path)
+      .await
+      .and_then(|metadata| metadata.modified())
+      .map_err(|err| StorageError::KeyNotFound(err.to_string()))?;
+
+    Ok(Some(modified))
+  }
+
+  /// Check that the base path is still a directory.
+  async fn probe(&self) -> Result<()> {
+    fs::metadata(&self.base_path)
+      .await
+      .ok()
+      .filter(|metadata| metadata.is_dir())
+      .map(|_| ())
+      .ok_or_else(|| StorageError::KeyNotFound(self.base_path.to_string_lossy().to_string()))
+  }
 }
 
 #[cfg(test)]
@@ -195,6 +246,44 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn get_path_traversal_rejected() {
+    with_local_storage(|storage, _| async move {
+      let storage = storage.with_reject_path_traversal(true);
+      for key in [
+        "../passwords",
+        "folder/..%2f..%2fpasswords",
+        "/etc/passwords",
+      ] {
+        let result = StorageTrait::get(
+          &storage,
+          key,
+          GetOptions::new_with_default_range(&Default::default()),
+        )
+        .await;
+        assert!(
+          matches!(result, Err(StorageError::InvalidInput(_))),
+          "{key}"
+        );
+
+        let result = StorageTrait::head(&storage, key, HeadOptions::new(&Default::default())).await;
+        assert!(
+          matches!(result, Err(StorageError::InvalidInput(_))),
+          "{key}"
+        );
+      }
+
+      let result = StorageTrait::get(
+        &storage,
+        "folder/key2",
+        GetOptions::new_with_default_range(&Default::default()),
+      )
+      .await;
+      assert!(result.is_ok());
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn get_existing_key() {
     with_local_storage(|storage, _| async move {
@@ -318,6 +407,19 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn probe_base_path() {
+    let (_, base_path) = create_local_test_files().await;
+    let storage = test_local_storage(base_path.path());
+    assert!(StorageTrait::probe(&storage).await.is_ok());
+
+    drop(base_path);
+    assert!(matches!(
+      StorageTrait::probe(&storage).await,
+      Err(StorageError::KeyNotFound(_))
+    ));
+  }
+
   pub(crate) async fn create_local_test_files() -> (String, TempDir) {
     let base_path = TempDir::new().unwrap();
 
@@ -0,0 +1,121 @@
+//! Read-ahead for sequential range reads from storage.
+//!
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::types::BytesPosition;
+
+/// Reads ahead of sequential range requests for a key. When a range directly follows the previous
+/// range requested for the same key, the range is extended by the read-ahead size and the extra
+/// bytes are buffered, so that the following adjacent ranges are served without another request
+/// to the inner storage.
+#[derive(Debug, Clone)]
+pub struct ReadAhead {
+  size: u64,
+  state: Arc<Mutex<HashMap<String, ReadAheadState>>>,
+}
+
+/// The read-ahead state of a key.
+#[derive(Debug, Default)]
+struct ReadAheadState {
+  last_end: Option<u64>,
+  buffer: Option<(u64, Arc<Vec<u8>>)>,
+}
+
+impl ReadAhead {
+  /// Create a new read-ahead which reads `size` bytes past the end of sequential ranges.
+  pub fn new(size: u64) -> Self {
+    Self {
+      size,
+      state: Default::default(),
+    }
+  }
+
+  /// Get the read-ahead size.
+  pub fn size(&self) -> u64 {
+    self.size
+  }
+
+  /// Get the bytes of the range from the buffer of the key, if the buffer contains the whole range.
+  pub fn get_buffered(&self, key: &str, range: &BytesPosition) -> Option<Vec<u8>> {
+    let (start, end) = (range.get_start()?, range.get_end()?);
+
+    let mut state = self.state.lock().expect("read-ahead lock poisoned");
+    let state = state.get_mut(key)?;
+    let (buffer_start, buffer) = state.buffer.as_ref()?;
+
+    let buffer_end = buffer_start + u64::try_from(buffer.len()).ok()?;
+    if start < *buffer_start || end > buffer_end || start > end {
+      return None;
+    }
+
+    let data = buffer[usize::try_from(start - buffer_start).ok()?..]
+      [..usize::try_from(end - start).ok()?]
+      .to_vec();
+    state.last_end = Some(end);
+
+    Some(data)
+  }
+
+  /// Record a request for the range of the key. If the range directly follows the previous range
+  /// requested for the key, this returns the range extended by the read-ahead size.
+  pub fn next_range(&self, key: &str, range: &BytesPosition) -> Option<BytesPosition> {
+    let mut state = self.state.lock().expect("read-ahead lock poisoned");
+    let state = state.entry(key.to_string()).or_default();
+
+    let previous_end = state.last_end.take();
+    let (start, end) = (range.get_start()?, range.get_end()?);
+    state.last_end = Some(end);
+
+    (previous_end == Some(start)).then(|| {
+      BytesPosition::default()
+        .with_start(start)
+        .with_end(end.saturating_add(self.size))
+    })
+  }
+
+  /// Buffer the data of the key, which starts at the `start` position.
+  pub fn insert(&self, key: &str, start: u64, data: Vec<u8>) {
+    let mut state = self.state.lock().expect("read-ahead lock poisoned");
+    state.entry(key.to_string()).or_default().buffer = Some((start, Arc::new(data)));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn next_range_adjacent() {
+    let read_ahead = ReadAhead::new(100);
+
+    let first = BytesPosition::default().with_start(0).with_end(10);
+    assert_eq!(read_ahead.next_range("key", &first), None);
+
+    let second = BytesPosition::default().with_start(10).with_end(20);
+    assert_eq!(
+      read_ahead.next_range("key", &second),
+      Some(BytesPosition::default().with_start(10).with_end(120))
+    );
+
+    let other = BytesPosition::default().with_start(20).with_end(30);
+    assert_eq!(read_ahead.next_range("other", &other), None);
+  }
+
+  #[test]
+  fn get_buffered() {
+    let read_ahead = ReadAhead::new(100);
+    read_ahead.insert("key", 10, (10..20).collect());
+
+    let range = BytesPosition::default().with_start(12).with_end(15);
+    assert_eq!(
+      read_ahead.get_buffered("key", &range),
+      Some(vec![12, 13, 14])
+    );
+
+    let range = BytesPosition::default().with_start(15).with_end(25);
+    assert_eq!(read_ahead.get_buffered("key", &range), None);
+    assert_eq!(read_ahead.get_buffered("other", &range), None);
+  }
+}
@@ -2,25 +2,28 @@
 //!
 
 use std::fmt::Debug;
+use std::future::Future;
 use std::io;
 use std::io::ErrorKind::Other;
 use std::pin::Pin;
+use std::result;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
 use async_trait::async_trait;
 use aws_config::BehaviorVersion;
-use aws_sdk_s3::error::{DisplayErrorContext, SdkError};
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
 use aws_sdk_s3::operation::get_object::builders::GetObjectFluentBuilder;
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
 use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::ByteStream;
-use aws_sdk_s3::types::StorageClass;
+use aws_sdk_s3::types::{RequestPayer, StorageClass};
 use aws_sdk_s3::Client;
 use bytes::Bytes;
 use futures::Stream;
 use pin_project_lite::pin_project;
+use tokio::time::sleep;
 use tokio_util::io::StreamReader;
 use tracing::instrument;
 use tracing::{debug, warn};
@@ -30,7 +33,10 @@ use crate::s3::Retrieval::{Delayed, Immediate};
 use crate::types::{BytesPosition, BytesRange};
 use crate::StorageError::{AwsS3Error, IoError, KeyNotFound};
 use crate::{HeadOptions, StorageError, StorageMiddleware, StorageTrait};
-use crate::{Streamable, Url};
+use crate::{Headers, Streamable, Url};
+
+/// The header which charges the requester for requests to requester pays buckets.
+const REQUEST_PAYER_HEADER: &str = "x-amz-request-payer";
 
 /// Represents data classes that can be retrieved immediately or after a delay.
 /// Specifically, Glacier Flexible, Glacier Deep Archive, and Intelligent Tiering archive
@@ -46,14 +52,98 @@ pub enum Retrieval {
 pub struct S3Storage {
   client: Client,
   bucket: String,
+  slow_down_retries: u32,
+  slow_down_backoff: Duration,
+  request_payer: bool,
+  presign_expiry: Duration,
 }
 
 impl S3Storage {
-  // Allow the user to set this?
+  /// The default number of seconds that presigned URLs are valid for.
   pub const PRESIGNED_REQUEST_EXPIRY: u64 = 1000;
+  /// The maximum number of seconds that presigned URLs can be valid for, which is 7 days.
+  pub const MAX_PRESIGNED_REQUEST_EXPIRY: u64 = 604800;
 
   pub fn new(client: Client, bucket: String) -> Self {
-    S3Storage { client, bucket }
+    S3Storage {
+      client,
+      bucket,
+      slow_down_retries: 0,
+      slow_down_backoff: Duration::default(),
+      request_payer: false,
+      presign_expiry: Duration::from_secs(Self::PRESIGNED_REQUEST_EXPIRY),
+    }
+  }
+
+  /// Set how long presigned URLs are valid for. Returns an error if this exceeds the maximum of
+  /// 7 days allowed by AWS.
+  pub fn with_presign_expiry(mut self, presign_expiry: Duration) -> Result<Self> {
+    if presign_expiry > Duration::from_secs(Self::MAX_PRESIGNED_REQUEST_EXPIRY) {
+      return Err(StorageError::InvalidInput(format!(
+        "presigned URL expiry of {}s exceeds the maximum of {}s",
+        presign_expiry.as_secs(),
+        Self::MAX_PRESIGNED_REQUEST_EXPIRY
+      )));
+    }
+
+    self.presign_expiry = presign_expiry;
+    Ok(self)
+  }
+
+  /// Send requests with `x-amz-request-payer: requester`, so that requests to requester pays
+  /// buckets succeed.
+  pub fn with_request_payer(mut self, request_payer: bool) -> Self {
+    self.request_payer = request_payer;
+    self
+  }
+
+  /// Get the request payer which is set on requests, if any.
+  fn request_payer(&self) -> Option<RequestPayer> {
+    self.request_payer.then_some(RequestPayer::Requester)
+  }
+
+  /// Retry requests up to `retries` times when S3 responds with `SlowDown`, starting with the
+  /// `backoff` delay and doubling it after each retry. This happens after the retries of the AWS
+  /// SDK have been exhausted, so a request is attempted up to `(retries + 1)` times the SDK's
+  /// maximum attempts.
+  pub fn with_slow_down_retry(mut self, retries: u32, backoff: Duration) -> Self {
+    self.slow_down_retries = retries;
+    self.slow_down_backoff = backoff;
+    self
+  }
+
+  /// Send a request, retrying it with exponential backoff if S3 responds with `SlowDown`.
+  async fn retry_slow_down<T, E, R, F, Fut>(
+    &self,
+    mut request: F,
+  ) -> result::Result<T, SdkError<E, R>>
+  where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = result::Result<T, SdkError<E, R>>>,
+    E: ProvideErrorMetadata,
+  {
+    let mut backoff = self.slow_down_backoff;
+    let mut retries = 0;
+    loop {
+      match request().await {
+        Err(err) if retries < self.slow_down_retries && Self::is_slow_down(&err) => {
+          warn!(
+            retries,
+            ?backoff,
+            "S3 responded with `SlowDown`, retrying request"
+          );
+
+          sleep(backoff).await;
+          backoff *= 2;
+          retries += 1;
+        }
+        result => return result,
+      }
+    }
+  }
+
+  fn is_slow_down<E: ProvideErrorMetadata, R>(err: &SdkError<E, R>) -> bool {
+    err.as_service_error().and_then(|err| err.code()) == Some("SlowDown")
   }
 
   pub async fn new_with_default_config(
@@ -83,12 +173,13 @@ impl S3Storage {
       .client
       .get_object()
       .bucket(&self.bucket)
-      .key(key.as_ref());
+      .key(key.as_ref())
+      .set_request_payer(self.request_payer());
     let response = Self::apply_range(response, range);
     Ok(
       response
         .presigned(
-          PresigningConfig::expires_in(Duration::from_secs(Self::PRESIGNED_REQUEST_EXPIRY))
+          PresigningConfig::expires_in(self.presign_expiry)
             .map_err(|err| AwsS3Error(err.to_string(), key.as_ref().to_string()))?,
         )
         .await
@@ -98,13 +189,41 @@ impl S3Storage {
     )
   }
 
+  /// Return an S3 pre-signed URL for a `HEAD` request of the key.
+  pub async fn s3_presign_head_url<K: AsRef<str> + Send>(&self, key: K) -> Result<String> {
+    Ok(
+      self
+        .client
+        .head_object()
+        .bucket(&self.bucket)
+        .key(key.as_ref())
+        .set_request_payer(self.request_payer())
+        .presigned(
+          PresigningConfig::expires_in(self.presign_expiry)
+            .map_err(|err| AwsS3Error(err.to_string(), key.as_ref().to_string()))?,
+        )
+        .await
+        .map_err(|err| {
+          AwsS3Error(
+            err.into_service_error().to_string(),
+            key.as_ref().to_string(),
+          )
+        })?
+        .uri()
+        .to_string(),
+    )
+  }
+
   async fn s3_head<K: AsRef<str> + Send>(&self, key: K) -> Result<HeadObjectOutput> {
-    self
+    let request = self
       .client
       .head_object()
       .bucket(&self.bucket)
       .key(key.as_ref())
-      .send()
+      .set_request_payer(self.request_payer());
+
+    self
+      .retry_slow_down(|| request.clone().send())
       .await
       .map_err(|err| {
         warn!("S3 error: {}", DisplayErrorContext(&err));
@@ -118,6 +237,20 @@ impl S3Storage {
       })
   }
 
+  /// Check that the bucket exists and is accessible.
+  async fn s3_head_bucket(&self) -> Result<()> {
+    let request = self.client.head_bucket().bucket(&self.bucket);
+
+    self
+      .retry_slow_down(|| request.clone().send())
+      .await
+      .map(|_| ())
+      .map_err(|err| {
+        warn!("S3 error: {}", DisplayErrorContext(&err));
+        AwsS3Error(err.into_service_error().to_string(), self.bucket.clone())
+      })
+  }
+
   /// Returns the retrieval type of the object stored with the key.
   #[instrument(level = "trace", skip_all, ret)]
   pub async fn get_retrieval_type<K: AsRef<str> + Send>(&self, key: K) -> Result<Retrieval> {
@@ -177,11 +310,12 @@ impl S3Storage {
       .client
       .get_object()
       .bucket(&self.bucket)
-      .key(key.as_ref());
+      .key(key.as_ref())
+      .set_request_payer(self.request_payer());
     let response = Self::apply_range(response, options.range());
     Ok(
-      response
-        .send()
+      self
+        .retry_slow_down(|| response.clone().send())
         .await
         .map_err(|err| Self::map_get_error(key, err))?
         .body,
@@ -261,7 +395,11 @@ impl StorageTrait for S3Storage {
   #[instrument(level = "trace", skip(self))]
   async fn range_url(&self, key: &str, options: RangeUrlOptions<'_>) -> Result<Url> {
     let presigned_url = self.s3_presign_url(key, options.range()).await?;
-    let url = options.apply(Url::new(presigned_url));
+    let mut url = options.apply(Url::new(presigned_url));
+    if self.request_payer {
+      // The request payer is a signed header of the presigned url, so clients must send it.
+      url = url.add_headers(Headers::default().with_header(REQUEST_PAYER_HEADER, "requester"));
+    }
 
     debug!(calling_from = ?self, key, ?url, "getting url with key {:?}", key);
     Ok(url)
@@ -286,14 +424,29 @@ impl StorageTrait for S3Storage {
     debug!(calling_from = ?self, key, len, "size of key {:?} is {}", key, len);
     Ok(len)
   }
+
+  /// Check that the S3 bucket is reachable.
+  #[instrument(level = "trace", skip(self))]
+  async fn probe(&self) -> Result<()> {
+    self.s3_head_bucket().await
+  }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
   use std::future::Future;
   use std::path::{Path, PathBuf};
-
+  use std::time::Duration;
+
+  use aws_sdk_s3::error::ErrorMetadata;
+  use aws_sdk_s3::operation::get_object::{GetObjectError, GetObjectOutput};
+  use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
+  use aws_sdk_s3::primitives::ByteStream;
+  use aws_sdk_s3::types::RequestPayer;
+  use aws_sdk_s3::Client;
+  use aws_smithy_mocks_experimental::{mock, mock_client, Rule, RuleMode};
   use htsget_test::aws_mocks::with_s3_test_server;
+  use tokio::io::AsyncReadExt;
 
   use crate::local::tests::create_local_test_files;
   use crate::s3::S3Storage;
@@ -420,6 +573,61 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn url_with_presign_expiry() {
+    with_aws_s3_storage(|storage, _| async move {
+      let result = storage
+        .with_presign_expiry(Duration::from_secs(3600))
+        .unwrap()
+        .range_url(
+          "key2",
+          RangeUrlOptions::new_with_default_range(&Default::default()),
+        )
+        .await
+        .unwrap();
+      assert!(result.url.contains("X-Amz-Expires=3600"));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn presign_expiry_exceeds_maximum() {
+    with_aws_s3_storage(|storage, _| async move {
+      let result = storage.with_presign_expiry(Duration::from_secs(
+        S3Storage::MAX_PRESIGNED_REQUEST_EXPIRY + 1,
+      ));
+      assert!(matches!(result, Err(StorageError::InvalidInput(_))));
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn url_with_request_payer() {
+    with_aws_s3_storage(|storage, _| async move {
+      let result = storage
+        .with_request_payer(true)
+        .range_url(
+          "key2",
+          RangeUrlOptions::new(
+            BytesPosition::new(Some(7), Some(9), None),
+            &Default::default(),
+          ),
+        )
+        .await
+        .unwrap();
+      assert!(result.url.contains("x-amz-request-payer"));
+      assert_eq!(
+        result.headers,
+        Some(
+          Headers::default()
+            .with_header("Range", "bytes=7-8")
+            .with_header("x-amz-request-payer", "requester")
+        )
+      );
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn file_size() {
     with_aws_s3_storage(|storage, _| async move {
@@ -440,4 +648,81 @@ pub(crate) mod tests {
     })
     .await;
   }
+
+  fn slow_down_metadata() -> ErrorMetadata {
+    ErrorMetadata::builder()
+      .code("SlowDown")
+      .message("Please reduce your request rate.")
+      .build()
+  }
+
+  fn head_object_rules() -> (Rule, Rule) {
+    let slow_down =
+      mock!(Client::head_object).then_error(|| HeadObjectError::generic(slow_down_metadata()));
+    let success = mock!(Client::head_object)
+      .then_output(|| HeadObjectOutput::builder().content_length(6).build());
+
+    (slow_down, success)
+  }
+
+  fn mock_s3_storage(rules: &[&Rule], retries: u32) -> S3Storage {
+    let client = mock_client!(aws_sdk_s3, RuleMode::Sequential, rules);
+
+    S3Storage::new(client, "bucket".to_string())
+      .with_slow_down_retry(retries, Duration::from_millis(1))
+  }
+
+  #[tokio::test]
+  async fn head_slow_down_retry() {
+    let (slow_down, success) = head_object_rules();
+    let storage = mock_s3_storage(&[&slow_down, &success], 3);
+
+    let result = storage
+      .head("key", HeadOptions::new(&Default::default()))
+      .await;
+
+    assert!(matches!(result, Ok(6)));
+  }
+
+  #[tokio::test]
+  async fn head_request_payer() {
+    let head = mock!(Client::head_object)
+      .match_requests(|request| request.request_payer() == Some(&RequestPayer::Requester))
+      .then_output(|| HeadObjectOutput::builder().content_length(6).build());
+    let storage = mock_s3_storage(&[&head], 0).with_request_payer(true);
+
+    let result = storage
+      .head("key", HeadOptions::new(&Default::default()))
+      .await;
+
+    assert!(matches!(result, Ok(6)));
+  }
+
+  #[tokio::test]
+  async fn get_slow_down_retry() {
+    let (head_slow_down, head_success) = head_object_rules();
+    let get_slow_down =
+      mock!(Client::get_object).then_error(|| GetObjectError::generic(slow_down_metadata()));
+    let get_success = mock!(Client::get_object).then_output(|| {
+      GetObjectOutput::builder()
+        .body(ByteStream::from_static(b"value2"))
+        .build()
+    });
+    let storage = mock_s3_storage(
+      &[&head_slow_down, &head_success, &get_slow_down, &get_success],
+      3,
+    );
+
+    let mut result = storage
+      .get(
+        "key",
+        GetOptions::new_with_default_range(&Default::default()),
+      )
+      .await
+      .unwrap();
+
+    let mut buf = vec![];
+    result.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"value2");
+  }
 }
@@ -21,6 +21,78 @@ impl DataBlock {
       .collect()
   }
 
+  /// Get the number of bytes that this block represents, if it is known.
+  pub fn byte_count(&self) -> Option<u64> {
+    match self {
+      DataBlock::Range(range) => Some(range.end? - range.start.unwrap_or_default()),
+      DataBlock::Data(data, _) => u64::try_from(data.len()).ok(),
+    }
+  }
+
+  /// Split all range blocks so that each covers at most `max_bytes`, merging a trailing fragment
+  /// smaller than `min_block_size` into the previous fragment. Data blocks are left unchanged.
+  pub fn split_ranges(blocks: Vec<Self>, max_bytes: u64, min_block_size: u64) -> Vec<Self> {
+    blocks
+      .into_iter()
+      .flat_map(|block| match block {
+        DataBlock::Range(range) => range
+          .split(max_bytes, min_block_size)
+          .into_iter()
+          .map(DataBlock::Range)
+          .collect(),
+        block => vec![block],
+      })
+      .collect()
+  }
+
+  /// Coalesce neighbouring range blocks across the smallest gaps between them, so that there are
+  /// at most `max_blocks` blocks. Coalesced ranges also cover the bytes in the gaps. Only ranges of
+  /// the same class which are not separated by a data block are coalesced, so there may still be
+  /// more than `max_blocks` blocks if there are not enough of these.
+  pub fn coalesce_ranges(blocks: Vec<Self>, max_blocks: usize) -> Vec<Self> {
+    let excess = blocks.len().saturating_sub(max_blocks.max(1));
+    if excess == 0 {
+      return blocks;
+    }
+
+    let mut gaps = blocks
+      .windows(2)
+      .filter_map(|pair| match pair {
+        [DataBlock::Range(previous), DataBlock::Range(range)] if previous.class == range.class => {
+          previous.gap_to(range)
+        }
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    gaps.sort_unstable();
+    let Some(gap) = gaps.get(excess.min(gaps.len()).saturating_sub(1)).copied() else {
+      return blocks;
+    };
+
+    let mut coalesced = Vec::with_capacity(blocks.len() - excess);
+    let mut ranges = Vec::new();
+    for block in blocks {
+      match block {
+        DataBlock::Range(range) => ranges.push(range),
+        block => {
+          coalesced.extend(
+            BytesPosition::merge(ranges.drain(..).collect(), true, gap)
+              .into_iter()
+              .map(DataBlock::Range),
+          );
+          coalesced.push(block);
+        }
+      }
+    }
+    coalesced.extend(
+      BytesPosition::merge(ranges, true, gap)
+        .into_iter()
+        .map(DataBlock::Range),
+    );
+
+    coalesced
+  }
+
   /// Update the classes of all blocks so that they all contain a class, or None. Does not merge
   /// byte positions.
   pub fn update_classes(blocks: Vec<Self>) -> Vec<Self> {
@@ -159,9 +231,81 @@ impl BytesPosition {
     self
   }
 
+  /// Split this position into consecutive positions which each cover at most `max_bytes`. If the
+  /// last fragment would be smaller than `min_block_size`, it is merged into the previous fragment,
+  /// so that fragment may exceed `max_bytes`. Positions without a known end are not split.
+  pub fn split(self, max_bytes: u64, min_block_size: u64) -> Vec<BytesPosition> {
+    let start = self.start.unwrap_or_default();
+    let Some(end) = self.end else {
+      return vec![self];
+    };
+    if max_bytes == 0 || end.saturating_sub(start) <= max_bytes {
+      return vec![self];
+    }
+
+    let mut fragments: Vec<BytesPosition> = Vec::new();
+    let mut fragment_start = start;
+    while fragment_start < end {
+      let fragment_end = fragment_start.saturating_add(max_bytes).min(end);
+      match fragments.last_mut() {
+        Some(previous) if fragment_end - fragment_start < min_block_size => {
+          previous.end = Some(fragment_end);
+        }
+        _ => fragments.push(BytesPosition::new(
+          Some(fragment_start),
+          Some(fragment_end),
+          self.class,
+        )),
+      }
+      fragment_start = fragment_end;
+    }
+
+    if let Some(first) = fragments.first_mut() {
+      first.start = self.start;
+    }
+
+    fragments
+  }
+
   /// Merge ranges, assuming ending byte ranges are exclusive.
   #[instrument(level = "trace", ret)]
-  pub fn merge_all(mut ranges: Vec<BytesPosition>) -> Vec<BytesPosition> {
+  pub fn merge_all(ranges: Vec<BytesPosition>) -> Vec<BytesPosition> {
+    Self::merge(ranges, false, 0)
+  }
+
+  /// Merge ranges, assuming ending byte ranges are exclusive, and also merge ranges of the same
+  /// class which are separated by at most `gap` bytes. This results in fewer ranges at the cost of
+  /// also fetching the bytes in the gaps.
+  #[instrument(level = "trace", ret)]
+  pub fn merge_all_with_gap(ranges: Vec<BytesPosition>, gap: u64) -> Vec<BytesPosition> {
+    Self::merge(ranges, false, gap)
+  }
+
+  /// Merge ranges which overlap or are separated by at most `gap` bytes, without merging ranges
+  /// which have different classes.
+  #[instrument(level = "trace", ret)]
+  pub fn merge_all_with_gap_preserving_classes(
+    ranges: Vec<BytesPosition>,
+    gap: u64,
+  ) -> Vec<BytesPosition> {
+    Self::merge(ranges, true, gap)
+  }
+
+  /// Get the number of bytes between the end of this position and the start of the following
+  /// position, if both are known and the positions do not overlap.
+  fn gap_to(&self, range: &BytesPosition) -> Option<u64> {
+    range.start?.checked_sub(self.end?)
+  }
+
+  /// Merge ranges, assuming ending byte ranges are exclusive, without merging ranges which have
+  /// different classes. Where a range overlaps a range of a different class, the overlapping bytes
+  /// are only returned by the range that comes first.
+  #[instrument(level = "trace", ret)]
+  pub fn merge_all_preserving_classes(ranges: Vec<BytesPosition>) -> Vec<BytesPosition> {
+    Self::merge(ranges, true, 0)
+  }
+
+  fn merge(mut ranges: Vec<BytesPosition>, preserve_classes: bool, gap: u64) -> Vec<BytesPosition> {
     if ranges.len() < 2 {
       ranges
     } else {
@@ -183,11 +327,25 @@ impl BytesPosition {
       let mut current_range = ranges[0].clone();
 
       for range in ranges.iter().skip(1) {
-        if current_range.overlaps(range) {
+        let same_class = current_range.class == range.class;
+        if !current_range.overlaps(range) {
+          if same_class && current_range.gap_to(range).is_some_and(|to| to <= gap) {
+            current_range.merge_with(range);
+          } else {
+            optimized_ranges.push(current_range);
+            current_range = range.clone();
+          }
+        } else if !preserve_classes || same_class {
           current_range.merge_with(range);
         } else {
-          optimized_ranges.push(current_range);
-          current_range = range.clone();
+          match (current_range.end, range.end) {
+            (None, _) => {}
+            (Some(current_end), Some(end)) if end <= current_end => {}
+            (Some(current_end), _) => {
+              optimized_ranges.push(current_range);
+              current_range = range.clone().with_start(current_end);
+            }
+          }
         }
       }
 
@@ -241,11 +399,40 @@ impl<'a> GetOptions<'a> {
 pub struct BytesPositionOptions<'a> {
   pub(crate) positions: Vec<BytesPosition>,
   pub(crate) headers: &'a HeaderMap,
+  pub(crate) preserve_classes: bool,
+  pub(crate) merge_gap: u64,
 }
 
 impl<'a> BytesPositionOptions<'a> {
   pub fn new(positions: Vec<BytesPosition>, headers: &'a HeaderMap) -> Self {
-    Self { positions, headers }
+    Self {
+      positions,
+      headers,
+      preserve_classes: false,
+      merge_gap: 0,
+    }
+  }
+
+  /// Set whether ranges with different classes are kept separate when merging.
+  pub fn with_preserve_classes(mut self, preserve_classes: bool) -> Self {
+    self.preserve_classes = preserve_classes;
+    self
+  }
+
+  /// Get whether ranges with different classes are kept separate when merging.
+  pub fn preserve_classes(&self) -> bool {
+    self.preserve_classes
+  }
+
+  /// Set the number of bytes that may separate two ranges which are merged.
+  pub fn with_merge_gap(mut self, merge_gap: u64) -> Self {
+    self.merge_gap = merge_gap;
+    self
+  }
+
+  /// Get the number of bytes that may separate two ranges which are merged.
+  pub fn merge_gap(&self) -> u64 {
+    self.merge_gap
   }
 
   /// Get the response headers.
@@ -262,9 +449,13 @@ impl<'a> BytesPositionOptions<'a> {
     self.positions
   }
 
-  /// Merge all bytes positions
+  /// Merge all bytes positions, including positions separated by at most the merge gap.
   pub fn merge_all(mut self) -> Self {
-    self.positions = BytesPosition::merge_all(self.positions);
+    self.positions = if self.preserve_classes {
+      BytesPosition::merge_all_with_gap_preserving_classes(self.positions, self.merge_gap)
+    } else {
+      BytesPosition::merge_all_with_gap(self.positions, self.merge_gap)
+    };
     self
   }
 }
@@ -554,6 +745,42 @@ mod tests {
     }
   }
 
+  #[test]
+  fn bytes_position_split() {
+    assert_eq!(
+      BytesPosition::new(Some(0), Some(25), Some(Class::Body)).split(10, 0),
+      vec![
+        BytesPosition::new(Some(0), Some(10), Some(Class::Body)),
+        BytesPosition::new(Some(10), Some(20), Some(Class::Body)),
+        BytesPosition::new(Some(20), Some(25), Some(Class::Body))
+      ]
+    );
+  }
+
+  #[test]
+  fn bytes_position_split_merges_small_fragment() {
+    let fragments = BytesPosition::new(Some(0), Some(25), None).split(10, 6);
+
+    assert_eq!(
+      fragments,
+      vec![
+        BytesPosition::new(Some(0), Some(10), None),
+        BytesPosition::new(Some(10), Some(25), None)
+      ]
+    );
+    assert!(fragments
+      .iter()
+      .all(|fragment| fragment.get_end().unwrap() - fragment.get_start().unwrap() >= 6));
+  }
+
+  #[test]
+  fn bytes_position_split_unbounded() {
+    assert_eq!(
+      BytesPosition::new(Some(5), None, None).split(10, 6),
+      vec![BytesPosition::new(Some(5), None, None)]
+    );
+  }
+
   #[test]
   fn bytes_range_merge_all_when_list_is_empty() {
     assert_eq!(BytesPosition::merge_all(Vec::new()), Vec::new());
@@ -611,6 +838,82 @@ mod tests {
     );
   }
 
+  #[test]
+  fn bytes_position_merge_preserving_classes_different() {
+    assert_eq!(
+      BytesPosition::merge_all_preserving_classes(vec![
+        BytesPosition::new(Some(2), Some(3), Some(Class::Body)),
+        BytesPosition::new(Some(1), Some(2), Some(Class::Header)),
+        BytesPosition::new(Some(3), Some(5), Some(Class::Body))
+      ]),
+      vec![
+        BytesPosition::new(Some(1), Some(2), Some(Class::Header)),
+        BytesPosition::new(Some(2), Some(5), Some(Class::Body))
+      ]
+    );
+  }
+
+  #[test]
+  fn bytes_position_merge_preserving_classes_overlapping() {
+    assert_eq!(
+      BytesPosition::merge_all_preserving_classes(vec![
+        BytesPosition::new(Some(1), Some(4), Some(Class::Header)),
+        BytesPosition::new(Some(2), Some(3), Some(Class::Body)),
+        BytesPosition::new(Some(3), Some(6), Some(Class::Body))
+      ]),
+      vec![
+        BytesPosition::new(Some(1), Some(4), Some(Class::Header)),
+        BytesPosition::new(Some(4), Some(6), Some(Class::Body))
+      ]
+    );
+  }
+
+  #[test]
+  fn bytes_position_options_merge_all_preserve_classes() {
+    let headers = HeaderMap::default();
+    let positions = vec![
+      BytesPosition::new(Some(1), Some(2), Some(Class::Header)),
+      BytesPosition::new(Some(2), Some(3), Some(Class::Body)),
+    ];
+
+    assert_eq!(
+      BytesPositionOptions::new(positions.clone(), &headers)
+        .merge_all()
+        .into_inner(),
+      vec![BytesPosition::new(Some(1), Some(3), None)]
+    );
+    assert_eq!(
+      BytesPositionOptions::new(positions.clone(), &headers)
+        .with_preserve_classes(true)
+        .merge_all()
+        .into_inner(),
+      positions
+    );
+  }
+
+  #[test]
+  fn bytes_position_options_merge_all_with_merge_gap() {
+    let headers = HeaderMap::default();
+    let positions = vec![
+      BytesPosition::new(Some(1), Some(2), None),
+      BytesPosition::new(Some(4), Some(5), None),
+    ];
+
+    assert_eq!(
+      BytesPositionOptions::new(positions.clone(), &headers)
+        .merge_all()
+        .into_inner(),
+      positions
+    );
+    assert_eq!(
+      BytesPositionOptions::new(positions, &headers)
+        .with_merge_gap(2)
+        .merge_all()
+        .into_inner(),
+      vec![BytesPosition::new(Some(1), Some(5), None)]
+    );
+  }
+
   #[test]
   fn bytes_range_merge_all_when_list_has_many_ranges() {
     let ranges = vec![
@@ -646,6 +949,175 @@ mod tests {
     assert_eq!(BytesPosition::merge_all(ranges), expected_ranges);
   }
 
+  #[test]
+  fn bytes_range_merge_all_with_gap() {
+    let ranges = vec![
+      BytesPosition::new(Some(0), Some(2), None),
+      BytesPosition::new(Some(4), Some(6), None),
+      BytesPosition::new(Some(1), Some(3), None),
+      BytesPosition::new(Some(10), Some(12), None),
+      BytesPosition::new(Some(13), None, None),
+    ];
+
+    assert_eq!(
+      BytesPosition::merge_all_with_gap(ranges.clone(), 0),
+      BytesPosition::merge_all(ranges.clone())
+    );
+    assert_eq!(
+      BytesPosition::merge_all_with_gap(ranges.clone(), 1),
+      vec![
+        BytesPosition::new(Some(0), Some(6), None),
+        BytesPosition::new(Some(10), None, None)
+      ]
+    );
+    assert_eq!(
+      BytesPosition::merge_all_with_gap(ranges, 4),
+      vec![BytesPosition::new(Some(0), None, None)]
+    );
+  }
+
+  #[test]
+  fn bytes_range_merge_all_with_gap_cases() {
+    let header = Some(Class::Header);
+    let body = Some(Class::Body);
+    let cases = vec![
+      // The existing `merge_all` cases, which do not change with a gap of zero.
+      (vec![], 0, vec![]),
+      (
+        vec![BytesPosition::default()],
+        0,
+        vec![BytesPosition::default()],
+      ),
+      (
+        vec![
+          BytesPosition::new(None, Some(1), header),
+          BytesPosition::new(None, Some(2), header),
+        ],
+        0,
+        vec![BytesPosition::new(None, Some(2), header)],
+      ),
+      (
+        vec![
+          BytesPosition::new(None, Some(1), body),
+          BytesPosition::new(None, Some(3), body),
+        ],
+        0,
+        vec![BytesPosition::new(None, Some(3), body)],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(2), Some(3), None),
+        ],
+        0,
+        vec![BytesPosition::new(Some(1), Some(3), None)],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), header),
+          BytesPosition::new(Some(2), Some(3), body),
+        ],
+        0,
+        vec![BytesPosition::new(Some(1), Some(3), None)],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(3), Some(4), None),
+        ],
+        0,
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(3), Some(4), None),
+        ],
+      ),
+      // Ranges separated by at most the gap are merged.
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(3), Some(4), None),
+        ],
+        1,
+        vec![BytesPosition::new(Some(1), Some(4), None)],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(5), Some(6), None),
+        ],
+        2,
+        vec![
+          BytesPosition::new(Some(1), Some(2), None),
+          BytesPosition::new(Some(5), Some(6), None),
+        ],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), body),
+          BytesPosition::new(Some(5), Some(6), body),
+        ],
+        3,
+        vec![BytesPosition::new(Some(1), Some(6), body)],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(1), Some(2), header),
+          BytesPosition::new(Some(5), Some(6), body),
+        ],
+        3,
+        vec![
+          BytesPosition::new(Some(1), Some(2), header),
+          BytesPosition::new(Some(5), Some(6), body),
+        ],
+      ),
+      (
+        vec![
+          BytesPosition::new(Some(5), None, None),
+          BytesPosition::new(None, Some(1), None),
+        ],
+        4,
+        vec![BytesPosition::new(None, None, None)],
+      ),
+    ];
+
+    for (ranges, gap, expected) in cases {
+      if gap == 0 {
+        assert_eq!(BytesPosition::merge_all(ranges.clone()), expected);
+      }
+      assert_eq!(BytesPosition::merge_all_with_gap(ranges, gap), expected);
+    }
+  }
+
+  #[test]
+  fn bytes_range_merge_all_with_gap_class_different() {
+    let ranges = vec![
+      BytesPosition::new(Some(0), Some(2), Some(Class::Header)),
+      BytesPosition::new(Some(3), Some(5), Some(Class::Body)),
+    ];
+
+    assert_eq!(
+      BytesPosition::merge_all_with_gap(ranges.clone(), 10),
+      ranges
+    );
+  }
+
+  #[test]
+  fn bytes_range_merge_all_with_gap_preserving_classes() {
+    let ranges = vec![
+      BytesPosition::new(Some(0), Some(2), Some(Class::Header)),
+      BytesPosition::new(Some(3), Some(5), Some(Class::Body)),
+      BytesPosition::new(Some(7), Some(9), Some(Class::Body)),
+    ];
+
+    assert_eq!(
+      BytesPosition::merge_all_with_gap_preserving_classes(ranges, 10),
+      vec![
+        BytesPosition::new(Some(0), Some(2), Some(Class::Header)),
+        BytesPosition::new(Some(3), Some(9), Some(Class::Body))
+      ]
+    );
+  }
+
   #[test]
   fn bytes_position_new() {
     let result = BytesPosition::new(Some(1), Some(2), Some(Class::Header));
@@ -708,6 +1180,23 @@ mod tests {
     }
   }
 
+  #[test]
+  fn data_block_byte_count() {
+    assert_eq!(
+      DataBlock::Range(BytesPosition::new(Some(2), Some(7), None)).byte_count(),
+      Some(5)
+    );
+    assert_eq!(
+      DataBlock::Range(BytesPosition::new(None, Some(7), None)).byte_count(),
+      Some(7)
+    );
+    assert_eq!(
+      DataBlock::Range(BytesPosition::new(Some(2), None, None)).byte_count(),
+      None
+    );
+    assert_eq!(DataBlock::Data(vec![1, 2, 3], None).byte_count(), Some(3));
+  }
+
   #[test]
   fn data_block_from_bytes_positions() {
     let blocks = DataBlock::from_bytes_positions(vec![
@@ -720,6 +1209,66 @@ mod tests {
     );
   }
 
+  #[test]
+  fn data_block_coalesce_ranges_count_bound() {
+    let ranges = (0..100)
+      .map(|i| BytesPosition::new(Some(i * 10), Some(i * 10 + 1 + i % 7), None))
+      .collect::<Vec<_>>();
+    let merged = BytesPosition::merge_all(ranges.clone());
+    assert_eq!(merged.len(), 100);
+
+    for max_blocks in [1, 2, 7, 10, 50, 99, 100, 200] {
+      let blocks =
+        DataBlock::coalesce_ranges(DataBlock::from_bytes_positions(ranges.clone()), max_blocks);
+      assert!(blocks.len() <= max_blocks);
+
+      // Every byte of the original ranges is still covered.
+      for range in &merged {
+        assert!(blocks.iter().any(|block| matches!(
+          block,
+          DataBlock::Range(block) if block.start <= range.start && block.end >= range.end
+        )));
+      }
+    }
+  }
+
+  #[test]
+  fn data_block_coalesce_ranges_smallest_gaps() {
+    let blocks = vec![
+      DataBlock::Range(BytesPosition::new(Some(0), Some(2), None)),
+      DataBlock::Range(BytesPosition::new(Some(10), Some(12), None)),
+      DataBlock::Range(BytesPosition::new(Some(13), Some(15), None)),
+      DataBlock::Data(vec![0], None),
+    ];
+
+    assert_eq!(
+      DataBlock::coalesce_ranges(blocks, 3),
+      vec![
+        DataBlock::Range(BytesPosition::new(Some(0), Some(2), None)),
+        DataBlock::Range(BytesPosition::new(Some(10), Some(15), None)),
+        DataBlock::Data(vec![0], None),
+      ]
+    );
+  }
+
+  #[test]
+  fn data_block_coalesce_ranges_across_data() {
+    let blocks = vec![
+      DataBlock::Range(BytesPosition::new(Some(0), Some(2), None)),
+      DataBlock::Data(vec![0], None),
+      DataBlock::Range(BytesPosition::new(Some(3), Some(5), None)),
+    ];
+
+    assert_eq!(
+      DataBlock::coalesce_ranges(blocks, 1),
+      vec![
+        DataBlock::Range(BytesPosition::new(Some(0), Some(2), None)),
+        DataBlock::Data(vec![0], None),
+        DataBlock::Range(BytesPosition::new(Some(3), Some(5), None)),
+      ]
+    );
+  }
+
   #[test]
   fn byte_range_from_byte_position() {
     let result: BytesRange = BytesRange::from(&BytesPosition::default().with_start(5).with_end(10));
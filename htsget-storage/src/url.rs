@@ -1,20 +1,41 @@
+use std::collections::hash_map::RandomState;
 use std::fmt::Debug;
+use std::hash::BuildHasher;
 use std::pin::Pin;
+#[cfg(feature = "s3-storage")]
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use async_trait::async_trait;
+#[cfg(feature = "s3-storage")]
+use aws_config::{BehaviorVersion, SdkConfig};
+#[cfg(feature = "s3-storage")]
+use aws_sdk_s3::config::Region;
 use bytes::Bytes;
 use futures::Stream;
 use futures_util::TryStreamExt;
 use http::header::CONTENT_LENGTH;
+#[cfg(feature = "s3-storage")]
+use http::uri::InvalidUri;
 use http::{HeaderMap, Method, Request, Uri};
 use pin_project_lite::pin_project;
 use reqwest::{Client, ClientBuilder};
+#[cfg(feature = "s3-storage")]
+use tokio::sync::OnceCell;
+use tokio::time::sleep;
 use tokio_util::io::StreamReader;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
 use htsget_config::error;
-
+use htsget_config::storage::drs::{DrsAccessUrl, DrsResolver};
+
+#[cfg(feature = "s3-storage")]
+use crate::s3::S3Storage;
+#[cfg(feature = "s3-storage")]
+use crate::types::BytesPosition;
+#[cfg(feature = "s3-storage")]
+use crate::StorageError::InvalidInput;
 use crate::StorageError::{InternalError, KeyNotFound, ResponseError, UrlParseError};
 use crate::{
   GetOptions, HeadOptions, RangeUrlOptions, Result, StorageError, StorageMiddleware, StorageTrait,
@@ -29,6 +50,61 @@ pub struct UrlStorage {
   response_url: Uri,
   forward_headers: bool,
   header_blacklist: Vec<String>,
+  probe_key: Option<String>,
+  retries: u32,
+  retry_backoff: Duration,
+  retry_jitter: Duration,
+  drs: Option<DrsResolver>,
+  #[cfg(feature = "s3-storage")]
+  s3_config: Arc<OnceCell<SdkConfig>>,
+  path_template: Option<PathTemplate>,
+}
+
+/// The target that requests for a key are sent to.
+#[derive(Debug, Clone)]
+pub enum Target {
+  /// A url which requests are sent to directly.
+  Url(Uri),
+  /// An S3 object which a DRS `s3` access url resolved to, with the storage of its bucket and its
+  /// key. Requests are sent to presigned urls of the object.
+  #[cfg(feature = "s3-storage")]
+  S3(S3Storage, String),
+}
+
+/// A template for the path requested for a key, with the ids of the request that it applies to.
+/// `{id}` is replaced by the original request id, `{key}` by the key and `{extension}` by the part
+/// of the key which follows the resolved id.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+  template: String,
+  id: String,
+  resolved_id: String,
+}
+
+impl PathTemplate {
+  /// Create a new path template for a request with the original id and the resolved id.
+  pub fn new(
+    template: impl Into<String>,
+    id: impl Into<String>,
+    resolved_id: impl Into<String>,
+  ) -> Self {
+    Self {
+      template: template.into(),
+      id: id.into(),
+      resolved_id: resolved_id.into(),
+    }
+  }
+
+  /// Get the path for the key.
+  pub fn path(&self, key: &str) -> String {
+    let extension = key.strip_prefix(&self.resolved_id).unwrap_or_default();
+
+    self
+      .template
+      .replace("{id}", &self.id)
+      .replace("{key}", key)
+      .replace("{extension}", extension)
+  }
 }
 
 impl UrlStorage {
@@ -46,6 +122,14 @@ impl UrlStorage {
       response_url,
       forward_headers,
       header_blacklist,
+      probe_key: None,
+      retries: 0,
+      retry_backoff: Duration::default(),
+      retry_jitter: Duration::default(),
+      drs: None,
+      #[cfg(feature = "s3-storage")]
+      s3_config: Default::default(),
+      path_template: None,
     }
   }
 
@@ -64,19 +148,168 @@ impl UrlStorage {
       response_url,
       forward_headers,
       header_blacklist,
+      probe_key: None,
+      retries: 0,
+      retry_backoff: Duration::default(),
+      retry_jitter: Duration::default(),
+      drs: None,
+      #[cfg(feature = "s3-storage")]
+      s3_config: Default::default(),
+      path_template: None,
     })
   }
 
+  /// Set the key which is requested with a HEAD request to check that the url is reachable. The
+  /// url is not probed if this is not set.
+  pub fn with_probe_key(mut self, probe_key: Option<impl Into<String>>) -> Self {
+    self.probe_key = probe_key.map(Into::into);
+    self
+  }
+
+  /// Retry requests up to `retries` times when they fail with a connection error or a server
+  /// error, starting with the `backoff` delay and doubling it after each retry. Only idempotent
+  /// `GET` and `HEAD` requests are retried.
+  pub fn with_retry(mut self, retries: u32, backoff: Duration) -> Self {
+    self.retries = retries;
+    self.retry_backoff = backoff;
+    self
+  }
+
+  /// Add a random delay of up to `jitter` to each retry backoff, so that clients which failed at
+  /// the same time do not retry at the same time.
+  pub fn with_retry_jitter(mut self, jitter: Duration) -> Self {
+    self.retry_jitter = jitter;
+    self
+  }
+
+  /// Get a random delay of up to the retry jitter.
+  fn jitter(&self) -> Duration {
+    let jitter = u64::try_from(self.retry_jitter.as_nanos()).unwrap_or(u64::MAX);
+    match jitter {
+      0 => Duration::ZERO,
+      jitter => Duration::from_nanos(RandomState::new().hash_one(()) % jitter),
+    }
+  }
+
+  /// Treat the url as a DRS server, which resolves keys to the access urls that data is fetched
+  /// from and that tickets point to. Keys which are `drs://<host>/<id>` URIs are only resolved if
+  /// the host is the DRS server or one of the allowed hosts. `s3` access urls are only used if the
+  /// `s3-storage` feature is enabled.
+  pub fn with_drs(mut self, drs: bool, allowed_hosts: Vec<String>) -> Self {
+    self.drs = drs.then(|| {
+      DrsResolver::new(self.client.clone(), self.url.clone())
+        .with_allowed_hosts(allowed_hosts)
+        .with_s3(cfg!(feature = "s3-storage"))
+    });
+    self
+  }
+
+  /// Set the AWS config used to presign DRS `s3` access urls. The default AWS config is loaded
+  /// when it is first needed if this is not set.
+  #[cfg(feature = "s3-storage")]
+  pub fn with_s3_config(mut self, s3_config: SdkConfig) -> Self {
+    self.s3_config = Arc::new(OnceCell::from(s3_config));
+    self
+  }
+
+  /// Request paths built from the path template rather than the key.
+  pub fn with_path_template(mut self, path_template: Option<PathTemplate>) -> Self {
+    self.path_template = path_template;
+    self
+  }
+
+  /// Get the path requested for the key, which is the key unless the path template is set.
+  pub fn key_path(&self, key: &str) -> String {
+    match &self.path_template {
+      Some(path_template) => path_template.path(key),
+      None => key.to_string(),
+    }
+  }
+
+  /// Get the target that requests for the key are sent to, resolving the key using the DRS server
+  /// if it is set.
+  pub async fn resolve<K: AsRef<str> + Send>(&self, key: K, headers: &HeaderMap) -> Result<Target> {
+    let key = key.as_ref();
+    let Some(drs) = &self.drs else {
+      return self.get_url_from_key(key).map(Target::Url);
+    };
+
+    let access_url = drs
+      .resolve(key, headers)
+      .await
+      .map_err(|err| KeyNotFound(format!("{} with key {}", err, key)))?;
+
+    match access_url {
+      DrsAccessUrl::Url(url) => Ok(Target::Url(url)),
+      #[cfg(feature = "s3-storage")]
+      DrsAccessUrl::S3 {
+        bucket,
+        key,
+        region,
+      } => Ok(Target::S3(self.s3_storage(bucket, region).await, key)),
+      #[cfg(not(feature = "s3-storage"))]
+      DrsAccessUrl::S3 { .. } => Err(KeyNotFound(format!(
+        "s3 access urls are not supported with key {}",
+        key
+      ))),
+    }
+  }
+
+  /// Create the storage of a bucket which a DRS `s3` access url resolved to, using the region of
+  /// the access method if it is set.
+  #[cfg(feature = "s3-storage")]
+  async fn s3_storage(&self, bucket: String, region: Option<String>) -> S3Storage {
+    let sdk_config = self
+      .s3_config
+      .get_or_init(|| aws_config::load_defaults(BehaviorVersion::latest()))
+      .await;
+
+    let mut s3_config = aws_sdk_s3::config::Builder::from(sdk_config);
+    if let Some(region) = region {
+      s3_config.set_region(Some(Region::new(region)));
+    }
+
+    S3Storage::new(aws_sdk_s3::Client::from_conf(s3_config.build()), bucket)
+  }
+
+  /// Presign a url for a request with the method to the S3 object. Only `GET` and `HEAD` requests
+  /// can be presigned.
+  #[cfg(feature = "s3-storage")]
+  async fn presign_url(storage: &S3Storage, key: &str, method: &Method) -> Result<Uri> {
+    let url = if method == Method::GET {
+      storage
+        .s3_presign_url(key, &BytesPosition::default())
+        .await?
+    } else if method == Method::HEAD {
+      storage.s3_presign_head_url(key).await?
+    } else {
+      return Err(InvalidInput(format!(
+        "cannot send a {} request to the s3 object with key {}",
+        method, key
+      )));
+    };
+
+    url
+      .parse()
+      .map_err(|err: InvalidUri| UrlParseError(err.to_string()))
+  }
+
+  /// Check whether a request with the method can be retried. Methods which may have side effects
+  /// are never retried.
+  pub fn is_retryable(method: &Method) -> bool {
+    method == Method::GET || method == Method::HEAD
+  }
+
   /// Get a url from the key.
   pub fn get_url_from_key<K: AsRef<str> + Send>(&self, key: K) -> Result<Uri> {
-    format!("{}{}", self.url, key.as_ref())
+    format!("{}{}", self.url, self.key_path(key.as_ref()))
       .parse::<Uri>()
       .map_err(|err| UrlParseError(err.to_string()))
   }
 
   /// Get a url from the key.
   pub fn get_response_url_from_key<K: AsRef<str> + Send>(&self, key: K) -> Result<Uri> {
-    format!("{}{}", self.response_url, key.as_ref())
+    format!("{}{}", self.response_url, self.key_path(key.as_ref()))
       .parse::<Uri>()
       .map_err(|err| UrlParseError(err.to_string()))
   }
@@ -89,7 +322,8 @@ impl UrlStorage {
     headers
   }
 
-  /// Construct and send a request
+  /// Construct and send a request. Requests to S3 objects are sent to presigned urls without the
+  /// headers, which are only meant for the url or DRS server.
   pub async fn send_request<K: AsRef<str> + Send>(
     &self,
     key: K,
@@ -97,26 +331,58 @@ impl UrlStorage {
     method: Method,
   ) -> Result<reqwest::Response> {
     let key = key.as_ref();
-    let url = self.get_url_from_key(key)?;
+    let (url, headers) = match self.resolve(key, headers).await? {
+      Target::Url(url) => (url, headers.clone()),
+      #[cfg(feature = "s3-storage")]
+      Target::S3(storage, s3_key) => (
+        Self::presign_url(&storage, &s3_key, &method).await?,
+        HeaderMap::default(),
+      ),
+    };
 
     println!("url: {:?}", url);
-    let request = Request::builder().method(method).uri(&url);
-
-    let request = headers
-      .iter()
-      .fold(request, |acc, (key, value)| acc.header(key, value))
-      .body(vec![])
-      .map_err(|err| UrlParseError(err.to_string()))?;
-
-    let response = self
-      .client
-      .execute(
-        request
-          .try_into()
-          .map_err(|err| InternalError(format!("failed to create http request: {}", err)))?,
-      )
-      .await
-      .map_err(|err| KeyNotFound(format!("{} with key {}", err, key)))?;
+
+    let mut backoff = self.retry_backoff;
+    let mut retries = 0;
+    let response = loop {
+      let request = Request::builder().method(method.clone()).uri(&url);
+
+      let request = headers
+        .iter()
+        .fold(request, |acc, (key, value)| acc.header(key, value))
+        .body(vec![])
+        .map_err(|err| UrlParseError(err.to_string()))?;
+
+      let response = self
+        .client
+        .execute(
+          request
+            .try_into()
+            .map_err(|err| InternalError(format!("failed to create http request: {}", err)))?,
+        )
+        .await;
+
+      let failed = match &response {
+        Ok(response) => response.status().is_server_error(),
+        Err(_) => true,
+      };
+      if !failed || retries >= self.retries || !Self::is_retryable(&method) {
+        break response;
+      }
+
+      warn!(
+        retries,
+        ?backoff,
+        key,
+        "url storage request failed, retrying request"
+      );
+
+      sleep(backoff + self.jitter()).await;
+      backoff *= 2;
+      retries += 1;
+    };
+
+    let response = response.map_err(|err| KeyNotFound(format!("{} with key {}", err, key)))?;
 
     let status = response.status();
     if status.is_client_error() || status.is_server_error() {
@@ -135,8 +401,12 @@ impl UrlStorage {
     key: K,
     options: RangeUrlOptions<'_>,
   ) -> Result<HtsGetUrl> {
-    let url = self.get_response_url_from_key(key)?.into_parts();
-    let url = Uri::from_parts(url)
+    self.format_response_url(self.get_response_url_from_key(key)?, options)
+  }
+
+  /// Format the url returned in tickets, adding the response headers if they are forwarded.
+  fn format_response_url(&self, url: Uri, options: RangeUrlOptions<'_>) -> Result<HtsGetUrl> {
+    let url = Uri::from_parts(url.into_parts())
       .map_err(|err| InternalError(format!("failed to convert to uri from parts: {}", err)))?;
 
     let mut url = HtsGetUrl::new(url.to_string());
@@ -220,7 +490,14 @@ impl StorageTrait for UrlStorage {
     let response_headers = self.remove_blacklisted_headers(options.response_headers().clone());
     let new_options = RangeUrlOptions::new(options.range().clone(), &response_headers);
 
-    self.format_url(key, new_options)
+    match &self.drs {
+      Some(_) => match self.resolve(key, &response_headers).await? {
+        Target::Url(url) => self.format_response_url(url, new_options),
+        #[cfg(feature = "s3-storage")]
+        Target::S3(storage, s3_key) => storage.range_url(&s3_key, new_options).await,
+      },
+      None => self.format_url(key, new_options),
+    }
   }
 
   #[instrument(level = "trace", skip(self))]
@@ -243,6 +520,19 @@ impl StorageTrait for UrlStorage {
     debug!(calling_from = ?self, key, len, "size of key {:?} is {}", key, len);
     Ok(len)
   }
+
+  /// Check that the url is reachable by sending a HEAD request for the probe key, if it is set.
+  #[instrument(level = "trace", skip(self))]
+  async fn probe(&self) -> Result<()> {
+    let Some(probe_key) = &self.probe_key else {
+      return Ok(());
+    };
+
+    self
+      .head_key(probe_key, &HeaderMap::default())
+      .await
+      .map(|_| ())
+  }
 }
 
 #[cfg(test)]
@@ -250,11 +540,17 @@ pub(crate) mod tests {
   use std::future::Future;
   use std::path::{Path, PathBuf};
   use std::str::FromStr;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
   use std::{result, vec};
 
+  #[cfg(feature = "s3-storage")]
+  use aws_sdk_s3::config::{Credentials, SharedCredentialsProvider};
   use axum::body::Body;
+  use axum::extract::Path as AxumPath;
   use axum::middleware::Next;
   use axum::response::Response;
+  use axum::routing::get;
   use axum::{middleware, Router};
   use http::header::{AUTHORIZATION, HOST};
   use http::{HeaderName, HeaderValue, Request, StatusCode};
@@ -262,9 +558,13 @@ pub(crate) mod tests {
   use tokio::net::TcpListener;
   use tower_http::services::ServeDir;
 
-  use htsget_config::types::Headers;
+  use htsget_config::storage::url::{
+    UrlStorage as ConfigUrlStorage, UrlStorageClient as UrlStorageConfig,
+  };
+  use htsget_config::types::{Headers, HtsGetError};
 
   use crate::local::tests::create_local_test_files;
+  use crate::Storage;
 
   use super::*;
 
@@ -300,6 +600,27 @@ pub(crate) mod tests {
     );
   }
 
+  #[test]
+  fn get_url_from_key_path_template() {
+    let storage = UrlStorage::new(
+      test_client(),
+      Uri::from_str("https://example.com").unwrap(),
+      Uri::from_str("https://localhost:8080").unwrap(),
+      true,
+      vec![],
+    )
+    .with_path_template(Some(PathTemplate::new("{id}/{key}", "id", "resolved")));
+
+    assert_eq!(
+      storage.get_url_from_key("resolved.bam").unwrap(),
+      Uri::from_str("https://example.com/id/resolved.bam").unwrap()
+    );
+    assert_eq!(
+      storage.get_response_url_from_key("resolved.bam").unwrap(),
+      Uri::from_str("https://localhost:8080/id/resolved.bam").unwrap()
+    );
+  }
+
   #[test]
   fn remove_blacklisted_headers() {
     let storage = UrlStorage::new(
@@ -408,6 +729,29 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn get_storage_path_template() {
+    with_url_test_server(|storage, _, _| async move {
+      let storage = storage.with_path_template(Some(PathTemplate::new(
+        "assets/{id}{extension}",
+        "key",
+        "resolved",
+      )));
+
+      let mut headers = HeaderMap::default();
+      let headers = test_headers(&mut headers);
+      let options = GetOptions::new_with_default_range(headers);
+
+      let mut reader = storage.get("resolved1", options).await.unwrap();
+
+      let mut response = [0; 6];
+      reader.read_exact(&mut response).await.unwrap();
+
+      assert_eq!(String::from_utf8(response.to_vec()).unwrap(), "value1");
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn range_url_storage() {
     with_url_test_server(|_, url, _| async move {
@@ -458,6 +802,36 @@ pub(crate) mod tests {
     .await;
   }
 
+  #[tokio::test]
+  async fn range_url_storage_forward_headers_override() {
+    with_url_test_server(|_, url, _| async move {
+      let url_storage = UrlStorageConfig::try_from(ConfigUrlStorage::new(
+        Uri::from_str(&url).unwrap(),
+        Uri::from_str(&url).unwrap(),
+        true,
+        vec![],
+        Default::default(),
+      ))
+      .unwrap()
+      .set_forward_headers_override(Some("x-forward-headers".to_string()));
+
+      let mut headers = HeaderMap::default();
+      headers.insert("x-forward-headers", HeaderValue::from_static("false"));
+      let headers = test_headers(&mut headers);
+
+      let storage = Storage::from_url(&url_storage.apply_forward_headers_override(headers))
+        .await
+        .unwrap();
+      let options = RangeUrlOptions::new_with_default_range(headers);
+
+      assert_eq!(
+        storage.range_url("assets/key1", options).await.unwrap(),
+        HtsGetUrl::new(format!("{}/assets/key1", url))
+      );
+    })
+    .await;
+  }
+
   #[tokio::test]
   async fn head_storage() {
     with_url_test_server(|storage, _, _| async move {
@@ -529,6 +903,247 @@ pub(crate) mod tests {
     );
   }
 
+  #[tokio::test]
+  async fn send_request_retries_get() {
+    with_unavailable_test_server(|storage, requests| async move {
+      let storage = storage.with_retry(2, Duration::from_millis(1));
+
+      let result = storage
+        .send_request("assets/key1", &HeaderMap::default(), Method::GET)
+        .await;
+
+      assert!(matches!(result, Err(KeyNotFound(_))));
+      assert_eq!(requests.load(Ordering::SeqCst), 3);
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn send_request_retries_until_success() {
+    with_failing_test_server(2, |storage, requests| async move {
+      let storage = storage
+        .with_retry(3, Duration::from_millis(1))
+        .with_retry_jitter(Duration::from_millis(1));
+
+      let response = storage
+        .send_request("assets/key1", &HeaderMap::default(), Method::GET)
+        .await
+        .unwrap();
+
+      assert_eq!(response.text().await.unwrap(), "value1");
+      assert_eq!(requests.load(Ordering::SeqCst), 3);
+    })
+    .await;
+  }
+
+  #[test]
+  fn retry_jitter() {
+    let storage = UrlStorage::new_with_default_client(
+      Uri::from_static("https://example.com"),
+      Uri::from_static("https://example.com"),
+      false,
+      vec![],
+    )
+    .unwrap();
+    assert_eq!(storage.jitter(), Duration::ZERO);
+
+    let storage = storage.with_retry_jitter(Duration::from_millis(10));
+    assert!(storage.jitter() < Duration::from_millis(10));
+  }
+
+  #[tokio::test]
+  async fn send_request_does_not_retry_post() {
+    with_unavailable_test_server(|storage, requests| async move {
+      let storage = storage.with_retry(2, Duration::from_millis(1));
+
+      let result = storage
+        .send_request("assets/key1", &HeaderMap::default(), Method::POST)
+        .await;
+
+      assert!(matches!(result, Err(KeyNotFound(_))));
+      assert_eq!(requests.load(Ordering::SeqCst), 1);
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn get_storage_drs() {
+    with_url_test_server(|_, url, _| async move {
+      with_drs_test_server(url.clone(), |drs_url| async move {
+        let storage = UrlStorage::new(
+          test_client(),
+          Uri::from_str(&drs_url).unwrap(),
+          Uri::from_str(&drs_url).unwrap(),
+          true,
+          vec![],
+        )
+        .with_drs(true, vec![]);
+
+        let mut headers = HeaderMap::default();
+        let headers = test_headers(&mut headers);
+
+        assert!(matches!(
+          storage.resolve("key1", headers).await.unwrap(),
+          Target::Url(resolved) if resolved == Uri::from_str(&format!("{}/assets/key1", url)).unwrap()
+        ));
+
+        let mut reader = storage
+          .get("key1", GetOptions::new_with_default_range(headers))
+          .await
+          .unwrap();
+        let mut response = [0; 6];
+        reader.read_exact(&mut response).await.unwrap();
+        assert_eq!(String::from_utf8(response.to_vec()).unwrap(), "value1");
+
+        assert_eq!(
+          storage
+            .range_url("key1", RangeUrlOptions::new_with_default_range(headers))
+            .await
+            .unwrap(),
+          HtsGetUrl::new(format!("{}/assets/key1", url))
+            .with_headers(Headers::default().with_header(AUTHORIZATION.as_str(), "secret"))
+        );
+
+        assert!(matches!(
+          storage.resolve("missing", headers).await,
+          Err(KeyNotFound(_))
+        ));
+      })
+      .await;
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn resolve_drs_uri_hosts() {
+    with_url_test_server(|_, url, _| async move {
+      with_drs_test_server(url.clone(), |drs_url| async move {
+        let storage = UrlStorage::new(
+          test_client(),
+          Uri::from_str(&drs_url).unwrap(),
+          Uri::from_str(&drs_url).unwrap(),
+          true,
+          vec![],
+        )
+        .with_drs(true, vec!["drs.example.org".to_string()]);
+        let headers = HeaderMap::default();
+
+        let server_host = drs_url.strip_prefix("http://").unwrap();
+        assert!(matches!(
+          storage.resolve(format!("drs://{server_host}/key1"), &headers).await.unwrap(),
+          Target::Url(resolved) if resolved == Uri::from_str(&format!("{}/assets/key1", url)).unwrap()
+        ));
+
+        let result = storage
+          .resolve("drs://169.254.169.254/key1", &headers)
+          .await;
+        assert!(matches!(result, Err(KeyNotFound(_))));
+        assert!(matches!(
+          HtsGetError::from(result.unwrap_err()),
+          HtsGetError::NotFound(_)
+        ));
+      })
+      .await;
+    })
+    .await;
+  }
+
+  #[tokio::test]
+  async fn resolve_url_drs_access_method_preference() {
+    with_drs_test_server("http://localhost".to_string(), |drs_url| async move {
+      let storage = UrlStorage::new(
+        test_client(),
+        Uri::from_str(&drs_url).unwrap(),
+        Uri::from_str(&drs_url).unwrap(),
+        true,
+        vec![],
+      )
+      .with_drs(true, vec![]);
+      let headers = HeaderMap::default();
+
+      assert!(matches!(
+        storage.resolve("multiple", &headers).await.unwrap(),
+        Target::Url(url) if url == Uri::from_str("https://example.com/multiple").unwrap()
+      ));
+
+      #[cfg(not(feature = "s3-storage"))]
+      assert!(matches!(
+        storage.resolve("s3", &headers).await.unwrap(),
+        Target::Url(url) if url == Uri::from_str("http://localhost/assets/s3").unwrap()
+      ));
+
+      let result = storage.resolve("unsupported", &headers).await;
+      assert!(matches!(result, Err(KeyNotFound(_))));
+      assert!(matches!(
+        HtsGetError::from(result.unwrap_err()),
+        HtsGetError::NotFound(_)
+      ));
+    })
+    .await;
+  }
+
+  #[cfg(feature = "s3-storage")]
+  #[tokio::test]
+  async fn range_url_drs_s3() {
+    with_drs_test_server("http://localhost".to_string(), |drs_url| async move {
+      let s3_config = SdkConfig::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .credentials_provider(SharedCredentialsProvider::new(Credentials::new(
+          "access_key_id",
+          "secret_access_key",
+          None,
+          None,
+          "test",
+        )))
+        .build();
+      let storage = UrlStorage::new(
+        test_client(),
+        Uri::from_str(&drs_url).unwrap(),
+        Uri::from_str(&drs_url).unwrap(),
+        true,
+        vec![],
+      )
+      .with_drs(true, vec![])
+      .with_s3_config(s3_config);
+
+      let mut headers = HeaderMap::default();
+      let headers = test_headers(&mut headers);
+
+      assert!(matches!(
+        storage.resolve("s3", headers).await.unwrap(),
+        Target::S3(_, key) if key == "dir/s3"
+      ));
+
+      let url = storage
+        .range_url(
+          "s3",
+          RangeUrlOptions::new(BytesPosition::new(Some(0), Some(10), None), headers),
+        )
+        .await
+        .unwrap();
+      assert!(url
+        .url
+        .starts_with("https://bucket.s3.ap-southeast-2.amazonaws.com/dir/s3?"));
+      assert!(url.url.contains("X-Amz-Signature="));
+      assert_eq!(
+        url.headers,
+        Some(Headers::default().with_header("Range", "bytes=0-9"))
+      );
+    })
+    .await;
+  }
+
+  #[test]
+  fn is_retryable() {
+    assert!(UrlStorage::is_retryable(&Method::GET));
+    assert!(UrlStorage::is_retryable(&Method::HEAD));
+    assert!(!UrlStorage::is_retryable(&Method::POST));
+    assert!(!UrlStorage::is_retryable(&Method::PUT));
+    assert!(!UrlStorage::is_retryable(&Method::PATCH));
+    assert!(!UrlStorage::is_retryable(&Method::DELETE));
+  }
+
   fn test_client() -> Client {
     ClientBuilder::new().build().unwrap()
   }
@@ -585,6 +1200,99 @@ pub(crate) mod tests {
     .await;
   }
 
+  /// Run a test against a mock DRS server, which resolves the `key1` id to an access url under
+  /// the `/assets` path of the data url. The `multiple`, `s3` and `unsupported` ids have several
+  /// access method types.
+  async fn with_drs_test_server<F, Fut>(data_url: String, test: F)
+  where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    let router = Router::new().route(
+      "/ga4gh/drs/v1/objects/:id",
+      get(move |AxumPath(id): AxumPath<String>| async move {
+        match id.as_str() {
+          "key1" => Ok(format!(
+            r#"{{"id": "{id}", "access_methods": [{{"type": "http", "access_url": {{"url": "{data_url}/assets/{id}"}}}}]}}"#
+          )),
+          "multiple" => Ok(format!(
+            r#"{{"id": "{id}", "access_methods": [
+              {{"type": "http", "access_url": {{"url": "{data_url}/assets/{id}"}}}},
+              {{"type": "s3", "access_url": {{"url": "s3://bucket/{id}"}}, "region": "ap-southeast-2"}},
+              {{"type": "https", "access_url": {{"url": "https://example.com/{id}"}}}}
+            ]}}"#
+          )),
+          "s3" => Ok(format!(
+            r#"{{"id": "{id}", "access_methods": [
+              {{"type": "http", "access_url": {{"url": "{data_url}/assets/{id}"}}}},
+              {{"type": "s3", "access_url": {{"url": "s3://bucket/dir/{id}"}}, "region": "ap-southeast-2"}}
+            ]}}"#
+          )),
+          "unsupported" => Ok(format!(
+            r#"{{"id": "{id}", "access_methods": [{{"type": "gs", "access_url": {{"url": "gs://bucket/{id}"}}}}]}}"#
+          )),
+          _ => Err(StatusCode::NOT_FOUND),
+        }
+      }),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router.into_make_service()).await });
+
+    test(format!("http://{}", addr)).await;
+  }
+
+  /// Run a test against a server which responds to every request with 503 Service Unavailable,
+  /// counting the requests it receives.
+  async fn with_unavailable_test_server<F, Fut>(test: F)
+  where
+    F: FnOnce(UrlStorage, Arc<AtomicUsize>) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    with_failing_test_server(usize::MAX, test).await;
+  }
+
+  /// Run a test against a server which responds to the first `failures` requests with 503 Service
+  /// Unavailable, and to later requests with `value1`, counting the requests it receives.
+  async fn with_failing_test_server<F, Fut>(failures: usize, test: F)
+  where
+    F: FnOnce(UrlStorage, Arc<AtomicUsize>) -> Fut,
+    Fut: Future<Output = ()>,
+  {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let router = Router::new().fallback(move || {
+      let counter = counter.clone();
+      async move {
+        if counter.fetch_add(1, Ordering::SeqCst) < failures {
+          Err(StatusCode::SERVICE_UNAVAILABLE)
+        } else {
+          Ok("value1")
+        }
+      }
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router.into_make_service()).await });
+
+    let url = format!("http://{}", addr);
+    test(
+      UrlStorage::new(
+        test_client(),
+        Uri::from_str(&url).unwrap(),
+        Uri::from_str(&url).unwrap(),
+        false,
+        vec![],
+      ),
+      requests,
+    )
+    .await;
+  }
+
   pub(crate) fn test_headers(headers: &mut HeaderMap) -> &HeaderMap {
     headers.append(
       HeaderName::from_str(AUTHORIZATION.as_str()).unwrap(),
@@ -1,4 +1,6 @@
 use crate::util::default_dir;
+use base64::engine::general_purpose;
+use base64::Engine;
 use crypt4gh::keys::{get_private_key, get_public_key};
 use crypt4gh::{decrypt, encrypt, Keys};
 use htsget_config::storage::c4gh::{local::C4GHLocal, C4GHKeys};
@@ -59,6 +61,13 @@ pub fn encrypt_data(data: &[u8]) -> Vec<u8> {
   writer.into_inner().unwrap().into_inner()
 }
 
+/// Get the base64 encoded public key which matches the private key used by `get_decryption_keys`.
+pub fn get_sender_public_key() -> String {
+  let public_key = get_public_key(default_dir().join("data/c4gh/keys/bob.pub")).unwrap();
+
+  general_purpose::STANDARD.encode(public_key)
+}
+
 pub async fn get_decryption_keys() -> Vec<Keys> {
   let private_key = default_dir().join("data/c4gh/keys/bob.sec");
   let public_key = default_dir().join("data/c4gh/keys/alice.pub");
@@ -1,7 +1,8 @@
-use crate::http::{Header, TestRequest, TestServer};
+use crate::http::{Header, Response, TestRequest, TestServer};
 use http::header::{
   ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
-  ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+  ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+  ORIGIN,
 };
 use http::Method;
 
@@ -98,3 +99,70 @@ pub async fn test_cors_preflight_request_uri<T: TestRequest>(
     .to_lowercase()
     .contains("post"));
 }
+
+/// A cors request test which checks that the elapsed time header is exposed.
+pub async fn test_cors_expose_custom_headers<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester
+    .request()
+    .method(Method::GET)
+    .uri("/variants/1-vcf/sample1-bcbio-cancer")
+    .insert_header(Header {
+      name: ORIGIN,
+      value: http::HeaderValue::from_static("http://example.com"),
+    });
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert!(response.is_success());
+  assert!(response
+    .headers
+    .get(ACCESS_CONTROL_EXPOSE_HEADERS)
+    .unwrap()
+    .to_str()
+    .unwrap()
+    .to_lowercase()
+    .split(',')
+    .any(|header| header.trim() == "x-htsget-elapsed-ms"));
+}
+
+/// A cors request test which results in an error response.
+pub async fn test_cors_error_response<T: TestRequest>(tester: &impl TestServer<T>) {
+  let response = cors_error_response(tester).await;
+
+  assert_eq!(
+    response
+      .headers
+      .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+      .unwrap()
+      .to_str()
+      .unwrap(),
+    "http://example.com"
+  );
+}
+
+/// A cors request test which results in an error response, where cors headers should not be
+/// included on errors.
+pub async fn test_cors_error_response_without_headers<T: TestRequest>(tester: &impl TestServer<T>) {
+  let response = cors_error_response(tester).await;
+
+  assert!(response.headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
+
+async fn cors_error_response<T: TestRequest>(tester: &impl TestServer<T>) -> Response {
+  let request = tester
+    .request()
+    .method(Method::GET)
+    .uri("/reads/1-bam/non-existent-id")
+    .insert_header(Header {
+      name: ORIGIN,
+      value: http::HeaderValue::from_static("http://example.com"),
+    });
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, 404);
+
+  response
+}
@@ -14,9 +14,10 @@ use async_trait::async_trait;
 use http::uri::Authority;
 use http::{HeaderMap, HeaderName, Method};
 use serde::de;
+use serde_json::json;
 
 use htsget_config::config::cors::{AllowType, CorsConfig};
-use htsget_config::config::{DataServerConfig, TicketServerConfig};
+use htsget_config::config::{DataServerConfig, ServiceInfo, TicketServerConfig};
 use htsget_config::resolver::Resolver;
 use htsget_config::storage::{local::Local, Storage};
 use htsget_config::tls::{
@@ -120,6 +121,44 @@ pub fn default_test_resolver(addr: SocketAddr, scheme: Scheme) -> Vec<Resolver>
   ]
 }
 
+/// Default config with the ticket server config modified by `f`.
+pub fn config_with_ticket_server(
+  f: impl FnOnce(TicketServerConfig) -> TicketServerConfig,
+) -> Config {
+  let config = default_test_config();
+
+  Config::new(
+    config.formatting_style(),
+    f(config.ticket_server().clone()),
+    config.data_server().clone(),
+    config.service_info().clone(),
+    config.owned_resolvers(),
+  )
+}
+
+/// Default config with resolver response caching and the `Age` header enabled.
+pub fn config_with_age_header() -> Config {
+  let config = config_with_ticket_server(|ticket_server| ticket_server.with_age_header(true));
+
+  Config::new(
+    config.formatting_style(),
+    config.ticket_server().clone(),
+    config.data_server().clone(),
+    config.service_info().clone(),
+    config
+      .owned_resolvers()
+      .into_iter()
+      .map(|resolver| resolver.with_response_cache_ttl(Some(60)))
+      .collect(),
+  )
+}
+
+/// Default config with server timings recorded in ticket responses.
+pub fn config_with_server_timing() -> Config {
+  config_with_ticket_server(|ticket_server| ticket_server.with_server_timing(true))
+    .resolvers_from_ticket_server_config()
+}
+
 /// Default config with fixed port.
 pub fn default_config_fixed_port() -> Config {
   let addr = "127.0.0.1:8081".parse().unwrap();
@@ -175,6 +214,63 @@ pub fn default_test_config() -> Config {
   default_test_config_params(addr, None, Scheme::Http)
 }
 
+/// Default config with the GA4GH service-info fields set.
+pub fn config_with_service_info() -> Config {
+  let config = default_test_config();
+  let service_info: ServiceInfo = serde_json::from_value(json!({
+    "type_group": "org.ga4gh",
+    "type_artifact": "htsget",
+    "type_version": "1.3.0",
+    "version": "0.1.0",
+    "created_at": "2022-01-01T12:00:00Z",
+    "updated_at": "2023-06-01T12:00:00Z",
+  }))
+  .unwrap();
+
+  Config::new(
+    config.formatting_style(),
+    config.ticket_server().clone(),
+    config.data_server().clone(),
+    service_info,
+    config.owned_resolvers(),
+  )
+}
+
+/// Default config with an additional resolver whose local storage path does not exist.
+pub fn config_with_unreachable_backend() -> Config {
+  let config = default_test_config();
+  let local_storage = Local::new(
+    Scheme::Http,
+    Authority::from_static("127.0.0.1:8081"),
+    default_dir()
+      .join("non-existent")
+      .to_str()
+      .unwrap()
+      .to_string(),
+    "/data".to_string(),
+    false,
+  );
+
+  let mut resolvers = config.clone().owned_resolvers();
+  resolvers.push(
+    Resolver::new(
+      Storage::Local(local_storage),
+      "^3-(.*)$",
+      "$1",
+      Default::default(),
+    )
+    .unwrap(),
+  );
+
+  Config::new(
+    config.formatting_style(),
+    config.ticket_server().clone(),
+    config.data_server().clone(),
+    config.service_info().clone(),
+    resolvers,
+  )
+}
+
 /// Config with tls ticket server, using the current cargo manifest directory.
 pub fn config_with_tls<P: AsRef<Path>>(path: P) -> Config {
   let addr = get_dynamic_addr();
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::net::SocketAddr;
 
+use http::header::{AGE, RETRY_AFTER};
 use http::{HeaderValue, Method, StatusCode};
 use reqwest::ClientBuilder;
 use serde::Deserialize;
@@ -9,6 +10,7 @@ use serde_json::{json, Value};
 use crate::http::concat::ConcatResponse;
 use htsget_config::types::Class;
 use htsget_config::types::Format;
+use htsget_config::types::RetryAfterFormat;
 
 use crate::http::{Header, Response, TestRequest, TestServer};
 use crate::Config;
@@ -69,9 +71,9 @@ pub fn test_response_service_info(response: &Response) {
       "url": "",
     },
     "type": {
-      "group": "",
-      "artifact": "",
-      "version": "",
+      "group": "org.ga4gh",
+      "artifact": "htsget",
+      "version": "1.3.0",
     },
     "htsget": {
       "datatype": "variants",
@@ -81,6 +83,16 @@ pub fn test_response_service_info(response: &Response) {
       ],
       "fieldsParametersEffective": false,
       "tagsParametersEffective": false,
+      "formatCapabilities": {
+        "VCF": {
+          "rangeQueries": true,
+          "headerClass": true,
+        },
+        "BCF": {
+          "rangeQueries": true,
+          "headerClass": true,
+        },
+      },
     },
     "contactUrl": "",
     "documentationUrl": "",
@@ -254,6 +266,631 @@ pub async fn test_service_info<T: TestRequest>(tester: &impl TestServer<T>) {
   test_response_service_info(&response);
 }
 
+/// A test that HEAD on service-info returns the headers without a body.
+pub async fn test_service_info_head<T: TestRequest>(tester: &impl TestServer<T>) {
+  for endpoint in ["reads", "variants"] {
+    let request = tester
+      .request()
+      .method(Method::HEAD)
+      .uri(format!("/{endpoint}/service-info"));
+    let response = tester
+      .test_server(request, tester.get_expected_path().await)
+      .await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(
+      response.headers.get(http::header::CONTENT_TYPE).unwrap(),
+      "application/json"
+    );
+    assert!(response.body.is_empty());
+  }
+}
+
+/// A test that HEAD requests check that the file exists and report its format, without a body.
+pub async fn test_head<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester
+    .request()
+    .method(Method::HEAD)
+    .uri("/variants/1-vcf/sample1-bcbio-cancer");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, 200);
+  assert_eq!(
+    response.headers.get(http::header::CONTENT_TYPE).unwrap(),
+    "application/json"
+  );
+  assert_eq!(response.headers.get("htsget-format").unwrap(), "VCF");
+  assert!(response.body.is_empty());
+
+  let request = tester
+    .request()
+    .method(Method::HEAD)
+    .uri("/variants/1-vcf/non-existent-id");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, 404);
+  assert!(response.headers.get("htsget-format").is_none());
+  assert!(response.body.is_empty());
+}
+
+/// A test that the GA4GH service-info fields are present and well-formed.
+pub async fn test_service_info_ga4gh_fields<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester
+    .request()
+    .method(Method::GET)
+    .uri("/reads/service-info");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert!(response.is_success());
+  let body = response.deserialize_body::<Value>().unwrap();
+
+  assert_eq!(body["type"]["group"], json!("org.ga4gh"));
+  assert_eq!(body["type"]["artifact"], json!("htsget"));
+  assert_eq!(body["type"]["version"], json!("1.3.0"));
+  assert_eq!(body["version"], json!("0.1.0"));
+  assert_eq!(body["createdAt"], json!("2022-01-01T12:00:00Z"));
+  assert_eq!(body["updatedAt"], json!("2023-06-01T12:00:00Z"));
+
+  for version in [&body["type"]["version"], &body["version"]] {
+    let parts: Vec<_> = version.as_str().unwrap().split('.').collect();
+    assert_eq!(parts.len(), 3);
+    assert!(parts.iter().all(|part| part.parse::<u64>().is_ok()));
+  }
+  for timestamp in [&body["createdAt"], &body["updatedAt"]] {
+    let (date, time) = timestamp.as_str().unwrap().split_once('T').unwrap();
+    assert_eq!(date.split('-').count(), 3);
+    assert!(time.ends_with('Z'));
+  }
+}
+
+/// A test that security headers are present in ticket and error responses.
+pub async fn test_security_headers<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  for uri in [
+    "/variants/1-vcf/sample1-bcbio-cancer",
+    "/variants/1-vcf/non-existent-id",
+  ] {
+    let response = tester
+      .test_server(
+        tester.request().method(Method::GET).uri(uri),
+        expected_path.clone(),
+      )
+      .await;
+
+    assert_eq!(
+      response.headers.get("x-content-type-options"),
+      Some(&HeaderValue::from_static("nosniff"))
+    );
+  }
+}
+
+/// A test that requests with a path longer than the maximum path length are rejected.
+pub async fn test_max_path_length<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri(format!("/variants/1-vcf/{}", "a".repeat(100))),
+      expected_path.clone(),
+    )
+    .await;
+  assert_eq!(response.status, StatusCode::URI_TOO_LONG);
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+}
+
+/// A test that POST requests with regions across too many distinct reference names are rejected.
+pub async fn test_max_distinct_references<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+  let region =
+    |reference_name: &str| json!({ "referenceName": reference_name, "start": 0, "end": 100 });
+
+  let response = tester
+    .test_server(
+      post_request_one(tester).set_payload(
+        json!({ "regions": [region("chrM"), region("chr1"), region("chr2")] }).to_string(),
+      ),
+      expected_path.clone(),
+    )
+    .await;
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  let body = response.deserialize_body::<Value>().unwrap();
+  assert_eq!(body["htsget"]["error"], json!("InvalidInput"));
+
+  let response = tester
+    .test_server(
+      post_request_one(tester)
+        .set_payload(json!({ "regions": [region("chrM"), region("chrM")] }).to_string()),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+}
+
+/// A test that the elapsed search time header is present and numeric when it is enabled, and absent
+/// otherwise.
+pub async fn test_elapsed_time_header<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  for request in [
+    tester
+      .request()
+      .method(Method::GET)
+      .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+    post_request_one(tester).set_payload("{}"),
+  ] {
+    let response = tester.test_server(request, expected_path.clone()).await;
+    assert!(response.is_success());
+
+    let elapsed = response.headers.get("x-htsget-elapsed-ms");
+    if tester.get_config().ticket_server().elapsed_time_header() {
+      let elapsed = elapsed.unwrap().to_str().unwrap().parse::<u64>().unwrap();
+      assert!(elapsed < 60_000);
+    } else {
+      assert!(elapsed.is_none());
+    }
+  }
+}
+
+/// A test that responses to deprecated routes have a `Deprecation` header, and other routes do not.
+pub async fn test_deprecation_header<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/reads/1-bam/htsnexus_test_NA12878"),
+      expected_path.clone(),
+    )
+    .await;
+  assert!(response.is_success());
+  assert_eq!(
+    response.headers.get("deprecation"),
+    Some(&HeaderValue::from_static("true"))
+  );
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+  assert!(response.headers.get("deprecation").is_none());
+}
+
+/// A test that ticket urls are relative when relative urls are enabled.
+pub async fn test_relative_urls<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+
+  let body = response.deserialize_body::<Value>().unwrap();
+  let urls = body["htsget"]["urls"].as_array().unwrap();
+  assert!(!urls.is_empty());
+  for url in urls {
+    let url = url["url"].as_str().unwrap();
+    assert!(
+      url.starts_with("data:") || url.starts_with("/data/"),
+      "{url}"
+    );
+  }
+}
+
+/// A test that the root path responds with a landing response linking to the service-info.
+pub async fn test_root_response<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester.request().method(Method::GET).uri("/");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert!(response.is_success());
+  let body = response.deserialize_body::<Value>().unwrap();
+
+  assert_eq!(body["version"], json!("0.1.0"));
+  assert_eq!(
+    body["serviceInfo"],
+    json!({
+      "reads": "/reads/service-info",
+      "variants": "/variants/service-info"
+    })
+  );
+}
+
+/// A test that `/robots.txt` and `/favicon.ico` respond with static responses.
+pub async fn test_crawler_responses<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester.request().method(Method::GET).uri("/robots.txt");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, 200);
+  assert_eq!(
+    response.headers.get(http::header::CONTENT_TYPE).unwrap(),
+    "text/plain"
+  );
+  assert_eq!(response.body, b"User-agent: *\nDisallow: /\n");
+
+  let request = tester.request().method(Method::GET).uri("/favicon.ico");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, 204);
+  assert!(response.body.is_empty());
+}
+
+/// A test that the format is matched case-insensitively, and the response uses the canonical
+/// uppercase format.
+pub async fn test_canonical_format<T: TestRequest>(tester: &impl TestServer<T>) {
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/reads/1-bam/htsnexus_test_NA12878?format=Bam"),
+      tester.get_expected_path().await,
+    )
+    .await;
+  assert!(response.is_success());
+
+  let body = response.deserialize_body::<Value>().unwrap();
+  assert_eq!(body["htsget"]["format"], json!("BAM"));
+}
+
+/// A test that ticket responses set a `Content-Length` header which matches the body length.
+pub async fn test_content_length<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+  assert_eq!(
+    response.headers.get(http::header::CONTENT_LENGTH).unwrap(),
+    response.body.len().to_string().as_str()
+  );
+}
+
+/// A test that ticket responses include a `Server-Timing` header with the durations of the stages
+/// of resolving the query.
+pub async fn test_server_timing<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer?referenceName=chrM"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+
+  let server_timing = response
+    .headers
+    .get("server-timing")
+    .unwrap()
+    .to_str()
+    .unwrap();
+  let metrics: Vec<_> = server_timing
+    .split(", ")
+    .map(|metric| metric.split(";dur=").next().unwrap())
+    .collect();
+  assert_eq!(metrics, vec!["resolve", "head", "index"]);
+}
+
+/// A test that queries are rejected with a `Retry-After` header while the maintenance file exists,
+/// and that the service-info and readiness endpoints are still served.
+pub async fn test_maintenance<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+  let maintenance = tester.get_config().ticket_server().maintenance().clone();
+  let file = maintenance.file().unwrap().to_path_buf();
+  let request = |uri: &str| tester.request().method(Method::GET).uri(uri);
+  let query = "/variants/1-vcf/sample1-bcbio-cancer";
+
+  let response = tester
+    .test_server(request(query), expected_path.clone())
+    .await;
+  assert!(response.is_success());
+
+  std::fs::write(&file, "").unwrap();
+
+  let response = tester
+    .test_server(request(query), expected_path.clone())
+    .await;
+  assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+  let retry_after = response.headers.get(RETRY_AFTER).unwrap().to_str().unwrap();
+  match maintenance.retry_after_format() {
+    RetryAfterFormat::Seconds => assert_eq!(retry_after, "120"),
+    RetryAfterFormat::HttpDate => {
+      assert_eq!(retry_after.len(), "Sun, 06 Nov 1994 08:49:37 GMT".len());
+      assert!(retry_after.ends_with(" GMT"));
+    }
+  }
+  assert_eq!(
+    response.deserialize_body::<Value>().unwrap()["htsget"]["error"],
+    json!("ServiceUnavailable")
+  );
+
+  for uri in ["/variants/service-info", "/ready"] {
+    let response = tester
+      .test_server(request(uri), expected_path.clone())
+      .await;
+    assert_eq!(response.status, StatusCode::OK);
+  }
+
+  std::fs::remove_file(&file).unwrap();
+
+  let response = tester.test_server(request(query), expected_path).await;
+  assert!(response.is_success());
+}
+
+/// A test that a second identical request is served from the response cache with an `Age` header.
+pub async fn test_age_header<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+  let request = || {
+    tester
+      .request()
+      .method(Method::GET)
+      .uri("/variants/1-vcf/sample1-bcbio-cancer")
+  };
+
+  let response = tester.test_server(request(), expected_path.clone()).await;
+  assert!(response.is_success());
+  assert!(response.headers.get(AGE).is_none());
+
+  let response = tester.test_server(request(), expected_path).await;
+  assert!(response.is_success());
+  assert!(response
+    .headers
+    .get(AGE)
+    .unwrap()
+    .to_str()
+    .unwrap()
+    .parse::<u64>()
+    .is_ok());
+}
+
+/// A test that ticket requests without an `Authorization` header are rejected with a 401 and a
+/// `WWW-Authenticate` header, while service-info remains accessible.
+pub async fn test_require_authorization<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  for request in [
+    tester
+      .request()
+      .method(Method::GET)
+      .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+    post_request_one(tester).set_payload("{}"),
+  ] {
+    let response = tester.test_server(request, expected_path.clone()).await;
+
+    assert_eq!(response.status, 401);
+    assert_eq!(
+      response
+        .headers
+        .get(http::header::WWW_AUTHENTICATE)
+        .unwrap(),
+      "Bearer"
+    );
+  }
+
+  let authorization = || Header {
+    name: http::header::AUTHORIZATION,
+    value: HeaderValue::from_static("Bearer token"),
+  };
+  for request in [
+    tester
+      .request()
+      .method(Method::GET)
+      .uri("/variants/1-vcf/sample1-bcbio-cancer")
+      .insert_header(authorization()),
+    post_request_one(tester)
+      .insert_header(authorization())
+      .set_payload("{}"),
+  ] {
+    let response = tester.test_server(request, expected_path.clone()).await;
+    assert!(response.is_success());
+  }
+
+  let request = tester
+    .request()
+    .method(Method::GET)
+    .uri("/variants/service-info");
+  let response = tester.test_server(request, expected_path).await;
+  assert!(response.is_success());
+}
+
+/// A test that the readiness endpoint reports all backends as ready.
+pub async fn test_ready<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester.request().method(Method::GET).uri("/ready");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+  let body = response.deserialize_body::<Value>().unwrap();
+  assert_eq!(body["ready"], json!(true));
+  assert_eq!(
+    body["backends"],
+    json!([
+      { "name": "^1-(.*)$", "backend": "Local", "ready": true },
+      { "name": "^2-(.*)$", "backend": "Local", "ready": true }
+    ])
+  );
+}
+
+/// A test that the readiness endpoint returns a 503 with a per-backend report when a backend
+/// is unreachable.
+pub async fn test_ready_backend_down<T: TestRequest>(tester: &impl TestServer<T>) {
+  let request = tester.request().method(Method::GET).uri("/ready");
+  let response = tester
+    .test_server(request, tester.get_expected_path().await)
+    .await;
+
+  assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+  let body = response.deserialize_body::<Value>().unwrap();
+  assert_eq!(body["ready"], json!(false));
+
+  let backends = body["backends"].as_array().unwrap();
+  assert_eq!(backends.len(), 3);
+  assert_eq!(backends[0]["ready"], json!(true));
+  assert_eq!(backends[1]["ready"], json!(true));
+  assert_eq!(backends[2]["name"], json!("^3-(.*)$"));
+  assert_eq!(backends[2]["backend"], json!("Local"));
+  assert_eq!(backends[2]["ready"], json!(false));
+  assert!(backends[2]["error"].is_string());
+}
+
+/// A test that GET requests with a body are rejected, and GET requests without a body are not.
+pub async fn test_reject_get_body<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer")
+        .set_payload("{}"),
+      expected_path.clone(),
+    )
+    .await;
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path,
+    )
+    .await;
+  assert!(response.is_success());
+}
+
+/// A test that GET requests which repeat a scalar query parameter are rejected, and GET requests
+/// which do not are not.
+pub async fn test_reject_duplicate_query_keys<T: TestRequest>(tester: &impl TestServer<T>) {
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer?referenceName=chrM&start=0&start=153"),
+      tester.get_expected_path().await,
+    )
+    .await;
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  assert_eq!(
+    response.deserialize_body::<Value>().unwrap()["htsget"]["error"],
+    json!("InvalidInput")
+  );
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer?referenceName=chrM&start=153"),
+      tester.get_expected_path().await,
+    )
+    .await;
+  assert!(response.is_success());
+}
+
+/// A test that a reference name which isn't valid UTF-8 once percent-decoded is rejected.
+pub async fn test_invalid_reference_name<T: TestRequest>(tester: &impl TestServer<T>) {
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer?referenceName=chr%FF"),
+      tester.get_expected_path().await,
+    )
+    .await;
+
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  let body = response.deserialize_body::<Value>().unwrap();
+  assert_eq!(body["htsget"]["error"], json!("InvalidInput"));
+}
+
+/// A test for the service-info `Link` header in ticket responses.
+pub async fn test_service_info_link<T: TestRequest>(tester: &impl TestServer<T>) {
+  let expected_path = tester.get_expected_path().await;
+
+  let response = tester
+    .test_server(
+      tester
+        .request()
+        .method(Method::GET)
+        .uri("/variants/1-vcf/sample1-bcbio-cancer"),
+      expected_path.clone(),
+    )
+    .await;
+  assert!(response.is_success());
+  assert_eq!(
+    response.headers.get(http::header::LINK).unwrap(),
+    "</variants/service-info>; rel=\"service-desc\""
+  );
+
+  let response = tester
+    .test_server(
+      post_request_one(tester)
+        .uri("/reads/1-bam/htsnexus_test_NA12878")
+        .set_payload("{}"),
+      expected_path,
+    )
+    .await;
+  assert_eq!(
+    response.headers.get(http::header::LINK).unwrap(),
+    "</reads/service-info>; rel=\"service-desc\""
+  );
+}
+
 /// Test requests that should result in errors.
 pub async fn test_errors<T>(tester: &impl TestServer<T>)
 where